@@ -0,0 +1,170 @@
+use clap::Args;
+use panicless_backend::{
+    crypto::TokenCrypto,
+    db::{applied_migrations, MIGRATOR},
+    DbPool,
+};
+
+use super::Context;
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// Also list the IDs of affected rows
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// Result of one check; no affected rows means it passed
+struct Finding {
+    name: &'static str,
+    severity: Severity,
+    affected: Vec<String>,
+}
+
+pub async fn run(ctx: &Context, args: CheckArgs) -> anyhow::Result<()> {
+    let crypto = TokenCrypto::from_config(&ctx.config)?;
+    let pool = &ctx.pool;
+
+    let findings = vec![
+        Finding {
+            name: "migrations are applied",
+            severity: Severity::Warning,
+            affected: pending_migrations(pool).await?,
+        },
+        Finding {
+            name: "an enabled admin exists",
+            severity: Severity::Error,
+            affected: sqlx::query_scalar::<_, String>(
+                "SELECT 'no enabled admin' WHERE EXISTS(SELECT 1 FROM users)
+                   AND NOT EXISTS(SELECT 1 FROM users WHERE role = 'admin' AND disabled_at IS NULL)"
+            )
+            .fetch_all(pool)
+            .await?,
+        },
+        Finding {
+            name: "readings belong to the owner of their book",
+            severity: Severity::Error,
+            affected: ids(pool,
+                "SELECT 'reading ' || r.id FROM readings r JOIN books b ON b.id = r.book_id WHERE r.user_id <> b.user_id ORDER BY r.id"
+            ).await?,
+        },
+        Finding {
+            name: "connector tokens decrypt with ENCRYPTION_KEY",
+            severity: Severity::Error,
+            affected: undecryptable(pool, &crypto,
+                "SELECT 'connector ' || id, encrypted_token FROM connectors WHERE encrypted_token IS NOT NULL ORDER BY id"
+            ).await?,
+        },
+        Finding {
+            name: "two-factor secrets decrypt with ENCRYPTION_KEY",
+            severity: Severity::Error,
+            affected: undecryptable(pool, &crypto,
+                "SELECT 'user ' || user_id, encrypted_secret FROM user_totp ORDER BY user_id"
+            ).await?,
+        },
+        Finding {
+            name: "OIDC signing keys decrypt with ENCRYPTION_KEY",
+            severity: Severity::Error,
+            affected: undecryptable(pool, &crypto,
+                "SELECT 'key ' || kid, encrypted_private_key FROM oidc_signing_keys ORDER BY id"
+            ).await?,
+        },
+        Finding {
+            name: "stored secrets use the active encryption key",
+            severity: Severity::Warning,
+            affected: outdated(pool, &crypto,
+                "SELECT 'connector ' || id, encrypted_token FROM connectors WHERE encrypted_token IS NOT NULL
+                 UNION ALL SELECT '2fa secret of user ' || user_id, encrypted_secret FROM user_totp
+                 UNION ALL SELECT 'key ' || kid, encrypted_private_key FROM oidc_signing_keys"
+            ).await?,
+        },
+        Finding {
+            name: "invite codes are within their use limit",
+            severity: Severity::Warning,
+            affected: ids(pool,
+                "SELECT 'invite ' || id FROM invite_codes WHERE use_count > max_uses ORDER BY id"
+            ).await?,
+        },
+        Finding {
+            name: "no long-expired sessions, codes or tokens are kept",
+            severity: Severity::Warning,
+            affected: ids(pool,
+                "SELECT 'session ' || id FROM user_sessions WHERE expires_at < NOW() - INTERVAL '30 days'
+                 UNION ALL SELECT 'oauth code ' || id FROM oauth_codes WHERE expires_at < NOW() - INTERVAL '30 days'
+                 UNION ALL SELECT 'oauth token ' || id FROM oauth_tokens WHERE expires_at < NOW() - INTERVAL '30 days'
+                 UNION ALL SELECT 'user token ' || id FROM user_tokens WHERE expires_at < NOW() - INTERVAL '30 days'"
+            ).await?,
+        },
+    ];
+
+    let mut errors = 0;
+    for finding in &findings {
+        let status = match (finding.affected.is_empty(), finding.severity) {
+            (true, _) => "ok",
+            (false, Severity::Warning) => "WARN",
+            (false, Severity::Error) => {
+                errors += 1;
+                "FAIL"
+            }
+        };
+
+        if finding.affected.is_empty() {
+            println!("[{:<4}] {}", status, finding.name);
+        } else {
+            println!("[{:<4}] {} ({} affected)", status, finding.name, finding.affected.len());
+            if args.verbose {
+                for item in &finding.affected {
+                    println!("         {}", item);
+                }
+            }
+        }
+    }
+
+    if errors > 0 {
+        eprintln!("{} check(s) failed", errors);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn pending_migrations(pool: &DbPool) -> anyhow::Result<Vec<String>> {
+    let applied = applied_migrations(pool).await?;
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| !applied.contains(&m.version))
+        .map(|m| format!("migration {} ({})", m.version, m.description))
+        .collect())
+}
+
+async fn ids(pool: &DbPool, sql: &str) -> anyhow::Result<Vec<String>> {
+    Ok(sqlx::query_scalar::<_, String>(sql).fetch_all(pool).await?)
+}
+
+async fn undecryptable(pool: &DbPool, crypto: &TokenCrypto, sql: &str) -> anyhow::Result<Vec<String>> {
+    let rows = sqlx::query_as::<_, (String, String)>(sql).fetch_all(pool).await?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, ciphertext)| crypto.decrypt(ciphertext).is_err())
+        .map(|(label, _)| label)
+        .collect())
+}
+
+async fn outdated(pool: &DbPool, crypto: &TokenCrypto, sql: &str) -> anyhow::Result<Vec<String>> {
+    let rows = sqlx::query_as::<_, (String, String)>(sql).fetch_all(pool).await?;
+
+    // Undecryptable values are reported by the decryption checks
+    Ok(rows
+        .into_iter()
+        .filter(|(_, ciphertext)| crypto.needs_reencryption(ciphertext) && crypto.decrypt(ciphertext).is_ok())
+        .map(|(label, _)| label)
+        .collect())
+}
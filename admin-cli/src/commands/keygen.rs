@@ -0,0 +1,58 @@
+use base64::{engine::general_purpose, Engine as _};
+use clap::{Args, ValueEnum};
+use panicless_backend::crypto::TokenCrypto;
+use rand::RngCore;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum KeyKind {
+    /// ENCRYPTION_KEY: 32 random bytes, base64
+    EncryptionKey,
+    /// JWT_SECRET: 64 random bytes, base64
+    JwtSecret,
+    /// OAUTH_CLIENT_SECRET: 32 random bytes, hex
+    OauthClientSecret,
+}
+
+#[derive(Debug, Args)]
+pub struct KeygenArgs {
+    /// Which secret to generate
+    #[arg(value_enum, default_value = "encryption-key")]
+    kind: KeyKind,
+}
+
+pub fn run(args: KeygenArgs) {
+    println!("{}", generate(args.kind));
+}
+
+pub fn generate(kind: KeyKind) -> String {
+    match kind {
+        KeyKind::EncryptionKey => TokenCrypto::generate_key(),
+        KeyKind::JwtSecret => general_purpose::STANDARD.encode(random_bytes(64)),
+        KeyKind::OauthClientSecret => random_bytes(32).iter().map(|b| format!("{:02x}", b)).collect(),
+    }
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_encryption_key_is_usable() {
+        let key = generate(KeyKind::EncryptionKey);
+        let crypto = TokenCrypto::new(&key).unwrap();
+        let encrypted = crypto.encrypt("secret").unwrap();
+        assert_eq!(crypto.decrypt(&encrypted).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_generated_secret_lengths() {
+        assert_eq!(general_purpose::STANDARD.decode(generate(KeyKind::JwtSecret)).unwrap().len(), 64);
+        assert_eq!(generate(KeyKind::OauthClientSecret).len(), 64);
+    }
+}
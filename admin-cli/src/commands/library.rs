@@ -0,0 +1,212 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Context as _;
+use clap::Args;
+use panicless_backend::{
+    handlers::admin::insert_audit_entry,
+    models::export::{ConflictPolicy, LibraryExport},
+    services::{
+        calibre::{read_library, sync_calibre_books},
+        library_export::{export_library, import_library},
+        openlibrary_dump::{load_dump, open_dump, DumpKind},
+    },
+};
+
+use super::{find_user, Context, AUDIT_ACTOR};
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Username or user ID
+    user: String,
+    /// Output file (stdout if omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// Username or user ID of the receiving user
+    user: String,
+    /// JSON file written by `panicless-admin export` or downloaded from /api/export
+    file: PathBuf,
+    /// What to do with books already in the library: skip, overwrite or duplicate
+    #[arg(long, default_value = "skip")]
+    on_conflict: ConflictPolicy,
+}
+
+#[derive(Debug, Args)]
+pub struct CalibreSyncArgs {
+    /// Username or user ID of the receiving user
+    user: String,
+    /// Calibre library directory, metadata.db or metadata.opf (default: CALIBRE_LIBRARY_PATH)
+    #[arg(long)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct OpenLibraryLoadArgs {
+    /// Authors dump (ol_dump_authors_*.txt.gz)
+    #[arg(long)]
+    authors: Option<PathBuf>,
+    /// Works dump (ol_dump_works_*.txt.gz)
+    #[arg(long)]
+    works: Option<PathBuf>,
+    /// Editions dump (ol_dump_editions_*.txt.gz)
+    #[arg(long)]
+    editions: Option<PathBuf>,
+}
+
+pub async fn export(ctx: &Context, args: ExportArgs) -> anyhow::Result<()> {
+    let user = find_user(&ctx.pool, &args.user).await?;
+    let export = export_library(&ctx.pool, user.id).await?;
+    let json = serde_json::to_string_pretty(&export)?;
+
+    let mut tx = ctx.pool.begin().await?;
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.export", Some(user.id), serde_json::json!({
+        "books": export.books.len(),
+        "readings": export.readings.len(),
+    }), None)
+    .await?;
+    tx.commit().await?;
+
+    match args.output {
+        Some(path) => {
+            fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "Exported {} book(s) and {} reading(s) of {} to {}",
+                export.books.len(),
+                export.readings.len(),
+                user.username,
+                path.display()
+            );
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+pub async fn import(ctx: &Context, args: ImportArgs) -> anyhow::Result<()> {
+    let user = find_user(&ctx.pool, &args.user).await?;
+
+    let contents = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+    let export: LibraryExport = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not a library export", args.file.display()))?;
+
+    let summary = import_library(
+        &ctx.pool,
+        user.id,
+        &export,
+        args.on_conflict,
+        &ctx.config.custom_connector_allowed_hosts,
+    )
+    .await?;
+
+    let mut tx = ctx.pool.begin().await?;
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.import", Some(user.id), serde_json::json!({
+        "source_user": export.user.username,
+        "on_conflict": args.on_conflict.as_str(),
+        "books": summary.books_imported,
+        "books_updated": summary.books_updated,
+        "readings": summary.readings_imported,
+        "highlights": summary.highlights_imported,
+        "connectors": summary.connectors_imported,
+    }), None)
+    .await?;
+    tx.commit().await?;
+
+    println!(
+        "Imported {} book(s), {} reading(s) and {} highlight(s) into the library of {}",
+        summary.books_imported, summary.readings_imported, summary.highlights_imported, user.username
+    );
+    if summary.books_updated + summary.books_skipped > 0 {
+        println!(
+            "Already in the library: {} book(s) updated, {} skipped; {} reading(s) updated, {} skipped",
+            summary.books_updated, summary.books_skipped, summary.readings_updated, summary.readings_skipped
+        );
+    }
+    if !summary.connectors_without_key.is_empty() {
+        println!(
+            "Connectors to set up again with a new API key: {}",
+            summary.connectors_without_key.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn calibre_sync(ctx: &Context, args: CalibreSyncArgs) -> anyhow::Result<()> {
+    let user = find_user(&ctx.pool, &args.user).await?;
+    let path = args
+        .path
+        .or_else(|| ctx.config.calibre_library_path.as_ref().map(PathBuf::from))
+        .context("No Calibre library given; pass --path or set CALIBRE_LIBRARY_PATH")?;
+
+    let books = read_library(&path).await.map_err(anyhow::Error::msg)?;
+    let summary = sync_calibre_books(
+        &ctx.pool,
+        user.id,
+        &books,
+        ctx.config.calibre_content_server_url.as_deref(),
+    )
+    .await?;
+
+    let mut tx = ctx.pool.begin().await?;
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.calibre_sync", Some(user.id), serde_json::json!({
+        "library": path.display().to_string(),
+        "books": summary.books_found,
+        "created": summary.books_created,
+        "updated": summary.books_updated,
+        "linked": summary.books_linked,
+    }), None)
+    .await?;
+    tx.commit().await?;
+
+    println!(
+        "Synced {} Calibre book(s) into the library of {}: {} created, {} updated, {} linked, {} unchanged",
+        summary.books_found,
+        user.username,
+        summary.books_created,
+        summary.books_updated,
+        summary.books_linked,
+        summary.books_unchanged
+    );
+
+    Ok(())
+}
+
+pub async fn openlibrary_load(ctx: &Context, args: OpenLibraryLoadArgs) -> anyhow::Result<()> {
+    let dumps = [
+        (DumpKind::Authors, "author", args.authors),
+        (DumpKind::Works, "work", args.works),
+        (DumpKind::Editions, "edition", args.editions),
+    ];
+    if dumps.iter().all(|(_, _, path)| path.is_none()) {
+        anyhow::bail!("No dump given; pass --authors, --works and/or --editions");
+    }
+
+    for (kind, name, path) in dumps {
+        let Some(path) = path else { continue };
+        let reader = open_dump(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+        let mut reported = 0;
+        let summary = load_dump(&ctx.pool, kind, reader, |lines| {
+            if lines >= reported + 1_000_000 {
+                reported = lines;
+                eprintln!("{}: {} line(s) read", path.display(), lines);
+            }
+        })
+        .await?;
+
+        println!(
+            "Loaded {} {}(s) from {} ({} line(s) skipped)",
+            summary.records,
+            name,
+            path.display(),
+            summary.skipped
+        );
+    }
+
+    Ok(())
+}
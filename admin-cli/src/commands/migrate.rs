@@ -0,0 +1,44 @@
+use clap::Args;
+use panicless_backend::db::{applied_migrations, baseline_migrations, run_migrations, MIGRATOR};
+
+use super::Context;
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    /// Only show which migrations are applied and pending
+    #[arg(long, conflicts_with = "baseline")]
+    status: bool,
+    /// Record all migrations as applied without running them, for databases
+    /// created by the Postgres init scripts (docker-compose)
+    #[arg(long)]
+    baseline: bool,
+}
+
+pub async fn run(ctx: &Context, args: MigrateArgs) -> anyhow::Result<()> {
+    if args.baseline {
+        let marked = baseline_migrations(&ctx.pool).await?;
+        println!("Marked {} migration(s) as applied", marked);
+        return Ok(());
+    }
+
+    let applied = applied_migrations(&ctx.pool).await?;
+
+    if args.status {
+        for migration in MIGRATOR.iter() {
+            let state = if applied.contains(&migration.version) { "applied" } else { "pending" };
+            println!("{:>14}  {:<8}  {}", migration.version, state, migration.description);
+        }
+        return Ok(());
+    }
+
+    let pending = MIGRATOR.iter().filter(|m| !applied.contains(&m.version)).count();
+    if pending == 0 {
+        println!("Database is up to date");
+        return Ok(());
+    }
+
+    run_migrations(&ctx.pool).await?;
+    println!("Applied {} migration(s)", pending);
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anyhow::{anyhow, bail, Context as _};
+use panicless_backend::{create_pool, models::User, Config, DbPool};
+
+pub mod check;
+pub mod keygen;
+pub mod library;
+pub mod migrate;
+pub mod oauth_client;
+pub mod reencrypt;
+pub mod user;
+
+/// Name recorded in the audit trail for actions taken with this tool
+pub const AUDIT_ACTOR: &str = "panicless-admin";
+
+/// Configuration and database connection shared by the subcommands
+pub struct Context {
+    pub config: Config,
+    pub pool: DbPool,
+}
+
+impl Context {
+    pub async fn connect() -> anyhow::Result<Self> {
+        let config = Config::from_env().map_err(|e| anyhow!("Invalid configuration: {}", e))?;
+        let pool = create_pool(&config.database_url)
+            .await
+            .context("Failed to connect to the database")?;
+
+        Ok(Self { config, pool })
+    }
+}
+
+/// Look up a user by username, or by ID when the argument is numeric
+pub async fn find_user(pool: &DbPool, username_or_id: &str) -> anyhow::Result<User> {
+    let user = match username_or_id.parse::<i32>() {
+        Ok(id) => sqlx::query_as::<_, User>(
+            "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?,
+        Err(_) => sqlx::query_as::<_, User>(
+            "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE username = $1"
+        )
+        .bind(username_or_id)
+        .fetch_optional(pool)
+        .await?,
+    };
+
+    match user {
+        Some(user) => Ok(user),
+        None => bail!("User '{}' not found", username_or_id),
+    }
+}
+
+/// Read a new password from stdin (one line) or interactively with confirmation
+pub fn read_new_password(from_stdin: bool) -> anyhow::Result<String> {
+    let password = if from_stdin {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        let password = rpassword::prompt_password("New password: ")?;
+        let confirmation = rpassword::prompt_password("Repeat password: ")?;
+        if password != confirmation {
+            bail!("Passwords do not match");
+        }
+        password
+    };
+
+    if password.chars().count() < 8 {
+        bail!("Password must be at least 8 characters");
+    }
+
+    Ok(password)
+}
@@ -0,0 +1,185 @@
+use anyhow::bail;
+use clap::{Args, Subcommand};
+use panicless_backend::{
+    crypto::hash_token,
+    handlers::admin::insert_audit_entry,
+    models::OAuthClient,
+};
+
+use super::{
+    keygen::{self, KeyKind},
+    Context, AUDIT_ACTOR,
+};
+
+#[derive(Debug, Subcommand)]
+pub enum OAuthClientCommand {
+    /// Register a client and print its secret
+    Create(CreateArgs),
+    /// List registered clients
+    List,
+    /// Replace the secret of a client and print the new one
+    RotateSecret(ClientArg),
+    /// Revoke a client and the access tokens issued to it
+    Revoke(ClientArg),
+}
+
+#[derive(Debug, Args)]
+pub struct CreateArgs {
+    client_id: String,
+    /// Display name
+    #[arg(long)]
+    name: String,
+    /// Allowed redirect URI (repeatable; none allows any)
+    #[arg(long = "redirect-uri")]
+    redirect_uris: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ClientArg {
+    client_id: String,
+}
+
+pub async fn run(ctx: &Context, command: OAuthClientCommand) -> anyhow::Result<()> {
+    match command {
+        OAuthClientCommand::Create(args) => create(ctx, args).await,
+        OAuthClientCommand::List => list(ctx).await,
+        OAuthClientCommand::RotateSecret(args) => rotate_secret(ctx, args).await,
+        OAuthClientCommand::Revoke(args) => revoke(ctx, args).await,
+    }
+}
+
+async fn create(ctx: &Context, args: CreateArgs) -> anyhow::Result<()> {
+    if args.client_id == ctx.config.oauth_client_id {
+        bail!("'{}' is the client configured with OAUTH_CLIENT_ID", args.client_id);
+    }
+
+    let secret = keygen::generate(KeyKind::OauthClientSecret);
+    let mut tx = ctx.pool.begin().await?;
+
+    let created = sqlx::query(
+        "INSERT INTO oauth_clients (client_id, name, client_secret_hash, redirect_uris)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (client_id) DO NOTHING"
+    )
+    .bind(&args.client_id)
+    .bind(&args.name)
+    .bind(hash_token(&secret))
+    .bind(&args.redirect_uris)
+    .execute(&mut *tx)
+    .await?;
+
+    if created.rows_affected() == 0 {
+        bail!("Client '{}' already exists", args.client_id);
+    }
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "oauth_client.create", None, serde_json::json!({
+        "client_id": args.client_id,
+        "name": args.name,
+        "redirect_uris": args.redirect_uris,
+    }), None)
+    .await?;
+
+    tx.commit().await?;
+
+    println!("client_id:     {}", args.client_id);
+    println!("client_secret: {}", secret);
+    eprintln!("The secret is not stored and cannot be shown again.");
+    Ok(())
+}
+
+async fn list(ctx: &Context) -> anyhow::Result<()> {
+    let clients = sqlx::query_as::<_, OAuthClient>(
+        "SELECT id, client_id, name, client_secret_hash, redirect_uris, created_at, revoked_at
+         FROM oauth_clients ORDER BY id"
+    )
+    .fetch_all(&ctx.pool)
+    .await?;
+
+    println!("{:<30}  {:<25}  {:<8}  {:<10}  REDIRECT URIS", "CLIENT ID", "NAME", "STATUS", "CREATED");
+    println!(
+        "{:<30}  {:<25}  {:<8}  {:<10}  (from OAUTH_CLIENT_ID)",
+        ctx.config.oauth_client_id, "configured", "active", "-"
+    );
+    for client in &clients {
+        let uris = if client.redirect_uris.is_empty() {
+            "any".to_string()
+        } else {
+            client.redirect_uris.join(", ")
+        };
+        println!(
+            "{:<30}  {:<25}  {:<8}  {:<10}  {}",
+            client.client_id,
+            client.name,
+            if client.revoked_at.is_some() { "revoked" } else { "active" },
+            client.created_at.format("%Y-%m-%d"),
+            uris
+        );
+    }
+
+    Ok(())
+}
+
+async fn rotate_secret(ctx: &Context, args: ClientArg) -> anyhow::Result<()> {
+    let secret = keygen::generate(KeyKind::OauthClientSecret);
+    let mut tx = ctx.pool.begin().await?;
+
+    let updated = sqlx::query(
+        "UPDATE oauth_clients SET client_secret_hash = $1 WHERE client_id = $2 AND revoked_at IS NULL"
+    )
+    .bind(hash_token(&secret))
+    .bind(&args.client_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if updated.rows_affected() == 0 {
+        bail!("No active registered client '{}'", args.client_id);
+    }
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "oauth_client.rotate_secret", None, serde_json::json!({
+        "client_id": args.client_id,
+    }), None)
+    .await?;
+
+    tx.commit().await?;
+
+    println!("client_secret: {}", secret);
+    eprintln!("The secret is not stored and cannot be shown again.");
+    Ok(())
+}
+
+async fn revoke(ctx: &Context, args: ClientArg) -> anyhow::Result<()> {
+    let mut tx = ctx.pool.begin().await?;
+
+    let revoked = sqlx::query(
+        "UPDATE oauth_clients SET revoked_at = NOW() WHERE client_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(&args.client_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if revoked.rows_affected() == 0 {
+        bail!("No active registered client '{}'", args.client_id);
+    }
+
+    let tokens = sqlx::query("DELETE FROM oauth_tokens WHERE client_id = $1")
+        .bind(&args.client_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    sqlx::query("DELETE FROM oauth_codes WHERE client_id = $1")
+        .bind(&args.client_id)
+        .execute(&mut *tx)
+        .await?;
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "oauth_client.revoke", None, serde_json::json!({
+        "client_id": args.client_id,
+        "tokens_revoked": tokens,
+    }), None)
+    .await?;
+
+    tx.commit().await?;
+
+    println!("Revoked client {} ({} access token(s) deleted)", args.client_id, tokens);
+    Ok(())
+}
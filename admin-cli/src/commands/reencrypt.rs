@@ -0,0 +1,59 @@
+use clap::Args;
+use panicless_backend::{
+    crypto::TokenCrypto,
+    handlers::admin::insert_audit_entry,
+    services::reencryption::reencrypt_all,
+};
+
+use super::{Context, AUDIT_ACTOR};
+
+#[derive(Debug, Args)]
+pub struct ReencryptArgs {
+    /// Only report what would be re-encrypted
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub async fn run(ctx: &Context, args: ReencryptArgs) -> anyhow::Result<()> {
+    let crypto = TokenCrypto::from_config(&ctx.config)?;
+    println!("Active key: {}", crypto.active_key_id());
+
+    let reports = reencrypt_all(&ctx.pool, &crypto, args.dry_run).await?;
+
+    let mut failed = 0;
+    for report in &reports {
+        println!(
+            "{}.{}: {} row(s), {} on an old key, {} {}",
+            report.table,
+            report.column,
+            report.total,
+            report.outdated,
+            report.reencrypted,
+            if args.dry_run { "to re-encrypt" } else { "re-encrypted" }
+        );
+        if !report.failed.is_empty() {
+            println!("  cannot decrypt rows: {:?}", report.failed);
+            failed += report.failed.len();
+        }
+    }
+
+    if !args.dry_run {
+        let mut tx = ctx.pool.begin().await?;
+        insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "crypto.reencrypt", None, serde_json::json!({
+            "active_key": crypto.active_key_id(),
+            "reports": reports,
+        }), None)
+        .await?;
+        tx.commit().await?;
+    }
+
+    if failed > 0 {
+        eprintln!(
+            "{} value(s) could not be decrypted; add the key they were written with to ENCRYPTION_OLD_KEYS",
+            failed
+        );
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
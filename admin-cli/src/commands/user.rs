@@ -0,0 +1,302 @@
+use anyhow::bail;
+use clap::{Args, Subcommand};
+use panicless_backend::{
+    handlers::{
+        admin::{insert_audit_entry, ADMIN_USER_SUMMARY_SELECT},
+        auth::hash_password,
+        sessions::revoke_all_sessions,
+    },
+    models::{
+        admin::AdminUserSummary,
+        user::{CreateUser, User, ROLE_ADMIN, ROLE_USER},
+    },
+};
+use validator::Validate;
+
+use super::{find_user, read_new_password, Context, AUDIT_ACTOR};
+
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Create a user (registration policy does not apply)
+    Create(CreateArgs),
+    /// List users
+    List(ListArgs),
+    /// Disable an account and revoke its sessions and OAuth tokens
+    Disable(DisableArgs),
+    /// Re-enable a disabled account
+    Enable(UserArg),
+    /// Set a new password and revoke all sessions
+    ResetPassword(ResetPasswordArgs),
+    /// Grant or remove the admin role
+    SetRole(SetRoleArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CreateArgs {
+    username: String,
+    email: String,
+    #[arg(long)]
+    full_name: Option<String>,
+    /// Give the user the admin role
+    #[arg(long)]
+    admin: bool,
+    /// Mark the email address as verified
+    #[arg(long)]
+    verified: bool,
+    /// Read the password from the first line of stdin instead of prompting
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Filter by username, email or name
+    #[arg(long)]
+    search: Option<String>,
+    /// Print JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UserArg {
+    /// Username or user ID
+    user: String,
+}
+
+#[derive(Debug, Args)]
+pub struct DisableArgs {
+    /// Username or user ID
+    user: String,
+    #[arg(long)]
+    reason: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ResetPasswordArgs {
+    /// Username or user ID
+    user: String,
+    /// Read the password from the first line of stdin instead of prompting
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SetRoleArgs {
+    /// Username or user ID
+    user: String,
+    #[arg(value_parser = [ROLE_USER, ROLE_ADMIN])]
+    role: String,
+}
+
+pub async fn run(ctx: &Context, command: UserCommand) -> anyhow::Result<()> {
+    match command {
+        UserCommand::Create(args) => create(ctx, args).await,
+        UserCommand::List(args) => list(ctx, args).await,
+        UserCommand::Disable(args) => disable(ctx, args).await,
+        UserCommand::Enable(args) => enable(ctx, args).await,
+        UserCommand::ResetPassword(args) => reset_password(ctx, args).await,
+        UserCommand::SetRole(args) => set_role(ctx, args).await,
+    }
+}
+
+async fn create(ctx: &Context, args: CreateArgs) -> anyhow::Result<()> {
+    let password = read_new_password(args.password_stdin)?;
+
+    let payload = CreateUser {
+        username: args.username,
+        email: args.email,
+        password,
+        full_name: args.full_name,
+        invite_code: None,
+    };
+    if let Err(e) = payload.validate() {
+        bail!("Invalid user: {}", e);
+    }
+
+    let role = if args.admin { ROLE_ADMIN } else { ROLE_USER };
+    let mut tx = ctx.pool.begin().await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (username, email, password_hash, full_name, role, email_verified_at)
+         VALUES ($1, $2, $3, $4, $5, CASE WHEN $6 THEN NOW() END)
+         RETURNING id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at"
+    )
+    .bind(&payload.username)
+    .bind(&payload.email)
+    .bind(hash_password(&payload.password)?)
+    .bind(&payload.full_name)
+    .bind(role)
+    .bind(args.verified)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            anyhow::anyhow!("A user with this username or email already exists")
+        }
+        e => e.into(),
+    })?;
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.create", Some(user.id), serde_json::json!({
+        "username": user.username,
+        "role": user.role,
+    }), None)
+    .await?;
+
+    tx.commit().await?;
+
+    println!("Created user {} (id {}, role {})", user.username, user.id, user.role);
+    Ok(())
+}
+
+async fn list(ctx: &Context, args: ListArgs) -> anyhow::Result<()> {
+    let users = sqlx::query_as::<_, AdminUserSummary>(&format!(
+        "{} WHERE $1::TEXT IS NULL OR u.username ILIKE $1 OR u.email ILIKE $1 OR u.full_name ILIKE $1 ORDER BY u.id",
+        ADMIN_USER_SUMMARY_SELECT
+    ))
+    .bind(args.search.map(|s| format!("%{}%", s)))
+    .fetch_all(&ctx.pool)
+    .await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&users)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:>6}  {:<20}  {:<30}  {:<6}  {:<10}  {:>6}  {:>8}  {:<10}",
+        "ID", "USERNAME", "EMAIL", "ROLE", "STATUS", "BOOKS", "READINGS", "CREATED"
+    );
+    for user in &users {
+        let status = if user.disabled_at.is_some() {
+            "disabled"
+        } else if user.email_verified_at.is_none() {
+            "unverified"
+        } else {
+            "active"
+        };
+        println!(
+            "{:>6}  {:<20}  {:<30}  {:<6}  {:<10}  {:>6}  {:>8}  {}",
+            user.id,
+            user.username,
+            user.email,
+            user.role,
+            status,
+            user.book_count,
+            user.reading_count,
+            user.created_at.format("%Y-%m-%d")
+        );
+    }
+    println!("{} user(s)", users.len());
+
+    Ok(())
+}
+
+async fn disable(ctx: &Context, args: DisableArgs) -> anyhow::Result<()> {
+    let user = find_user(&ctx.pool, &args.user).await?;
+    if user.is_disabled() {
+        bail!("User {} is already disabled", user.username);
+    }
+
+    let mut tx = ctx.pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE users SET disabled_at = NOW(), disabled_reason = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2"
+    )
+    .bind(&args.reason)
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM oauth_tokens WHERE user_id = $1")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.disable", Some(user.id), serde_json::json!({
+        "reason": args.reason,
+    }), None)
+    .await?;
+
+    tx.commit().await?;
+
+    let sessions = revoke_all_sessions(&ctx.pool, user.id, None, "account_disabled").await?;
+
+    println!("Disabled user {} ({} session(s) revoked)", user.username, sessions);
+    Ok(())
+}
+
+async fn enable(ctx: &Context, args: UserArg) -> anyhow::Result<()> {
+    let user = find_user(&ctx.pool, &args.user).await?;
+    if !user.is_disabled() {
+        bail!("User {} is not disabled", user.username);
+    }
+
+    let mut tx = ctx.pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE users SET disabled_at = NULL, disabled_reason = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = $1"
+    )
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await?;
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.enable", Some(user.id), serde_json::json!({}), None).await?;
+
+    tx.commit().await?;
+
+    println!("Enabled user {}", user.username);
+    Ok(())
+}
+
+async fn reset_password(ctx: &Context, args: ResetPasswordArgs) -> anyhow::Result<()> {
+    let user = find_user(&ctx.pool, &args.user).await?;
+    let password = read_new_password(args.password_stdin)?;
+
+    let mut tx = ctx.pool.begin().await?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+        .bind(hash_password(&password)?)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.reset_password", Some(user.id), serde_json::json!({}), None).await?;
+
+    tx.commit().await?;
+
+    let sessions = revoke_all_sessions(&ctx.pool, user.id, None, "password_reset").await?;
+
+    println!("Password of {} changed ({} session(s) revoked)", user.username, sessions);
+    Ok(())
+}
+
+async fn set_role(ctx: &Context, args: SetRoleArgs) -> anyhow::Result<()> {
+    let user = find_user(&ctx.pool, &args.user).await?;
+    if user.role == args.role {
+        println!("User {} already has role {}", user.username, user.role);
+        return Ok(());
+    }
+
+    let mut tx = ctx.pool.begin().await?;
+
+    sqlx::query("UPDATE users SET role = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+        .bind(&args.role)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    insert_audit_entry(&mut tx, None, AUDIT_ACTOR, "user.role", Some(user.id), serde_json::json!({
+        "from": user.role,
+        "to": args.role,
+    }), None)
+    .await?;
+
+    tx.commit().await?;
+
+    // Access tokens carry the role, so existing sessions must log in again
+    revoke_all_sessions(&ctx.pool, user.id, None, "role_changed").await?;
+
+    println!("User {} now has role {}", user.username, args.role);
+    Ok(())
+}
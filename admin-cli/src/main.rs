@@ -0,0 +1,80 @@
+use clap::{Parser, Subcommand};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+mod commands;
+
+use commands::{
+    check, keygen, library, migrate, oauth_client, reencrypt, user, Context,
+};
+
+/// Operate a Panicless Library backend
+///
+/// Reads the same environment (and .env file) as the backend server.
+#[derive(Debug, Parser)]
+#[command(name = "panicless-admin", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Manage user accounts
+    #[command(subcommand)]
+    User(user::UserCommand),
+    /// Generate secrets for the configuration
+    Keygen(keygen::KeygenArgs),
+    /// Apply database migrations
+    Migrate(migrate::MigrateArgs),
+    /// Export a user's profile, books, readings, highlights and connectors as JSON
+    Export(library::ExportArgs),
+    /// Import a JSON export into a user's library
+    Import(library::ImportArgs),
+    /// Sync a Calibre library into a user's library
+    #[command(name = "calibre-sync")]
+    CalibreSync(library::CalibreSyncArgs),
+    /// Load Open Library dumps used to fill in missing book details
+    #[command(name = "openlibrary-load")]
+    OpenLibraryLoad(library::OpenLibraryLoadArgs),
+    /// Manage OAuth2 clients
+    #[command(subcommand, name = "oauth-client")]
+    OAuthClient(oauth_client::OAuthClientCommand),
+    /// Re-encrypt stored secrets with the active ENCRYPTION_KEY
+    Reencrypt(reencrypt::ReencryptArgs),
+    /// Check the database for inconsistencies
+    Check(check::CheckArgs),
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "warn".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    let cli = Cli::parse();
+
+    // keygen is used to write the configuration, so it must work without one
+    if let Command::Keygen(args) = cli.command {
+        keygen::run(args);
+        return Ok(());
+    }
+
+    let ctx = Context::connect().await?;
+
+    match cli.command {
+        Command::User(command) => user::run(&ctx, command).await,
+        Command::Migrate(args) => migrate::run(&ctx, args).await,
+        Command::Export(args) => library::export(&ctx, args).await,
+        Command::Import(args) => library::import(&ctx, args).await,
+        Command::CalibreSync(args) => library::calibre_sync(&ctx, args).await,
+        Command::OpenLibraryLoad(args) => library::openlibrary_load(&ctx, args).await,
+        Command::OAuthClient(command) => oauth_client::run(&ctx, command).await,
+        Command::Reencrypt(args) => reencrypt::run(&ctx, args).await,
+        Command::Check(args) => check::run(&ctx, args).await,
+        Command::Keygen(_) => unreachable!("handled above"),
+    }
+}
@@ -0,0 +1,259 @@
+use serde_json::{json, Value};
+
+use super::{check_key, send_json, ChatMessage, ChatProvider, ProviderRequest, ProviderResponse, ToolCall, Verification};
+use crate::errors::AppResult;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic Messages API adapter
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(client: reqwest::Client, base_url: &str, model: &str, api_key: String) -> Self {
+        AnthropicProvider {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    fn request_body(&self, request: &ProviderRequest) -> Value {
+        let messages: Vec<Value> = request.messages.iter().map(message_to_wire).collect();
+        let tools: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "input_schema": tool.input_schema,
+                })
+            })
+            .collect();
+
+        json!({
+            "model": self.model,
+            "max_tokens": request.max_tokens,
+            "system": request.system,
+            "messages": messages,
+            "tools": tools,
+        })
+    }
+}
+
+fn message_to_wire(message: &ChatMessage) -> Value {
+    match message {
+        ChatMessage::User { text } => json!({ "role": "user", "content": text }),
+        ChatMessage::Assistant { text, tool_calls } => {
+            let mut content = Vec::new();
+            if !text.is_empty() {
+                content.push(json!({ "type": "text", "text": text }));
+            }
+            for call in tool_calls {
+                content.push(json!({
+                    "type": "tool_use",
+                    "id": call.id,
+                    "name": call.name,
+                    "input": call.arguments,
+                }));
+            }
+            json!({ "role": "assistant", "content": content })
+        }
+        ChatMessage::ToolResults { results } => {
+            let content: Vec<Value> = results
+                .iter()
+                .map(|result| {
+                    json!({
+                        "type": "tool_result",
+                        "tool_use_id": result.call_id,
+                        "content": result.content,
+                        "is_error": result.is_error,
+                    })
+                })
+                .collect();
+            json!({ "role": "user", "content": content })
+        }
+    }
+}
+
+fn parse_response(body: &Value) -> ProviderResponse {
+    let mut response = ProviderResponse::default();
+    for block in body["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => response.text.push_str(block["text"].as_str().unwrap_or_default()),
+            Some("tool_use") => response.tool_calls.push(ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: block["input"].clone(),
+            }),
+            _ => {}
+        }
+    }
+    response
+}
+
+#[async_trait::async_trait]
+impl ChatProvider for AnthropicProvider {
+    async fn complete(&self, request: &ProviderRequest) -> AppResult<ProviderResponse> {
+        let http_request = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&self.request_body(request));
+
+        let body = send_json(http_request, "Anthropic").await?;
+        Ok(parse_response(&body))
+    }
+
+    async fn verify(&self) -> Verification {
+        let probe = self
+            .client
+            .get(format!("{}/v1/models?limit=1", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION);
+
+        check_key(probe, "Anthropic", &[401, 403]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{library_tool_specs, ToolResult};
+    use crate::errors::AppError;
+    use crate::models::connector::ConnectorStatus;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> AnthropicProvider {
+        AnthropicProvider::new(reqwest::Client::new(), &server.uri(), "test-model", "sk-test".to_string())
+    }
+
+    fn request(messages: Vec<ChatMessage>) -> ProviderRequest {
+        ProviderRequest {
+            system: "be helpful".to_string(),
+            messages,
+            tools: library_tool_specs(),
+            max_tokens: 128,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_use_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "sk-test"))
+            .and(header("anthropic-version", ANTHROPIC_VERSION))
+            .and(body_partial_json(json!({"model": "test-model", "system": "be helpful"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "content": [
+                    {"type": "text", "text": "Let me look."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "search_books", "input": {"query": "Dune"}}
+                ],
+                "stop_reason": "tool_use"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = provider(&server)
+            .complete(&request(vec![ChatMessage::User { text: "Find Dune".to_string() }]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "Let me look.");
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].id, "toolu_1");
+        assert_eq!(response.tool_calls[0].arguments, json!({"query": "Dune"}));
+    }
+
+    #[tokio::test]
+    async fn test_error_response_carries_provider_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "type": "error",
+                "error": {"type": "authentication_error", "message": "invalid x-api-key"}
+            })))
+            .mount(&server)
+            .await;
+
+        let err = provider(&server)
+            .complete(&request(vec![ChatMessage::User { text: "hi".to_string() }]))
+            .await
+            .unwrap_err();
+
+        match err {
+            AppError::Internal(message) => assert!(message.contains("401") && message.contains("invalid x-api-key")),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_classifies_responses() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("x-api-key", "sk-test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("x-api-key", "sk-revoked"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": {"type": "authentication_error", "message": "invalid x-api-key"}
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("x-api-key", "sk-busy"))
+            .respond_with(ResponseTemplate::new(429))
+            .mount(&server)
+            .await;
+
+        let verify = |key: &str| {
+            AnthropicProvider::new(reqwest::Client::new(), &server.uri(), "test-model", key.to_string())
+        };
+
+        assert_eq!(verify("sk-test").verify().await.status, ConnectorStatus::Valid);
+        let revoked = verify("sk-revoked").verify().await;
+        assert_eq!(revoked.status, ConnectorStatus::Invalid);
+        assert_eq!(revoked.detail.as_deref(), Some("invalid x-api-key"));
+        assert_eq!(verify("sk-busy").verify().await.status, ConnectorStatus::RateLimited);
+    }
+
+    #[tokio::test]
+    async fn test_verify_unreachable_provider() {
+        let provider = AnthropicProvider::new(reqwest::Client::new(), "http://127.0.0.1:1", "test-model", "sk".to_string());
+        assert_eq!(provider.verify().await.status, ConnectorStatus::Unreachable);
+    }
+
+    #[test]
+    fn test_tool_round_trip_wire_format() {
+        let call = ToolCall { id: "toolu_1".to_string(), name: "search_books".to_string(), arguments: json!({}) };
+        let assistant = message_to_wire(&ChatMessage::Assistant { text: String::new(), tool_calls: vec![call] });
+        assert_eq!(assistant["content"][0]["type"], "tool_use");
+        assert_eq!(assistant["content"].as_array().unwrap().len(), 1);
+
+        let results = message_to_wire(&ChatMessage::ToolResults {
+            results: vec![ToolResult {
+                call_id: "toolu_1".to_string(),
+                name: "search_books".to_string(),
+                content: "[]".to_string(),
+                is_error: false,
+            }],
+        });
+        assert_eq!(results["role"], "user");
+        assert_eq!(results["content"][0]["tool_use_id"], "toolu_1");
+    }
+}
@@ -0,0 +1,179 @@
+//! Requests to endpoints users choose
+//!
+//! A custom connector points the server at a URL of the user's choosing. Unless the
+//! operator allowlisted its host (CUSTOM_CONNECTOR_ALLOWED_HOSTS), that host must only
+//! resolve to public addresses: never loopback, private, link-local or unique-local
+//! ones such as the cloud metadata service at 169.254.169.254. The check runs when the
+//! URL is saved and again on every connection, through the HTTP client's resolver, so
+//! a name that resolves somewhere else later is caught too.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect, Client, ClientBuilder,
+};
+
+/// Whether an address is on the public internet
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // "This network", 0.0.0.0/8
+                || a == 0
+                // Shared address space (carrier-grade NAT), 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn is_allowlisted(host: &str, allowed_hosts: &[String]) -> bool {
+    allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host))
+}
+
+/// The address of a URL host written as an IP address ("10.0.0.5", "[::1]")
+fn literal_ip(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+fn not_public(host: &str, ip: IpAddr) -> String {
+    format!(
+        "Host '{}' resolves to {}, which is not a public address; the administrator can allow it with CUSTOM_CONNECTOR_ALLOWED_HOSTS",
+        host, ip
+    )
+}
+
+/// Check that the host of a base URL may be connected to
+/// Resolves it; every address must be public unless the host is allowlisted.
+pub async fn check_endpoint(base_url: &str, allowed_hosts: &[String]) -> Result<(), String> {
+    let url = reqwest::Url::parse(base_url).map_err(|_| format!("Invalid base_url '{}'", base_url))?;
+    let host = url.host_str().unwrap_or_default();
+    if is_allowlisted(host, allowed_hosts) {
+        return Ok(());
+    }
+    if let Some(ip) = literal_ip(host) {
+        return if is_public_ip(ip) { Ok(()) } else { Err(not_public(host, ip)) };
+    }
+
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("Host '{}' could not be resolved: {}", host, e))?;
+    for addr in addrs {
+        if !is_public_ip(addr.ip()) {
+            return Err(not_public(host, addr.ip()));
+        }
+    }
+    Ok(())
+}
+
+/// HTTP client for a user-chosen base URL
+/// Connects to public addresses only (allowlisted hosts excepted) and does not
+/// follow redirects, which could lead anywhere.
+pub fn guarded_client(builder: ClientBuilder, base_url: &str, allowed_hosts: &[String]) -> Result<Client, String> {
+    // The resolver is not asked about hosts written as addresses
+    let url = reqwest::Url::parse(base_url).map_err(|_| format!("Invalid base_url '{}'", base_url))?;
+    let host = url.host_str().unwrap_or_default();
+    if let Some(ip) = literal_ip(host) {
+        if !is_allowlisted(host, allowed_hosts) && !is_public_ip(ip) {
+            return Err(not_public(host, ip));
+        }
+    }
+
+    builder
+        .dns_resolver(Arc::new(PublicResolver { allowed_hosts: allowed_hosts.to_vec() }))
+        .redirect(redirect::Policy::none())
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Resolver leaving out the addresses that are not public, for hosts not allowlisted
+struct PublicResolver {
+    allowed_hosts: Vec<String>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allowlisted = is_allowlisted(name.as_str(), &self.allowed_hosts);
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|addr| allowlisted || is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("Host '{}' has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["8.8.8.8", "203.0.114.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0", "100.64.0.1",
+            "::1", "::", "fd00::1", "fe80::1", "::ffff:127.0.0.1", "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_endpoint() {
+        let none: Vec<String> = Vec::new();
+        assert!(check_endpoint("http://169.254.169.254/latest", &none).await.unwrap_err().contains("not a public address"));
+        assert!(check_endpoint("http://[::1]:11434", &none).await.is_err());
+        assert!(check_endpoint("http://localhost:11434", &none).await.is_err());
+        assert!(check_endpoint("https://8.8.8.8", &none).await.is_ok());
+
+        // Operators may allow private hosts by name
+        let allowed = vec!["localhost".to_string(), "10.0.0.5".to_string()];
+        assert!(check_endpoint("http://localhost:11434", &allowed).await.is_ok());
+        assert!(check_endpoint("http://10.0.0.5:8000", &allowed).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_guarded_client_refuses_private_hosts() {
+        let none: Vec<String> = Vec::new();
+        assert!(guarded_client(Client::builder(), "http://10.0.0.5:8000", &none).is_err());
+
+        // Names are checked when connecting
+        let client = guarded_client(Client::builder(), "http://localhost:1", &none).unwrap();
+        let error = client.get("http://localhost:1/v1/models").send().await.unwrap_err();
+        assert!(format!("{:?}", error).contains("no public address"));
+
+        let allowed = vec!["10.0.0.5".to_string()];
+        assert!(guarded_client(Client::builder(), "http://10.0.0.5:8000", &allowed).is_ok());
+    }
+}
@@ -0,0 +1,248 @@
+use serde_json::{json, Map, Value};
+
+use super::{check_key, send_json, ChatMessage, ChatProvider, ProviderRequest, ProviderResponse, ToolCall, Verification};
+use crate::errors::AppResult;
+
+/// JSON Schema keywords accepted in Gemini function declarations
+const SUPPORTED_SCHEMA_KEYS: &[&str] = &["type", "description", "enum", "properties", "required", "items", "nullable"];
+
+/// Gemini generateContent adapter
+pub struct GeminiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: String,
+}
+
+impl GeminiProvider {
+    pub fn new(client: reqwest::Client, base_url: &str, model: &str, api_key: String) -> Self {
+        GeminiProvider {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    fn request_body(&self, request: &ProviderRequest) -> Value {
+        let contents: Vec<Value> = request.messages.iter().map(message_to_wire).collect();
+        let declarations: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut declaration = json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                });
+                // Gemini rejects object schemas without properties
+                let has_properties = tool.input_schema["properties"]
+                    .as_object()
+                    .is_some_and(|properties| !properties.is_empty());
+                if has_properties {
+                    declaration["parameters"] = sanitize_schema(&tool.input_schema);
+                }
+                declaration
+            })
+            .collect();
+
+        json!({
+            "systemInstruction": { "parts": [{ "text": request.system }] },
+            "contents": contents,
+            "tools": [{ "functionDeclarations": declarations }],
+            "generationConfig": { "maxOutputTokens": request.max_tokens },
+        })
+    }
+}
+
+/// Strip the JSON Schema keywords Gemini does not understand
+fn sanitize_schema(schema: &Value) -> Value {
+    let Some(object) = schema.as_object() else {
+        return schema.clone();
+    };
+
+    let mut sanitized = Map::new();
+    for (key, value) in object {
+        if !SUPPORTED_SCHEMA_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let value = match key.as_str() {
+            "properties" => Value::Object(
+                value
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(name, property)| (name.clone(), sanitize_schema(property)))
+                    .collect(),
+            ),
+            "items" => sanitize_schema(value),
+            "required" if value.as_array().is_some_and(Vec::is_empty) => continue,
+            _ => value.clone(),
+        };
+        sanitized.insert(key.clone(), value);
+    }
+    Value::Object(sanitized)
+}
+
+fn message_to_wire(message: &ChatMessage) -> Value {
+    match message {
+        ChatMessage::User { text } => json!({ "role": "user", "parts": [{ "text": text }] }),
+        ChatMessage::Assistant { text, tool_calls } => {
+            let mut parts = Vec::new();
+            if !text.is_empty() {
+                parts.push(json!({ "text": text }));
+            }
+            for call in tool_calls {
+                parts.push(json!({ "functionCall": { "name": call.name, "args": call.arguments } }));
+            }
+            json!({ "role": "model", "parts": parts })
+        }
+        ChatMessage::ToolResults { results } => {
+            let parts: Vec<Value> = results
+                .iter()
+                .map(|result| {
+                    json!({
+                        "functionResponse": {
+                            "name": result.name,
+                            "response": { "content": result.content, "is_error": result.is_error },
+                        }
+                    })
+                })
+                .collect();
+            json!({ "role": "user", "parts": parts })
+        }
+    }
+}
+
+fn parse_response(body: &Value) -> ProviderResponse {
+    let mut response = ProviderResponse::default();
+    let parts = body["candidates"][0]["content"]["parts"].as_array().into_iter().flatten();
+    for part in parts {
+        if let Some(text) = part["text"].as_str() {
+            response.text.push_str(text);
+        }
+        if let Some(call) = part.get("functionCall") {
+            // Gemini has no call ids; number the calls within the turn
+            let name = call["name"].as_str().unwrap_or_default().to_string();
+            response.tool_calls.push(ToolCall {
+                id: format!("{}-{}", name, response.tool_calls.len()),
+                name,
+                arguments: call.get("args").cloned().unwrap_or_else(|| json!({})),
+            });
+        }
+    }
+    response
+}
+
+#[async_trait::async_trait]
+impl ChatProvider for GeminiProvider {
+    async fn complete(&self, request: &ProviderRequest) -> AppResult<ProviderResponse> {
+        let http_request = self
+            .client
+            .post(format!("{}/v1beta/models/{}:generateContent", self.base_url, self.model))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&self.request_body(request));
+
+        let body = send_json(http_request, "Gemini").await?;
+        Ok(parse_response(&body))
+    }
+
+    async fn verify(&self) -> Verification {
+        let probe = self
+            .client
+            .get(format!("{}/v1beta/models?pageSize=1", self.base_url))
+            .header("x-goog-api-key", &self.api_key);
+
+        // Gemini answers 400 (API_KEY_INVALID) for unknown keys
+        check_key(probe, "Gemini", &[400, 401, 403]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::library_tool_specs;
+    use crate::models::connector::ConnectorStatus;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_function_call_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1beta/models/test-model:generateContent"))
+            .and(header("x-goog-api-key", "g-test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{
+                    "content": {
+                        "role": "model",
+                        "parts": [
+                            {"functionCall": {"name": "search_books", "args": {"author": "Le Guin"}}},
+                            {"functionCall": {"name": "get_reading_statistics", "args": {}}}
+                        ]
+                    }
+                }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = GeminiProvider::new(reqwest::Client::new(), &server.uri(), "test-model", "g-test".to_string());
+        let response = provider
+            .complete(&ProviderRequest {
+                system: "be helpful".to_string(),
+                messages: vec![ChatMessage::User { text: "Le Guin?".to_string() }],
+                tools: library_tool_specs(),
+                max_tokens: 128,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.tool_calls.len(), 2);
+        assert_eq!(response.tool_calls[0].id, "search_books-0");
+        assert_eq!(response.tool_calls[0].arguments, json!({"author": "Le Guin"}));
+        assert_eq!(response.tool_calls[1].id, "get_reading_statistics-1");
+
+        let received = &server.received_requests().await.unwrap()[0];
+        let body: Value = serde_json::from_slice(&received.body).unwrap();
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "be helpful");
+        assert_eq!(body["contents"][0]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn test_verify_invalid_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1beta/models"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": {"code": 400, "message": "API key not valid. Please pass a valid API key.", "status": "INVALID_ARGUMENT"}
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = GeminiProvider::new(reqwest::Client::new(), &server.uri(), "test-model", "bad".to_string());
+        assert_eq!(provider.verify().await.status, ConnectorStatus::Invalid);
+    }
+
+    #[test]
+    fn test_sanitize_schema_drops_unsupported_keywords() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": {"type": "integer", "description": "max", "minimum": 1, "maximum": 500},
+                "tags": {"type": "array", "items": {"type": "string", "format": "x"}}
+            },
+            "required": []
+        });
+
+        assert_eq!(
+            sanitize_schema(&schema),
+            json!({
+                "type": "object",
+                "properties": {
+                    "limit": {"type": "integer", "description": "max"},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,461 @@
+pub mod anthropic;
+pub mod egress;
+pub mod gemini;
+pub mod openai;
+
+pub use anthropic::AnthropicProvider;
+pub use gemini::GeminiProvider;
+pub use openai::OpenAiProvider;
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use panicless_mcp_lib as mcp;
+
+use crate::{
+    config::Config,
+    crypto::TokenCrypto,
+    db::DbPool,
+    errors::{AppError, AppResult},
+    models::connector::{Connector, ConnectorStatus},
+};
+
+/// One turn of a provider-neutral conversation
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatMessage {
+    User { text: String },
+    Assistant { text: String, tool_calls: Vec<ToolCall> },
+    ToolResults { results: Vec<ToolResult> },
+}
+
+/// A function call requested by the model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// The outcome of running a tool call, sent back to the model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolResult {
+    pub call_id: String,
+    pub name: String,
+    pub content: String,
+    pub is_error: bool,
+}
+
+/// A function tool offered to the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+/// Everything a provider needs to produce the next assistant turn
+#[derive(Debug, Clone)]
+pub struct ProviderRequest {
+    pub system: String,
+    pub messages: Vec<ChatMessage>,
+    pub tools: Vec<ToolSpec>,
+    pub max_tokens: u32,
+}
+
+/// One assistant turn: text, tool calls, or both
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderResponse {
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Outcome of checking an API key with the provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    pub status: ConnectorStatus,
+    /// Provider message when the check did not succeed
+    pub detail: Option<String>,
+}
+
+/// A chat completion API (Anthropic, OpenAI, Gemini)
+/// Adapters translate the neutral request into the provider's wire format
+#[async_trait::async_trait]
+pub trait ChatProvider: Send + Sync {
+    async fn complete(&self, request: &ProviderRequest) -> AppResult<ProviderResponse>;
+
+    /// Check the key with a cheap authenticated call that generates no tokens
+    async fn verify(&self) -> Verification;
+}
+
+/// Executes the tool calls requested by the model
+#[async_trait::async_trait]
+pub trait ToolRunner: Send + Sync {
+    async fn run(&self, call: &ToolCall) -> ToolResult;
+}
+
+/// Runs the MCP library tools against the requesting user's library
+pub struct LibraryTools {
+    pub pool: DbPool,
+    pub user_id: i32,
+}
+
+#[async_trait::async_trait]
+impl ToolRunner for LibraryTools {
+    async fn run(&self, call: &ToolCall) -> ToolResult {
+        let outcome = mcp::tools::execute_tool(&self.pool, &call.name, Some(call.arguments.clone()), self.user_id).await;
+
+        let (content, is_error) = match outcome {
+            Ok(result) => {
+                let text = result
+                    .content
+                    .into_iter()
+                    .map(|item| match item {
+                        mcp::protocol::ContentItem::Text { text } => text,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                (text, result.is_error.unwrap_or(false))
+            }
+            Err(message) => (message, true),
+        };
+
+        ToolResult {
+            call_id: call.id.clone(),
+            name: call.name.clone(),
+            content,
+            is_error,
+        }
+    }
+}
+
+/// The MCP tool definitions, offered to the model as function tools
+pub fn library_tool_specs() -> Vec<ToolSpec> {
+    mcp::tools::get_tool_definitions()
+        .into_iter()
+        .map(|tool| ToolSpec {
+            name: tool.name,
+            description: tool.description,
+            input_schema: tool.input_schema,
+        })
+        .collect()
+}
+
+/// Build the adapter for a stored connector, decrypting its key
+pub fn provider_for(connector: &Connector, config: &Config) -> AppResult<Box<dyn ChatProvider>> {
+    let api_key = connector
+        .encrypted_token
+        .as_deref()
+        .map(|token| TokenCrypto::from_config(config)?.decrypt(token))
+        .transpose()?;
+    let key_required = || {
+        api_key.clone().ok_or_else(|| {
+            AppError::Internal(format!("Connector {} has no API key", connector.id))
+        })
+    };
+    let model = |default: &str| connector.model.clone().unwrap_or_else(|| default.to_string());
+
+    let builder = || reqwest::Client::builder().timeout(Duration::from_secs(config.chat_request_timeout_seconds));
+    let client = || {
+        builder()
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))
+    };
+
+    let adapter: Box<dyn ChatProvider> = match connector.provider.as_str() {
+        "anthropic" => Box::new(AnthropicProvider::new(
+            client()?,
+            &config.chat_anthropic_base_url,
+            &model(&config.chat_anthropic_model),
+            key_required()?,
+        )),
+        "chatgpt" => Box::new(OpenAiProvider::new(
+            client()?,
+            &config.chat_openai_base_url,
+            &model(&config.chat_openai_model),
+            Some(key_required()?),
+        )),
+        "gemini" => Box::new(GeminiProvider::new(
+            client()?,
+            &config.chat_gemini_base_url,
+            &model(&config.chat_gemini_model),
+            key_required()?,
+        )),
+        "custom" => {
+            let base_url = connector.base_url.as_deref().ok_or_else(|| {
+                AppError::Internal(format!("Custom connector {} has no base URL", connector.id))
+            })?;
+            // The user chose the endpoint: checked again on every connection
+            let client = egress::guarded_client(builder(), base_url, &config.custom_connector_allowed_hosts)
+                .map_err(AppError::Validation)?;
+            Box::new(OpenAiProvider::new(client, base_url, &model(""), api_key).named("Custom provider"))
+        }
+        other => {
+            return Err(AppError::Validation(format!(
+                "Provider '{}' does not support chat",
+                other
+            )))
+        }
+    };
+    Ok(adapter)
+}
+
+/// Progress of a conversation, streamed to the client as SSE events
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ChatEvent {
+    Message { text: String },
+    ToolCall(ToolCall),
+    ToolResult(ToolResult),
+    Done { rounds: u32 },
+    Error { message: String },
+}
+
+impl ChatEvent {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChatEvent::Message { .. } => "message",
+            ChatEvent::ToolCall(_) => "tool_call",
+            ChatEvent::ToolResult(_) => "tool_result",
+            ChatEvent::Done { .. } => "done",
+            ChatEvent::Error { .. } => "error",
+        }
+    }
+}
+
+/// Drive the model until it answers without calling tools
+/// Every step is reported on `events`; stops early if the receiver is gone
+pub async fn run_conversation(
+    provider: &dyn ChatProvider,
+    tools: &dyn ToolRunner,
+    mut request: ProviderRequest,
+    max_rounds: u32,
+    events: &mpsc::Sender<ChatEvent>,
+) -> AppResult<()> {
+    for round in 1..=max_rounds {
+        let response = provider.complete(&request).await?;
+
+        if !response.text.is_empty()
+            && events.send(ChatEvent::Message { text: response.text.clone() }).await.is_err()
+        {
+            return Ok(());
+        }
+
+        request.messages.push(ChatMessage::Assistant {
+            text: response.text,
+            tool_calls: response.tool_calls.clone(),
+        });
+
+        if response.tool_calls.is_empty() {
+            let _ = events.send(ChatEvent::Done { rounds: round }).await;
+            return Ok(());
+        }
+
+        let mut results = Vec::with_capacity(response.tool_calls.len());
+        for call in response.tool_calls {
+            if events.send(ChatEvent::ToolCall(call.clone())).await.is_err() {
+                return Ok(());
+            }
+            let result = tools.run(&call).await;
+            if events.send(ChatEvent::ToolResult(result.clone())).await.is_err() {
+                return Ok(());
+            }
+            results.push(result);
+        }
+        request.messages.push(ChatMessage::ToolResults { results });
+    }
+
+    Err(AppError::Validation(format!(
+        "The assistant did not finish within {} tool rounds",
+        max_rounds
+    )))
+}
+
+/// Send a JSON request and return the decoded body
+/// Non-2xx responses become errors carrying the provider's own message
+pub(crate) async fn send_json(request: reqwest::RequestBuilder, provider: &str) -> AppResult<Value> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AppError::Internal(format!("{} request failed: {}", provider, e)))?;
+
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+
+    if !status.is_success() {
+        let detail = body["error"]["message"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| body.to_string());
+        return Err(AppError::Internal(format!(
+            "{} API returned {}: {}",
+            provider,
+            status.as_u16(),
+            detail
+        )));
+    }
+
+    Ok(body)
+}
+
+/// Send an authenticated probe request and classify the answer
+/// `invalid_statuses` are the codes the provider uses for a rejected key
+pub(crate) async fn check_key(request: reqwest::RequestBuilder, provider: &str, invalid_statuses: &[u16]) -> Verification {
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            return Verification {
+                status: ConnectorStatus::Unreachable,
+                detail: Some(format!("{} request failed: {}", provider, e)),
+            }
+        }
+    };
+
+    let status = response.status();
+    if status.is_success() {
+        return Verification { status: ConnectorStatus::Valid, detail: None };
+    }
+
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    let detail = body["error"]["message"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{} API returned {}", provider, status.as_u16()));
+
+    let status = match status.as_u16() {
+        429 => ConnectorStatus::RateLimited,
+        code if invalid_statuses.contains(&code) => ConnectorStatus::Invalid,
+        _ => ConnectorStatus::Unreachable,
+    };
+    Verification { status, detail: Some(detail) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Replays canned responses and records the requests it saw
+    struct ScriptedProvider {
+        responses: Mutex<Vec<ProviderResponse>>,
+        seen: Mutex<Vec<ProviderRequest>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ChatProvider for ScriptedProvider {
+        async fn complete(&self, request: &ProviderRequest) -> AppResult<ProviderResponse> {
+            self.seen.lock().unwrap().push(request.clone());
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+
+        async fn verify(&self) -> Verification {
+            Verification { status: ConnectorStatus::Valid, detail: None }
+        }
+    }
+
+    struct EchoTools;
+
+    #[async_trait::async_trait]
+    impl ToolRunner for EchoTools {
+        async fn run(&self, call: &ToolCall) -> ToolResult {
+            ToolResult {
+                call_id: call.id.clone(),
+                name: call.name.clone(),
+                content: format!("ran {}", call.name),
+                is_error: false,
+            }
+        }
+    }
+
+    fn request() -> ProviderRequest {
+        ProviderRequest {
+            system: "system".to_string(),
+            messages: vec![ChatMessage::User { text: "How many books?".to_string() }],
+            tools: library_tool_specs(),
+            max_tokens: 256,
+        }
+    }
+
+    fn tool_call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "get_reading_statistics".to_string(),
+            arguments: json!({}),
+        }
+    }
+
+    async fn collect(mut rx: mpsc::Receiver<ChatEvent>) -> Vec<ChatEvent> {
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_conversation_runs_tools_until_final_answer() {
+        let provider = ScriptedProvider {
+            responses: Mutex::new(vec![
+                ProviderResponse { text: String::new(), tool_calls: vec![tool_call("c1")] },
+                ProviderResponse { text: "You have 3 books.".to_string(), tool_calls: vec![] },
+            ]),
+            seen: Mutex::new(Vec::new()),
+        };
+        let (tx, rx) = mpsc::channel(16);
+
+        run_conversation(&provider, &EchoTools, request(), 4, &tx).await.unwrap();
+        drop(tx);
+        let events = collect(rx).await;
+
+        assert_eq!(events.iter().map(ChatEvent::name).collect::<Vec<_>>(), vec!["tool_call", "tool_result", "message", "done"]);
+        assert_eq!(events[3], ChatEvent::Done { rounds: 2 });
+
+        // The second round sees the assistant's call and its result
+        let seen = provider.seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[1].messages.len(), 3);
+        match &seen[1].messages[2] {
+            ChatMessage::ToolResults { results } => {
+                assert_eq!(results[0].call_id, "c1");
+                assert_eq!(results[0].content, "ran get_reading_statistics");
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conversation_stops_at_round_limit() {
+        let provider = ScriptedProvider {
+            responses: Mutex::new(vec![
+                ProviderResponse { text: String::new(), tool_calls: vec![tool_call("c1")] },
+                ProviderResponse { text: String::new(), tool_calls: vec![tool_call("c2")] },
+            ]),
+            seen: Mutex::new(Vec::new()),
+        };
+        let (tx, _rx) = mpsc::channel(16);
+
+        let result = run_conversation(&provider, &EchoTools, request(), 2, &tx).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_library_tool_specs_match_mcp_tools() {
+        let specs = library_tool_specs();
+        assert_eq!(specs.len(), mcp::tools::get_tool_definitions().len());
+        assert!(specs.iter().any(|spec| spec.name == "search_books"));
+        assert!(specs.iter().all(|spec| spec.input_schema["type"] == "object"));
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = ChatEvent::ToolCall(tool_call("c1"));
+        assert_eq!(event.name(), "tool_call");
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({"id": "c1", "name": "get_reading_statistics", "arguments": {}})
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use serde_json::{json, Value};
+
+use super::{check_key, send_json, ChatMessage, ChatProvider, ProviderRequest, ProviderResponse, ToolCall, Verification};
+use crate::errors::AppResult;
+
+/// OpenAI Chat Completions adapter
+/// Also speaks to any server exposing the same API
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    /// Name used in error messages
+    name: &'static str,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiProvider {
+    pub fn new(client: reqwest::Client, base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        OpenAiProvider {
+            client,
+            name: "OpenAI",
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    /// Name the server in error messages (for OpenAI-compatible servers)
+    pub fn named(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    fn request_body(&self, request: &ProviderRequest) -> Value {
+        let mut messages = vec![json!({ "role": "system", "content": request.system })];
+        for message in &request.messages {
+            messages.extend(message_to_wire(message));
+        }
+
+        let tools: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema,
+                    }
+                })
+            })
+            .collect();
+
+        json!({
+            "model": self.model,
+            "max_tokens": request.max_tokens,
+            "messages": messages,
+            "tools": tools,
+        })
+    }
+}
+
+/// Tool results become one `tool` message per call
+fn message_to_wire(message: &ChatMessage) -> Vec<Value> {
+    match message {
+        ChatMessage::User { text } => vec![json!({ "role": "user", "content": text })],
+        ChatMessage::Assistant { text, tool_calls } => {
+            let mut wire = json!({
+                "role": "assistant",
+                "content": if text.is_empty() { Value::Null } else { json!(text) },
+            });
+            if !tool_calls.is_empty() {
+                wire["tool_calls"] = tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": {
+                                "name": call.name,
+                                "arguments": call.arguments.to_string(),
+                            }
+                        })
+                    })
+                    .collect();
+            }
+            vec![wire]
+        }
+        ChatMessage::ToolResults { results } => results
+            .iter()
+            .map(|result| {
+                json!({
+                    "role": "tool",
+                    "tool_call_id": result.call_id,
+                    "content": result.content,
+                })
+            })
+            .collect(),
+    }
+}
+
+fn parse_response(body: &Value) -> ProviderResponse {
+    let message = &body["choices"][0]["message"];
+    let tool_calls = message["tool_calls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|call| {
+            // Arguments arrive as a JSON-encoded string
+            let arguments = call["function"]["arguments"]
+                .as_str()
+                .and_then(|raw| serde_json::from_str(raw).ok())
+                .unwrap_or_else(|| json!({}));
+            ToolCall {
+                id: call["id"].as_str().unwrap_or_default().to_string(),
+                name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                arguments,
+            }
+        })
+        .collect();
+
+    ProviderResponse {
+        text: message["content"].as_str().unwrap_or_default().to_string(),
+        tool_calls,
+    }
+}
+
+#[async_trait::async_trait]
+impl ChatProvider for OpenAiProvider {
+    async fn complete(&self, request: &ProviderRequest) -> AppResult<ProviderResponse> {
+        let mut http_request = self
+            .client
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .json(&self.request_body(request));
+        if let Some(api_key) = &self.api_key {
+            http_request = http_request.bearer_auth(api_key);
+        }
+
+        let body = send_json(http_request, self.name).await?;
+        Ok(parse_response(&body))
+    }
+
+    async fn verify(&self) -> Verification {
+        let mut probe = self.client.get(format!("{}/v1/models", self.base_url));
+        if let Some(api_key) = &self.api_key {
+            probe = probe.bearer_auth(api_key);
+        }
+
+        check_key(probe, self.name, &[401, 403]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{library_tool_specs, ToolResult};
+    use crate::models::connector::ConnectorStatus;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn request(messages: Vec<ChatMessage>) -> ProviderRequest {
+        ProviderRequest {
+            system: "be helpful".to_string(),
+            messages,
+            tools: library_tool_specs(),
+            max_tokens: 128,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_partial_json(json!({"model": "test-model", "messages": [{"role": "system", "content": "be helpful"}]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {"name": "list_readings", "arguments": "{\"status\":\"reading\"}"}
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiProvider::new(reqwest::Client::new(), &server.uri(), "test-model", Some("sk-test".to_string()));
+        let response = provider
+            .complete(&request(vec![ChatMessage::User { text: "What am I reading?".to_string() }]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "");
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].name, "list_readings");
+        assert_eq!(response.tool_calls[0].arguments, json!({"status": "reading"}));
+    }
+
+    #[tokio::test]
+    async fn test_text_response_without_api_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"message": {"role": "assistant", "content": "Hello!"}}]
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiProvider::new(reqwest::Client::new(), &server.uri(), "local", None);
+        let response = provider
+            .complete(&request(vec![ChatMessage::User { text: "hi".to_string() }]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "Hello!");
+        assert!(response.tool_calls.is_empty());
+        let received = &server.received_requests().await.unwrap()[0];
+        assert!(received.headers.get("authorization").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_revoked_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("authorization", "Bearer sk-revoked"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": {"message": "Incorrect API key provided", "code": "invalid_api_key"}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiProvider::new(reqwest::Client::new(), &server.uri(), "test-model", Some("sk-revoked".to_string()));
+        let verification = provider.verify().await;
+        assert_eq!(verification.status, ConnectorStatus::Invalid);
+        assert_eq!(verification.detail.as_deref(), Some("Incorrect API key provided"));
+    }
+
+    #[test]
+    fn test_tool_results_become_tool_messages() {
+        let result = |id: &str| ToolResult {
+            call_id: id.to_string(),
+            name: "search_books".to_string(),
+            content: "[]".to_string(),
+            is_error: false,
+        };
+        let wire = message_to_wire(&ChatMessage::ToolResults { results: vec![result("a"), result("b")] });
+        assert_eq!(wire.len(), 2);
+        assert_eq!(wire[1]["role"], "tool");
+        assert_eq!(wire[1]["tool_call_id"], "b");
+
+        let call = ToolCall { id: "a".to_string(), name: "search_books".to_string(), arguments: json!({"query": "x"}) };
+        let assistant = message_to_wire(&ChatMessage::Assistant { text: String::new(), tool_calls: vec![call] });
+        assert_eq!(assistant[0]["content"], Value::Null);
+        assert_eq!(assistant[0]["tool_calls"][0]["function"]["arguments"], "{\"query\":\"x\"}");
+    }
+}
@@ -1,5 +1,7 @@
 use std::env;
 
+use ipnet::IpNet;
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub database_url: String,
@@ -11,9 +13,56 @@ pub struct Config {
     pub cors_allowed_origins: Vec<String>,
     pub environment: String,
     pub encryption_key: String,
+    pub encryption_old_keys: Vec<String>,
     pub oauth_client_id: String,
     pub oauth_client_secret: String,
     pub public_base_url: Option<String>,
+    pub oidc_signing_alg: String,
+    pub oidc_key_rotation_days: i64,
+    pub oidc_id_token_expiry: i64,
+    pub mail_transport: String,
+    pub mail_from: String,
+    pub mail_file_dir: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_tls: String,
+    pub password_reset_token_expiry: i64,
+    pub email_verification_token_expiry: i64,
+    pub totp_issuer: String,
+    pub mfa_challenge_expiry: i64,
+    pub rate_limit_enabled: bool,
+    pub rate_limit_backend: String,
+    pub rate_limit_auth_per_minute: u32,
+    pub rate_limit_oauth_per_minute: u32,
+    pub rate_limit_api_per_minute: u32,
+    pub rate_limit_import_per_hour: u32,
+    pub trusted_proxies: Vec<IpNet>,
+    pub login_lockout_threshold: u32,
+    pub login_lockout_base_seconds: i64,
+    pub login_lockout_max_seconds: i64,
+    pub chat_anthropic_base_url: String,
+    pub chat_anthropic_model: String,
+    pub chat_openai_base_url: String,
+    pub chat_openai_model: String,
+    pub chat_gemini_base_url: String,
+    pub chat_gemini_model: String,
+    pub chat_max_tool_rounds: u32,
+    pub chat_max_tokens: u32,
+    pub chat_request_timeout_seconds: u64,
+    pub connector_verify_interval_hours: u64,
+    pub custom_connector_allowed_hosts: Vec<String>,
+    pub instance_id: String,
+    pub calibre_library_path: Option<String>,
+    pub calibre_content_server_url: Option<String>,
+    pub cover_storage: String,
+    pub cover_storage_dir: String,
+    pub cover_s3_endpoint: Option<String>,
+    pub cover_s3_bucket: Option<String>,
+    pub cover_s3_region: String,
+    pub cover_s3_access_key_id: Option<String>,
+    pub cover_s3_secret_access_key: Option<String>,
 }
 
 impl Config {
@@ -53,6 +102,14 @@ impl Config {
         let encryption_key = env::var("ENCRYPTION_KEY")
             .expect("ENCRYPTION_KEY must be set");
 
+        // Previous keys, still accepted for decryption during a key rotation
+        let encryption_old_keys = env::var("ENCRYPTION_OLD_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
         let oauth_client_id = env::var("OAUTH_CLIENT_ID")
             .unwrap_or_else(|_| "panicless-library-dev".to_string());
 
@@ -61,6 +118,208 @@ impl Config {
 
         let public_base_url = env::var("PUBLIC_BASE_URL").ok();
 
+        let oidc_signing_alg = env::var("OIDC_SIGNING_ALG")
+            .unwrap_or_else(|_| "RS256".to_string());
+        crate::crypto::signing::SigningAlgorithm::parse(&oidc_signing_alg)?;
+
+        let oidc_key_rotation_days = env::var("OIDC_KEY_ROTATION_DAYS")
+            .unwrap_or_else(|_| "90".to_string())
+            .parse::<i64>()?;
+
+        let oidc_id_token_expiry = env::var("OIDC_ID_TOKEN_EXPIRY")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<i64>()?;
+
+        let mail_transport = env::var("MAIL_TRANSPORT")
+            .unwrap_or_else(|_| "log".to_string());
+        if !["log", "file", "smtp"].contains(&mail_transport.as_str()) {
+            return Err(format!(
+                "Invalid MAIL_TRANSPORT '{}'. Must be one of: log, file, smtp",
+                mail_transport
+            ).into());
+        }
+
+        let mail_from = env::var("MAIL_FROM")
+            .unwrap_or_else(|_| "Panicless Library <noreply@localhost>".to_string());
+
+        let mail_file_dir = env::var("MAIL_FILE_DIR")
+            .unwrap_or_else(|_| "./mail".to_string());
+
+        let smtp_host = env::var("SMTP_HOST")
+            .unwrap_or_else(|_| "localhost".to_string());
+
+        let smtp_tls = env::var("SMTP_TLS")
+            .unwrap_or_else(|_| "starttls".to_string());
+        if !["tls", "starttls", "none"].contains(&smtp_tls.as_str()) {
+            return Err(format!(
+                "Invalid SMTP_TLS '{}'. Must be one of: tls, starttls, none",
+                smtp_tls
+            ).into());
+        }
+
+        let smtp_port = env::var("SMTP_PORT")
+            .unwrap_or_else(|_| match smtp_tls.as_str() {
+                "tls" => "465".to_string(),
+                "starttls" => "587".to_string(),
+                _ => "25".to_string(),
+            })
+            .parse::<u16>()?;
+
+        let smtp_username = env::var("SMTP_USERNAME").ok().filter(|v| !v.is_empty());
+        let smtp_password = env::var("SMTP_PASSWORD").ok().filter(|v| !v.is_empty());
+
+        let password_reset_token_expiry = env::var("PASSWORD_RESET_TOKEN_EXPIRY")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<i64>()?;
+
+        let email_verification_token_expiry = env::var("EMAIL_VERIFICATION_TOKEN_EXPIRY")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse::<i64>()?;
+
+        let totp_issuer = env::var("TOTP_ISSUER")
+            .unwrap_or_else(|_| "Panicless Library".to_string());
+
+        let mfa_challenge_expiry = env::var("MFA_CHALLENGE_EXPIRY")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<i64>()?;
+
+        let rate_limit_enabled = env::var("RATE_LIMIT_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()?;
+
+        let rate_limit_backend = env::var("RATE_LIMIT_BACKEND")
+            .unwrap_or_else(|_| "memory".to_string());
+        if !["memory", "postgres"].contains(&rate_limit_backend.as_str()) {
+            return Err(format!(
+                "Invalid RATE_LIMIT_BACKEND '{}'. Must be one of: memory, postgres",
+                rate_limit_backend
+            ).into());
+        }
+
+        let rate_limit_auth_per_minute = env::var("RATE_LIMIT_AUTH_PER_MINUTE")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse::<u32>()?;
+
+        let rate_limit_oauth_per_minute = env::var("RATE_LIMIT_OAUTH_PER_MINUTE")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u32>()?;
+
+        let rate_limit_api_per_minute = env::var("RATE_LIMIT_API_PER_MINUTE")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<u32>()?;
+
+        let rate_limit_import_per_hour = env::var("RATE_LIMIT_IMPORT_PER_HOUR")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse::<u32>()?;
+
+        // Proxies whose X-Forwarded-For / X-Real-IP headers name the client; empty trusts none
+        let trusted_proxies = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<IpNet>()
+                    .or_else(|_| s.parse::<std::net::IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("Invalid TRUSTED_PROXIES entry '{}'. Expected an IP address or CIDR range", s))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let login_lockout_threshold = env::var("LOGIN_LOCKOUT_THRESHOLD")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u32>()?;
+
+        let login_lockout_base_seconds = env::var("LOGIN_LOCKOUT_BASE_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<i64>()?;
+
+        let login_lockout_max_seconds = env::var("LOGIN_LOCKOUT_MAX_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<i64>()?;
+
+        let chat_anthropic_base_url = env::var("CHAT_ANTHROPIC_BASE_URL")
+            .unwrap_or_else(|_| "https://api.anthropic.com".to_string());
+
+        let chat_anthropic_model = env::var("CHAT_ANTHROPIC_MODEL")
+            .unwrap_or_else(|_| "claude-3-5-haiku-latest".to_string());
+
+        let chat_openai_base_url = env::var("CHAT_OPENAI_BASE_URL")
+            .unwrap_or_else(|_| "https://api.openai.com".to_string());
+
+        let chat_openai_model = env::var("CHAT_OPENAI_MODEL")
+            .unwrap_or_else(|_| "gpt-4o-mini".to_string());
+
+        let chat_gemini_base_url = env::var("CHAT_GEMINI_BASE_URL")
+            .unwrap_or_else(|_| "https://generativelanguage.googleapis.com".to_string());
+
+        let chat_gemini_model = env::var("CHAT_GEMINI_MODEL")
+            .unwrap_or_else(|_| "gemini-2.0-flash".to_string());
+
+        let chat_max_tool_rounds = env::var("CHAT_MAX_TOOL_ROUNDS")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse::<u32>()?;
+
+        let chat_max_tokens = env::var("CHAT_MAX_TOKENS")
+            .unwrap_or_else(|_| "1024".to_string())
+            .parse::<u32>()?;
+
+        let chat_request_timeout_seconds = env::var("CHAT_REQUEST_TIMEOUT_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()?;
+
+        // Re-check stored connector keys this often; 0 disables the background check
+        let connector_verify_interval_hours = env::var("CONNECTOR_VERIFY_INTERVAL_HOURS")
+            .unwrap_or_else(|_| "24".to_string())
+            .parse::<u64>()?;
+
+        // Hosts custom (OpenAI-compatible) connectors may point to, private ones included;
+        // empty allows any host with public addresses only
+        let custom_connector_allowed_hosts = env::var("CUSTOM_CONNECTOR_ALLOWED_HOSTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        // Name of this server among replicas sharing the database; import jobs are tagged
+        // with it. Defaults to the hostname, which differs between containers
+        let instance_id = ["INSTANCE_ID", "HOSTNAME"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok())
+            .map(|s| s.trim().to_string())
+            .find(|s| !s.is_empty())
+            .unwrap_or_else(|| "panicless".to_string());
+
+        // Calibre library on the server's disk, and the content server that serves its covers
+        let calibre_library_path = env::var("CALIBRE_LIBRARY_PATH").ok().filter(|s| !s.trim().is_empty());
+        let calibre_content_server_url = env::var("CALIBRE_CONTENT_SERVER_URL")
+            .ok()
+            .map(|s| s.trim().trim_end_matches('/').to_string())
+            .filter(|s| !s.is_empty());
+
+        // Where uploaded covers are kept: a local directory or an S3-compatible bucket
+        let cover_storage = env::var("COVER_STORAGE")
+            .unwrap_or_else(|_| "local".to_string());
+        if !["local", "s3"].contains(&cover_storage.as_str()) {
+            return Err(format!(
+                "Invalid COVER_STORAGE '{}'. Must be one of: local, s3",
+                cover_storage
+            ).into());
+        }
+
+        let cover_storage_dir = env::var("COVER_STORAGE_DIR")
+            .unwrap_or_else(|_| "./covers".to_string());
+
+        let cover_s3_endpoint = env::var("COVER_S3_ENDPOINT")
+            .ok()
+            .map(|s| s.trim().trim_end_matches('/').to_string())
+            .filter(|s| !s.is_empty());
+        let cover_s3_bucket = env::var("COVER_S3_BUCKET").ok().filter(|s| !s.trim().is_empty());
+        let cover_s3_region = env::var("COVER_S3_REGION")
+            .unwrap_or_else(|_| "us-east-1".to_string());
+        let cover_s3_access_key_id = env::var("COVER_S3_ACCESS_KEY_ID").ok().filter(|s| !s.is_empty());
+        let cover_s3_secret_access_key = env::var("COVER_S3_SECRET_ACCESS_KEY").ok().filter(|s| !s.is_empty());
+
         Ok(Config {
             database_url,
             jwt_secret,
@@ -71,9 +330,56 @@ impl Config {
             cors_allowed_origins,
             environment,
             encryption_key,
+            encryption_old_keys,
             oauth_client_id,
             oauth_client_secret,
             public_base_url,
+            oidc_signing_alg,
+            oidc_key_rotation_days,
+            oidc_id_token_expiry,
+            mail_transport,
+            mail_from,
+            mail_file_dir,
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            smtp_tls,
+            password_reset_token_expiry,
+            email_verification_token_expiry,
+            totp_issuer,
+            mfa_challenge_expiry,
+            rate_limit_enabled,
+            rate_limit_backend,
+            rate_limit_auth_per_minute,
+            rate_limit_oauth_per_minute,
+            rate_limit_api_per_minute,
+            rate_limit_import_per_hour,
+            trusted_proxies,
+            login_lockout_threshold,
+            login_lockout_base_seconds,
+            login_lockout_max_seconds,
+            chat_anthropic_base_url,
+            chat_anthropic_model,
+            chat_openai_base_url,
+            chat_openai_model,
+            chat_gemini_base_url,
+            chat_gemini_model,
+            chat_max_tool_rounds,
+            chat_max_tokens,
+            chat_request_timeout_seconds,
+            connector_verify_interval_hours,
+            custom_connector_allowed_hosts,
+            instance_id,
+            calibre_library_path,
+            calibre_content_server_url,
+            cover_storage,
+            cover_storage_dir,
+            cover_s3_endpoint,
+            cover_s3_bucket,
+            cover_s3_region,
+            cover_s3_access_key_id,
+            cover_s3_secret_access_key,
         })
     }
 
@@ -92,3 +398,71 @@ impl Config {
             .unwrap_or_else(|| format!("http://{}:{}", self.server_host, self.server_port))
     }
 }
+
+#[cfg(test)]
+impl Config {
+    /// The defaults of `from_env`, with fixed secrets and no environment read
+    pub fn for_tests() -> Self {
+        Config {
+            database_url: String::new(),
+            jwt_secret: "test-jwt-secret".to_string(),
+            jwt_access_token_expiry: 3600,
+            jwt_refresh_token_expiry: 604800,
+            server_host: "127.0.0.1".to_string(),
+            server_port: 8080,
+            cors_allowed_origins: vec!["http://localhost:5173".to_string()],
+            environment: "development".to_string(),
+            encryption_key: "M0bYJ6vy95B/Gy5VmiOaJd7clzhB7jMWACzr1kMDpHo=".to_string(),
+            encryption_old_keys: Vec::new(),
+            oauth_client_id: "panicless-library-dev".to_string(),
+            oauth_client_secret: "dev-secret-change-in-production".to_string(),
+            public_base_url: None,
+            oidc_signing_alg: "RS256".to_string(),
+            oidc_key_rotation_days: 90,
+            oidc_id_token_expiry: 3600,
+            mail_transport: "log".to_string(),
+            mail_from: "Panicless Library <noreply@localhost>".to_string(),
+            mail_file_dir: "./mail".to_string(),
+            smtp_host: "localhost".to_string(),
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            smtp_tls: "starttls".to_string(),
+            password_reset_token_expiry: 3600,
+            email_verification_token_expiry: 86400,
+            totp_issuer: "Panicless Library".to_string(),
+            mfa_challenge_expiry: 300,
+            rate_limit_enabled: false,
+            rate_limit_backend: "memory".to_string(),
+            rate_limit_auth_per_minute: 10,
+            rate_limit_oauth_per_minute: 30,
+            rate_limit_api_per_minute: 300,
+            rate_limit_import_per_hour: 10,
+            trusted_proxies: Vec::new(),
+            login_lockout_threshold: 5,
+            login_lockout_base_seconds: 60,
+            login_lockout_max_seconds: 3600,
+            chat_anthropic_base_url: "https://api.anthropic.com".to_string(),
+            chat_anthropic_model: "claude-3-5-haiku-latest".to_string(),
+            chat_openai_base_url: "https://api.openai.com".to_string(),
+            chat_openai_model: "gpt-4o-mini".to_string(),
+            chat_gemini_base_url: "https://generativelanguage.googleapis.com".to_string(),
+            chat_gemini_model: "gemini-2.0-flash".to_string(),
+            chat_max_tool_rounds: 8,
+            chat_max_tokens: 1024,
+            chat_request_timeout_seconds: 60,
+            connector_verify_interval_hours: 0,
+            custom_connector_allowed_hosts: Vec::new(),
+            instance_id: "panicless".to_string(),
+            calibre_library_path: None,
+            calibre_content_server_url: None,
+            cover_storage: "local".to_string(),
+            cover_storage_dir: "./covers".to_string(),
+            cover_s3_endpoint: None,
+            cover_s3_bucket: None,
+            cover_s3_region: "us-east-1".to_string(),
+            cover_s3_access_key_id: None,
+            cover_s3_secret_access_key: None,
+        }
+    }
+}
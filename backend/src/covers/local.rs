@@ -0,0 +1,87 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::CoverStore;
+use crate::errors::{AppError, AppResult};
+
+/// Cover store keeping each object as a file under a directory
+/// Default storage; the directory is created on the first upload
+pub struct LocalCoverStore {
+    dir: PathBuf,
+}
+
+impl LocalCoverStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The file of a key, refusing keys that would leave the directory
+    fn path(&self, key: &str) -> AppResult<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(AppError::Internal(format!("Invalid cover key '{}'", key)));
+        }
+        Ok(self.dir.join(relative))
+    }
+}
+
+#[async_trait::async_trait]
+impl CoverStore for LocalCoverStore {
+    async fn put(&self, key: &str, data: &[u8], _content_type: &str) -> AppResult<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to create cover directory: {}", e)))?;
+        }
+
+        // Written aside and renamed, so readers never see half a file
+        let partial = path.with_extension("part");
+        tokio::fs::write(&partial, data)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to write cover: {}", e)))?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to write cover: {}", e)))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> AppResult<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AppError::Internal(format!("Failed to read cover: {}", e))),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> AppResult<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(AppError::Internal(format!("Failed to delete cover: {}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("panicless-covers-{:016x}", rand::random::<u64>()));
+        let store = LocalCoverStore::new(&dir);
+
+        store.put("4/12/abc/original.png", b"png bytes", "image/png").await.unwrap();
+        assert_eq!(store.get("4/12/abc/original.png").await.unwrap(), Some(b"png bytes".to_vec()));
+        assert_eq!(store.get("4/12/abc/medium.jpg").await.unwrap(), None);
+
+        store.delete("4/12/abc/original.png").await.unwrap();
+        store.delete("4/12/abc/original.png").await.unwrap();
+        assert_eq!(store.get("4/12/abc/original.png").await.unwrap(), None);
+
+        assert!(store.put("../outside.png", b"x", "image/png").await.is_err());
+        assert!(store.get("/etc/passwd").await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
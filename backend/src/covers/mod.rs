@@ -0,0 +1,36 @@
+pub mod local;
+pub mod s3;
+pub mod variants;
+
+pub use local::LocalCoverStore;
+pub use s3::S3CoverStore;
+pub use variants::{decode_limited, process_cover, CoverSize, DecodeError, ProcessedCover};
+
+use std::sync::Arc;
+
+use crate::{config::Config, errors::AppResult};
+
+/// Stores uploaded cover images by key
+/// Keys are generated by the server and look like "4/1021/9f86d081884c7d65/medium.jpg"
+#[async_trait::async_trait]
+pub trait CoverStore: Send + Sync {
+    async fn put(&self, key: &str, data: &[u8], content_type: &str) -> AppResult<()>;
+
+    /// None when no object has this key
+    async fn get(&self, key: &str) -> AppResult<Option<Vec<u8>>>;
+
+    /// Deleting a missing object is not an error
+    async fn delete(&self, key: &str) -> AppResult<()>;
+}
+
+/// Shared cover store handle stored in the application state
+pub type SharedCoverStore = Arc<dyn CoverStore>;
+
+/// Build the cover store selected by COVER_STORAGE
+pub fn from_config(config: &Config) -> AppResult<SharedCoverStore> {
+    let store: SharedCoverStore = match config.cover_storage.as_str() {
+        "s3" => Arc::new(S3CoverStore::new(config)?),
+        _ => Arc::new(LocalCoverStore::new(&config.cover_storage_dir)),
+    };
+    Ok(store)
+}
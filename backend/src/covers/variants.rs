@@ -0,0 +1,182 @@
+use std::io::Cursor;
+
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat, ImageReader, Limits};
+use serde::Deserialize;
+
+/// Largest width or height of an uploaded image, in pixels
+const MAX_DIMENSION: u32 = 10_000;
+
+/// Memory the decoder may allocate for one uploaded image
+const MAX_DECODE_ALLOC: u64 = 512 * 1024 * 1024;
+
+/// Cover formats, as listed in the error for other files
+const COVER_FORMATS: [ImageFormat; 4] = [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP, ImageFormat::Gif];
+
+const JPEG_QUALITY: u8 = 85;
+
+/// The sizes a cover is served in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverSize {
+    /// Fits 160×240, for lists
+    Thumbnail,
+    /// Fits 400×600, for detail pages
+    Medium,
+    /// The uploaded file as is
+    #[default]
+    Original,
+}
+
+impl CoverSize {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoverSize::Thumbnail => "thumbnail",
+            CoverSize::Medium => "medium",
+            CoverSize::Original => "original",
+        }
+    }
+
+    /// Bounding box of the resized variants
+    fn bounds(&self) -> Option<(u32, u32)> {
+        match self {
+            CoverSize::Thumbnail => Some((160, 240)),
+            CoverSize::Medium => Some((400, 600)),
+            CoverSize::Original => None,
+        }
+    }
+}
+
+/// An uploaded cover checked and resized
+/// The original is kept as uploaded; the variants are JPEGs.
+#[derive(Debug)]
+pub struct ProcessedCover {
+    /// Media type of the original, from its content rather than what the client claimed
+    pub content_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub medium: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+}
+
+/// Why an uploaded image could not be decoded
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Not in one of the accepted formats
+    Format,
+    /// Unreadable, or over the size limits
+    Image(image::ImageError),
+}
+
+/// Decode an uploaded image, within the limits every upload is held to
+/// The format is told from the content, not from what the client claimed, and must be
+/// one of `allowed_formats`. Decoding is CPU bound; call it from a blocking task.
+pub fn decode_limited(data: &[u8], allowed_formats: &[ImageFormat]) -> Result<(ImageFormat, DynamicImage), DecodeError> {
+    let format = image::guess_format(data)
+        .ok()
+        .filter(|format| allowed_formats.contains(format))
+        .ok_or(DecodeError::Format)?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(DecodeError::Image)?;
+
+    Ok((format, image))
+}
+
+/// Check that an upload is a JPEG, PNG, WebP or GIF image and make its variants
+/// Decoding and resizing are CPU bound; call it from a blocking task.
+pub fn process_cover(data: &[u8]) -> Result<ProcessedCover, String> {
+    let (format, image) = decode_limited(data, &COVER_FORMATS).map_err(|e| match e {
+        DecodeError::Format => "Covers must be JPEG, PNG, WebP or GIF images".to_string(),
+        DecodeError::Image(e) => format!("The cover image could not be read: {}", e),
+    })?;
+
+    Ok(ProcessedCover {
+        content_type: format.to_mime_type(),
+        width: image.width(),
+        height: image.height(),
+        medium: variant(&image, CoverSize::Medium)?,
+        thumbnail: variant(&image, CoverSize::Thumbnail)?,
+    })
+}
+
+/// The image scaled down to fit the size's bounds, as a JPEG
+/// Images already smaller are only re-encoded.
+fn variant(image: &DynamicImage, size: CoverSize) -> Result<Vec<u8>, String> {
+    let (max_width, max_height) = size.bounds().unwrap_or((image.width(), image.height()));
+    let resized = if image.width() > max_width || image.height() > max_height {
+        image.resize(max_width, max_height, FilterType::Lanczos3)
+    } else {
+        image.clone()
+    };
+
+    // JPEG has no alpha channel
+    let mut encoded = Vec::new();
+    DynamicImage::ImageRgb8(resized.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY))
+        .map_err(|e| format!("Failed to encode the {} cover: {}", size.as_str(), e))?;
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgba};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgba([200u8, 40, 40, 128]));
+        let mut data = Vec::new();
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn dimensions(jpeg: &[u8]) -> (u32, u32) {
+        assert_eq!(image::guess_format(jpeg).unwrap(), ImageFormat::Jpeg);
+        let image = image::load_from_memory(jpeg).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn test_process_cover_makes_variants() {
+        let cover = process_cover(&png(800, 1000)).unwrap();
+        assert_eq!(cover.content_type, "image/png");
+        assert_eq!((cover.width, cover.height), (800, 1000));
+        assert_eq!(dimensions(&cover.medium), (400, 500));
+        assert_eq!(dimensions(&cover.thumbnail), (160, 200));
+
+        // Small images are not enlarged
+        let cover = process_cover(&png(100, 150)).unwrap();
+        assert_eq!(dimensions(&cover.medium), (100, 150));
+        assert_eq!(dimensions(&cover.thumbnail), (100, 150));
+    }
+
+    #[test]
+    fn test_process_cover_rejects_non_images() {
+        assert!(process_cover(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap_err().contains("JPEG, PNG"));
+        assert!(process_cover(b"%PDF-1.7").is_err());
+
+        // Right magic bytes, broken image
+        let truncated = &png(50, 50)[..40];
+        assert!(process_cover(truncated).unwrap_err().contains("could not be read"));
+    }
+
+    #[test]
+    fn test_decode_limited() {
+        let (format, image) = decode_limited(&png(20, 10), &[ImageFormat::Png]).unwrap();
+        assert_eq!((format, image.width(), image.height()), (ImageFormat::Png, 20, 10));
+        assert!(matches!(decode_limited(&png(20, 10), &[ImageFormat::Jpeg]), Err(DecodeError::Format)));
+
+        // Too wide to decode, however small the file
+        assert!(matches!(
+            decode_limited(&png(MAX_DIMENSION + 1, 1), &COVER_FORMATS),
+            Err(DecodeError::Image(_))
+        ));
+    }
+}
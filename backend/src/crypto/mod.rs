@@ -1,20 +1,40 @@
+pub mod signing;
+pub mod totp;
+
 use chacha20poly1305::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
     ChaCha20Poly1305, Key, Nonce,
 };
-use crate::errors::{AppError, AppResult};
+use base64::{engine::general_purpose, Engine as _};
+use sha2::{Digest, Sha256};
+use crate::{
+    config::Config,
+    errors::{AppError, AppResult},
+};
+
+/// Prefix of versioned ciphertexts: "v1:<key id>:<base64(nonce + ciphertext)>"
+/// Ciphertexts without it were written before key ids existed.
+const CIPHERTEXT_VERSION: &str = "v1";
 
 /// TokenCrypto handles encryption and decryption of sensitive tokens
 /// using ChaCha20-Poly1305 (AEAD cipher)
+///
+/// It is a keyring: new ciphertexts are always written with the active key
+/// and name it by key id, while older keys are kept for decryption only so
+/// that the active key can be rotated without losing stored secrets.
 pub struct TokenCrypto {
+    active: KeyEntry,
+    decrypt_only: Vec<KeyEntry>,
+}
+
+struct KeyEntry {
+    id: String,
     cipher: ChaCha20Poly1305,
 }
 
-impl TokenCrypto {
-    /// Creates a new TokenCrypto instance from a base64-encoded key
-    /// The key must be 32 bytes (44 characters when base64-encoded)
-    pub fn new(key_base64: &str) -> AppResult<Self> {
-        let key_bytes = base64::decode(key_base64)
+impl KeyEntry {
+    fn parse(key_base64: &str) -> AppResult<Self> {
+        let key_bytes = general_purpose::STANDARD.decode(key_base64.trim())
             .map_err(|_| AppError::Internal("Invalid encryption key format".to_string()))?;
 
         if key_bytes.len() != 32 {
@@ -24,29 +44,68 @@ impl TokenCrypto {
         }
 
         let key = Key::from_slice(&key_bytes);
-        let cipher = ChaCha20Poly1305::new(key);
 
-        Ok(Self { cipher })
+        Ok(Self {
+            id: key_id(&key_bytes),
+            cipher: ChaCha20Poly1305::new(key),
+        })
+    }
+}
+
+impl TokenCrypto {
+    /// Creates a new TokenCrypto instance from a base64-encoded key
+    /// The key must be 32 bytes (44 characters when base64-encoded)
+    pub fn new(key_base64: &str) -> AppResult<Self> {
+        Ok(Self {
+            active: KeyEntry::parse(key_base64)?,
+            decrypt_only: Vec::new(),
+        })
+    }
+
+    /// Creates a keyring with an active key and decrypt-only previous keys
+    pub fn with_old_keys(key_base64: &str, old_keys_base64: &[String]) -> AppResult<Self> {
+        let mut crypto = Self::new(key_base64)?;
+
+        for old_key in old_keys_base64 {
+            let entry = KeyEntry::parse(old_key)?;
+            if entry.id != crypto.active.id && !crypto.decrypt_only.iter().any(|k| k.id == entry.id) {
+                crypto.decrypt_only.push(entry);
+            }
+        }
+
+        Ok(crypto)
+    }
+
+    /// Keyring from ENCRYPTION_KEY and ENCRYPTION_OLD_KEYS
+    pub fn from_config(config: &Config) -> AppResult<Self> {
+        Self::with_old_keys(&config.encryption_key, &config.encryption_old_keys)
     }
 
     /// Generates a new random encryption key and returns it as base64
     /// Use this once during setup and store the result in ENCRYPTION_KEY env var
     pub fn generate_key() -> String {
         let key = ChaCha20Poly1305::generate_key(&mut OsRng);
-        base64::encode(key)
+        general_purpose::STANDARD.encode(key)
     }
 
-    /// Encrypts plaintext and returns base64(nonce + ciphertext)
+    /// Id of the key new ciphertexts are written with
+    pub fn active_key_id(&self) -> &str {
+        &self.active.id
+    }
+
+    /// Encrypts plaintext with the active key and returns "v1:<key id>:base64(nonce + ciphertext)"
     /// Each call uses a random nonce for security
     pub fn encrypt(&self, plaintext: &str) -> AppResult<String> {
         // Generate 96-bit random nonce
         let nonce_bytes = rand::random::<[u8; 12]>();
         let nonce = Nonce::from_slice(&nonce_bytes);
 
-        // Encrypt the plaintext
+        // The version and key id are authenticated, so they cannot be swapped
+        let header = format!("{}:{}", CIPHERTEXT_VERSION, self.active.id);
         let ciphertext = self
+            .active
             .cipher
-            .encrypt(nonce, plaintext.as_bytes())
+            .encrypt(nonce, Payload { msg: plaintext.as_bytes(), aad: header.as_bytes() })
             .map_err(|_| AppError::Internal("Token encryption failed".to_string()))?;
 
         // Concatenate nonce + ciphertext
@@ -54,40 +113,105 @@ impl TokenCrypto {
         result.extend_from_slice(&nonce_bytes);
         result.extend_from_slice(&ciphertext);
 
-        // Return as base64
-        Ok(base64::encode(result))
+        Ok(format!("{}:{}", header, general_purpose::STANDARD.encode(result)))
     }
 
-    /// Decrypts a token encrypted by encrypt()
-    /// Expects base64(nonce + ciphertext) format
-    pub fn decrypt(&self, encrypted_base64: &str) -> AppResult<String> {
-        // Decode from base64
-        let encrypted_data = base64::decode(encrypted_base64)
-            .map_err(|_| AppError::Internal("Invalid encrypted token format".to_string()))?;
-
-        if encrypted_data.len() < 12 {
-            return Err(AppError::Internal(
-                "Encrypted token is too short (corrupted?)".to_string(),
-            ));
-        }
-
-        // Split nonce and ciphertext
-        let (nonce_bytes, ciphertext) = encrypted_data.split_at(12);
-        let nonce = Nonce::from_slice(nonce_bytes);
-
-        // Decrypt
-        let plaintext = self
-            .cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|_| AppError::Authentication(
-                "Failed to decrypt token (corrupted or tampered?)".to_string(),
-            ))?;
+    /// Decrypts a token encrypted by encrypt() with any key of the keyring
+    /// Unversioned base64(nonce + ciphertext) tokens are tried with every key.
+    pub fn decrypt(&self, encrypted: &str) -> AppResult<String> {
+        let plaintext = match parse_versioned(encrypted) {
+            Some((key_id, body)) => {
+                let key = self.key(key_id).ok_or_else(|| {
+                    AppError::Internal(format!(
+                        "Token was encrypted with unknown key '{}' (missing from ENCRYPTION_OLD_KEYS?)",
+                        key_id
+                    ))
+                })?;
+                let header = format!("{}:{}", CIPHERTEXT_VERSION, key_id);
+                open(&key.cipher, body, header.as_bytes())?
+            }
+            None => self
+                .keys()
+                .find_map(|key| open(&key.cipher, encrypted, b"").ok())
+                .ok_or_else(|| AppError::Authentication(
+                    "Failed to decrypt token (corrupted or tampered?)".to_string(),
+                ))?,
+        };
 
         // Convert to string
         String::from_utf8(plaintext).map_err(|_| {
             AppError::Internal("Decrypted token is not valid UTF-8".to_string())
         })
     }
+
+    /// Whether a ciphertext should be rewritten with the active key
+    pub fn needs_reencryption(&self, encrypted: &str) -> bool {
+        match parse_versioned(encrypted) {
+            Some((key_id, _)) => key_id != self.active.id,
+            None => true,
+        }
+    }
+
+    /// Decrypts with any key and encrypts again with the active key
+    pub fn reencrypt(&self, encrypted: &str) -> AppResult<String> {
+        self.encrypt(&self.decrypt(encrypted)?)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &KeyEntry> {
+        std::iter::once(&self.active).chain(self.decrypt_only.iter())
+    }
+
+    fn key(&self, key_id: &str) -> Option<&KeyEntry> {
+        self.keys().find(|key| key.id == key_id)
+    }
+}
+
+/// Short public identifier of a key: hex of the first 4 bytes of SHA-256(key)
+fn key_id(key_bytes: &[u8]) -> String {
+    Sha256::digest(key_bytes)[..4]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Split "v1:<key id>:<body>" into key id and body
+fn parse_versioned(encrypted: &str) -> Option<(&str, &str)> {
+    let rest = encrypted.strip_prefix(CIPHERTEXT_VERSION)?.strip_prefix(':')?;
+    rest.split_once(':')
+}
+
+/// Decrypt base64(nonce + ciphertext)
+fn open(cipher: &ChaCha20Poly1305, body_base64: &str, aad: &[u8]) -> AppResult<Vec<u8>> {
+    // Decode from base64
+    let encrypted_data = general_purpose::STANDARD.decode(body_base64)
+        .map_err(|_| AppError::Internal("Invalid encrypted token format".to_string()))?;
+
+    if encrypted_data.len() < 12 {
+        return Err(AppError::Internal(
+            "Encrypted token is too short (corrupted?)".to_string(),
+        ));
+    }
+
+    // Split nonce and ciphertext
+    let (nonce_bytes, ciphertext) = encrypted_data.split_at(12);
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, Payload { msg: ciphertext, aad })
+        .map_err(|_| AppError::Authentication(
+            "Failed to decrypt token (corrupted or tampered?)".to_string(),
+        ))
+}
+
+/// Hashes an opaque bearer token (refresh token, reset token, ...) for storage
+/// Returns base64url(SHA-256(token)); tokens are high-entropy so no salt is needed
+pub fn hash_token(token: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Compare secrets in time that depends only on their length
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 #[cfg(test)]
@@ -144,7 +268,7 @@ mod tests {
 
     #[test]
     fn test_invalid_key_length() {
-        let short_key = base64::encode(vec![0u8; 16]); // 16 bytes instead of 32
+        let short_key = general_purpose::STANDARD.encode(vec![0u8; 16]); // 16 bytes instead of 32
         let result = TokenCrypto::new(&short_key);
         assert!(result.is_err());
     }
@@ -157,12 +281,83 @@ mod tests {
         let encrypted = crypto.encrypt("secret").unwrap();
 
         // Tamper with the ciphertext
-        let mut tampered = base64::decode(&encrypted).unwrap();
+        let (header, body) = encrypted.rsplit_once(':').unwrap();
+        let mut tampered = general_purpose::STANDARD.decode(body).unwrap();
         tampered[15] ^= 0xFF; // Flip some bits
-        let tampered_base64 = base64::encode(tampered);
+        let tampered_base64 = format!("{}:{}", header, general_purpose::STANDARD.encode(tampered));
 
         // Decryption should fail due to authentication tag verification
         let result = crypto.decrypt(&tampered_base64);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_ciphertext_names_active_key() {
+        let crypto = TokenCrypto::new(&TokenCrypto::generate_key()).unwrap();
+
+        let encrypted = crypto.encrypt("secret").unwrap();
+        let expected_prefix = format!("v1:{}:", crypto.active_key_id());
+
+        assert!(encrypted.starts_with(&expected_prefix));
+        assert_eq!(crypto.active_key_id().len(), 8);
+        assert!(!crypto.needs_reencryption(&encrypted));
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_ciphertexts_readable() {
+        let old_key = TokenCrypto::generate_key();
+        let new_key = TokenCrypto::generate_key();
+        let old = TokenCrypto::new(&old_key).unwrap();
+        let encrypted = old.encrypt("sk-old").unwrap();
+
+        let rotated = TokenCrypto::with_old_keys(&new_key, &[old_key]).unwrap();
+        assert_eq!(rotated.decrypt(&encrypted).unwrap(), "sk-old");
+        assert!(rotated.needs_reencryption(&encrypted));
+
+        let reencrypted = rotated.reencrypt(&encrypted).unwrap();
+        assert!(!rotated.needs_reencryption(&reencrypted));
+        assert_eq!(TokenCrypto::new(&new_key).unwrap().decrypt(&reencrypted).unwrap(), "sk-old");
+
+        // Without the old key the original ciphertext is unreadable
+        assert!(TokenCrypto::new(&new_key).unwrap().decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_legacy_unversioned_ciphertext() {
+        let key = TokenCrypto::generate_key();
+        let crypto = TokenCrypto::new(&key).unwrap();
+
+        // Format written before key ids: base64(nonce + ciphertext), no AAD
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&general_purpose::STANDARD.decode(&key).unwrap()));
+        let nonce_bytes = [7u8; 12];
+        let mut legacy = nonce_bytes.to_vec();
+        legacy.extend(cipher.encrypt(Nonce::from_slice(&nonce_bytes), b"sk-legacy".as_ref()).unwrap());
+        let legacy = general_purpose::STANDARD.encode(legacy);
+
+        let rotated = TokenCrypto::with_old_keys(&TokenCrypto::generate_key(), &[key]).unwrap();
+        assert_eq!(rotated.decrypt(&legacy).unwrap(), "sk-legacy");
+        assert!(rotated.needs_reencryption(&legacy));
+        assert!(crypto.needs_reencryption(&legacy));
+    }
+
+    #[test]
+    fn test_key_id_is_authenticated() {
+        let key_a = TokenCrypto::generate_key();
+        let key_b = TokenCrypto::generate_key();
+        let crypto = TokenCrypto::with_old_keys(&key_a, std::slice::from_ref(&key_b)).unwrap();
+        let other = TokenCrypto::new(&key_b).unwrap();
+
+        // Relabelling a ciphertext with another known key id must fail
+        let encrypted = crypto.encrypt("secret").unwrap();
+        let body = encrypted.rsplit_once(':').unwrap().1;
+        let relabelled = format!("v1:{}:{}", other.active_key_id(), body);
+        assert!(crypto.decrypt(&relabelled).is_err());
+    }
+
+    #[test]
+    fn test_hash_token_is_deterministic() {
+        assert_eq!(hash_token("refresh-token"), hash_token("refresh-token"));
+        assert_ne!(hash_token("refresh-token"), hash_token("refresh-token2"));
+        assert_eq!(hash_token("refresh-token").len(), 43);
+    }
 }
@@ -0,0 +1,254 @@
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::pkcs8::EncodePrivateKey as _;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use rand::rngs::OsRng;
+use rsa::{traits::PublicKeyParts, RsaPrivateKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::{AppError, AppResult};
+
+/// RSA modulus size used for newly generated RS256 keys
+const RSA_KEY_BITS: usize = 2048;
+
+/// Asymmetric algorithms supported for OpenID Connect token signing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningAlgorithm {
+    Rs256,
+    EdDsa,
+}
+
+impl SigningAlgorithm {
+    /// Parses the JOSE algorithm name ("RS256" or "EdDSA")
+    pub fn parse(alg: &str) -> Result<Self, String> {
+        match alg {
+            "RS256" => Ok(Self::Rs256),
+            "EdDSA" => Ok(Self::EdDsa),
+            _ => Err(format!(
+                "Invalid signing algorithm '{}'. Must be one of: RS256, EdDSA",
+                alg
+            )),
+        }
+    }
+
+    /// JOSE algorithm name as used in the "alg" header and JWKS
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rs256 => "RS256",
+            Self::EdDsa => "EdDSA",
+        }
+    }
+
+    pub fn jwt_algorithm(&self) -> Algorithm {
+        match self {
+            Self::Rs256 => Algorithm::RS256,
+            Self::EdDsa => Algorithm::EdDSA,
+        }
+    }
+}
+
+/// Public half of a signing key in JSON Web Key format (RFC 7517)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    pub alg: String,
+    pub kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+/// JSON Web Key Set served from the JWKS endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+/// A freshly generated key pair, ready to be persisted
+/// The private key is a PKCS#8 PEM document and must be encrypted before storage
+pub struct GeneratedSigningKey {
+    pub kid: String,
+    pub algorithm: SigningAlgorithm,
+    pub private_key_pem: String,
+    pub public_jwk: Jwk,
+}
+
+/// Generates a new signing key pair for the given algorithm
+/// The key id is the RFC 7638 JWK thumbprint of the public key
+pub fn generate_signing_key(algorithm: SigningAlgorithm) -> AppResult<GeneratedSigningKey> {
+    let (private_key_pem, mut public_jwk) = match algorithm {
+        SigningAlgorithm::Rs256 => {
+            let private_key = RsaPrivateKey::new(&mut OsRng, RSA_KEY_BITS)
+                .map_err(|e| AppError::Internal(format!("RSA key generation failed: {}", e)))?;
+            let pem = private_key
+                .to_pkcs8_pem(Default::default())
+                .map_err(|e| AppError::Internal(format!("RSA key encoding failed: {}", e)))?
+                .to_string();
+            let jwk = Jwk {
+                kty: "RSA".to_string(),
+                key_use: "sig".to_string(),
+                alg: algorithm.as_str().to_string(),
+                kid: String::new(),
+                n: Some(general_purpose::URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be())),
+                e: Some(general_purpose::URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be())),
+                crv: None,
+                x: None,
+            };
+            (pem, jwk)
+        }
+        SigningAlgorithm::EdDsa => {
+            let signing_key = ed25519_dalek::SigningKey::generate(&mut OsRng);
+            let pem = signing_key
+                .to_pkcs8_pem(Default::default())
+                .map_err(|e| AppError::Internal(format!("Ed25519 key encoding failed: {}", e)))?
+                .to_string();
+            let jwk = Jwk {
+                kty: "OKP".to_string(),
+                key_use: "sig".to_string(),
+                alg: algorithm.as_str().to_string(),
+                kid: String::new(),
+                n: None,
+                e: None,
+                crv: Some("Ed25519".to_string()),
+                x: Some(general_purpose::URL_SAFE_NO_PAD.encode(signing_key.verifying_key().as_bytes())),
+            };
+            (pem, jwk)
+        }
+    };
+
+    public_jwk.kid = jwk_thumbprint(&public_jwk);
+
+    Ok(GeneratedSigningKey {
+        kid: public_jwk.kid.clone(),
+        algorithm,
+        private_key_pem,
+        public_jwk,
+    })
+}
+
+/// Computes the RFC 7638 thumbprint of a public JWK (base64url SHA-256)
+pub fn jwk_thumbprint(jwk: &Jwk) -> String {
+    // Required members only, in lexicographic order, no whitespace
+    let canonical = match jwk.kty.as_str() {
+        "RSA" => format!(
+            r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#,
+            jwk.e.as_deref().unwrap_or_default(),
+            jwk.n.as_deref().unwrap_or_default()
+        ),
+        _ => format!(
+            r#"{{"crv":"{}","kty":"{}","x":"{}"}}"#,
+            jwk.crv.as_deref().unwrap_or_default(),
+            jwk.kty,
+            jwk.x.as_deref().unwrap_or_default()
+        ),
+    };
+
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Builds a jsonwebtoken encoding key from a PKCS#8 PEM private key
+pub fn encoding_key(algorithm: SigningAlgorithm, private_key_pem: &str) -> AppResult<EncodingKey> {
+    let key = match algorithm {
+        SigningAlgorithm::Rs256 => EncodingKey::from_rsa_pem(private_key_pem.as_bytes())?,
+        SigningAlgorithm::EdDsa => EncodingKey::from_ed_pem(private_key_pem.as_bytes())?,
+    };
+    Ok(key)
+}
+
+/// Builds a jsonwebtoken decoding key from a public JWK
+pub fn decoding_key(jwk: &Jwk) -> AppResult<DecodingKey> {
+    let key = match jwk.kty.as_str() {
+        "RSA" => DecodingKey::from_rsa_components(
+            jwk.n.as_deref().unwrap_or_default(),
+            jwk.e.as_deref().unwrap_or_default(),
+        )?,
+        "OKP" => DecodingKey::from_ed_components(jwk.x.as_deref().unwrap_or_default())?,
+        other => {
+            return Err(AppError::Internal(format!("Unsupported JWK key type: {}", other)));
+        }
+    };
+    Ok(key)
+}
+
+/// Computes an OpenID Connect hash claim (at_hash / c_hash) for the given token
+/// The left-most half of the SHA-256 digest, base64url encoded
+pub fn oidc_token_hash(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    general_purpose::URL_SAFE_NO_PAD.encode(&digest[..digest.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, encode, Header, Validation};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    #[test]
+    fn test_parse_algorithm() {
+        assert_eq!(SigningAlgorithm::parse("RS256"), Ok(SigningAlgorithm::Rs256));
+        assert_eq!(SigningAlgorithm::parse("EdDSA"), Ok(SigningAlgorithm::EdDsa));
+        assert!(SigningAlgorithm::parse("HS256").is_err());
+        assert!(SigningAlgorithm::parse("").is_err());
+    }
+
+    #[test]
+    fn test_eddsa_sign_and_verify_with_jwk() {
+        let key = generate_signing_key(SigningAlgorithm::EdDsa).unwrap();
+        assert_eq!(key.public_jwk.kty, "OKP");
+        assert_eq!(key.public_jwk.kid, key.kid);
+
+        let claims = TestClaims { sub: "42".to_string(), exp: 4_000_000_000 };
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(key.kid.clone());
+
+        let encoding = encoding_key(key.algorithm, &key.private_key_pem).unwrap();
+        let token = encode(&header, &claims, &encoding).unwrap();
+
+        let decoding = decoding_key(&key.public_jwk).unwrap();
+        let decoded = decode::<TestClaims>(&token, &decoding, &Validation::new(Algorithm::EdDSA)).unwrap();
+        assert_eq!(decoded.claims, claims);
+        assert_eq!(decoded.header.kid, Some(key.kid));
+    }
+
+    #[test]
+    fn test_generated_keys_have_distinct_kids() {
+        let key1 = generate_signing_key(SigningAlgorithm::EdDsa).unwrap();
+        let key2 = generate_signing_key(SigningAlgorithm::EdDsa).unwrap();
+        assert_ne!(key1.kid, key2.kid);
+    }
+
+    #[test]
+    fn test_rsa_jwk_thumbprint() {
+        // Example key from RFC 7638 section 3.1
+        let jwk = Jwk {
+            kty: "RSA".to_string(),
+            key_use: "sig".to_string(),
+            alg: "RS256".to_string(),
+            kid: String::new(),
+            n: Some("0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw".to_string()),
+            e: Some("AQAB".to_string()),
+            crv: None,
+            x: None,
+        };
+        assert_eq!(jwk_thumbprint(&jwk), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+    }
+
+    #[test]
+    fn test_oidc_token_hash_length() {
+        // SHA-256 left half is 16 bytes, i.e. 22 base64url characters
+        assert_eq!(oidc_token_hash("access-token").len(), 22);
+        assert_ne!(oidc_token_hash("a"), oidc_token_hash("b"));
+    }
+}
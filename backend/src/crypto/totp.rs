@@ -0,0 +1,213 @@
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+
+use super::constant_time_eq;
+
+/// Length of generated shared secrets (160 bits, as recommended by RFC 4226)
+pub const SECRET_LEN: usize = 20;
+/// Time step in seconds
+pub const STEP_SECONDS: i64 = 30;
+/// Number of digits in a code
+pub const DIGITS: u32 = 6;
+/// Accepted clock drift, in steps, on either side of the current one
+pub const ALLOWED_SKEW: i64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+/// Recovery codes avoid characters that are easy to confuse (0/o, 1/l/i)
+const RECOVERY_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Generates a new random shared secret
+pub fn generate_secret() -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    (0..SECRET_LEN).map(|_| rng.gen::<u8>()).collect()
+}
+
+/// Encodes bytes as unpadded RFC 4648 base32, the format authenticator apps expect
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    output
+}
+
+/// Decodes unpadded or padded RFC 4648 base32 (case-insensitive, spaces ignored)
+pub fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+/// Computes the HOTP value (RFC 4226) for a counter
+pub fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | digest[offset + 3] as u32;
+
+    binary % 10u32.pow(digits)
+}
+
+/// Time step for a Unix timestamp
+pub fn time_step(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECONDS)
+}
+
+/// Computes the TOTP code (RFC 6238) for a time step, zero-padded
+pub fn code_at_step(secret: &[u8], step: i64) -> String {
+    format!("{:0width$}", hotp(secret, step as u64, DIGITS), width = DIGITS as usize)
+}
+
+/// Checks a code against the steps around `unix_time`
+/// Returns the matching step, so callers can refuse to accept it twice.
+/// Steps at or before `last_used_step` are never accepted.
+pub fn verify_code(secret: &[u8], code: &str, unix_time: i64, last_used_step: Option<i64>) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = time_step(unix_time);
+    (current - ALLOWED_SKEW..=current + ALLOWED_SKEW)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| constant_time_eq(code_at_step(secret, *step).as_bytes(), code.as_bytes()))
+}
+
+/// Builds the otpauth:// URI shown as a QR code during enrollment
+pub fn otpauth_uri(issuer: &str, account: &str, secret_base32: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret_base32,
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+/// Generates a one-time recovery code formatted as "xxxxx-xxxxx"
+pub fn generate_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let mut chunk = || -> String {
+        (0..5)
+            .map(|_| RECOVERY_ALPHABET[rng.gen_range(0..RECOVERY_ALPHABET.len())] as char)
+            .collect()
+    };
+    format!("{}-{}", chunk(), chunk())
+}
+
+/// Normalizes a recovery code as typed by a user before hashing
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B, SHA-1 seed
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let vectors = [
+            (59, 94287082),
+            (1111111109, 7081804),
+            (1111111111, 14050471),
+            (1234567890, 89005924),
+            (2000000000, 69279037),
+            (20000000000, 65353130),
+        ];
+        for (time, expected) in vectors {
+            assert_eq!(hotp(RFC_SECRET, time_step(time) as u64, 8), expected, "t={}", time);
+        }
+    }
+
+    #[test]
+    fn test_verify_code_with_skew_and_replay() {
+        let now = 1_111_111_111;
+        let step = time_step(now);
+        let previous = code_at_step(RFC_SECRET, step - 1);
+
+        assert_eq!(verify_code(RFC_SECRET, &previous, now, None), Some(step - 1));
+        assert_eq!(verify_code(RFC_SECRET, &previous, now, Some(step - 1)), None);
+        assert_eq!(verify_code(RFC_SECRET, &code_at_step(RFC_SECRET, step - 2), now, None), None);
+        assert_eq!(verify_code(RFC_SECRET, "12345", now, None), None);
+        assert_eq!(verify_code(RFC_SECRET, "abcdef", now, None), None);
+    }
+
+    #[test]
+    fn test_base32_round_trip() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("MZXW6YTBOI======"), Some(b"foobar".to_vec()));
+        assert_eq!(base32_decode("mzxw 6ytb oi"), Some(b"foobar".to_vec()));
+        assert_eq!(base32_decode("MZ1"), None);
+
+        let secret = generate_secret();
+        assert_eq!(base32_decode(&base32_encode(&secret)), Some(secret));
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let uri = otpauth_uri("Panicless Library", "alice", "JBSWY3DPEHPK3PXP");
+        assert_eq!(
+            uri,
+            "otpauth://totp/Panicless%20Library:alice?secret=JBSWY3DPEHPK3PXP&issuer=Panicless%20Library&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(normalize_recovery_code(&code.to_uppercase()), code.replace('-', ""));
+        assert_ne!(generate_recovery_code(), code);
+    }
+}
@@ -1,4 +1,8 @@
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    postgres::PgPoolOptions,
+    PgPool,
+};
 use std::time::Duration;
 
 pub type DbPool = PgPool;
@@ -17,18 +21,55 @@ pub async fn create_pool(database_url: &str) -> Result<DbPool, sqlx::Error> {
     Ok(pool)
 }
 
-pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
+/// SQL migrations in database/migrations, embedded at build time
+pub static MIGRATOR: Migrator = sqlx::migrate!("../database/migrations");
+
+/// Apply all pending migrations
+pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::migrate::MigrateError> {
     tracing::info!("Running database migrations");
 
-    // Note: sqlx::migrate! macro looks for migrations in the migrations/ directory
-    // For now, we're using manual SQL files in the database/ directory
-    // If you want to use sqlx migrations, copy them to backend/migrations/
+    MIGRATOR.run(pool).await?;
 
     tracing::info!("Migrations completed successfully");
 
     Ok(())
 }
 
+/// Mark every migration as applied without running it
+/// For databases whose schema was created by the Postgres init scripts
+/// (docker-compose mounts database/migrations into docker-entrypoint-initdb.d).
+pub async fn baseline_migrations(pool: &DbPool) -> Result<u64, sqlx::migrate::MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    let mut marked = 0;
+    for migration in MIGRATOR.iter() {
+        let result = sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+             VALUES ($1, $2, TRUE, $3, 0)
+             ON CONFLICT (version) DO NOTHING"
+        )
+        .bind(migration.version)
+        .bind(&*migration.description)
+        .bind(&*migration.checksum)
+        .execute(&mut *conn)
+        .await?;
+        marked += result.rows_affected();
+    }
+
+    Ok(marked)
+}
+
+/// Versions of the applied migrations
+pub async fn applied_migrations(pool: &DbPool) -> Result<Vec<i64>, sqlx::migrate::MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    let applied = conn.list_applied_migrations().await?;
+
+    Ok(applied.into_iter().map(|m| m.version).collect())
+}
+
 pub async fn test_connection(pool: &DbPool) -> Result<(), sqlx::Error> {
     tracing::info!("Testing database connection");
 
@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -34,10 +34,18 @@ pub enum AppError {
 
     #[error("Argon2 password hash error")]
     PasswordHash,
+
+    #[error("Too many requests: {message}")]
+    RateLimited { message: String, retry_after: u64 },
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let retry_after = match self {
+            AppError::RateLimited { retry_after, .. } => Some(retry_after),
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             AppError::Database(ref e) => {
                 tracing::error!("Database error: {}", e);
@@ -60,6 +68,7 @@ impl IntoResponse for AppError {
                 tracing::error!("Password hash error");
                 (StatusCode::INTERNAL_SERVER_ERROR, "Password processing error")
             }
+            AppError::RateLimited { ref message, .. } => (StatusCode::TOO_MANY_REQUESTS, message.as_str()),
         };
 
         let body = Json(json!({
@@ -67,7 +76,11 @@ impl IntoResponse for AppError {
             "message": self.to_string(),
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 }
 
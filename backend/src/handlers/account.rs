@@ -0,0 +1,270 @@
+use axum::{extract::State, Json};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use rand::Rng;
+use validator::Validate;
+
+use crate::{
+    config::Config,
+    crypto::hash_token,
+    db::DbPool,
+    errors::{AppError, AppResult},
+    handlers::{auth::hash_password, sessions::revoke_all_sessions},
+    mail::{EmailMessage, SharedMailer},
+    middleware::Claims,
+    models::user::{ForgotPasswordRequest, ResetPasswordRequest, User, VerifyEmailRequest},
+};
+
+const PURPOSE_PASSWORD_RESET: &str = "password_reset";
+const PURPOSE_EMAIL_VERIFICATION: &str = "email_verification";
+
+/// Request a password reset link
+/// POST /api/auth/password/forgot
+/// Always answers the same way so it cannot be used to probe for accounts
+pub async fn forgot_password(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    State(mailer): State<SharedMailer>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    payload.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE LOWER(email) = LOWER($1)"
+    )
+    .bind(&payload.email)
+    .fetch_optional(&pool)
+    .await?;
+
+    if let Some(user) = user.filter(|u| !u.is_disabled()) {
+        if let Err(e) = send_password_reset_email(&pool, &config, mailer.as_ref(), &user).await {
+            tracing::error!("Failed to send password reset email to user {}: {}", user.id, e);
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "If an account exists for this email, a reset link has been sent"
+    })))
+}
+
+/// Set a new password using a reset token
+/// POST /api/auth/password/reset
+pub async fn reset_password(
+    State(pool): State<DbPool>,
+    State(mailer): State<SharedMailer>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    payload.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let (user_id, email) = consume_user_token(&pool, PURPOSE_PASSWORD_RESET, &payload.token).await?;
+    let password_hash = hash_password(&payload.new_password)?;
+
+    // The reset link proves control of the mailbox, so it also verifies it
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users
+         SET password_hash = $1, email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = CURRENT_TIMESTAMP
+         WHERE id = $2 AND email = $3
+         RETURNING id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at"
+    )
+    .bind(&password_hash)
+    .bind(user_id)
+    .bind(&email)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::Validation("Invalid or expired token".to_string()))?;
+
+    revoke_all_sessions(&pool, user.id, None, "password_reset").await?;
+
+    if let Err(e) = mailer.send(&EmailMessage::password_changed(&user.email, &user.username)).await {
+        tracing::error!("Failed to send password change notice to user {}: {}", user.id, e);
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "Password has been reset, please log in again"
+    })))
+}
+
+/// Confirm an email address
+/// POST /api/auth/verify-email
+pub async fn verify_email(
+    State(pool): State<DbPool>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let (user_id, email) = consume_user_token(&pool, PURPOSE_EMAIL_VERIFICATION, &payload.token).await?;
+
+    // Only verifies the address the token was sent to, if it is still current
+    let result = sqlx::query(
+        "UPDATE users SET email_verified_at = NOW() WHERE id = $1 AND email = $2"
+    )
+    .bind(user_id)
+    .bind(&email)
+    .execute(&pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::Validation(
+            "The email address has changed since this link was sent".to_string(),
+        ));
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "Email verified successfully"
+    })))
+}
+
+/// Send a new verification email to the authenticated user
+/// POST /api/auth/verify-email/resend
+pub async fn resend_verification(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    State(mailer): State<SharedMailer>,
+    claims: Claims,
+) -> AppResult<Json<serde_json::Value>> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+    )
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if user.email_verified_at.is_some() {
+        return Err(AppError::Conflict("Email is already verified".to_string()));
+    }
+
+    send_verification_email(&pool, &config, mailer.as_ref(), &user).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Verification email sent"
+    })))
+}
+
+/// Issue a verification token for the user's current email and mail the link
+pub async fn send_verification_email(
+    pool: &DbPool,
+    config: &Config,
+    mailer: &dyn crate::mail::Mailer,
+    user: &User,
+) -> AppResult<()> {
+    let token = issue_user_token(
+        pool,
+        user,
+        PURPOSE_EMAIL_VERIFICATION,
+        config.email_verification_token_expiry,
+    )
+    .await?;
+
+    let link = format!("{}/verify-email?token={}", config.base_url(), token);
+    let message = EmailMessage::email_verification(
+        &user.email,
+        &user.username,
+        &link,
+        config.email_verification_token_expiry / 3600,
+    );
+
+    mailer.send(&message).await
+}
+
+/// Issue a password reset token and mail the link
+pub async fn send_password_reset_email(
+    pool: &DbPool,
+    config: &Config,
+    mailer: &dyn crate::mail::Mailer,
+    user: &User,
+) -> AppResult<()> {
+    let token = issue_user_token(
+        pool,
+        user,
+        PURPOSE_PASSWORD_RESET,
+        config.password_reset_token_expiry,
+    )
+    .await?;
+
+    let link = format!("{}/reset-password?token={}", config.base_url(), token);
+    let message = EmailMessage::password_reset(
+        &user.email,
+        &user.username,
+        &link,
+        config.password_reset_token_expiry / 60,
+    );
+
+    mailer.send(&message).await
+}
+
+/// Create a single-use token, invalidating earlier unused ones of the same purpose
+/// Returns the plain token; only its hash is stored
+async fn issue_user_token(
+    pool: &DbPool,
+    user: &User,
+    purpose: &str,
+    expiry_seconds: i64,
+) -> AppResult<String> {
+    let token = generate_user_token();
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE user_tokens SET used_at = NOW()
+         WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL"
+    )
+    .bind(user.id)
+    .bind(purpose)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO user_tokens (user_id, purpose, token_hash, email, expires_at)
+         VALUES ($1, $2, $3, $4, $5)"
+    )
+    .bind(user.id)
+    .bind(purpose)
+    .bind(hash_token(&token))
+    .bind(&user.email)
+    .bind(Utc::now() + chrono::Duration::seconds(expiry_seconds))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(token)
+}
+
+/// Atomically mark a token as used, returning its user id and email
+async fn consume_user_token(pool: &DbPool, purpose: &str, token: &str) -> AppResult<(i32, String)> {
+    sqlx::query_as::<_, (i32, String)>(
+        "UPDATE user_tokens SET used_at = NOW()
+         WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+         RETURNING user_id, email"
+    )
+    .bind(hash_token(token))
+    .bind(purpose)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::Validation("Invalid or expired token".to_string()))
+}
+
+fn generate_user_token() -> String {
+    let mut rng = rand::thread_rng();
+    // 32 random bytes encode to 43 URL-safe base64 characters
+    let random_bytes: Vec<u8> = (0..32)
+        .map(|_| rng.gen::<u8>())
+        .collect();
+
+    general_purpose::URL_SAFE_NO_PAD.encode(&random_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_user_token() {
+        let token1 = generate_user_token();
+        let token2 = generate_user_token();
+        assert_eq!(token1.len(), 43);
+        assert_ne!(token1, token2);
+        assert!(token1.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+}
@@ -0,0 +1,700 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use rand::Rng;
+use validator::Validate;
+
+use crate::{
+    config::Config,
+    crypto::hash_token,
+    db::DbPool,
+    errors::{AppError, AppResult},
+    handlers::{
+        account::{send_password_reset_email, send_verification_email},
+        sessions::revoke_all_sessions,
+    },
+    mail::SharedMailer,
+    middleware::AdminUser,
+    models::{
+        admin::{
+            AdminUserQuery, AdminUserSummary, AuditEntry, AuditQuery, CreateInviteRequest,
+            CreatedInviteResponse, DisableUserRequest, InstanceStats, InviteCode,
+            RegistrationPolicy, RegistrationSettings, UpdateRoleRequest,
+        },
+        user::{User, ROLE_ADMIN, ROLE_USER},
+    },
+};
+
+/// Query for `AdminUserSummary` rows; callers append their own filter
+pub const ADMIN_USER_SUMMARY_SELECT: &str =
+    "SELECT u.id, u.username, u.email, u.full_name, u.role, u.email_verified_at, u.disabled_at, u.disabled_reason,
+            EXISTS(SELECT 1 FROM user_totp t WHERE t.user_id = u.id AND t.enabled_at IS NOT NULL) AS two_factor_enabled,
+            (SELECT COUNT(*) FROM books b WHERE b.user_id = u.id) AS book_count,
+            (SELECT COUNT(*) FROM readings r WHERE r.user_id = u.id) AS reading_count,
+            (SELECT COUNT(*) FROM user_sessions s WHERE s.user_id = u.id AND s.revoked_at IS NULL AND s.expires_at > NOW()) AS active_sessions,
+            (SELECT MAX(s.last_used_at) FROM user_sessions s WHERE s.user_id = u.id) AS last_active_at,
+            u.created_at
+     FROM users u";
+
+/// Get the current registration policy (public, used by the sign-up form)
+/// GET /api/auth/registration
+pub async fn get_registration_settings(
+    State(pool): State<DbPool>,
+) -> AppResult<Json<RegistrationSettings>> {
+    Ok(Json(RegistrationSettings {
+        registration_policy: registration_policy(&pool).await?,
+    }))
+}
+
+/// List users
+/// GET /api/admin/users
+pub async fn list_users(
+    State(pool): State<DbPool>,
+    Query(query): Query<AdminUserQuery>,
+    _admin: AdminUser,
+) -> AppResult<Json<Vec<AdminUserSummary>>> {
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = (page - 1) * limit;
+
+    let mut sql = format!("{} WHERE 1 = 1", ADMIN_USER_SUMMARY_SELECT);
+    let mut param_count = 1;
+
+    if query.search.is_some() {
+        sql.push_str(&format!(
+            " AND (u.username ILIKE ${0} OR u.email ILIKE ${0} OR u.full_name ILIKE ${0})",
+            param_count
+        ));
+        param_count += 1;
+    }
+
+    if query.role.is_some() {
+        sql.push_str(&format!(" AND u.role = ${}", param_count));
+        param_count += 1;
+    }
+
+    match query.disabled {
+        Some(true) => sql.push_str(" AND u.disabled_at IS NOT NULL"),
+        Some(false) => sql.push_str(" AND u.disabled_at IS NULL"),
+        None => {}
+    }
+
+    sql.push_str(&format!(" ORDER BY u.id LIMIT ${} OFFSET ${}", param_count, param_count + 1));
+
+    let mut query_builder = sqlx::query_as::<_, AdminUserSummary>(&sql);
+
+    if let Some(search) = query.search {
+        query_builder = query_builder.bind(format!("%{}%", search));
+    }
+
+    if let Some(role) = query.role {
+        query_builder = query_builder.bind(role);
+    }
+
+    let users = query_builder.bind(limit).bind(offset).fetch_all(&pool).await?;
+
+    Ok(Json(users))
+}
+
+/// Get one user with usage counters
+/// GET /api/admin/users/:id
+pub async fn get_user_details(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<i32>,
+    _admin: AdminUser,
+) -> AppResult<Json<AdminUserSummary>> {
+    Ok(Json(fetch_user_summary(&pool, user_id).await?))
+}
+
+/// Disable an account and sign it out everywhere
+/// POST /api/admin/users/:id/disable
+pub async fn disable_user(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<i32>,
+    admin: AdminUser,
+    Json(payload): Json<DisableUserRequest>,
+) -> AppResult<Json<AdminUserSummary>> {
+    payload.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    if user_id == admin.id() {
+        return Err(AppError::Validation("You cannot disable your own account".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        "UPDATE users SET disabled_at = NOW(), disabled_reason = $1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = $2 AND disabled_at IS NULL"
+    )
+    .bind(&payload.reason)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        ensure_user_exists(&mut tx, user_id).await?;
+        return Err(AppError::Conflict("User is already disabled".to_string()));
+    }
+
+    // OAuth access tokens are not tied to sessions
+    sqlx::query("DELETE FROM oauth_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(&mut tx, &admin, "user.disable", Some(user_id), serde_json::json!({
+        "reason": payload.reason,
+    }))
+    .await?;
+
+    tx.commit().await?;
+
+    revoke_all_sessions(&pool, user_id, None, "account_disabled").await?;
+
+    Ok(Json(fetch_user_summary(&pool, user_id).await?))
+}
+
+/// Re-enable a disabled account
+/// POST /api/admin/users/:id/enable
+pub async fn enable_user(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<i32>,
+    admin: AdminUser,
+) -> AppResult<Json<AdminUserSummary>> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        "UPDATE users SET disabled_at = NULL, disabled_reason = NULL, updated_at = CURRENT_TIMESTAMP
+         WHERE id = $1 AND disabled_at IS NOT NULL"
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        ensure_user_exists(&mut tx, user_id).await?;
+        return Err(AppError::Conflict("User is not disabled".to_string()));
+    }
+
+    record_audit(&mut tx, &admin, "user.enable", Some(user_id), serde_json::json!({})).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(fetch_user_summary(&pool, user_id).await?))
+}
+
+/// Grant or remove the admin role
+/// PUT /api/admin/users/:id/role
+pub async fn update_user_role(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<i32>,
+    admin: AdminUser,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> AppResult<Json<AdminUserSummary>> {
+    if payload.role != ROLE_USER && payload.role != ROLE_ADMIN {
+        return Err(AppError::Validation(format!(
+            "Invalid role '{}'. Must be one of: user, admin",
+            payload.role
+        )));
+    }
+
+    if user_id == admin.id() && payload.role != ROLE_ADMIN {
+        return Err(AppError::Validation("You cannot remove your own admin role".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let previous_role = sqlx::query_scalar::<_, String>("SELECT role FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if previous_role != payload.role {
+        sqlx::query("UPDATE users SET role = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+            .bind(&payload.role)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        record_audit(&mut tx, &admin, "user.role", Some(user_id), serde_json::json!({
+            "from": previous_role,
+            "to": payload.role,
+        }))
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(fetch_user_summary(&pool, user_id).await?))
+}
+
+/// Sign a user out of every session and revoke their OAuth tokens
+/// POST /api/admin/users/:id/sessions/revoke
+pub async fn revoke_user_sessions(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<i32>,
+    admin: AdminUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let mut tx = pool.begin().await?;
+    ensure_user_exists(&mut tx, user_id).await?;
+
+    let oauth_tokens = sqlx::query("DELETE FROM oauth_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+
+    let sessions = revoke_all_sessions(&pool, user_id, None, "revoked_by_admin").await?;
+
+    let mut tx = pool.begin().await?;
+    record_audit(&mut tx, &admin, "user.revoke_sessions", Some(user_id), serde_json::json!({
+        "sessions": sessions,
+        "oauth_tokens": oauth_tokens,
+    }))
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Sessions revoked successfully",
+        "sessions": sessions,
+        "oauth_tokens": oauth_tokens
+    })))
+}
+
+/// Email the user a password reset link; the admin never sees the password or token
+/// POST /api/admin/users/:id/password-reset
+pub async fn send_user_password_reset(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    State(mailer): State<SharedMailer>,
+    Path(user_id): Path<i32>,
+    admin: AdminUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let user = fetch_user(&pool, user_id).await?;
+
+    send_password_reset_email(&pool, &config, mailer.as_ref(), &user).await?;
+
+    let mut tx = pool.begin().await?;
+    record_audit(&mut tx, &admin, "user.password_reset_email", Some(user_id), serde_json::json!({})).await?;
+    tx.commit().await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Password reset email sent"
+    })))
+}
+
+/// Email the user a new verification link
+/// POST /api/admin/users/:id/verification-email
+pub async fn send_user_verification(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    State(mailer): State<SharedMailer>,
+    Path(user_id): Path<i32>,
+    admin: AdminUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let user = fetch_user(&pool, user_id).await?;
+
+    if user.email_verified_at.is_some() {
+        return Err(AppError::Conflict("Email is already verified".to_string()));
+    }
+
+    send_verification_email(&pool, &config, mailer.as_ref(), &user).await?;
+
+    let mut tx = pool.begin().await?;
+    record_audit(&mut tx, &admin, "user.verification_email", Some(user_id), serde_json::json!({})).await?;
+    tx.commit().await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Verification email sent"
+    })))
+}
+
+/// Remove two-factor authentication for a user who lost their authenticator
+/// and recovery codes
+/// DELETE /api/admin/users/:id/2fa
+pub async fn reset_user_two_factor(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<i32>,
+    admin: AdminUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let mut tx = pool.begin().await?;
+    ensure_user_exists(&mut tx, user_id).await?;
+
+    let removed = sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    if removed == 0 {
+        return Err(AppError::Conflict("Two-factor authentication is not set up".to_string()));
+    }
+
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(&mut tx, &admin, "user.reset_2fa", Some(user_id), serde_json::json!({})).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Two-factor authentication removed"
+    })))
+}
+
+/// Instance-wide statistics
+/// GET /api/admin/stats
+pub async fn get_instance_stats(
+    State(pool): State<DbPool>,
+    _admin: AdminUser,
+) -> AppResult<Json<InstanceStats>> {
+    let stats = sqlx::query_as::<_, InstanceStats>(
+        "SELECT
+            (SELECT COUNT(*) FROM users) AS users_total,
+            (SELECT COUNT(*) FROM users WHERE role = 'admin') AS users_admin,
+            (SELECT COUNT(*) FROM users WHERE disabled_at IS NOT NULL) AS users_disabled,
+            (SELECT COUNT(*) FROM users WHERE email_verified_at IS NULL) AS users_unverified,
+            (SELECT COUNT(DISTINCT user_id) FROM user_sessions WHERE last_used_at > NOW() - INTERVAL '30 days') AS users_active_30d,
+            (SELECT COUNT(*) FROM books) AS books_total,
+            (SELECT COUNT(*) FROM readings) AS readings_total,
+            (SELECT COUNT(*) FROM readings WHERE end_date IS NOT NULL) AS readings_completed,
+            (SELECT COUNT(*) FROM connectors WHERE is_active) AS connectors_enabled,
+            (SELECT COUNT(*) FROM user_sessions WHERE revoked_at IS NULL AND expires_at > NOW()) AS sessions_active"
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(stats))
+}
+
+/// Get the registration policy
+/// GET /api/admin/settings/registration
+pub async fn get_admin_registration_settings(
+    State(pool): State<DbPool>,
+    _admin: AdminUser,
+) -> AppResult<Json<RegistrationSettings>> {
+    Ok(Json(RegistrationSettings {
+        registration_policy: registration_policy(&pool).await?,
+    }))
+}
+
+/// Change the registration policy
+/// PUT /api/admin/settings/registration
+pub async fn update_registration_settings(
+    State(pool): State<DbPool>,
+    admin: AdminUser,
+    Json(payload): Json<RegistrationSettings>,
+) -> AppResult<Json<RegistrationSettings>> {
+    let mut tx = pool.begin().await?;
+
+    let previous = sqlx::query_scalar::<_, String>(
+        "SELECT registration_policy FROM instance_settings WHERE id FOR UPDATE"
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE instance_settings SET registration_policy = $1, updated_at = NOW() WHERE id")
+        .bind(payload.registration_policy.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(&mut tx, &admin, "settings.registration", None, serde_json::json!({
+        "from": previous,
+        "to": payload.registration_policy.as_str(),
+    }))
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(payload))
+}
+
+/// List invite codes
+/// GET /api/admin/invites
+pub async fn list_invites(
+    State(pool): State<DbPool>,
+    _admin: AdminUser,
+) -> AppResult<Json<Vec<InviteCode>>> {
+    let invites = sqlx::query_as::<_, InviteCode>(
+        "SELECT id, code_hint, created_by, email, max_uses, use_count, expires_at, revoked_at, created_at
+         FROM invite_codes ORDER BY created_at DESC"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(invites))
+}
+
+/// Create an invite code
+/// POST /api/admin/invites
+pub async fn create_invite(
+    State(pool): State<DbPool>,
+    admin: AdminUser,
+    Json(payload): Json<CreateInviteRequest>,
+) -> AppResult<Json<CreatedInviteResponse>> {
+    payload.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let code = generate_invite_code();
+    let expires_at = payload
+        .expires_in_days
+        .map(|days| Utc::now() + chrono::Duration::days(days));
+
+    let mut tx = pool.begin().await?;
+
+    let invite = sqlx::query_as::<_, InviteCode>(
+        "INSERT INTO invite_codes (code_hash, code_hint, created_by, email, max_uses, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING id, code_hint, created_by, email, max_uses, use_count, expires_at, revoked_at, created_at"
+    )
+    .bind(hash_token(&code))
+    .bind(&code[..4])
+    .bind(admin.id())
+    .bind(&payload.email)
+    .bind(payload.max_uses.unwrap_or(1))
+    .bind(expires_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(&mut tx, &admin, "invite.create", None, serde_json::json!({
+        "invite_id": invite.id,
+        "email": invite.email,
+        "max_uses": invite.max_uses,
+        "expires_at": invite.expires_at,
+    }))
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(CreatedInviteResponse { code, invite }))
+}
+
+/// Revoke an invite code
+/// DELETE /api/admin/invites/:id
+pub async fn revoke_invite(
+    State(pool): State<DbPool>,
+    Path(invite_id): Path<i32>,
+    admin: AdminUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query("UPDATE invite_codes SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+        .bind(invite_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Invite not found".to_string()));
+    }
+
+    record_audit(&mut tx, &admin, "invite.revoke", None, serde_json::json!({
+        "invite_id": invite_id,
+    }))
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Invite revoked successfully"
+    })))
+}
+
+/// Read the audit trail, newest first
+/// GET /api/admin/audit
+pub async fn list_audit_log(
+    State(pool): State<DbPool>,
+    Query(query): Query<AuditQuery>,
+    _admin: AdminUser,
+) -> AppResult<Json<Vec<AuditEntry>>> {
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = (page - 1) * limit;
+
+    let mut sql = String::from(
+        "SELECT id, admin_user_id, admin_username, action, target_user_id, details, ip_address, created_at
+         FROM admin_audit_log WHERE 1 = 1"
+    );
+    let mut param_count = 1;
+
+    if query.action.is_some() {
+        sql.push_str(&format!(" AND action = ${}", param_count));
+        param_count += 1;
+    }
+
+    if query.target_user_id.is_some() {
+        sql.push_str(&format!(" AND target_user_id = ${}", param_count));
+        param_count += 1;
+    }
+
+    sql.push_str(&format!(" ORDER BY created_at DESC, id DESC LIMIT ${} OFFSET ${}", param_count, param_count + 1));
+
+    let mut query_builder = sqlx::query_as::<_, AuditEntry>(&sql);
+
+    if let Some(action) = query.action {
+        query_builder = query_builder.bind(action);
+    }
+
+    if let Some(target_user_id) = query.target_user_id {
+        query_builder = query_builder.bind(target_user_id);
+    }
+
+    let entries = query_builder.bind(limit).bind(offset).fetch_all(&pool).await?;
+
+    Ok(Json(entries))
+}
+
+/// Current registration policy
+pub async fn registration_policy(pool: &DbPool) -> AppResult<RegistrationPolicy> {
+    let policy = sqlx::query_scalar::<_, String>("SELECT registration_policy FROM instance_settings WHERE id")
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or_else(|| RegistrationPolicy::Open.as_str().to_string());
+
+    RegistrationPolicy::parse(&policy)
+        .ok_or_else(|| AppError::Internal(format!("Unknown registration policy '{}'", policy)))
+}
+
+/// Use up one slot of an invite code within the registration transaction
+pub async fn consume_invite_code(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    code: &str,
+    email: &str,
+) -> AppResult<()> {
+    let consumed = sqlx::query(
+        "UPDATE invite_codes SET use_count = use_count + 1
+         WHERE code_hash = $1
+           AND revoked_at IS NULL
+           AND (expires_at IS NULL OR expires_at > NOW())
+           AND use_count < max_uses
+           AND (email IS NULL OR LOWER(email) = LOWER($2))"
+    )
+    .bind(hash_token(code.trim()))
+    .bind(email)
+    .execute(&mut **tx)
+    .await?;
+
+    if consumed.rows_affected() == 0 {
+        return Err(AppError::Authorization("Invalid or expired invite code".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Append an entry to the audit trail
+pub async fn record_audit(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    admin: &AdminUser,
+    action: &str,
+    target_user_id: Option<i32>,
+    details: serde_json::Value,
+) -> AppResult<()> {
+    insert_audit_entry(
+        tx,
+        Some(admin.id()),
+        &admin.claims.username,
+        action,
+        target_user_id,
+        details,
+        admin.ip_address.as_deref(),
+    )
+    .await
+}
+
+/// Append an entry to the audit trail for an actor that may not be a user
+/// (panicless-admin records its actions with no admin_user_id)
+pub async fn insert_audit_entry(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    admin_user_id: Option<i32>,
+    admin_username: &str,
+    action: &str,
+    target_user_id: Option<i32>,
+    details: serde_json::Value,
+    ip_address: Option<&str>,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO admin_audit_log (admin_user_id, admin_username, action, target_user_id, details, ip_address)
+         VALUES ($1, $2, $3, $4, $5, $6)"
+    )
+    .bind(admin_user_id)
+    .bind(admin_username)
+    .bind(action)
+    .bind(target_user_id)
+    .bind(details)
+    .bind(ip_address)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+async fn fetch_user(pool: &DbPool, user_id: i32) -> AppResult<User> {
+    sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+async fn fetch_user_summary(pool: &DbPool, user_id: i32) -> AppResult<AdminUserSummary> {
+    sqlx::query_as::<_, AdminUserSummary>(&format!("{} WHERE u.id = $1", ADMIN_USER_SUMMARY_SELECT))
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+async fn ensure_user_exists(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, user_id: i32) -> AppResult<()> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+    if !exists {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    Ok(())
+}
+
+fn generate_invite_code() -> String {
+    let mut rng = rand::thread_rng();
+    // 15 random bytes encode to exactly 20 URL-safe base64 characters
+    let random_bytes: Vec<u8> = (0..15)
+        .map(|_| rng.gen::<u8>())
+        .collect();
+
+    general_purpose::URL_SAFE_NO_PAD.encode(&random_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_policy_round_trip() {
+        for policy in [RegistrationPolicy::Open, RegistrationPolicy::InviteOnly, RegistrationPolicy::Closed] {
+            assert_eq!(RegistrationPolicy::parse(policy.as_str()), Some(policy));
+            let json = serde_json::to_string(&policy).unwrap();
+            assert_eq!(json, format!("\"{}\"", policy.as_str()));
+        }
+        assert_eq!(RegistrationPolicy::parse("invite"), None);
+    }
+
+    #[test]
+    fn test_generate_invite_code() {
+        let code = generate_invite_code();
+        assert_eq!(code.len(), 20);
+        assert_ne!(code, generate_invite_code());
+    }
+}
@@ -6,20 +6,38 @@ use axum::{
     extract::State,
     Json,
 };
+use std::sync::LazyLock;
 use validator::Validate;
 
 use crate::{
     config::Config,
     db::DbPool,
     errors::{AppError, AppResult},
-    middleware::Claims,
-    models::user::{AuthResponse, CreateUser, LoginRequest, User, UserResponse},
+    handlers::{
+        account::send_verification_email,
+        admin::{consume_invite_code, registration_policy},
+        sessions::{revoke_session_by_id, rotate_session, start_session},
+        two_factor::two_factor_enabled,
+    },
+    mail::SharedMailer,
+    middleware::{auth::generate_jwt, Claims, ClientInfo},
+    models::{
+        admin::RegistrationPolicy,
+        session::RefreshRequest,
+        two_factor::{LoginResponse, MfaChallengeResponse},
+        user::{AuthResponse, CreateUser, LoginRequest, User, UserResponse, ROLE_ADMIN, ROLE_USER},
+    },
+    rate_limit::RateLimiter,
 };
-use crate::middleware::auth::generate_jwt;
+
+/// Advisory lock serializing the first-user check of registrations
+const REGISTRATION_LOCK: i64 = 0x05E6_1571;
 
 pub async fn register(
     State(pool): State<DbPool>,
     State(config): State<Config>,
+    State(mailer): State<SharedMailer>,
+    client: ClientInfo,
     Json(payload): Json<CreateUser>,
 ) -> AppResult<Json<AuthResponse>> {
     payload.validate()
@@ -27,7 +45,7 @@ pub async fn register(
 
     // Check if username already exists
     let existing_user = sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, full_name, created_at, updated_at FROM users WHERE username = $1"
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE username = $1"
     )
     .bind(&payload.username)
     .fetch_optional(&pool)
@@ -39,7 +57,7 @@ pub async fn register(
 
     // Check if email already exists
     let existing_email = sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, full_name, created_at, updated_at FROM users WHERE email = $1"
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE email = $1"
     )
     .bind(&payload.email)
     .fetch_optional(&pool)
@@ -49,144 +67,152 @@ pub async fn register(
         return Err(AppError::Conflict("Email already exists".to_string()));
     }
 
-    // Hash password using Argon2
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = argon2
-        .hash_password(payload.password.as_bytes(), &salt)
-        .map_err(|_| AppError::PasswordHash)?
-        .to_string();
+    let password_hash = hash_password(&payload.password)?;
+
+    let mut tx = pool.begin().await?;
+
+    // The first account of a fresh instance becomes its administrator and is
+    // not subject to the registration policy. Registrations take turns here, or
+    // two concurrent first sign-ups could both see an empty table.
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(REGISTRATION_LOCK)
+        .execute(&mut *tx)
+        .await?;
+    let is_first_user = !sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users)")
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if !is_first_user {
+        match registration_policy(&pool).await? {
+            RegistrationPolicy::Open => {}
+            RegistrationPolicy::Closed => {
+                return Err(AppError::Authorization("Registration is closed".to_string()));
+            }
+            RegistrationPolicy::InviteOnly => {
+                let code = payload.invite_code.as_deref().ok_or_else(|| {
+                    AppError::Authorization("An invite code is required to register".to_string())
+                })?;
+                consume_invite_code(&mut tx, code, &payload.email).await?;
+            }
+        }
+    }
 
     // Insert user into database
     let user = sqlx::query_as::<_, User>(
-        "INSERT INTO users (username, email, password_hash, full_name)
-         VALUES ($1, $2, $3, $4)
-         RETURNING id, username, email, password_hash, full_name, created_at, updated_at"
+        "INSERT INTO users (username, email, password_hash, full_name, role)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at"
     )
     .bind(&payload.username)
     .bind(&payload.email)
     .bind(&password_hash)
     .bind(&payload.full_name)
-    .fetch_one(&pool)
+    .bind(if is_first_user { ROLE_ADMIN } else { ROLE_USER })
+    .fetch_one(&mut *tx)
     .await?;
 
-    // Generate JWT tokens
-    let access_claims = Claims::new_access_token(
-        user.id,
-        user.username.clone(),
-        config.jwt_access_token_expiry,
-    );
-    let refresh_claims = Claims::new_refresh_token(
-        user.id,
-        user.username.clone(),
-        config.jwt_refresh_token_expiry,
-    );
-
-    let access_token = generate_jwt(&access_claims, &config.jwt_secret)?;
-    let refresh_token = generate_jwt(&refresh_claims, &config.jwt_secret)?;
-
-    Ok(Json(AuthResponse {
-        access_token,
-        refresh_token,
-        token_type: "Bearer".to_string(),
-        expires_in: config.jwt_access_token_expiry,
-        user: user.into(),
-    }))
+    tx.commit().await?;
+
+    // A failed verification email must not fail the registration;
+    // the user can request a new one later
+    if let Err(e) = send_verification_email(&pool, &config, mailer.as_ref(), &user).await {
+        tracing::error!("Failed to send verification email to user {}: {}", user.id, e);
+    }
+
+    let response = start_session(&pool, &config, user, &client).await?;
+
+    Ok(Json(response))
 }
 
 pub async fn login(
     State(pool): State<DbPool>,
     State(config): State<Config>,
+    State(rate_limiter): State<RateLimiter>,
+    client: ClientInfo,
     Json(payload): Json<LoginRequest>,
-) -> AppResult<Json<AuthResponse>> {
+) -> AppResult<Json<LoginResponse>> {
+    // Progressive lockout per username, applied whether or not the account exists
+    let lockout_key = format!("login:user:{}", payload.username.to_lowercase());
+    rate_limiter.ensure_not_locked(&lockout_key).await?;
+
     // Fetch user by username
     let user = sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, full_name, created_at, updated_at FROM users WHERE username = $1"
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE username = $1"
     )
     .bind(&payload.username)
     .fetch_optional(&pool)
-    .await?
-    .ok_or_else(|| AppError::Authentication("Invalid credentials".to_string()))?;
+    .await?;
 
-    // Verify password
-    let parsed_hash = PasswordHash::new(&user.password_hash)
-        .map_err(|_| AppError::PasswordHash)?;
+    // Verify password; without an account against a dummy hash, so the response
+    // time does not tell which usernames exist
+    let password_hash = user.as_ref().map_or(DUMMY_PASSWORD_HASH.as_str(), |user| user.password_hash.as_str());
+    let password_matches = verify_password(&payload.password, password_hash)?;
+    let user = match user {
+        Some(user) if password_matches => user,
+        _ => {
+            rate_limiter.record_failure(&lockout_key).await;
+            return Err(AppError::Authentication("Invalid credentials".to_string()));
+        }
+    };
+    rate_limiter.clear_failures(&lockout_key).await;
+
+    // Accounts with two-factor authentication get a challenge instead of tokens
+    if two_factor_enabled(&pool, user.id).await? {
+        let challenge_claims = Claims::new_mfa_challenge(
+            user.id,
+            user.username.clone(),
+            config.mfa_challenge_expiry,
+        );
 
-    Argon2::default()
-        .verify_password(payload.password.as_bytes(), &parsed_hash)
-        .map_err(|_| AppError::Authentication("Invalid credentials".to_string()))?;
-
-    // Generate JWT tokens
-    let access_claims = Claims::new_access_token(
-        user.id,
-        user.username.clone(),
-        config.jwt_access_token_expiry,
-    );
-    let refresh_claims = Claims::new_refresh_token(
-        user.id,
-        user.username.clone(),
-        config.jwt_refresh_token_expiry,
-    );
-
-    let access_token = generate_jwt(&access_claims, &config.jwt_secret)?;
-    let refresh_token = generate_jwt(&refresh_claims, &config.jwt_secret)?;
-
-    Ok(Json(AuthResponse {
-        access_token,
-        refresh_token,
-        token_type: "Bearer".to_string(),
-        expires_in: config.jwt_access_token_expiry,
-        user: user.into(),
-    }))
+        return Ok(Json(LoginResponse::MfaRequired(MfaChallengeResponse {
+            mfa_required: true,
+            challenge_token: generate_jwt(&challenge_claims, &config.jwt_secret)?,
+            expires_in: config.mfa_challenge_expiry,
+        })));
+    }
+
+    let response = start_session(&pool, &config, user, &client).await?;
+
+    Ok(Json(LoginResponse::Authenticated(response)))
 }
 
 pub async fn refresh(
     State(pool): State<DbPool>,
     State(config): State<Config>,
-    Json(refresh_token): Json<serde_json::Value>,
+    client: ClientInfo,
+    Json(payload): Json<RefreshRequest>,
 ) -> AppResult<Json<AuthResponse>> {
-    let token = refresh_token
-        .get("refresh_token")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| AppError::Validation("Missing refresh_token field".to_string()))?;
+    let claims = crate::middleware::auth::verify_jwt(&payload.refresh_token, &config.jwt_secret)?;
+
+    if claims.token_type != "refresh" {
+        return Err(AppError::Authentication("Invalid token type".to_string()));
+    }
+
+    let response = rotate_session(&pool, &config, &payload.refresh_token, &claims, &client).await?;
+
+    Ok(Json(response))
+}
 
-    let claims = crate::middleware::auth::verify_jwt(token, &config.jwt_secret)?;
+/// Log out by revoking the session the refresh token belongs to
+/// POST /api/auth/logout
+pub async fn logout(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    Json(payload): Json<RefreshRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let claims = crate::middleware::auth::verify_jwt(&payload.refresh_token, &config.jwt_secret)?;
 
     if claims.token_type != "refresh" {
         return Err(AppError::Authentication("Invalid token type".to_string()));
     }
 
-    // Fetch user to ensure they still exist
-    let user = sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, full_name, created_at, updated_at FROM users WHERE id = $1"
-    )
-    .bind(claims.sub)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or_else(|| AppError::Authentication("User not found".to_string()))?;
-
-    // Generate new tokens
-    let access_claims = Claims::new_access_token(
-        user.id,
-        user.username.clone(),
-        config.jwt_access_token_expiry,
-    );
-    let refresh_claims = Claims::new_refresh_token(
-        user.id,
-        user.username.clone(),
-        config.jwt_refresh_token_expiry,
-    );
-
-    let access_token = generate_jwt(&access_claims, &config.jwt_secret)?;
-    let new_refresh_token = generate_jwt(&refresh_claims, &config.jwt_secret)?;
-
-    Ok(Json(AuthResponse {
-        access_token,
-        refresh_token: new_refresh_token,
-        token_type: "Bearer".to_string(),
-        expires_in: config.jwt_access_token_expiry,
-        user: user.into(),
-    }))
+    if let Some(session_id) = claims.sid {
+        revoke_session_by_id(&pool, claims.sub, session_id, "logout").await?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "Logged out successfully"
+    })))
 }
 
 pub async fn get_current_user(
@@ -194,7 +220,7 @@ pub async fn get_current_user(
     claims: Claims,
 ) -> AppResult<Json<UserResponse>> {
     let user = sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, full_name, created_at, updated_at FROM users WHERE id = $1"
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
     )
     .bind(claims.sub)
     .fetch_optional(&pool)
@@ -203,3 +229,29 @@ pub async fn get_current_user(
 
     Ok(Json(user.into()))
 }
+
+/// Hash checked when logging in to an account that does not exist
+/// Made with `hash_password` so it costs as much to verify as a real one.
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("no account has this password").expect("hashing a fixed password"));
+
+/// Hash a password using Argon2
+pub fn hash_password(password: &str) -> AppResult<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|_| AppError::PasswordHash)?
+        .to_string();
+
+    Ok(password_hash)
+}
+
+/// Check a password against a stored Argon2 hash
+pub fn verify_password(password: &str, password_hash: &str) -> AppResult<bool> {
+    let parsed_hash = PasswordHash::new(password_hash)
+        .map_err(|_| AppError::PasswordHash)?;
+
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok())
+}
@@ -2,13 +2,18 @@ use axum::{
     extract::{Path, Query, State},
     Json,
 };
+use panicless_mcp_lib::{enrichment::mark_user_fields, isbn::normalize_isbn};
 use validator::Validate;
 
 use crate::{
+    covers::SharedCoverStore,
     db::DbPool,
     errors::{AppError, AppResult},
+    handlers::covers::delete_cover_files,
     middleware::Claims,
-    models::book::{Book, BookQuery, AdvancedBookSearchQuery, CreateBook, UpdateBook},
+    models::cover::{BookCover, BOOK_COVER_COLUMNS},
+    models::book::{Book, BookQuery, AdvancedBookSearchQuery, CreateBook, UpdateBook, BOOK_COLUMNS},
+    models::identifier::{identifier_candidates, isbn_identifier},
     models::reading::Reading,
 };
 
@@ -22,9 +27,7 @@ pub async fn list_books(
     let limit = query.limit.unwrap_or(20);
     let offset = (page - 1) * limit;
 
-    let mut sql = String::from(
-        "SELECT id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, created_at, updated_at FROM books WHERE user_id = $1"
-    );
+    let mut sql = format!("SELECT {} FROM books WHERE user_id = $1", BOOK_COLUMNS);
 
     let mut param_count = 2;
 
@@ -74,9 +77,10 @@ pub async fn get_book(
     claims: Claims,
 ) -> AppResult<Json<Book>> {
 
-    let book = sqlx::query_as::<_, Book>(
-        "SELECT id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, created_at, updated_at FROM books WHERE id = $1 AND user_id = $2"
-    )
+    let book = sqlx::query_as::<_, Book>(&format!(
+        "SELECT {} FROM books WHERE id = $1 AND user_id = $2",
+        BOOK_COLUMNS
+    ))
     .bind(book_id)
     .bind(claims.sub)
     .fetch_optional(&pool)
@@ -94,26 +98,45 @@ pub async fn create_book(
 
     payload.validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
-
-    let book = sqlx::query_as::<_, Book>(
-        "INSERT INTO books (user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url)
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-         RETURNING id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, created_at, updated_at"
-    )
+    let isbn = normalize_isbn(payload.isbn.as_deref()).map_err(AppError::Validation)?;
+    let user_fields = set_fields(&[
+        ("description", payload.description.is_some()),
+        ("cover_image_url", payload.cover_image_url.is_some()),
+        ("language", payload.language.is_some()),
+        ("pages", payload.pages.is_some()),
+        ("publisher", payload.publisher.is_some()),
+        ("publication_year", payload.publication_year.is_some()),
+        ("original_publication_year", payload.original_publication_year.is_some()),
+    ]);
+
+    let book = sqlx::query_as::<_, Book>(&format!(
+        "INSERT INTO books (user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, series, series_index, tags, format, original_publication_year, owned_copies)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+         RETURNING {}",
+        BOOK_COLUMNS
+    ))
     .bind(claims.sub)
     .bind(&payload.title)
     .bind(&payload.author)
     .bind(&payload.edition)
-    .bind(&payload.isbn)
-    .bind(&payload.publication_year)
+    .bind(&isbn)
+    .bind(payload.publication_year)
     .bind(&payload.publisher)
-    .bind(&payload.pages)
+    .bind(payload.pages)
     .bind(&payload.language)
     .bind(&payload.description)
     .bind(&payload.cover_image_url)
+    .bind(&payload.series)
+    .bind(payload.series_index)
+    .bind(payload.tags.clone().unwrap_or_default())
+    .bind(&payload.format)
+    .bind(payload.original_publication_year)
+    .bind(payload.owned_copies)
     .fetch_one(&pool)
     .await?;
 
+    mark_user_fields(&pool, book.id, &user_fields).await?;
+
     Ok(Json(book))
 }
 
@@ -126,11 +149,19 @@ pub async fn update_book(
 
     payload.validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
+    // A blank ISBN clears it
+    let isbn = payload
+        .isbn
+        .as_deref()
+        .map(|isbn| normalize_isbn(Some(isbn)))
+        .transpose()
+        .map_err(AppError::Validation)?;
 
     // Verify book belongs to user
-    let existing = sqlx::query_as::<_, Book>(
-        "SELECT id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, created_at, updated_at FROM books WHERE id = $1 AND user_id = $2"
-    )
+    let existing = sqlx::query_as::<_, Book>(&format!(
+        "SELECT {} FROM books WHERE id = $1 AND user_id = $2",
+        BOOK_COLUMNS
+    ))
     .bind(book_id)
     .bind(claims.sub)
     .fetch_optional(&pool)
@@ -153,7 +184,7 @@ pub async fn update_book(
         updates.push(format!("edition = ${}", param_count));
         param_count += 1;
     }
-    if payload.isbn.is_some() {
+    if isbn.is_some() {
         updates.push(format!("isbn = ${}", param_count));
         param_count += 1;
     }
@@ -181,16 +212,52 @@ pub async fn update_book(
         updates.push(format!("cover_image_url = ${}", param_count));
         param_count += 1;
     }
+    if payload.series.is_some() {
+        updates.push(format!("series = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.series_index.is_some() {
+        updates.push(format!("series_index = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.tags.is_some() {
+        updates.push(format!("tags = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.format.is_some() {
+        updates.push(format!("format = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.original_publication_year.is_some() {
+        updates.push(format!("original_publication_year = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.owned_copies.is_some() {
+        updates.push(format!("owned_copies = ${}", param_count));
+        param_count += 1;
+    }
 
     if updates.is_empty() {
         return Ok(Json(existing));
     }
 
+    // Fields set here, even to blank, are the user's from now on
+    let user_fields = set_fields(&[
+        ("description", payload.description.is_some()),
+        ("cover_image_url", payload.cover_image_url.is_some()),
+        ("language", payload.language.is_some()),
+        ("pages", payload.pages.is_some()),
+        ("publisher", payload.publisher.is_some()),
+        ("publication_year", payload.publication_year.is_some()),
+        ("original_publication_year", payload.original_publication_year.is_some()),
+    ]);
+
     updates.push("updated_at = CURRENT_TIMESTAMP".to_string());
     let sql = format!(
-        "UPDATE books SET {} WHERE id = ${} RETURNING id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, created_at, updated_at",
+        "UPDATE books SET {} WHERE id = ${} RETURNING {}",
         updates.join(", "),
-        param_count
+        param_count,
+        BOOK_COLUMNS
     );
 
     let mut query_builder = sqlx::query_as::<_, Book>(&sql);
@@ -204,7 +271,7 @@ pub async fn update_book(
     if let Some(edition) = payload.edition {
         query_builder = query_builder.bind(edition);
     }
-    if let Some(isbn) = payload.isbn {
+    if let Some(isbn) = isbn {
         query_builder = query_builder.bind(isbn);
     }
     if let Some(publication_year) = payload.publication_year {
@@ -225,32 +292,72 @@ pub async fn update_book(
     if let Some(cover_image_url) = payload.cover_image_url {
         query_builder = query_builder.bind(cover_image_url);
     }
+    if let Some(series) = payload.series {
+        query_builder = query_builder.bind(series);
+    }
+    if let Some(series_index) = payload.series_index {
+        query_builder = query_builder.bind(series_index);
+    }
+    if let Some(tags) = payload.tags {
+        query_builder = query_builder.bind(tags);
+    }
+    if let Some(format) = payload.format {
+        query_builder = query_builder.bind(format);
+    }
+    if let Some(original_publication_year) = payload.original_publication_year {
+        query_builder = query_builder.bind(original_publication_year);
+    }
+    if let Some(owned_copies) = payload.owned_copies {
+        query_builder = query_builder.bind(owned_copies);
+    }
 
     query_builder = query_builder.bind(book_id);
 
     let book = query_builder.fetch_one(&pool).await?;
 
+    mark_user_fields(&pool, book.id, &user_fields).await?;
+
     Ok(Json(book))
 }
 
 pub async fn delete_book(
     State(pool): State<DbPool>,
+    State(covers): State<SharedCoverStore>,
     Path(book_id): Path<i32>,
     claims: Claims,
 ) -> AppResult<Json<serde_json::Value>> {
 
+    let mut tx = pool.begin().await?;
+
+    // Taken out first so the files can be removed once the book is gone
+    let cover = sqlx::query_as::<_, BookCover>(&format!(
+        "DELETE FROM book_covers WHERE book_id = $1 AND book_id IN (SELECT id FROM books WHERE user_id = $2)
+         RETURNING {}",
+        BOOK_COVER_COLUMNS
+    ))
+    .bind(book_id)
+    .bind(claims.sub)
+    .fetch_optional(&mut *tx)
+    .await?;
+
     let result = sqlx::query(
         "DELETE FROM books WHERE id = $1 AND user_id = $2"
     )
     .bind(book_id)
     .bind(claims.sub)
-    .execute(&pool)
+    .execute(&mut *tx)
     .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound("Book not found".to_string()));
     }
 
+    tx.commit().await?;
+
+    if let Some(cover) = cover {
+        delete_cover_files(covers.as_ref(), &cover).await;
+    }
+
     Ok(Json(serde_json::json!({
         "message": "Book deleted successfully"
     })))
@@ -263,9 +370,10 @@ pub async fn get_book_readings(
 ) -> AppResult<Json<Vec<Reading>>> {
 
     // Verify book belongs to user
-    let _ = sqlx::query_as::<_, Book>(
-        "SELECT id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, created_at, updated_at FROM books WHERE id = $1 AND user_id = $2"
-    )
+    let _ = sqlx::query_as::<_, Book>(&format!(
+        "SELECT {} FROM books WHERE id = $1 AND user_id = $2",
+        BOOK_COLUMNS
+    ))
     .bind(book_id)
     .bind(claims.sub)
     .fetch_optional(&pool)
@@ -293,9 +401,20 @@ pub async fn advanced_search_books(
     let limit = query.limit.unwrap_or(20);
     let offset = (page - 1) * limit;
 
-    let mut sql = String::from(
-        "SELECT id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, created_at, updated_at FROM books WHERE user_id = $1"
-    );
+    // ISBN-10s and ISBN-13s match any ISBN the book carries, however they are
+    // hyphenated; anything else is compared with the book's ISBN as typed
+    let isbn = query.isbn.as_deref().map(|isbn| isbn_identifier(isbn).ok_or_else(|| isbn.to_string()));
+    let identifiers = match query.identifier.as_deref() {
+        Some(identifier) => {
+            let candidates = identifier_candidates(query.identifier_type, identifier).map_err(AppError::Validation)?;
+            let (types, values): (Vec<&str>, Vec<String>) =
+                candidates.into_iter().map(|(id_type, value)| (id_type.as_str(), value)).unzip();
+            Some((types, values))
+        }
+        None => None,
+    };
+
+    let mut sql = format!("SELECT {} FROM books WHERE user_id = $1", BOOK_COLUMNS);
 
     let mut param_count = 2;
 
@@ -309,9 +428,20 @@ pub async fn advanced_search_books(
         param_count += 1;
     }
 
-    if query.isbn.is_some() {
-        sql.push_str(&format!(" AND isbn = ${}", param_count));
-        param_count += 1;
+    match isbn {
+        Some(Ok(_)) => {
+            sql.push_str(&format!(
+                " AND id IN (SELECT book_id FROM book_identifiers WHERE id_type = ${} AND value = ${})",
+                param_count,
+                param_count + 1
+            ));
+            param_count += 2;
+        }
+        Some(Err(_)) => {
+            sql.push_str(&format!(" AND isbn = ${}", param_count));
+            param_count += 1;
+        }
+        None => {}
     }
 
     if query.edition.is_some() {
@@ -339,6 +469,16 @@ pub async fn advanced_search_books(
         param_count += 1;
     }
 
+    if identifiers.is_some() {
+        sql.push_str(&format!(
+            " AND id IN (SELECT book_id FROM book_identifiers
+                         WHERE (id_type, value) IN (SELECT * FROM UNNEST(${}::text[], ${}::text[])))",
+            param_count,
+            param_count + 1
+        ));
+        param_count += 2;
+    }
+
     sql.push_str(&format!(" ORDER BY title LIMIT ${} OFFSET ${}", param_count, param_count + 1));
 
     let mut query_builder = sqlx::query_as::<_, Book>(&sql).bind(claims.sub);
@@ -353,8 +493,10 @@ pub async fn advanced_search_books(
         query_builder = query_builder.bind(author_pattern);
     }
 
-    if let Some(isbn) = query.isbn {
-        query_builder = query_builder.bind(isbn);
+    match isbn {
+        Some(Ok((id_type, value))) => query_builder = query_builder.bind(id_type.as_str()).bind(value),
+        Some(Err(isbn)) => query_builder = query_builder.bind(isbn),
+        None => {}
     }
 
     if let Some(edition) = query.edition {
@@ -381,9 +523,18 @@ pub async fn advanced_search_books(
         query_builder = query_builder.bind(description_pattern);
     }
 
+    if let Some((types, values)) = identifiers {
+        query_builder = query_builder.bind(types).bind(values);
+    }
+
     query_builder = query_builder.bind(limit).bind(offset);
 
     let books = query_builder.fetch_all(&pool).await?;
 
     Ok(Json(books))
 }
+
+/// Names of the enrichable fields a request sets
+fn set_fields(fields: &[(&'static str, bool)]) -> Vec<&'static str> {
+    fields.iter().filter(|(_, set)| *set).map(|(field, _)| *field).collect()
+}
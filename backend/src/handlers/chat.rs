@@ -0,0 +1,88 @@
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::stream::{Stream, StreamExt};
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    chat::{self, ChatEvent, LibraryTools, ProviderRequest},
+    config::Config,
+    db::DbPool,
+    errors::{AppError, AppResult},
+    middleware::auth::Claims,
+    models::{
+        chat::ChatRequest,
+        connector::{Connector, CONNECTOR_COLUMNS},
+    },
+};
+
+const SYSTEM_PROMPT: &str = "You are the reading assistant of Panicless Library, a personal book and reading tracker. \
+Use the provided tools to look up, add or update the user's books and readings instead of guessing. \
+Keep answers short and mention book titles exactly as stored.";
+
+/// Run a tool-using conversation against the user's active connector
+/// POST /api/chat
+///
+/// Streams `message`, `tool_call`, `tool_result`, and finally `done` or `error` events
+pub async fn chat(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Json(payload): Json<ChatRequest>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let messages = payload.to_messages().map_err(AppError::Validation)?;
+
+    let connector = sqlx::query_as::<_, Connector>(&format!(
+        r#"
+        SELECT {}
+        FROM connectors
+        WHERE user_id = $1 AND is_active = TRUE AND ($2::TEXT IS NULL OR provider = $2)
+        ORDER BY last_used_at DESC NULLS LAST, updated_at DESC
+        LIMIT 1
+        "#,
+        CONNECTOR_COLUMNS
+    ))
+    .bind(claims.sub)
+    .bind(payload.provider.as_deref())
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| match &payload.provider {
+        Some(provider) => AppError::NotFound(format!("No active '{}' connector configured", provider)),
+        None => AppError::NotFound("No active connector configured".to_string()),
+    })?;
+
+    let provider = chat::provider_for(&connector, &config)?;
+
+    sqlx::query("UPDATE connectors SET last_used_at = NOW() WHERE id = $1")
+        .bind(connector.id)
+        .execute(&pool)
+        .await?;
+
+    let request = ProviderRequest {
+        system: format!("{}\nToday is {}.", SYSTEM_PROMPT, chrono::Utc::now().format("%Y-%m-%d")),
+        messages,
+        tools: chat::library_tool_specs(),
+        max_tokens: config.chat_max_tokens,
+    };
+    let tools = LibraryTools { pool, user_id: claims.sub };
+    let max_rounds = config.chat_max_tool_rounds;
+
+    let (tx, rx) = mpsc::channel(32);
+    tokio::spawn(async move {
+        if let Err(e) = chat::run_conversation(provider.as_ref(), &tools, request, max_rounds, &tx).await {
+            tracing::warn!("Chat with {} connector failed: {}", connector.provider, e);
+            let _ = tx.send(ChatEvent::Error { message: e.to_string() }).await;
+        }
+    });
+
+    let stream = ReceiverStream::new(rx).map(|event| {
+        let data = serde_json::to_string(&event).unwrap_or_default();
+        Ok::<_, Infallible>(Event::default().event(event.name()).data(data))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -3,15 +3,16 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use chrono::Utc;
 
 use crate::{
+    chat::egress::check_endpoint,
     config::Config,
     crypto::TokenCrypto,
     db::DbPool,
     errors::{AppError, AppResult},
     middleware::auth::Claims,
-    models::connector::{Connector, ConnectorResponse, CreateConnectorRequest, validate_provider},
+    models::connector::{Connector, ConnectorResponse, CreateConnectorRequest, CONNECTOR_COLUMNS},
+    services::connector_verification::verify_connector,
 };
 
 /// Create a new connector or update existing one
@@ -22,36 +23,48 @@ pub async fn create_or_update_connector(
     claims: Claims,
     Json(payload): Json<CreateConnectorRequest>,
 ) -> AppResult<(StatusCode, Json<ConnectorResponse>)> {
-    // Validate provider
-    validate_provider(&payload.provider)
-        .map_err(|e| AppError::Validation(e))?;
-
-    // Validate token is not empty
-    if payload.api_token.trim().is_empty() {
-        return Err(AppError::Validation(
-            "API token cannot be empty".to_string(),
-        ));
+    // Validate provider and the fields it needs
+    let settings = payload
+        .validate(&config.custom_connector_allowed_hosts)
+        .map_err(AppError::Validation)?;
+    if let Some(base_url) = &settings.base_url {
+        check_endpoint(base_url, &config.custom_connector_allowed_hosts)
+            .await
+            .map_err(AppError::Validation)?;
     }
 
     // Encrypt the token
-    let crypto = TokenCrypto::new(&config.encryption_key)?;
-    let encrypted_token = crypto.encrypt(&payload.api_token)?;
+    let crypto = TokenCrypto::from_config(&config)?;
+    let encrypted_token = settings
+        .api_token
+        .as_deref()
+        .map(|token| crypto.encrypt(token))
+        .transpose()?;
 
     // Upsert in database
-    let connector = sqlx::query_as::<_, Connector>(
+    // A new key has not been checked yet
+    let connector = sqlx::query_as::<_, Connector>(&format!(
         r#"
-        INSERT INTO connectors (user_id, provider, encrypted_token, created_at, updated_at)
-        VALUES ($1, $2, $3, NOW(), NOW())
+        INSERT INTO connectors (user_id, provider, encrypted_token, base_url, model, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
         ON CONFLICT (user_id, provider)
         DO UPDATE SET
             encrypted_token = $3,
+            base_url = $4,
+            model = $5,
+            status = 'unverified',
+            status_detail = NULL,
+            verified_at = NULL,
             updated_at = NOW()
-        RETURNING id, user_id, provider, encrypted_token, is_active, last_used_at, created_at, updated_at
+        RETURNING {}
         "#,
-    )
+        CONNECTOR_COLUMNS
+    ))
     .bind(claims.sub)
     .bind(&payload.provider)
     .bind(&encrypted_token)
+    .bind(&settings.base_url)
+    .bind(&settings.model)
     .fetch_one(&pool)
     .await
     .map_err(|e| {
@@ -68,9 +81,10 @@ pub async fn list_connectors(
     State(pool): State<DbPool>,
     claims: Claims,
 ) -> AppResult<Json<Vec<ConnectorResponse>>> {
-    let connectors = sqlx::query_as::<_, Connector>(
-        "SELECT id, user_id, provider, encrypted_token, is_active, last_used_at, created_at, updated_at FROM connectors WHERE user_id = $1 ORDER BY created_at DESC",
-    )
+    let connectors = sqlx::query_as::<_, Connector>(&format!(
+        "SELECT {} FROM connectors WHERE user_id = $1 ORDER BY created_at DESC",
+        CONNECTOR_COLUMNS
+    ))
     .bind(claims.sub)
     .fetch_all(&pool)
     .await
@@ -90,9 +104,10 @@ pub async fn get_connector(
     claims: Claims,
     Path(provider): Path<String>,
 ) -> AppResult<Json<ConnectorResponse>> {
-    let connector = sqlx::query_as::<_, Connector>(
-        "SELECT id, user_id, provider, encrypted_token, is_active, last_used_at, created_at, updated_at FROM connectors WHERE user_id = $1 AND provider = $2",
-    )
+    let connector = sqlx::query_as::<_, Connector>(&format!(
+        "SELECT {} FROM connectors WHERE user_id = $1 AND provider = $2",
+        CONNECTOR_COLUMNS
+    ))
     .bind(claims.sub)
     .bind(&provider)
     .fetch_optional(&pool)
@@ -140,9 +155,10 @@ pub async fn toggle_connector(
     claims: Claims,
     Path(provider): Path<String>,
 ) -> AppResult<Json<ConnectorResponse>> {
-    let connector = sqlx::query_as::<_, Connector>(
-        "SELECT id, user_id, provider, encrypted_token, is_active, last_used_at, created_at, updated_at FROM connectors WHERE user_id = $1 AND provider = $2",
-    )
+    let connector = sqlx::query_as::<_, Connector>(&format!(
+        "SELECT {} FROM connectors WHERE user_id = $1 AND provider = $2",
+        CONNECTOR_COLUMNS
+    ))
     .bind(claims.sub)
     .bind(&provider)
     .fetch_optional(&pool)
@@ -153,11 +169,12 @@ pub async fn toggle_connector(
     })?
     .ok_or_else(|| AppError::NotFound(format!("Connector '{}' not found", provider)))?;
 
-    let updated_connector = sqlx::query_as::<_, Connector>(
+    let updated_connector = sqlx::query_as::<_, Connector>(&format!(
         "UPDATE connectors SET is_active = NOT is_active, updated_at = NOW()
          WHERE id = $1
-         RETURNING id, user_id, provider, encrypted_token, is_active, last_used_at, created_at, updated_at",
-    )
+         RETURNING {}",
+        CONNECTOR_COLUMNS
+    ))
     .bind(connector.id)
     .fetch_one(&pool)
     .await
@@ -168,3 +185,31 @@ pub async fn toggle_connector(
 
     Ok(Json(updated_connector.into()))
 }
+
+/// Check the stored key with the provider and record the outcome
+/// POST /api/connectors/:provider/verify
+pub async fn verify_connector_key(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Path(provider): Path<String>,
+) -> AppResult<Json<ConnectorResponse>> {
+    let connector = sqlx::query_as::<_, Connector>(&format!(
+        "SELECT {} FROM connectors WHERE user_id = $1 AND provider = $2",
+        CONNECTOR_COLUMNS
+    ))
+    .bind(claims.sub)
+    .bind(&provider)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch connector: {}", e);
+        AppError::Internal("Failed to fetch connector".to_string())
+    })?
+    .ok_or_else(|| AppError::NotFound(format!("Connector '{}' not found", provider)))?;
+
+    // A manual check only reports; deactivation is left to the user
+    let verified = verify_connector(&pool, &config, &connector, false).await?;
+
+    Ok(Json(verified.into()))
+}
@@ -0,0 +1,224 @@
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use panicless_mcp_lib::enrichment::mark_user_fields;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    covers::{process_cover, CoverSize, CoverStore, SharedCoverStore},
+    db::DbPool,
+    errors::{AppError, AppResult},
+    handlers::{identifiers::ensure_book_owned, import::extract_file_from_multipart},
+    middleware::Claims,
+    models::cover::{cover_file_name, BookCover, CoverInfo, CoverQuery, BOOK_COVER_COLUMNS},
+};
+
+/// Maximum cover upload size
+pub const MAX_COVER_SIZE: usize = 10 * 1024 * 1024; // 10 MB
+
+/// Upload a book's cover
+/// The image is stored as uploaded along with medium and thumbnail JPEGs, and
+/// the book's `cover_image_url` is pointed at it.
+/// POST /api/books/:id/cover (multipart, field "file")
+pub async fn upload_cover(
+    State(pool): State<DbPool>,
+    State(covers): State<SharedCoverStore>,
+    Path(book_id): Path<i32>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> AppResult<Json<CoverInfo>> {
+
+    ensure_book_owned(&pool, book_id, claims.sub).await?;
+
+    let data = extract_file_from_multipart(&mut multipart).await?;
+    if data.len() > MAX_COVER_SIZE {
+        return Err(AppError::Validation(format!(
+            "Cover images are limited to {} MB",
+            MAX_COVER_SIZE / 1024 / 1024
+        )));
+    }
+
+    let (data, processed) = tokio::task::spawn_blocking(move || {
+        let processed = process_cover(&data);
+        (data, processed)
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Cover processing failed: {}", e)))?;
+    let processed = processed.map_err(AppError::Validation)?;
+
+    let sha256: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+    let storage_prefix = format!("{}/{}/{}", claims.sub, book_id, &sha256[..16]);
+
+    // Files first: a cover row always has its files
+    for (size, bytes, content_type) in [
+        (CoverSize::Original, &data, processed.content_type),
+        (CoverSize::Medium, &processed.medium, "image/jpeg"),
+        (CoverSize::Thumbnail, &processed.thumbnail, "image/jpeg"),
+    ] {
+        let key = format!("{}/{}", storage_prefix, cover_file_name(size, processed.content_type));
+        covers.put(&key, bytes, content_type).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let previous = sqlx::query_as::<_, BookCover>(&format!(
+        "SELECT {} FROM book_covers WHERE book_id = $1 FOR UPDATE",
+        BOOK_COVER_COLUMNS
+    ))
+    .bind(book_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let cover = sqlx::query_as::<_, BookCover>(&format!(
+        "INSERT INTO book_covers (book_id, storage_prefix, content_type, width, height, byte_size, sha256)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (book_id) DO UPDATE SET
+             storage_prefix = EXCLUDED.storage_prefix,
+             content_type = EXCLUDED.content_type,
+             width = EXCLUDED.width,
+             height = EXCLUDED.height,
+             byte_size = EXCLUDED.byte_size,
+             sha256 = EXCLUDED.sha256,
+             uploaded_at = CURRENT_TIMESTAMP
+         RETURNING {}",
+        BOOK_COVER_COLUMNS
+    ))
+    .bind(book_id)
+    .bind(&storage_prefix)
+    .bind(processed.content_type)
+    .bind(processed.width as i32)
+    .bind(processed.height as i32)
+    .bind(data.len() as i32)
+    .bind(&sha256)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE books SET cover_image_url = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+        .bind(cover.url())
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+    mark_user_fields(&mut *tx, book_id, &["cover_image_url"]).await?;
+
+    tx.commit().await?;
+
+    // Uploading the same image again keeps the same files
+    if let Some(previous) = previous.filter(|previous| previous.storage_prefix != cover.storage_prefix) {
+        delete_cover_files(covers.as_ref(), &previous).await;
+    }
+
+    Ok(Json(CoverInfo::from(&cover)))
+}
+
+/// A book's uploaded cover
+/// `?size=thumbnail|medium|original`. Responses carry an ETag; requested with the
+/// current `?v=` of the cover URL they may be cached for good, otherwise the
+/// client revalidates.
+/// GET /api/books/:id/cover
+pub async fn get_cover(
+    State(pool): State<DbPool>,
+    State(covers): State<SharedCoverStore>,
+    Path(book_id): Path<i32>,
+    Query(query): Query<CoverQuery>,
+    claims: Claims,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+
+    let cover = find_cover(&pool, book_id, claims.sub).await?;
+
+    let etag = format!("\"{}-{}\"", cover.version(), query.size.as_str());
+    let cache_control = if query.v.as_deref() == Some(cover.version()) {
+        "private, max-age=31536000, immutable"
+    } else {
+        "private, no-cache"
+    };
+    let last_modified = cover.uploaded_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| matches!(tag.trim(), "*") || tag.trim() == etag));
+
+    let builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::LAST_MODIFIED, &last_modified);
+
+    if not_modified {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .map_err(|e| AppError::Internal(e.to_string()));
+    }
+
+    let data = covers.get(&cover.key(query.size)).await?.ok_or_else(|| {
+        AppError::Internal(format!("Cover file {} is missing from storage", cover.key(query.size)))
+    })?;
+
+    builder
+        .header(header::CONTENT_TYPE, cover.size_content_type(query.size))
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(Body::from(data))
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// Remove a book's uploaded cover
+/// DELETE /api/books/:id/cover
+pub async fn delete_cover(
+    State(pool): State<DbPool>,
+    State(covers): State<SharedCoverStore>,
+    Path(book_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<serde_json::Value>> {
+
+    let cover = find_cover(&pool, book_id, claims.sub).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM book_covers WHERE book_id = $1")
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+    // Only when it still points at the upload; the user may have set another URL since
+    sqlx::query(
+        "UPDATE books SET cover_image_url = NULL, updated_at = CURRENT_TIMESTAMP
+         WHERE id = $1 AND cover_image_url LIKE '/api/books/' || id || '/cover%'"
+    )
+    .bind(book_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    delete_cover_files(covers.as_ref(), &cover).await;
+
+    Ok(Json(serde_json::json!({
+        "message": "Cover deleted successfully"
+    })))
+}
+
+/// Remove a cover's files from storage
+/// Failures are logged rather than returned: the cover is already gone from the
+/// database, so what is left behind is only unreferenced files.
+pub(crate) async fn delete_cover_files(covers: &dyn CoverStore, cover: &BookCover) {
+    for key in cover.keys() {
+        if let Err(e) = covers.delete(&key).await {
+            tracing::warn!("Failed to delete cover file {}: {}", key, e);
+        }
+    }
+}
+
+async fn find_cover(pool: &DbPool, book_id: i32, user_id: i32) -> AppResult<BookCover> {
+    sqlx::query_as::<_, BookCover>(&format!(
+        "SELECT {} FROM book_covers
+         WHERE book_id = $1 AND book_id IN (SELECT id FROM books WHERE user_id = $2)",
+        BOOK_COVER_COLUMNS
+    ))
+    .bind(book_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Cover not found".to_string()))
+}
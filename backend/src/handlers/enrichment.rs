@@ -0,0 +1,56 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+use panicless_mcp_lib::enrichment::{self, BookEnrichment, EnrichmentSummary, FieldSource};
+
+use crate::{
+    db::DbPool,
+    errors::{AppError, AppResult},
+    handlers::identifiers::ensure_book_owned,
+    middleware::Claims,
+};
+
+/// Fill the empty fields of a book from the loaded Open Library data
+/// Fields the user has edited are never changed.
+/// POST /api/books/:id/enrich
+pub async fn enrich_book(
+    State(pool): State<DbPool>,
+    Path(book_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<BookEnrichment>> {
+
+    let enrichment = enrichment::enrich_book(&pool, claims.sub, book_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Book not found".to_string()))?;
+
+    Ok(Json(enrichment))
+}
+
+/// Fill the empty fields of every book in the library
+/// POST /api/books/enrich
+pub async fn enrich_library(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> AppResult<Json<EnrichmentSummary>> {
+
+    let summary = enrichment::enrich_library(&pool, claims.sub).await?;
+
+    Ok(Json(summary))
+}
+
+/// Where the fields of a book came from, for those that are known
+/// GET /api/books/:id/provenance
+pub async fn get_book_provenance(
+    State(pool): State<DbPool>,
+    Path(book_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<Vec<FieldSource>>> {
+
+    ensure_book_owned(&pool, book_id, claims.sub).await?;
+
+    let sources = enrichment::field_sources(&pool, book_id).await?;
+
+    Ok(Json(sources))
+}
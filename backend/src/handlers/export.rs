@@ -0,0 +1,55 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+
+use crate::{
+    db::DbPool,
+    errors::{AppError, AppResult},
+    middleware::Claims,
+    services::{goodreads_export::write_goodreads_csv, library_export::export_library},
+};
+
+/// Download the user's profile, books, readings and connectors as a JSON archive
+/// Connector keys are not included.
+/// GET /api/export
+pub async fn export_account(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> AppResult<impl IntoResponse> {
+    let export = export_library(&pool, claims.sub).await?;
+
+    let disposition = format!(
+        "attachment; filename=\"panicless-export-{}.json\"",
+        Utc::now().format("%Y-%m-%d")
+    );
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(export)))
+}
+
+/// Download the library in the Goodreads CSV format, which most reading sites import
+/// GET /api/export/goodreads.csv
+pub async fn export_goodreads_csv(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> AppResult<impl IntoResponse> {
+    let export = export_library(&pool, claims.sub).await?;
+    let csv = write_goodreads_csv(&export.books, &export.readings).map_err(|e| {
+        tracing::error!("Failed to write Goodreads CSV: {}", e);
+        AppError::Internal("Failed to export library".to_string())
+    })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"goodreads_library_export.csv\"".to_string(),
+            ),
+        ],
+        csv,
+    ))
+}
@@ -0,0 +1,299 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use validator::Validate;
+
+use crate::{
+    db::DbPool,
+    errors::{AppError, AppResult},
+    middleware::Claims,
+    models::highlight::{
+        CreateHighlight, Highlight, HighlightQuery, HighlightWithBook, UpdateHighlight,
+        HIGHLIGHT_COLUMNS,
+    },
+};
+
+/// List highlights, newest first, optionally searched or limited to one book
+/// GET /api/highlights?q=fear&book_id=1
+pub async fn list_highlights(
+    State(pool): State<DbPool>,
+    Query(query): Query<HighlightQuery>,
+    claims: Claims,
+) -> AppResult<Json<Vec<HighlightWithBook>>> {
+
+    let page = query.page.unwrap_or(1);
+    let limit = query.limit.unwrap_or(20);
+    let offset = (page - 1) * limit;
+
+    let mut sql = String::from(
+        "SELECT h.id, h.user_id, h.book_id, h.reading_id, h.text, h.note, h.location_start, h.location_end, h.page,
+                h.highlighted_at, h.source, h.created_at, h.updated_at, b.title as book_title, b.author as book_author
+         FROM highlights h
+         JOIN books b ON h.book_id = b.id
+         WHERE h.user_id = $1"
+    );
+
+    let mut param_count = 2;
+
+    if query.q.is_some() {
+        sql.push_str(&format!(
+            " AND (h.text ILIKE ${} OR h.note ILIKE ${} OR b.title ILIKE ${})",
+            param_count, param_count, param_count
+        ));
+        param_count += 1;
+    }
+
+    if query.book_id.is_some() {
+        sql.push_str(&format!(" AND h.book_id = ${}", param_count));
+        param_count += 1;
+    }
+
+    sql.push_str(&format!(
+        " ORDER BY COALESCE(h.highlighted_at, h.created_at) DESC, h.id DESC LIMIT ${} OFFSET ${}",
+        param_count,
+        param_count + 1
+    ));
+
+    let mut query_builder = sqlx::query_as::<_, HighlightWithBook>(&sql).bind(claims.sub);
+
+    if let Some(q) = query.q {
+        query_builder = query_builder.bind(format!("%{}%", q));
+    }
+
+    if let Some(book_id) = query.book_id {
+        query_builder = query_builder.bind(book_id);
+    }
+
+    query_builder = query_builder.bind(limit).bind(offset);
+
+    let highlights = query_builder.fetch_all(&pool).await?;
+
+    Ok(Json(highlights))
+}
+
+/// Highlights of one book in reading order
+/// GET /api/books/:id/highlights
+pub async fn get_book_highlights(
+    State(pool): State<DbPool>,
+    Path(book_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<Vec<Highlight>>> {
+
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM books WHERE id = $1 AND user_id = $2)"
+    )
+    .bind(book_id)
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+
+    if !exists {
+        return Err(AppError::NotFound("Book not found".to_string()));
+    }
+
+    let highlights = sqlx::query_as::<_, Highlight>(&format!(
+        "SELECT {} FROM highlights WHERE book_id = $1 AND user_id = $2
+         ORDER BY COALESCE(location_start, page * 100000), highlighted_at, id",
+        HIGHLIGHT_COLUMNS
+    ))
+    .bind(book_id)
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(highlights))
+}
+
+pub async fn get_highlight(
+    State(pool): State<DbPool>,
+    Path(highlight_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<Highlight>> {
+
+    let highlight = sqlx::query_as::<_, Highlight>(&format!(
+        "SELECT {} FROM highlights WHERE id = $1 AND user_id = $2",
+        HIGHLIGHT_COLUMNS
+    ))
+    .bind(highlight_id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Highlight not found".to_string()))?;
+
+    Ok(Json(highlight))
+}
+
+pub async fn create_highlight(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Json(payload): Json<CreateHighlight>,
+) -> AppResult<Json<Highlight>> {
+
+    payload
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    if payload.text.is_none() && payload.note.is_none() {
+        return Err(AppError::Validation(
+            "A highlight needs a text, a note or both".to_string(),
+        ));
+    }
+
+    let highlight = sqlx::query_as::<_, Highlight>(&format!(
+        "INSERT INTO highlights (user_id, book_id, reading_id, text, note, location_start, location_end, page, highlighted_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING {}",
+        HIGHLIGHT_COLUMNS
+    ))
+    .bind(claims.sub)
+    .bind(payload.book_id)
+    .bind(payload.reading_id)
+    .bind(&payload.text)
+    .bind(&payload.note)
+    .bind(payload.location_start)
+    .bind(payload.location_end)
+    .bind(payload.page)
+    .bind(payload.highlighted_at)
+    .fetch_one(&pool)
+    .await
+    .map_err(highlight_error)?;
+
+    Ok(Json(highlight))
+}
+
+pub async fn update_highlight(
+    State(pool): State<DbPool>,
+    Path(highlight_id): Path<i32>,
+    claims: Claims,
+    Json(payload): Json<UpdateHighlight>,
+) -> AppResult<Json<Highlight>> {
+
+    payload
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let existing = sqlx::query_as::<_, Highlight>(&format!(
+        "SELECT {} FROM highlights WHERE id = $1 AND user_id = $2",
+        HIGHLIGHT_COLUMNS
+    ))
+    .bind(highlight_id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Highlight not found".to_string()))?;
+
+    // Build dynamic update query
+    let mut updates = Vec::new();
+    let mut param_count = 1;
+
+    if payload.reading_id.is_some() {
+        updates.push(format!("reading_id = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.text.is_some() {
+        updates.push(format!("text = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.note.is_some() {
+        updates.push(format!("note = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.location_start.is_some() {
+        updates.push(format!("location_start = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.location_end.is_some() {
+        updates.push(format!("location_end = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.page.is_some() {
+        updates.push(format!("page = ${}", param_count));
+        param_count += 1;
+    }
+    if payload.highlighted_at.is_some() {
+        updates.push(format!("highlighted_at = ${}", param_count));
+        param_count += 1;
+    }
+
+    if updates.is_empty() {
+        return Ok(Json(existing));
+    }
+
+    updates.push("updated_at = CURRENT_TIMESTAMP".to_string());
+    let sql = format!(
+        "UPDATE highlights SET {} WHERE id = ${} RETURNING {}",
+        updates.join(", "),
+        param_count,
+        HIGHLIGHT_COLUMNS
+    );
+
+    let mut query_builder = sqlx::query_as::<_, Highlight>(&sql);
+
+    if let Some(reading_id) = payload.reading_id {
+        query_builder = query_builder.bind(reading_id);
+    }
+    if let Some(text) = payload.text {
+        query_builder = query_builder.bind(text);
+    }
+    if let Some(note) = payload.note {
+        query_builder = query_builder.bind(note);
+    }
+    if let Some(location_start) = payload.location_start {
+        query_builder = query_builder.bind(location_start);
+    }
+    if let Some(location_end) = payload.location_end {
+        query_builder = query_builder.bind(location_end);
+    }
+    if let Some(page) = payload.page {
+        query_builder = query_builder.bind(page);
+    }
+    if let Some(highlighted_at) = payload.highlighted_at {
+        query_builder = query_builder.bind(highlighted_at);
+    }
+
+    query_builder = query_builder.bind(highlight_id);
+
+    let highlight = query_builder.fetch_one(&pool).await.map_err(highlight_error)?;
+
+    Ok(Json(highlight))
+}
+
+pub async fn delete_highlight(
+    State(pool): State<DbPool>,
+    Path(highlight_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<serde_json::Value>> {
+
+    let result = sqlx::query("DELETE FROM highlights WHERE id = $1 AND user_id = $2")
+        .bind(highlight_id)
+        .bind(claims.sub)
+        .execute(&pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Highlight not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "Highlight deleted successfully"
+    })))
+}
+
+/// Ownership and consistency checks of the highlights table as API errors
+fn highlight_error(e: sqlx::Error) -> AppError {
+    if let sqlx::Error::Database(db_err) = &e {
+        if db_err.message().contains("does not belong to user") {
+            return AppError::Authorization(
+                "Cannot add a highlight to a book that doesn't belong to you".to_string(),
+            );
+        }
+        if db_err.message().contains("is not a reading of book") {
+            return AppError::Validation("The reading is not a reading of this book".to_string());
+        }
+        if db_err.constraint() == Some("highlight_location_check") {
+            return AppError::Validation("location_end must not be before location_start".to_string());
+        }
+    }
+    AppError::Database(e)
+}
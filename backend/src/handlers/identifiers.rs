@@ -0,0 +1,161 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+
+use panicless_mcp_lib::isbn::Isbn;
+
+use crate::{
+    db::DbPool,
+    errors::{AppError, AppResult},
+    middleware::Claims,
+    models::{
+        book::{Book, BOOK_COLUMNS},
+        identifier::{
+            identifier_candidates, BookIdentifier, CreateIdentifier, IdentifierLookupQuery, IsbnInfo,
+            BOOK_IDENTIFIER_COLUMNS,
+        },
+    },
+};
+
+/// Identifiers of one book
+/// GET /api/books/:id/identifiers
+pub async fn list_book_identifiers(
+    State(pool): State<DbPool>,
+    Path(book_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<Vec<BookIdentifier>>> {
+
+    ensure_book_owned(&pool, book_id, claims.sub).await?;
+
+    let identifiers = sqlx::query_as::<_, BookIdentifier>(&format!(
+        "SELECT {} FROM book_identifiers WHERE book_id = $1 ORDER BY id_type, value",
+        BOOK_IDENTIFIER_COLUMNS
+    ))
+    .bind(book_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(identifiers))
+}
+
+/// Add an identifier to a book
+/// ISBN-10s are stored as the equivalent ISBN-13.
+/// POST /api/books/:id/identifiers {"id_type": "asin", "value": "B00B7NPRY8"}
+pub async fn create_book_identifier(
+    State(pool): State<DbPool>,
+    Path(book_id): Path<i32>,
+    claims: Claims,
+    Json(payload): Json<CreateIdentifier>,
+) -> AppResult<Json<BookIdentifier>> {
+
+    let (id_type, value) = payload.id_type.canonicalize(&payload.value).map_err(AppError::Validation)?;
+
+    ensure_book_owned(&pool, book_id, claims.sub).await?;
+
+    let identifier = sqlx::query_as::<_, BookIdentifier>(&format!(
+        "INSERT INTO book_identifiers (book_id, id_type, value, source)
+         VALUES ($1, $2, $3, 'manual')
+         ON CONFLICT (book_id, id_type, value) DO NOTHING
+         RETURNING {}",
+        BOOK_IDENTIFIER_COLUMNS
+    ))
+    .bind(book_id)
+    .bind(id_type.as_str())
+    .bind(&value)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::Conflict(format!("The book already has {} {}", id_type.as_str(), value)))?;
+
+    Ok(Json(identifier))
+}
+
+/// Remove an identifier from a book
+/// Identifiers derived from the book's ISBN, Goodreads ID or Calibre UUID follow
+/// those fields and cannot be removed on their own.
+/// DELETE /api/books/:id/identifiers/:identifier_id
+pub async fn delete_book_identifier(
+    State(pool): State<DbPool>,
+    Path((book_id, identifier_id)): Path<(i32, i32)>,
+    claims: Claims,
+) -> AppResult<Json<serde_json::Value>> {
+
+    ensure_book_owned(&pool, book_id, claims.sub).await?;
+
+    let source = sqlx::query_scalar::<_, String>(
+        "SELECT source FROM book_identifiers WHERE id = $1 AND book_id = $2"
+    )
+    .bind(identifier_id)
+    .bind(book_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Identifier not found".to_string()))?;
+
+    if source == "book" {
+        return Err(AppError::Validation(
+            "This identifier comes from the book's ISBN, Goodreads ID or Calibre UUID; edit the book instead".to_string(),
+        ));
+    }
+
+    sqlx::query("DELETE FROM book_identifiers WHERE id = $1")
+        .bind(identifier_id)
+        .execute(&pool)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Identifier deleted successfully"
+    })))
+}
+
+/// Books carrying an identifier
+/// Without a type, the value is looked up as every type it is valid for, so an
+/// ISBN, a Goodreads URL or an Open Library ID can be pasted as is.
+/// GET /api/books/lookup?type=isbn13&value=978-0-441-01359-3
+pub async fn lookup_books(
+    State(pool): State<DbPool>,
+    Query(query): Query<IdentifierLookupQuery>,
+    claims: Claims,
+) -> AppResult<Json<Vec<Book>>> {
+
+    let candidates = identifier_candidates(query.id_type, &query.value).map_err(AppError::Validation)?;
+    let (types, values): (Vec<&str>, Vec<String>) =
+        candidates.into_iter().map(|(id_type, value)| (id_type.as_str(), value)).unzip();
+
+    let books = sqlx::query_as::<_, Book>(&format!(
+        "SELECT {} FROM books
+         WHERE user_id = $1
+           AND id IN (SELECT book_id FROM book_identifiers
+                      WHERE (id_type, value) IN (SELECT * FROM UNNEST($2::text[], $3::text[])))
+         ORDER BY title, id",
+        BOOK_COLUMNS
+    ))
+    .bind(claims.sub)
+    .bind(&types)
+    .bind(&values)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(books))
+}
+
+/// Validate an ISBN-10 or ISBN-13 and give its ISBN-13, ISBN-10 and hyphenated forms
+/// GET /api/isbn/0441013597
+pub async fn isbn_info(Path(isbn): Path<String>, _claims: Claims) -> AppResult<Json<IsbnInfo>> {
+    let isbn = Isbn::parse(&isbn).map_err(AppError::Validation)?;
+    Ok(Json(isbn.into()))
+}
+
+pub(crate) async fn ensure_book_owned(pool: &DbPool, book_id: i32, user_id: i32) -> AppResult<()> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM books WHERE id = $1 AND user_id = $2)"
+    )
+    .bind(book_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    if !exists {
+        return Err(AppError::NotFound("Book not found".to_string()));
+    }
+    Ok(())
+}
@@ -1,28 +1,269 @@
-use axum::{extract::{Multipart, State}, Json};
+use axum::{
+    extract::{Multipart, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
 use std::collections::HashMap;
 
 use crate::{
+    config::Config,
     db::DbPool,
     errors::{AppError, AppResult},
-    middleware::Claims,
-    models::import::{
-        BookImportResult, ImportError, ImportResponse, ImportSuccess,
-        ImportSummary, TransformedBook,
+    middleware::{AdminUser, Claims},
+    models::{
+        export::{ConflictPolicy, LibraryExport, LibraryImportSummary},
+        highlight::{KindleImportQuery, KindleImportSummary},
+        import::{
+            ImportAction, ImportError, ImportPreview, ImportQuery, MergePolicy, PreviewRow,
+            PreviewSummary, RowDecision,
+        },
+    },
+    services::{
+        calibre::{read_library, read_upload, sync_calibre_books, CalibreBook, CalibreSyncSummary},
+        import_jobs::{create_job, plan_import, run_planned_import, PlannedRow},
+        importer::{GoodreadsImporter, Importer},
+        kindle_clippings::{group_by_book, import_kindle_highlights, parse_clippings},
+        librarything_parser::LibraryThingImporter,
+        library_export::import_library,
+        storygraph_parser::StoryGraphImporter,
     },
-    services::goodreads_parser::{parse_csv_data, transform_record},
 };
 
 const MAX_FILE_SIZE: usize = 10 * 1024 * 1024; // 10 MB
 
+/// Largest accepted Panicless archive; also the request body limit of its route
+pub const MAX_ARCHIVE_SIZE: usize = 50 * 1024 * 1024; // 50 MB
+
+/// Largest accepted Calibre upload; a metadata.db grows with the library
+pub const MAX_CALIBRE_SIZE: usize = 100 * 1024 * 1024; // 100 MB
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveImportQuery {
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+}
+
+/// Import a Goodreads "Export Library" CSV
+/// POST /api/import/goodreads/csv?dry_run=true
 pub async fn import_goodreads_csv(
     State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Query(query): Query<ImportQuery>,
+    multipart: Multipart,
+) -> AppResult<Response> {
+    import_file(&pool, &config, claims.sub, &GoodreadsImporter, query, multipart).await
+}
+
+/// Import a StoryGraph library CSV
+/// POST /api/import/storygraph/csv?dry_run=true
+pub async fn import_storygraph_csv(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Query(query): Query<ImportQuery>,
+    multipart: Multipart,
+) -> AppResult<Response> {
+    import_file(&pool, &config, claims.sub, &StoryGraphImporter, query, multipart).await
+}
+
+/// Import a LibraryThing export, tab-separated or JSON
+/// POST /api/import/librarything?dry_run=true
+pub async fn import_librarything(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
     claims: Claims,
+    Query(query): Query<ImportQuery>,
+    multipart: Multipart,
+) -> AppResult<Response> {
+    import_file(&pool, &config, claims.sub, &LibraryThingImporter, query, multipart).await
+}
+
+/// Parse an uploaded export with `importer` and add its books to the library
+/// The optional `decisions` form field overrides the proposed action of single rows.
+/// With `dry_run` nothing is written and the proposed actions are returned instead;
+/// otherwise the import runs as a job while the request waits, each book in its own
+/// transaction so one bad row does not stop the rest.
+async fn import_file(
+    pool: &DbPool,
+    config: &Config,
+    user_id: i32,
+    importer: &dyn Importer,
+    query: ImportQuery,
     mut multipart: Multipart,
-) -> AppResult<Json<ImportResponse>> {
-    // Extract file from multipart form
-    let file_data = extract_file_from_multipart(&mut multipart).await?;
+) -> AppResult<Response> {
+    let form = extract_import_form(&mut multipart).await?;
 
     // Check file size
+    if form.file_data.len() > MAX_FILE_SIZE {
+        return Err(AppError::Validation(format!(
+            "File too large: {} bytes (max: {} bytes)",
+            form.file_data.len(),
+            MAX_FILE_SIZE
+        )));
+    }
+
+    let (plans, errors) = plan_import(pool, user_id, importer, &form.file_data, &form.decisions, query.merge).await?;
+
+    if query.dry_run {
+        return Ok(Json(preview(plans, errors)).into_response());
+    }
+
+    // Recorded as a job like background imports, so it shows in the history and can be reverted
+    let job = create_job(pool, &config.instance_id, user_id, importer.key(), form.file_name.as_deref()).await?;
+    let response = run_planned_import(pool, &job, plans, errors).await?;
+
+    Ok(Json(response).into_response())
+}
+
+/// Proposed actions of a dry run
+fn preview(plans: Vec<PlannedRow>, errors: Vec<ImportError>) -> ImportPreview {
+    let count = |action| plans.iter().filter(|(_, _, plan)| plan.action == action).count();
+    let summary = PreviewSummary {
+        total_rows: plans.len() + errors.len(),
+        to_create: count(ImportAction::Create),
+        to_update: count(ImportAction::Update),
+        to_skip: count(ImportAction::Skip),
+        failed_rows: errors.len(),
+        readings_to_create: plans
+            .iter()
+            .map(|(_, _, plan)| plan.creates_reading as usize + plan.extra_readings)
+            .sum(),
+    };
+
+    let rows = plans
+        .into_iter()
+        .map(|(row_number, book, plan)| PreviewRow {
+            row_number,
+            book_title: book.title,
+            action: plan.action,
+            book_id: plan.book_id,
+            matched_by: plan.matched_by,
+            changes: plan.changes,
+            creates_reading: plan.creates_reading,
+            extra_readings: plan.extra_readings,
+        })
+        .collect();
+
+    ImportPreview { summary, rows, errors }
+}
+
+/// Restore a Panicless archive (written by GET /api/export) into the user's library
+/// POST /api/import/panicless?on_conflict=skip|overwrite|duplicate
+pub async fn import_panicless_archive(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Query(query): Query<ArchiveImportQuery>,
+    mut multipart: Multipart,
+) -> AppResult<Json<LibraryImportSummary>> {
+    let file_data = extract_file_from_multipart(&mut multipart).await?;
+
+    if file_data.len() > MAX_ARCHIVE_SIZE {
+        return Err(AppError::Validation(format!(
+            "File too large: {} bytes (max: {} bytes)",
+            file_data.len(),
+            MAX_ARCHIVE_SIZE
+        )));
+    }
+
+    let export: LibraryExport = serde_json::from_slice(&file_data)
+        .map_err(|e| AppError::Validation(format!("Not a Panicless archive: {}", e)))?;
+
+    let summary = import_library(
+        &pool,
+        claims.sub,
+        &export,
+        query.on_conflict,
+        &config.custom_connector_allowed_hosts,
+    )
+    .await?;
+
+    tracing::info!(
+        "User {} restored an archive ({}): {} books imported, {} updated, {} skipped",
+        claims.sub,
+        query.on_conflict.as_str(),
+        summary.books_imported,
+        summary.books_updated,
+        summary.books_skipped
+    );
+
+    Ok(Json(summary))
+}
+
+/// Sync an uploaded Calibre `metadata.db` or `metadata.opf` into the user's library
+/// POST /api/import/calibre
+pub async fn import_calibre(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> AppResult<Json<CalibreSyncSummary>> {
+    let file_data = extract_file_from_multipart(&mut multipart).await?;
+
+    if file_data.len() > MAX_CALIBRE_SIZE {
+        return Err(AppError::Validation(format!(
+            "File too large: {} bytes (max: {} bytes)",
+            file_data.len(),
+            MAX_CALIBRE_SIZE
+        )));
+    }
+
+    let books = read_upload(&file_data).await.map_err(AppError::Validation)?;
+    sync_calibre(&pool, &config, claims.sub, &books).await
+}
+
+/// Sync the Calibre library at CALIBRE_LIBRARY_PATH into the admin's own library
+/// Admin only, as the library lives on the server.
+/// POST /api/import/calibre/library
+pub async fn import_calibre_library(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    admin: AdminUser,
+) -> AppResult<Json<CalibreSyncSummary>> {
+    let path = config
+        .calibre_library_path
+        .as_deref()
+        .ok_or_else(|| AppError::NotFound("No Calibre library is configured".to_string()))?;
+
+    let books = read_library(std::path::Path::new(path))
+        .await
+        .map_err(AppError::Internal)?;
+    sync_calibre(&pool, &config, admin.id(), &books).await
+}
+
+async fn sync_calibre(
+    pool: &DbPool,
+    config: &Config,
+    user_id: i32,
+    books: &[CalibreBook],
+) -> AppResult<Json<CalibreSyncSummary>> {
+    let summary =
+        sync_calibre_books(pool, user_id, books, config.calibre_content_server_url.as_deref()).await?;
+
+    tracing::info!(
+        "User {} synced a Calibre library: {} created, {} updated, {} linked, {} unchanged",
+        user_id,
+        summary.books_created,
+        summary.books_updated,
+        summary.books_linked,
+        summary.books_unchanged
+    );
+
+    Ok(Json(summary))
+}
+
+/// Import the highlights and notes of a Kindle `My Clippings.txt`
+/// POST /api/import/kindle?create_books=false
+pub async fn import_kindle_clippings(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Query(query): Query<KindleImportQuery>,
+    mut multipart: Multipart,
+) -> AppResult<Json<KindleImportSummary>> {
+    let file_data = extract_file_from_multipart(&mut multipart).await?;
+
     if file_data.len() > MAX_FILE_SIZE {
         return Err(AppError::Validation(format!(
             "File too large: {} bytes (max: {} bytes)",
@@ -31,110 +272,23 @@ pub async fn import_goodreads_csv(
         )));
     }
 
-    // Pre-fetch user's existing books for duplicate detection
-    let book_maps = build_book_maps(&pool, claims.sub).await?;
-
-    // Parse CSV data
-    let csv_results = parse_csv_data(file_data.as_slice())
-        .map_err(|e| AppError::Validation(format!("CSV parsing failed: {}", e)))?;
-
-    // Process each record
-    let mut successes = Vec::new();
-    let mut errors = Vec::new();
-    let mut books_created = 0;
-    let mut books_updated = 0;
-    let mut readings_created = 0;
-
-    for (idx, result) in csv_results.into_iter().enumerate() {
-        let row_number = idx + 2; // +2 because CSV has header row and arrays are 0-indexed
-
-        let record = match result {
-            Ok(rec) => rec,
-            Err(e) => {
-                errors.push(ImportError {
-                    row_number,
-                    book_title: None,
-                    error: e,
-                });
-                continue;
-            }
-        };
-
-        // Transform record
-        let transformed = match transform_record(&record) {
-            Ok(t) => t,
-            Err(e) => {
-                errors.push(ImportError {
-                    row_number,
-                    book_title: Some(record.title.clone()),
-                    error: e,
-                });
-                continue;
-            }
-        };
-
-        // Process book import (in individual transaction)
-        match process_book_import(&pool, claims.sub, transformed, &book_maps).await {
-            Ok(result) => match result {
-                BookImportResult::Success {
-                    book_id,
-                    book_title,
-                    operation,
-                    reading_created,
-                } => {
-                    if operation == "created" {
-                        books_created += 1;
-                    } else {
-                        books_updated += 1;
-                    }
-                    if reading_created {
-                        readings_created += 1;
-                    }
-                    successes.push(ImportSuccess {
-                        row_number,
-                        book_id,
-                        book_title,
-                        operation,
-                    });
-                }
-                BookImportResult::Failure { book_title, error } => {
-                    errors.push(ImportError {
-                        row_number,
-                        book_title,
-                        error,
-                    });
-                }
-            },
-            Err(e) => {
-                errors.push(ImportError {
-                    row_number,
-                    book_title: Some(record.title.clone()),
-                    error: format!("Database error: {}", e),
-                });
-            }
-        }
-    }
+    let clippings = parse_clippings(&file_data).map_err(AppError::Validation)?;
+    let books = group_by_book(clippings);
+    let summary = import_kindle_highlights(&pool, claims.sub, &books, query.create_books).await?;
 
-    let total_rows = successes.len() + errors.len();
-    let successful_imports = successes.len();
-    let failed_imports = errors.len();
-
-    Ok(Json(ImportResponse {
-        summary: ImportSummary {
-            total_rows,
-            successful_imports,
-            failed_imports,
-            books_created,
-            books_updated,
-            readings_created,
-        },
-        successes,
-        errors,
-    }))
+    tracing::info!(
+        "User {} imported Kindle clippings: {} highlights imported, {} updated, {} books unmatched",
+        claims.sub,
+        summary.highlights_imported,
+        summary.highlights_updated,
+        summary.unmatched_books.len()
+    );
+
+    Ok(Json(summary))
 }
 
 /// Extract file data from multipart form
-async fn extract_file_from_multipart(multipart: &mut Multipart) -> AppResult<Vec<u8>> {
+pub(crate) async fn extract_file_from_multipart(multipart: &mut Multipart) -> AppResult<Vec<u8>> {
     while let Some(field) = multipart
         .next_field()
         .await
@@ -155,186 +309,90 @@ async fn extract_file_from_multipart(multipart: &mut Multipart) -> AppResult<Vec
     Err(AppError::Validation("No file provided in request".to_string()))
 }
 
-/// Build HashMaps for duplicate detection
-async fn build_book_maps(
-    pool: &DbPool,
-    user_id: i32,
-) -> AppResult<(HashMap<String, i32>, HashMap<(String, String), i32>)> {
-    #[derive(sqlx::FromRow)]
-    struct BookInfo {
-        id: i32,
-        isbn: Option<String>,
-        title: String,
-        author: Option<String>,
-    }
-
-    let books = sqlx::query_as::<_, BookInfo>(
-        "SELECT id, isbn, title, author FROM books WHERE user_id = $1",
-    )
-    .bind(user_id)
-    .fetch_all(pool)
-    .await?;
+/// Form fields of a reading-site import
+pub(crate) struct ImportForm {
+    /// Importer key; only background imports send it
+    pub source: Option<String>,
+    /// Only background imports send it; direct imports take `?merge=`
+    pub merge: Option<MergePolicy>,
+    pub file_name: Option<String>,
+    pub file_data: Vec<u8>,
+    pub decisions: HashMap<usize, RowDecision>,
+}
 
-    let mut isbn_map = HashMap::new();
-    let mut title_author_map = HashMap::new();
+/// Extract the file and the optional `source`, `merge` and `decisions` (a JSON array of `RowDecision`) fields
+pub(crate) async fn extract_import_form(multipart: &mut Multipart) -> AppResult<ImportForm> {
+    let mut source = None;
+    let mut merge = None;
+    let mut file = None;
+    let mut decisions = HashMap::new();
 
-    for book in books {
-        // Map by ISBN if present
-        if let Some(isbn) = &book.isbn {
-            let clean_isbn = isbn.trim().to_lowercase();
-            if !clean_isbn.is_empty() {
-                isbn_map.insert(clean_isbn, book.id);
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::Validation(format!("Failed to read multipart field: {}", e)))?
+    {
+        match field.name().unwrap_or("") {
+            "source" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::Validation(format!("Failed to read source: {}", e)))?;
+                source = Some(text);
             }
-        }
-
-        // Map by title + author
-        let title_key = book.title.trim().to_lowercase();
-        let author_key = book
-            .author
-            .as_ref()
-            .map(|a| a.trim().to_lowercase())
-            .unwrap_or_default();
-        title_author_map.insert((title_key, author_key), book.id);
-    }
-
-    Ok((isbn_map, title_author_map))
-}
-
-/// Find duplicate book ID
-fn find_duplicate(
-    book: &TransformedBook,
-    isbn_map: &HashMap<String, i32>,
-    title_author_map: &HashMap<(String, String), i32>,
-) -> Option<i32> {
-    // Try ISBN first
-    if let Some(isbn) = &book.isbn {
-        let clean_isbn = isbn.trim().to_lowercase();
-        if let Some(&book_id) = isbn_map.get(&clean_isbn) {
-            return Some(book_id);
+            "merge" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::Validation(format!("Failed to read merge policy: {}", e)))?;
+                merge = Some(text.parse().map_err(AppError::Validation)?);
+            }
+            "file" => {
+                let file_name = field.file_name().map(str::to_string);
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::Validation(format!("Failed to read file data: {}", e)))?;
+                file = Some((file_name, data.to_vec()));
+            }
+            "decisions" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::Validation(format!("Failed to read decisions: {}", e)))?;
+                decisions = parse_decisions(&text)?;
+            }
+            _ => {}
         }
     }
 
-    // Try title + author
-    let title_key = book.title.trim().to_lowercase();
-    let author_key = book
-        .author
-        .as_ref()
-        .map(|a| a.trim().to_lowercase())
-        .unwrap_or_default();
+    let (file_name, file_data) =
+        file.ok_or_else(|| AppError::Validation("No file provided in request".to_string()))?;
 
-    title_author_map.get(&(title_key, author_key)).copied()
+    Ok(ImportForm {
+        source,
+        merge,
+        file_name,
+        file_data,
+        decisions,
+    })
 }
 
-/// Process a single book import in a transaction
-async fn process_book_import(
-    pool: &DbPool,
-    user_id: i32,
-    book: TransformedBook,
-    book_maps: &(HashMap<String, i32>, HashMap<(String, String), i32>),
-) -> AppResult<BookImportResult> {
-    let (isbn_map, title_author_map) = book_maps;
-
-    // Check for duplicate
-    let existing_book_id = find_duplicate(&book, isbn_map, title_author_map);
-
-    let mut tx = pool.begin().await?;
-
-    let (book_id, operation) = if let Some(existing_id) = existing_book_id {
-        // Update existing book
-        let updated_id = sqlx::query_scalar::<_, i32>(
-            "UPDATE books
-             SET title = $1, author = $2, isbn = $3, publication_year = $4,
-                 publisher = $5, pages = $6, updated_at = CURRENT_TIMESTAMP
-             WHERE id = $7 AND user_id = $8
-             RETURNING id",
-        )
-        .bind(&book.title)
-        .bind(&book.author)
-        .bind(&book.isbn)
-        .bind(&book.publication_year)
-        .bind(&book.publisher)
-        .bind(&book.pages)
-        .bind(existing_id)
-        .bind(user_id)
-        .fetch_one(&mut *tx)
-        .await?;
-
-        (updated_id, "updated")
-    } else {
-        // Insert new book
-        let new_id = sqlx::query_scalar::<_, i32>(
-            "INSERT INTO books (user_id, title, author, isbn, publication_year, publisher, pages)
-             VALUES ($1, $2, $3, $4, $5, $6, $7)
-             RETURNING id",
-        )
-        .bind(user_id)
-        .bind(&book.title)
-        .bind(&book.author)
-        .bind(&book.isbn)
-        .bind(&book.publication_year)
-        .bind(&book.publisher)
-        .bind(&book.pages)
-        .fetch_one(&mut *tx)
-        .await?;
-
-        (new_id, "created")
-    };
-
-    // Handle reading creation based on shelf
-    let mut reading_created = false;
-    let shelf_lower = book.shelf.to_lowercase();
-
-    match shelf_lower.as_str() {
-        "read" => {
-            // Create completed reading
-            if let (Some(start), Some(end)) = (book.start_date, book.end_date) {
-                sqlx::query(
-                    "INSERT INTO readings (user_id, book_id, start_date, end_date, rating, notes)
-                     VALUES ($1, $2, $3, $4, $5, $6)
-                     ON CONFLICT DO NOTHING",
-                )
-                .bind(user_id)
-                .bind(book_id)
-                .bind(start)
-                .bind(end)
-                .bind(&book.rating)
-                .bind(&book.notes)
-                .execute(&mut *tx)
-                .await?;
-
-                reading_created = true;
-            }
-        }
-        "currently-reading" => {
-            // Create active reading (no end_date)
-            if let Some(start) = book.start_date {
-                sqlx::query(
-                    "INSERT INTO readings (user_id, book_id, start_date, end_date, rating, notes)
-                     VALUES ($1, $2, $3, NULL, $4, $5)
-                     ON CONFLICT DO NOTHING",
-                )
-                .bind(user_id)
-                .bind(book_id)
-                .bind(start)
-                .bind(&book.rating)
-                .bind(&book.notes)
-                .execute(&mut *tx)
-                .await?;
-
-                reading_created = true;
-            }
-        }
-        _ => {
-            // "to-read" or unknown shelf - no reading record
+/// Row decisions by row number, from the JSON array of a `decisions` form field
+fn parse_decisions(text: &str) -> AppResult<HashMap<usize, RowDecision>> {
+    let list: Vec<RowDecision> = serde_json::from_str(text)
+        .map_err(|e| AppError::Validation(format!("Invalid decisions: {}", e)))?;
+
+    let mut decisions = HashMap::new();
+    for decision in list {
+        let row_number = decision.row_number;
+        if decisions.insert(row_number, decision).is_some() {
+            return Err(AppError::Validation(format!(
+                "More than one decision for row {}",
+                row_number
+            )));
         }
     }
 
-    tx.commit().await?;
-
-    Ok(BookImportResult::Success {
-        book_id,
-        book_title: book.title.clone(),
-        operation: operation.to_string(),
-        reading_created,
-    })
+    Ok(decisions)
 }
@@ -0,0 +1,201 @@
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::stream::{self, Stream};
+use std::{convert::Infallible, time::Duration};
+
+use crate::{
+    config::Config,
+    db::DbPool,
+    errors::{AppError, AppResult},
+    handlers::import::{extract_import_form, ImportForm},
+    middleware::Claims,
+    models::import_job::{ImportJob, ImportJobDetails, ImportJobQuery, RevertSummary, IMPORT_JOB_COLUMNS},
+    services::import_jobs::{self, create_job, importer_for, spawn_import_job},
+};
+
+/// Largest export accepted by a background import; also the body limit of its route
+pub const MAX_JOB_FILE_SIZE: usize = 50 * 1024 * 1024; // 50 MB
+
+/// How often the progress stream looks at the job
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Start a background import of a Goodreads, StoryGraph or LibraryThing export
+/// POST /api/import/jobs (multipart: source, file, optional merge and decisions)
+pub async fn create_import_job(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> AppResult<(StatusCode, Json<ImportJob>)> {
+    let ImportForm {
+        source,
+        merge,
+        file_name,
+        file_data: data,
+        decisions,
+    } = extract_import_form(&mut multipart).await?;
+
+    let source = source.ok_or_else(|| AppError::Validation("No source provided in request".to_string()))?;
+    let importer = importer_for(&source).ok_or_else(|| {
+        AppError::Validation(format!(
+            "Unknown source '{}' (expected goodreads, storygraph or librarything)",
+            source
+        ))
+    })?;
+
+    if data.len() > MAX_JOB_FILE_SIZE {
+        return Err(AppError::Validation(format!(
+            "File too large: {} bytes (max: {} bytes)",
+            data.len(),
+            MAX_JOB_FILE_SIZE
+        )));
+    }
+
+    let job = create_job(&pool, &config.instance_id, claims.sub, &source, file_name.as_deref()).await?;
+    spawn_import_job(pool, job.clone(), importer, data, decisions, merge.unwrap_or_default());
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Import history, newest first
+/// GET /api/import/jobs
+pub async fn list_import_jobs(
+    State(pool): State<DbPool>,
+    Query(query): Query<ImportJobQuery>,
+    claims: Claims,
+) -> AppResult<Json<Vec<ImportJob>>> {
+    let page = query.page.unwrap_or(1);
+    let limit = query.limit.unwrap_or(20);
+    let offset = (page - 1) * limit;
+
+    let jobs = sqlx::query_as::<_, ImportJob>(&format!(
+        "SELECT {} FROM import_jobs WHERE user_id = $1
+         ORDER BY created_at DESC, id DESC LIMIT $2 OFFSET $3",
+        IMPORT_JOB_COLUMNS
+    ))
+    .bind(claims.sub)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(jobs))
+}
+
+/// A job with its per-row results
+/// GET /api/import/jobs/:id
+pub async fn get_import_job(
+    State(pool): State<DbPool>,
+    Path(job_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<ImportJobDetails>> {
+    let job = find_job(&pool, job_id, claims.sub).await?;
+
+    let result = sqlx::query_scalar::<_, Option<serde_json::Value>>(
+        "SELECT result FROM import_jobs WHERE id = $1",
+    )
+    .bind(job_id)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(ImportJobDetails { job, result }))
+}
+
+/// Ask a queued or running job to stop
+/// Books imported before it stops are kept.
+/// POST /api/import/jobs/:id/cancel
+pub async fn cancel_import_job(
+    State(pool): State<DbPool>,
+    Path(job_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<ImportJob>> {
+    let job = find_job(&pool, job_id, claims.sub).await?;
+    if job.is_finished() {
+        return Err(AppError::Conflict(format!("The import has already finished ({})", job.status)));
+    }
+
+    let job = sqlx::query_as::<_, ImportJob>(&format!(
+        "UPDATE import_jobs SET cancel_requested = TRUE WHERE id = $1 RETURNING {}",
+        IMPORT_JOB_COLUMNS
+    ))
+    .bind(job_id)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(job))
+}
+
+/// Undo an import: delete the books and readings it created and restore the book
+/// fields it overwrote, except where they were edited since
+/// POST /api/import/jobs/:id/revert
+pub async fn revert_import_job(
+    State(pool): State<DbPool>,
+    Path(job_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<RevertSummary>> {
+    let job = find_job(&pool, job_id, claims.sub).await?;
+    let summary = import_jobs::revert_import_job(&pool, &job).await?;
+
+    Ok(Json(summary))
+}
+
+/// Follow a job's progress
+/// GET /api/import/jobs/:id/events
+///
+/// Streams a `progress` event with the job whenever it changes, and finally one
+/// `completed`, `failed` or `cancelled` event
+pub async fn import_job_events(
+    State(pool): State<DbPool>,
+    Path(job_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let user_id = claims.sub;
+    let job = find_job(&pool, job_id, user_id).await?;
+
+    // State: the pool, the last job sent, whether it is the first event, and whether the stream is over
+    let stream = stream::unfold(
+        (pool, job, true, false),
+        move |(pool, last, first, done)| async move {
+            if done {
+                return None;
+            }
+
+            let mut job = last.clone();
+            if !first {
+                loop {
+                    tokio::time::sleep(PROGRESS_POLL_INTERVAL).await;
+                    // The job is gone when the account has been deleted
+                    job = find_job(&pool, job_id, user_id).await.ok()?;
+                    if job.updated_at != last.updated_at || job.is_finished() {
+                        break;
+                    }
+                }
+            }
+
+            let finished = job.is_finished();
+            let name = if finished { job.status.clone() } else { "progress".to_string() };
+            let data = serde_json::to_string(&job).unwrap_or_default();
+            let event = Event::default().event(name).data(data);
+
+            Some((Ok::<_, Infallible>(event), (pool, job, false, finished)))
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn find_job(pool: &DbPool, job_id: i32, user_id: i32) -> AppResult<ImportJob> {
+    sqlx::query_as::<_, ImportJob>(&format!(
+        "SELECT {} FROM import_jobs WHERE id = $1 AND user_id = $2",
+        IMPORT_JOB_COLUMNS
+    ))
+    .bind(job_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Import job not found".to_string()))
+}
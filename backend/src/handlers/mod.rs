@@ -1,19 +1,50 @@
+pub mod account;
 pub mod auth;
+pub mod sessions;
+pub mod two_factor;
 pub mod books;
 pub mod readings;
+pub mod highlights;
+pub mod identifiers;
+pub mod enrichment;
+pub mod covers;
+pub mod scan;
 pub mod users;
 pub mod import;
+pub mod import_jobs;
+pub mod export;
 pub mod connectors;
 pub mod openapi;
 pub mod oauth;
+pub mod oidc;
 pub mod mcp;
+pub mod admin;
+pub mod chat;
 
-pub use auth::{register, login, refresh, get_current_user};
+pub use auth::{register, login, refresh, logout, get_current_user};
+pub use account::{forgot_password, reset_password, verify_email, resend_verification};
+pub use two_factor::{get_two_factor_status, setup_two_factor, enable_two_factor, disable_two_factor, regenerate_recovery_codes, verify_two_factor};
+pub use sessions::{list_sessions, revoke_session, revoke_other_sessions};
 pub use books::{list_books, advanced_search_books, get_book, create_book, update_book, delete_book, get_book_readings};
 pub use readings::{list_readings, get_reading, create_reading, update_reading, delete_reading, complete_reading, get_reading_stats};
-pub use users::{get_user, update_user, delete_user};
-pub use import::import_goodreads_csv;
-pub use connectors::{create_or_update_connector, list_connectors, get_connector, delete_connector, toggle_connector};
+pub use highlights::{list_highlights, get_highlight, create_highlight, update_highlight, delete_highlight, get_book_highlights};
+pub use identifiers::{list_book_identifiers, create_book_identifier, delete_book_identifier, lookup_books, isbn_info};
+pub use enrichment::{enrich_book, enrich_library, get_book_provenance};
+pub use covers::{upload_cover, get_cover, delete_cover};
+pub use scan::scan_books;
+pub use users::{get_user, update_user, change_password, delete_user};
+pub use import::{import_goodreads_csv, import_storygraph_csv, import_librarything, import_panicless_archive, import_calibre, import_calibre_library, import_kindle_clippings};
+pub use import_jobs::{create_import_job, list_import_jobs, get_import_job, cancel_import_job, revert_import_job, import_job_events};
+pub use export::{export_account, export_goodreads_csv};
+pub use connectors::{create_or_update_connector, list_connectors, get_connector, delete_connector, toggle_connector, verify_connector_key};
 pub use openapi::openapi_schema;
 pub use oauth::{authorize, token, authorization_server_metadata, protected_resource_metadata};
+pub use oidc::{openid_configuration, jwks, userinfo};
 pub use mcp::{handle_mcp_sse, handle_mcp_sse_post};
+pub use chat::chat;
+pub use admin::{
+    get_registration_settings, list_users, get_user_details, disable_user, enable_user, update_user_role,
+    revoke_user_sessions, send_user_password_reset, send_user_verification, reset_user_two_factor,
+    get_instance_stats, get_admin_registration_settings, update_registration_settings,
+    list_invites, create_invite, revoke_invite, list_audit_log,
+};
@@ -12,8 +12,11 @@ use base64::{Engine as _, engine::general_purpose};
 
 use crate::{
     config::Config,
+    crypto::{constant_time_eq, hash_token},
     errors::AppError,
+    handlers::oidc::{has_scope, issue_id_token},
     middleware::Claims,
+    models::OAuthClient,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,6 +26,7 @@ pub struct AuthorizeRequest {
     pub response_type: String, // "code"
     pub scope: Option<String>,
     pub state: Option<String>,
+    pub nonce: Option<String>, // OpenID Connect, echoed in the id_token
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,6 +52,8 @@ pub struct TokenResponse {
     pub expires_in: i64,
     pub scope: String,
     pub jwt_token: String, // JWT for accessing protected endpoints
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>, // Only when the openid scope was granted
 }
 
 /// OAuth2 Authorize endpoint
@@ -68,14 +74,27 @@ pub async fn authorize(
         params.state
     );
 
-    // Verify client_id
-    if params.client_id != config.oauth_client_id {
-        tracing::error!(
-            "OAuth authorize failed: Invalid client_id. Expected: {}, Got: {}",
-            config.oauth_client_id,
-            params.client_id
-        );
-        return Err(AppError::Authentication("Invalid client_id".to_string()));
+    // Verify client_id and redirect_uri
+    match find_registered_client(&pool, &params.client_id).await? {
+        Some(client) => {
+            if !client.allows_redirect_uri(&params.redirect_uri) {
+                tracing::error!(
+                    "OAuth authorize failed: redirect_uri not registered for client {}: {}",
+                    params.client_id,
+                    params.redirect_uri
+                );
+                return Err(AppError::Validation("redirect_uri is not registered for this client".to_string()));
+            }
+        }
+        None if params.client_id == config.oauth_client_id => {}
+        None => {
+            tracing::error!(
+                "OAuth authorize failed: Invalid client_id. Expected: {} or a registered client, Got: {}",
+                config.oauth_client_id,
+                params.client_id
+            );
+            return Err(AppError::Authentication("Invalid client_id".to_string()));
+        }
     }
 
     // Validate response_type is "code"
@@ -96,13 +115,14 @@ pub async fn authorize(
     let expires_at = Utc::now() + chrono::Duration::minutes(10);
 
     sqlx::query(
-        "INSERT INTO oauth_codes (code, client_id, user_id, redirect_uri, scope, expires_at) VALUES ($1, $2, $3, $4, $5, $6)"
+        "INSERT INTO oauth_codes (code, client_id, user_id, redirect_uri, scope, nonce, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
     )
     .bind(&code)
     .bind(&params.client_id)
     .bind(claims.sub)
     .bind(&params.redirect_uri)
     .bind(&params.scope)
+    .bind(&params.nonce)
     .bind(expires_at)
     .execute(&pool)
     .await?;
@@ -145,8 +165,7 @@ pub async fn token(
     } else if let Some(auth_header) = headers.get(axum::http::header::AUTHORIZATION) {
         // Try to extract from Basic auth
         if let Ok(auth_str) = auth_header.to_str() {
-            if auth_str.starts_with("Basic ") {
-                let encoded = &auth_str[6..];
+            if let Some(encoded) = auth_str.strip_prefix("Basic ") {
                 if let Ok(decoded_bytes) = general_purpose::STANDARD.decode(encoded) {
                     if let Ok(decoded) = String::from_utf8(decoded_bytes) {
                         // Format is "client_id:client_secret"
@@ -179,11 +198,18 @@ pub async fn token(
     };
 
     // Verify client credentials
-    if payload.client_id != config.oauth_client_id || client_secret != config.oauth_client_secret {
+    let client_valid = match find_registered_client(&pool, &payload.client_id).await? {
+        Some(client) => constant_time_eq(client.client_secret_hash.as_bytes(), hash_token(&client_secret).as_bytes()),
+        None => {
+            payload.client_id == config.oauth_client_id
+                && constant_time_eq(client_secret.as_bytes(), config.oauth_client_secret.as_bytes())
+        }
+    };
+
+    if !client_valid {
         tracing::error!(
-            "OAuth token failed: Invalid client credentials. client_id={}, secret_match={}",
-            payload.client_id,
-            client_secret == config.oauth_client_secret
+            "OAuth token failed: Invalid client credentials. client_id={}",
+            payload.client_id
         );
         return Err(AppError::Authentication("Invalid client credentials".to_string()));
     }
@@ -198,7 +224,7 @@ pub async fn token(
 
     // Look up authorization code
     let oauth_code = sqlx::query(
-        "SELECT id, user_id, redirect_uri, scope, expires_at, used_at FROM oauth_codes WHERE code = $1 AND client_id = $2"
+        "SELECT id, user_id, redirect_uri, scope, nonce, expires_at, used_at FROM oauth_codes WHERE code = $1 AND client_id = $2"
     )
     .bind(&payload.code)
     .bind(&payload.client_id)
@@ -217,6 +243,7 @@ pub async fn token(
     let user_id: i32 = oauth_code.get("user_id");
     let redirect_uri: String = oauth_code.get("redirect_uri");
     let scope: Option<String> = oauth_code.get("scope");
+    let nonce: Option<String> = oauth_code.get("nonce");
     let expires_at: chrono::DateTime<Utc> = oauth_code.get("expires_at");
     let used_at: Option<chrono::DateTime<Utc>> = oauth_code.get("used_at");
 
@@ -300,6 +327,9 @@ pub async fn token(
         exp,
         iat,
         token_type: "access".to_string(),
+        sid: None,
+        jti: None,
+        role: None,
     };
 
     let jwt_token = encode(
@@ -309,6 +339,13 @@ pub async fn token(
     )
     .map_err(|_| AppError::Validation("Failed to generate JWT".to_string()))?;
 
+    // OpenID Connect: issue an id_token when the openid scope was granted
+    let id_token = if has_scope(&scope_str, "openid") {
+        Some(issue_id_token(&pool, &config, user_id, &payload.client_id, &scope_str, nonce, &access_token).await?)
+    } else {
+        None
+    };
+
     tracing::info!(
         "OAuth access token issued for user {} and client {}",
         user_id,
@@ -323,6 +360,7 @@ pub async fn token(
             expires_in: 86400, // 24 hours
             scope: scope_str,
             jwt_token,
+            id_token,
         }),
     ))
 }
@@ -330,29 +368,23 @@ pub async fn token(
 /// Generate a random authorization code
 fn generate_code() -> String {
     let mut rng = rand::thread_rng();
-    let random_bytes: Vec<u8> = (0..32)
+    // 36 random bytes encode to exactly 48 URL-safe base64 characters
+    let random_bytes: Vec<u8> = (0..36)
         .map(|_| rng.gen::<u8>())
         .collect();
 
-    general_purpose::STANDARD.encode(&random_bytes)
-        .chars()
-        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
-        .take(48)
-        .collect()
+    general_purpose::URL_SAFE_NO_PAD.encode(&random_bytes)
 }
 
 /// Generate a random access token
 fn generate_token() -> String {
     let mut rng = rand::thread_rng();
-    let random_bytes: Vec<u8> = (0..64)
+    // 72 random bytes encode to exactly 96 URL-safe base64 characters
+    let random_bytes: Vec<u8> = (0..72)
         .map(|_| rng.gen::<u8>())
         .collect();
 
-    general_purpose::STANDARD.encode(&random_bytes)
-        .chars()
-        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
-        .take(96)
-        .collect()
+    general_purpose::URL_SAFE_NO_PAD.encode(&random_bytes)
 }
 
 /// OAuth 2.0 Authorization Server Metadata (RFC 8414)
@@ -362,6 +394,8 @@ pub struct AuthorizationServerMetadata {
     pub issuer: String,
     pub authorization_endpoint: String,
     pub token_endpoint: String,
+    pub jwks_uri: String,
+    pub userinfo_endpoint: String,
     pub response_types_supported: Vec<String>,
     pub grant_types_supported: Vec<String>,
     pub token_endpoint_auth_methods_supported: Vec<String>,
@@ -391,6 +425,8 @@ pub async fn authorization_server_metadata(
         // Frontend authorization page (user-facing) - not the API endpoint
         authorization_endpoint: format!("{}/authorize", base_url),
         token_endpoint: format!("{}/oauth/token", base_url),
+        jwks_uri: format!("{}/oauth/jwks", base_url),
+        userinfo_endpoint: format!("{}/oauth/userinfo", base_url),
         response_types_supported: vec!["code".to_string()],
         grant_types_supported: vec!["authorization_code".to_string()],
         token_endpoint_auth_methods_supported: vec!["client_secret_post".to_string(), "client_secret_basic".to_string()],
@@ -414,6 +450,20 @@ pub async fn protected_resource_metadata(
     })
 }
 
+/// Look up an active client registered in the database
+/// The client configured with OAUTH_CLIENT_ID is not stored there.
+pub async fn find_registered_client(pool: &PgPool, client_id: &str) -> Result<Option<OAuthClient>, AppError> {
+    let client = sqlx::query_as::<_, OAuthClient>(
+        "SELECT id, client_id, name, client_secret_hash, redirect_uris, created_at, revoked_at
+         FROM oauth_clients WHERE client_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(client_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(client)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -438,3 +488,4 @@ mod tests {
         assert_ne!(token1, token2);
     }
 }
+
@@ -0,0 +1,427 @@
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{encode, Header};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, FromRow, PgPool};
+
+use crate::{
+    config::Config,
+    crypto::{
+        signing::{self, Jwk, JwkSet, SigningAlgorithm},
+        TokenCrypto,
+    },
+    errors::{AppError, AppResult},
+    middleware::Claims,
+    models::user::User,
+};
+
+/// Retired keys stay published in the JWKS for this long so that
+/// relying parties can still verify id_tokens issued before a rotation
+const RETIRED_KEY_PUBLISH_DAYS: i64 = 7;
+
+/// Advisory lock id serializing signing key rotation across instances
+const SIGNING_KEY_ROTATION_LOCK: i64 = 0x01DC_5EC0;
+
+/// OpenID Connect Discovery 1.0 provider metadata
+/// /.well-known/openid-configuration
+#[derive(Debug, Serialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+/// Claims carried by an OpenID Connect id_token
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+/// Response of the UserInfo endpoint, filtered by granted scopes
+#[derive(Debug, Serialize)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+/// A stored signing key
+#[derive(Debug, FromRow)]
+struct OidcSigningKey {
+    kid: String,
+    algorithm: String,
+    encrypted_private_key: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Returns true if the space-separated scope string contains the given scope
+pub fn has_scope(scope: &str, wanted: &str) -> bool {
+    scope.split_whitespace().any(|s| s == wanted)
+}
+
+/// Get OpenID Connect Discovery metadata
+pub async fn openid_configuration(
+    State(config): State<Config>,
+) -> Json<OpenIdConfiguration> {
+    let base_url = config.base_url();
+
+    Json(OpenIdConfiguration {
+        issuer: base_url.clone(),
+        // Frontend authorization page (user-facing) - not the API endpoint
+        authorization_endpoint: format!("{}/authorize", base_url),
+        token_endpoint: format!("{}/oauth/token", base_url),
+        userinfo_endpoint: format!("{}/oauth/userinfo", base_url),
+        jwks_uri: format!("{}/oauth/jwks", base_url),
+        scopes_supported: vec!["openid".to_string(), "profile".to_string(), "email".to_string()],
+        response_types_supported: vec!["code".to_string()],
+        grant_types_supported: vec!["authorization_code".to_string()],
+        subject_types_supported: vec!["public".to_string()],
+        id_token_signing_alg_values_supported: vec![config.oidc_signing_alg.clone()],
+        token_endpoint_auth_methods_supported: vec!["client_secret_post".to_string(), "client_secret_basic".to_string()],
+        claims_supported: vec![
+            "sub".to_string(),
+            "iss".to_string(),
+            "aud".to_string(),
+            "exp".to_string(),
+            "iat".to_string(),
+            "nonce".to_string(),
+            "preferred_username".to_string(),
+            "name".to_string(),
+            "email".to_string(),
+            "email_verified".to_string(),
+        ],
+    })
+}
+
+/// Get the JSON Web Key Set with the active and recently retired signing keys
+/// GET /oauth/jwks
+pub async fn jwks(
+    State(pool): State<PgPool>,
+    State(config): State<Config>,
+) -> Result<Json<JwkSet>, AppError> {
+    // Make sure there is a key to publish before the first id_token is issued
+    active_signing_key(&pool, &config).await?;
+
+    let keys = sqlx::query_scalar::<_, SqlJson<Jwk>>(
+        "SELECT public_jwk FROM oidc_signing_keys
+         WHERE retired_at IS NULL OR retired_at > NOW() - make_interval(days => $1)
+         ORDER BY created_at DESC",
+    )
+    .bind(RETIRED_KEY_PUBLISH_DAYS as i32)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(JwkSet {
+        keys: keys.into_iter().map(|k| k.0).collect(),
+    }))
+}
+
+/// OpenID Connect UserInfo endpoint
+/// GET /oauth/userinfo
+/// Claims are filtered by the scopes granted to the OAuth access token;
+/// first-party session tokens see every claim. The JWT the token endpoint also
+/// hands to OAuth clients has no session and no grant to check, so it is refused.
+pub async fn userinfo(
+    State(pool): State<PgPool>,
+    claims: Claims,
+    headers: HeaderMap,
+) -> Result<Json<UserInfoResponse>, AppError> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    let granted_scope = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT scope FROM oauth_tokens WHERE token = $1",
+    )
+    .bind(bearer)
+    .fetch_optional(&pool)
+    .await?;
+
+    let (profile, email) = match (granted_scope, claims.sid) {
+        (Some(scope), _) => {
+            let scope = scope.unwrap_or_default();
+            if !has_scope(&scope, "openid") {
+                return Err(AppError::Authorization(
+                    "Access token was not granted the openid scope".to_string(),
+                ));
+            }
+            (has_scope(&scope, "profile"), has_scope(&scope, "email"))
+        }
+        (None, Some(_)) => (true, true),
+        (None, None) => {
+            return Err(AppError::Authorization(
+                "UserInfo requires the OAuth access token or a session token".to_string(),
+            ));
+        }
+    };
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+    )
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    Ok(Json(UserInfoResponse {
+        sub: user.id.to_string(),
+        preferred_username: profile.then(|| user.username.clone()),
+        name: if profile { user.full_name.clone() } else { None },
+        updated_at: profile.then(|| user.updated_at.timestamp()),
+        email: email.then(|| user.email.clone()),
+        email_verified: email.then(|| user.email_verified_at.is_some()),
+    }))
+}
+
+/// Issue a signed id_token for the given user and client
+pub async fn issue_id_token(
+    pool: &PgPool,
+    config: &Config,
+    user_id: i32,
+    client_id: &str,
+    scope: &str,
+    nonce: Option<String>,
+    access_token: &str,
+) -> Result<String, AppError> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    let key = active_signing_key(pool, config).await?;
+    let algorithm = SigningAlgorithm::parse(&key.algorithm).map_err(AppError::Internal)?;
+    let private_key_pem = TokenCrypto::from_config(config)?.decrypt(&key.encrypted_private_key)?;
+    let encoding_key = signing::encoding_key(algorithm, &private_key_pem)?;
+
+    let profile = has_scope(scope, "profile");
+    let email = has_scope(scope, "email");
+    let now = Utc::now().timestamp();
+
+    let claims = IdTokenClaims {
+        iss: config.base_url(),
+        sub: user.id.to_string(),
+        aud: client_id.to_string(),
+        exp: (now + config.oidc_id_token_expiry) as usize,
+        iat: now as usize,
+        nonce,
+        at_hash: Some(signing::oidc_token_hash(access_token)),
+        preferred_username: profile.then(|| user.username.clone()),
+        name: if profile { user.full_name.clone() } else { None },
+        email: email.then(|| user.email.clone()),
+        email_verified: email.then(|| user.email_verified_at.is_some()),
+    };
+
+    let mut header = Header::new(algorithm.jwt_algorithm());
+    header.kid = Some(key.kid.clone());
+
+    Ok(encode(&header, &claims, &encoding_key)?)
+}
+
+/// Returns the active signing key, creating or rotating it as needed
+/// A key is rotated once it is older than OIDC_KEY_ROTATION_DAYS, or when
+/// the configured algorithm no longer matches
+async fn active_signing_key(pool: &PgPool, config: &Config) -> AppResult<OidcSigningKey> {
+    let current = fetch_active_signing_key(pool).await?;
+    if let Some(key) = current {
+        if !needs_rotation(&key, config) {
+            return Ok(key);
+        }
+        rotate(pool, config, Some(key.kid)).await?;
+    } else {
+        rotate(pool, config, None).await?;
+    }
+
+    fetch_active_signing_key(pool)
+        .await?
+        .ok_or_else(|| AppError::Internal("No active signing key after rotation".to_string()))
+}
+
+async fn fetch_active_signing_key(pool: &PgPool) -> AppResult<Option<OidcSigningKey>> {
+    let key = sqlx::query_as::<_, OidcSigningKey>(
+        "SELECT kid, algorithm, encrypted_private_key, created_at FROM oidc_signing_keys WHERE retired_at IS NULL",
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(key)
+}
+
+fn needs_rotation(key: &OidcSigningKey, config: &Config) -> bool {
+    key.algorithm != config.oidc_signing_alg
+        || key.created_at + chrono::Duration::days(config.oidc_key_rotation_days) < Utc::now()
+}
+
+/// Generate a new signing key and retire the current one
+/// Returns the kid of the new active key
+pub async fn rotate_signing_key(pool: &PgPool, config: &Config) -> AppResult<String> {
+    let current = fetch_active_signing_key(pool).await?;
+    rotate(pool, config, current.map(|k| k.kid)).await
+}
+
+/// Replace the active key, provided it is still `expected_kid`
+/// If another instance rotated in the meantime, its key is kept instead
+async fn rotate(pool: &PgPool, config: &Config, expected_kid: Option<String>) -> AppResult<String> {
+    let algorithm = SigningAlgorithm::parse(&config.oidc_signing_alg).map_err(AppError::Internal)?;
+
+    // RSA key generation is CPU bound, keep it off the async executor
+    let generated = tokio::task::spawn_blocking(move || signing::generate_signing_key(algorithm))
+        .await
+        .map_err(|e| AppError::Internal(format!("Key generation task failed: {}", e)))??;
+
+    let encrypted_private_key = TokenCrypto::from_config(config)?.encrypt(&generated.private_key_pem)?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(SIGNING_KEY_ROTATION_LOCK)
+        .execute(&mut *tx)
+        .await?;
+
+    // Another instance may have rotated while we were generating
+    let current = sqlx::query_as::<_, OidcSigningKey>(
+        "SELECT kid, algorithm, encrypted_private_key, created_at FROM oidc_signing_keys WHERE retired_at IS NULL",
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if current.as_ref().map(|k| &k.kid) != expected_kid.as_ref() {
+        tx.commit().await?;
+        return current
+            .map(|k| k.kid)
+            .ok_or_else(|| AppError::Internal("Active signing key disappeared during rotation".to_string()));
+    }
+
+    if let Some(current) = current {
+        sqlx::query("UPDATE oidc_signing_keys SET retired_at = NOW() WHERE kid = $1")
+            .bind(&current.kid)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query(
+        "INSERT INTO oidc_signing_keys (kid, algorithm, encrypted_private_key, public_jwk) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(&generated.kid)
+    .bind(generated.algorithm.as_str())
+    .bind(&encrypted_private_key)
+    .bind(SqlJson(&generated.public_jwk))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!("OIDC signing key rotated, new kid={}", generated.kid);
+
+    Ok(generated.kid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_scope() {
+        assert!(has_scope("openid profile email", "openid"));
+        assert!(has_scope("openid  email", "email"));
+        assert!(!has_scope("openid profile", "email"));
+        assert!(!has_scope("openidprofile", "openid"));
+        assert!(!has_scope("", "openid"));
+    }
+
+    async fn create_user(pool: &PgPool) -> i32 {
+        sqlx::query_scalar("INSERT INTO users (username, email, password_hash) VALUES ('reader', 'reader@example.com', '') RETURNING id")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    fn claims(user_id: i32, sid: Option<i32>) -> Claims {
+        Claims {
+            sub: user_id,
+            username: "reader".to_string(),
+            exp: usize::MAX,
+            iat: 0,
+            token_type: "access".to_string(),
+            sid,
+            jti: None,
+            role: None,
+        }
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_userinfo_follows_granted_scopes(pool: PgPool) {
+        let user_id = create_user(&pool).await;
+        sqlx::query(
+            "INSERT INTO oauth_tokens (token, client_id, user_id, scope, expires_at)
+             VALUES ('openid-only', 'client', $1, 'openid', NOW() + INTERVAL '1 hour'),
+                    ('with-email', 'client', $1, 'openid email', NOW() + INTERVAL '1 hour')",
+        )
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Granted openid alone, the client gets neither the email nor the profile
+        let Json(info) = userinfo(State(pool.clone()), claims(user_id, None), bearer("openid-only")).await.unwrap();
+        assert_eq!(info.sub, user_id.to_string());
+        assert_eq!((info.email, info.email_verified, info.preferred_username), (None, None, None));
+
+        let Json(info) = userinfo(State(pool.clone()), claims(user_id, None), bearer("with-email")).await.unwrap();
+        assert_eq!((info.email.as_deref(), info.preferred_username), (Some("reader@example.com"), None));
+
+        // The token endpoint's JWT carries no grant, so it cannot widen one
+        let jwt = userinfo(State(pool.clone()), claims(user_id, None), bearer("eyJ.not-a-grant.x")).await;
+        assert!(matches!(jwt, Err(AppError::Authorization(_))));
+
+        // Session tokens are first-party
+        let Json(info) = userinfo(State(pool.clone()), claims(user_id, Some(1)), bearer("eyJ.session.x")).await.unwrap();
+        assert_eq!(info.email.as_deref(), Some("reader@example.com"));
+        assert_eq!(info.preferred_username.as_deref(), Some("reader"));
+    }
+}
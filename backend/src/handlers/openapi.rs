@@ -106,7 +106,7 @@ pub async fn openapi_schema() -> (StatusCode, Json<Value>) {
                                     "properties": {
                                         "title": { "type": "string", "description": "Book title" },
                                         "author": { "type": "string", "description": "Author name" },
-                                        "isbn": { "type": "string", "nullable": true },
+                                        "isbn": { "type": "string", "nullable": true, "description": "ISBN-10 or ISBN-13 with a valid check digit, stored as ISBN-13 digits" },
                                         "pages": { "type": "integer", "nullable": true },
                                         "language": { "type": "string", "default": "en" }
                                     }
@@ -169,6 +169,338 @@ pub async fn openapi_schema() -> (StatusCode, Json<Value>) {
                     }
                 }
             },
+            "/api/books/lookup": {
+                "get": {
+                    "summary": "Find books by identifier",
+                    "description": "Books carrying an external identifier. Without `type`, the value is matched as every identifier type it is valid for, so ISBNs and Goodreads or Open Library URLs can be passed as is",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "value", "in": "query", "required": true, "schema": { "type": "string" } },
+                        {
+                            "name": "type",
+                            "in": "query",
+                            "schema": {
+                                "type": "string",
+                                "enum": ["isbn10", "isbn13", "asin", "goodreads", "openlibrary_work", "openlibrary_edition", "calibre_uuid", "oclc", "lccn"]
+                            }
+                        }
+                    ],
+                    "responses": {
+                        "200": { "description": "Matching books, in the same shape as `/api/books`" },
+                        "400": { "description": "The value is not a valid identifier" }
+                    }
+                }
+            },
+            "/api/isbn/{isbn}": {
+                "get": {
+                    "summary": "Validate an ISBN",
+                    "description": "Checks an ISBN-10 or ISBN-13 and returns its ISBN-13, ISBN-10 and hyphenated forms",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "isbn", "in": "path", "required": true, "schema": { "type": "string" } }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The ISBN's forms",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "isbn13": { "type": "string" },
+                                            "isbn10": { "type": "string", "nullable": true },
+                                            "hyphenated": { "type": "string" }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "400": { "description": "Not an ISBN, or a wrong check digit" }
+                    }
+                }
+            },
+            "/api/books/{id}/identifiers": {
+                "get": {
+                    "summary": "List a book's identifiers",
+                    "description": "ISBNs, ASIN, Goodreads, Open Library, Calibre, OCLC and LCCN identifiers of a book",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The book's identifiers",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "id": { "type": "integer" },
+                                                "id_type": { "type": "string" },
+                                                "value": { "type": "string" },
+                                                "source": { "type": "string", "enum": ["book", "manual", "import", "calibre", "openlibrary"] }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "post": {
+                    "summary": "Add an identifier to a book",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["id_type", "value"],
+                                    "properties": {
+                                        "id_type": { "type": "string" },
+                                        "value": { "type": "string" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "The identifier, normalized" },
+                        "400": { "description": "Invalid value for the type" },
+                        "409": { "description": "The book already has this identifier" }
+                    }
+                }
+            },
+            "/api/books/{id}/cover": {
+                "get": {
+                    "summary": "Get a book's uploaded cover",
+                    "description": "Served with an ETag; cacheable for good when requested with the current ?v= of the cover URL",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                        { "name": "size", "in": "query", "schema": { "type": "string", "enum": ["thumbnail", "medium", "original"], "default": "original" } },
+                        { "name": "v", "in": "query", "schema": { "type": "string" }, "description": "Cover version, from the cover URL" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The image",
+                            "content": {
+                                "image/jpeg": { "schema": { "type": "string", "format": "binary" } },
+                                "image/png": { "schema": { "type": "string", "format": "binary" } },
+                                "image/webp": { "schema": { "type": "string", "format": "binary" } },
+                                "image/gif": { "schema": { "type": "string", "format": "binary" } }
+                            }
+                        },
+                        "304": { "description": "Not modified (If-None-Match)" },
+                        "404": { "description": "The book has no uploaded cover" }
+                    }
+                },
+                "post": {
+                    "summary": "Upload a book's cover",
+                    "description": "JPEG, PNG, WebP or GIF up to 10 MB, sniffed from the content. Thumbnail and medium variants are made, and the book's cover_image_url points to the cover",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "string", "format": "binary" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The stored cover",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "book_id": { "type": "integer" },
+                                            "content_type": { "type": "string" },
+                                            "width": { "type": "integer" },
+                                            "height": { "type": "integer" },
+                                            "byte_size": { "type": "integer" },
+                                            "url": { "type": "string" },
+                                            "uploaded_at": { "type": "string", "format": "date-time" }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "400": { "description": "Not a supported or readable image, or too large" },
+                        "404": { "description": "Book not found" }
+                    }
+                },
+                "delete": {
+                    "summary": "Remove a book's uploaded cover",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                    ],
+                    "responses": {
+                        "200": { "description": "Cover deleted" },
+                        "404": { "description": "The book has no uploaded cover" }
+                    }
+                }
+            },
+            "/api/books/{id}/enrich": {
+                "post": {
+                    "summary": "Fill a book's missing fields from Open Library",
+                    "description": "Matches the book in the loaded Open Library data by edition identifier, ISBN, or title and author, and fills empty fields the user has not set",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "What matched and which fields were filled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "book_id": { "type": "integer" },
+                                            "matched_by": { "type": "string", "nullable": true, "enum": ["identifier", "isbn", "title_author"] },
+                                            "edition_key": { "type": "string", "nullable": true },
+                                            "work_key": { "type": "string", "nullable": true },
+                                            "fields": { "type": "array", "items": { "type": "string" } }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "404": { "description": "Book not found" }
+                    }
+                }
+            },
+            "/api/books/enrich": {
+                "post": {
+                    "summary": "Fill the missing fields of every book from Open Library",
+                    "tags": ["Books"],
+                    "responses": {
+                        "200": {
+                            "description": "Enrichment totals",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "books_checked": { "type": "integer" },
+                                            "books_matched": { "type": "integer" },
+                                            "books_enriched": { "type": "integer" },
+                                            "fields_filled": { "type": "integer" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/api/books/scan": {
+                "post": {
+                    "summary": "Read ISBN barcodes from photos of books",
+                    "description": "Up to 20 photos of 10 MB, one per file field. Each ISBN read is looked up in the library; with create=true one that is not there gets a book tagged draft, filled from Open Library",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "create", "in": "query", "schema": { "type": "boolean", "default": false }, "description": "Create draft books for ISBNs not in the library" }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "array", "items": { "type": "string", "format": "binary" } }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "What was read from each photo",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "file_name": { "type": "string", "nullable": true },
+                                                "barcodes": { "type": "array", "items": { "type": "string" } },
+                                                "isbns": {
+                                                    "type": "array",
+                                                    "items": {
+                                                        "type": "object",
+                                                        "properties": {
+                                                            "isbn13": { "type": "string" },
+                                                            "isbn10": { "type": "string", "nullable": true },
+                                                            "hyphenated": { "type": "string" },
+                                                            "books": { "type": "array", "items": { "type": "object" } },
+                                                            "draft": { "type": "object", "nullable": true },
+                                                            "enrichment": { "type": "object", "nullable": true }
+                                                        }
+                                                    }
+                                                },
+                                                "error": { "type": "string", "nullable": true }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "400": { "description": "No photo, too many photos or a photo over 10 MB" }
+                    }
+                }
+            },
+            "/api/books/{id}/provenance": {
+                "get": {
+                    "summary": "Where a book's fields came from",
+                    "description": "Fields set by the user, which enrichment never changes, and fields filled from Open Library",
+                    "tags": ["Books"],
+                    "parameters": [
+                        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Recorded field sources",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "field": { "type": "string" },
+                                                "source": { "type": "string", "enum": ["user", "openlibrary"] },
+                                                "reference": { "type": "string", "nullable": true },
+                                                "recorded_at": { "type": "string", "format": "date-time" }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "404": { "description": "Book not found" }
+                    }
+                }
+            },
             "/api/readings": {
                 "get": {
                     "summary": "List all reading sessions",
@@ -231,10 +563,81 @@ pub async fn openapi_schema() -> (StatusCode, Json<Value>) {
                     }
                 }
             },
+            "/api/highlights": {
+                "get": {
+                    "summary": "List and search highlights",
+                    "description": "Your highlights, quotes and notes, most recent first. `q` searches the passage, the note and the book title",
+                    "tags": ["Highlights"],
+                    "parameters": [
+                        { "name": "q", "in": "query", "schema": { "type": "string" } },
+                        { "name": "book_id", "in": "query", "schema": { "type": "integer" } },
+                        { "name": "page", "in": "query", "schema": { "type": "integer", "default": 1 } },
+                        { "name": "limit", "in": "query", "schema": { "type": "integer", "default": 20 } }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "List of highlights",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "id": { "type": "integer" },
+                                                "book_id": { "type": "integer" },
+                                                "book_title": { "type": "string" },
+                                                "reading_id": { "type": "integer", "nullable": true },
+                                                "text": { "type": "string", "nullable": true },
+                                                "note": { "type": "string", "nullable": true },
+                                                "location_start": { "type": "integer", "nullable": true },
+                                                "location_end": { "type": "integer", "nullable": true },
+                                                "page": { "type": "integer", "nullable": true },
+                                                "highlighted_at": { "type": "string", "format": "date-time", "nullable": true },
+                                                "source": { "type": "string", "enum": ["manual", "kindle"] }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "post": {
+                    "summary": "Add a highlight",
+                    "description": "Add a quote, a note or both to one of your books",
+                    "tags": ["Highlights"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["book_id"],
+                                    "properties": {
+                                        "book_id": { "type": "integer" },
+                                        "reading_id": { "type": "integer" },
+                                        "text": { "type": "string" },
+                                        "note": { "type": "string" },
+                                        "location_start": { "type": "integer" },
+                                        "location_end": { "type": "integer" },
+                                        "page": { "type": "integer" },
+                                        "highlighted_at": { "type": "string", "format": "date-time" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "The created highlight" },
+                        "400": { "description": "Neither text nor note, or invalid location" }
+                    }
+                }
+            },
             "/api/connectors": {
                 "get": {
                     "summary": "List your AI connectors",
-                    "description": "Get all configured AI provider connectors (Anthropic, Gemini, ChatGPT)",
+                    "description": "Get all configured AI provider connectors (Anthropic, Gemini, ChatGPT, self-hosted OpenAI-compatible servers)",
                     "tags": ["Connectors"],
                     "responses": {
                         "200": {
@@ -247,9 +650,15 @@ pub async fn openapi_schema() -> (StatusCode, Json<Value>) {
                                             "type": "object",
                                             "properties": {
                                                 "id": { "type": "integer" },
-                                                "provider": { "type": "string", "enum": ["anthropic", "gemini", "chatgpt"] },
+                                                "provider": { "type": "string", "enum": ["anthropic", "gemini", "chatgpt", "custom"] },
+                                                "has_api_key": { "type": "boolean" },
+                                                "base_url": { "type": "string", "nullable": true },
+                                                "model": { "type": "string", "nullable": true },
                                                 "is_active": { "type": "boolean" },
                                                 "last_used_at": { "type": "string", "format": "date-time", "nullable": true },
+                                                "status": { "type": "string", "enum": ["unverified", "valid", "invalid", "rate_limited", "unreachable"] },
+                                                "status_detail": { "type": "string", "nullable": true },
+                                                "verified_at": { "type": "string", "format": "date-time", "nullable": true },
                                                 "created_at": { "type": "string", "format": "date-time" }
                                             }
                                         }
@@ -259,6 +668,375 @@ pub async fn openapi_schema() -> (StatusCode, Json<Value>) {
                         }
                     }
                 }
+            },
+            "/api/chat": {
+                "post": {
+                    "summary": "Chat with your library",
+                    "description": "Run a tool-using conversation with your active AI connector. Streams message, tool_call, tool_result, done and error events",
+                    "tags": ["Chat"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["messages"],
+                                    "properties": {
+                                        "messages": {
+                                            "type": "array",
+                                            "items": {
+                                                "type": "object",
+                                                "properties": {
+                                                    "role": { "type": "string", "enum": ["user", "assistant"] },
+                                                    "content": { "type": "string" }
+                                                }
+                                            }
+                                        },
+                                        "provider": { "type": "string", "enum": ["anthropic", "gemini", "chatgpt", "custom"] }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Server-sent event stream",
+                            "content": { "text/event-stream": {} }
+                        },
+                        "404": { "description": "No active connector" }
+                    }
+                }
+            },
+            "/api/export": {
+                "get": {
+                    "summary": "Export your account",
+                    "description": "Download profile, books, readings and connectors (without API keys) as a versioned JSON archive",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": {
+                            "description": "Archive, sent as an attachment",
+                            "content": { "application/json": {} }
+                        }
+                    }
+                }
+            },
+            "/api/export/goodreads.csv": {
+                "get": {
+                    "summary": "Export as Goodreads CSV",
+                    "description": "Download the library in the Goodreads export format: one row per book, shelf and rating from the latest reading; earlier readings only count towards Read Count",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": {
+                            "description": "CSV file, sent as an attachment",
+                            "content": { "text/csv": {} }
+                        }
+                    }
+                }
+            },
+            "/api/import/goodreads/csv": {
+                "post": {
+                    "summary": "Import from Goodreads",
+                    "description": "Import a Goodreads library export CSV",
+                    "tags": ["Import"],
+                    "parameters": [
+                        {
+                            "name": "dry_run",
+                            "in": "query",
+                            "description": "Return the proposed action and field changes of each row without writing",
+                            "schema": { "type": "boolean", "default": false }
+                        },
+                        {
+                            "name": "merge",
+                            "in": "query",
+                            "description": "How rows matching a book change it: fill_empty fills in missing fields, overwrite replaces them, keep_local only adds readings",
+                            "schema": { "type": "string", "enum": ["fill_empty", "overwrite", "keep_local"], "default": "fill_empty" }
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "string", "format": "binary" },
+                                        "decisions": {
+                                            "type": "string",
+                                            "description": "JSON array overriding single rows, e.g. [{\"row_number\": 5, \"action\": \"update\", \"book_id\": 42}]; action is create, update or skip"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "Per-row import results, or the proposed actions with dry_run" },
+                        "400": { "description": "File missing, too large or unreadable" }
+                    }
+                }
+            },
+            "/api/import/storygraph/csv": {
+                "post": {
+                    "summary": "Import from StoryGraph",
+                    "description": "Import a StoryGraph library export CSV. Moods, pace, tags and did-not-finish status are kept in the reading notes",
+                    "tags": ["Import"],
+                    "parameters": [
+                        {
+                            "name": "dry_run",
+                            "in": "query",
+                            "description": "Return the proposed action and field changes of each row without writing",
+                            "schema": { "type": "boolean", "default": false }
+                        },
+                        {
+                            "name": "merge",
+                            "in": "query",
+                            "description": "How rows matching a book change it: fill_empty fills in missing fields, overwrite replaces them, keep_local only adds readings",
+                            "schema": { "type": "string", "enum": ["fill_empty", "overwrite", "keep_local"], "default": "fill_empty" }
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "string", "format": "binary" },
+                                        "decisions": {
+                                            "type": "string",
+                                            "description": "JSON array overriding single rows, e.g. [{\"row_number\": 5, \"action\": \"update\", \"book_id\": 42}]; action is create, update or skip"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "Per-row import results, or the proposed actions with dry_run" },
+                        "400": { "description": "File missing, too large or unreadable" }
+                    }
+                }
+            },
+            "/api/import/librarything": {
+                "post": {
+                    "summary": "Import from LibraryThing",
+                    "description": "Import a LibraryThing export (tab-separated or JSON). Tags and collections are kept in the reading notes",
+                    "tags": ["Import"],
+                    "parameters": [
+                        {
+                            "name": "dry_run",
+                            "in": "query",
+                            "description": "Return the proposed action and field changes of each row without writing",
+                            "schema": { "type": "boolean", "default": false }
+                        },
+                        {
+                            "name": "merge",
+                            "in": "query",
+                            "description": "How rows matching a book change it: fill_empty fills in missing fields, overwrite replaces them, keep_local only adds readings",
+                            "schema": { "type": "string", "enum": ["fill_empty", "overwrite", "keep_local"], "default": "fill_empty" }
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "string", "format": "binary" },
+                                        "decisions": {
+                                            "type": "string",
+                                            "description": "JSON array overriding single rows, e.g. [{\"row_number\": 5, \"action\": \"update\", \"book_id\": 42}]; action is create, update or skip"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "Per-row import results, or the proposed actions with dry_run" },
+                        "400": { "description": "File missing, too large or unreadable" }
+                    }
+                }
+            },
+            "/api/import/jobs": {
+                "post": {
+                    "summary": "Start a background import",
+                    "description": "Import a Goodreads, StoryGraph or LibraryThing export in the background. Only one import per user runs at a time",
+                    "tags": ["Import"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "source": { "type": "string", "enum": ["goodreads", "storygraph", "librarything"] },
+                                        "file": { "type": "string", "format": "binary" },
+                                        "merge": { "type": "string", "enum": ["fill_empty", "overwrite", "keep_local"], "default": "fill_empty" },
+                                        "decisions": { "type": "string", "description": "JSON array of row decisions" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "202": { "description": "Job created" },
+                        "400": { "description": "Source unknown, file missing or too large" },
+                        "409": { "description": "Another import is still running" }
+                    }
+                },
+                "get": {
+                    "summary": "Import history",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": { "description": "Import jobs, newest first" }
+                    }
+                }
+            },
+            "/api/import/jobs/{id}": {
+                "get": {
+                    "summary": "Get an import job",
+                    "description": "The job with its per-row results once it has finished",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": { "description": "Import job" },
+                        "404": { "description": "Import job not found" }
+                    }
+                }
+            },
+            "/api/import/jobs/{id}/events": {
+                "get": {
+                    "summary": "Follow an import job",
+                    "description": "Server-sent events: progress, then completed, failed or cancelled",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": { "description": "Event stream" },
+                        "404": { "description": "Import job not found" }
+                    }
+                }
+            },
+            "/api/import/jobs/{id}/cancel": {
+                "post": {
+                    "summary": "Cancel an import job",
+                    "description": "Stops the job before its next row; books imported until then are kept",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": { "description": "Cancellation requested" },
+                        "409": { "description": "The import has already finished" }
+                    }
+                }
+            },
+            "/api/import/jobs/{id}/revert": {
+                "post": {
+                    "summary": "Revert an import job",
+                    "description": "Deletes the books and readings the import created and restores the book fields it overwrote. Rows edited since are kept and reported as conflicts",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": { "description": "Books deleted and restored, readings deleted, and conflicts" },
+                        "404": { "description": "Import job not found" },
+                        "409": { "description": "The import is still running or has already been reverted" }
+                    }
+                }
+            },
+            "/api/import/kindle": {
+                "post": {
+                    "summary": "Import Kindle highlights",
+                    "description": "Import the highlights and notes of a Kindle 'My Clippings.txt'. Books are matched by title and author; edited highlights replace earlier versions, and highlights already imported are skipped",
+                    "tags": ["Import"],
+                    "parameters": [
+                        {
+                            "name": "create_books",
+                            "in": "query",
+                            "description": "Add books that match nothing in the library instead of listing them as unmatched",
+                            "schema": { "type": "boolean", "default": false }
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "string", "format": "binary" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "Import summary with unmatched books" },
+                        "400": { "description": "File missing, too large or not a clippings file" }
+                    }
+                }
+            },
+            "/api/import/calibre": {
+                "post": {
+                    "summary": "Sync from Calibre",
+                    "description": "Sync an uploaded Calibre metadata.db or metadata.opf into your library. Books are matched by Calibre UUID, so re-uploading only applies what changed",
+                    "tags": ["Import"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "string", "format": "binary" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "Sync summary" },
+                        "400": { "description": "File missing, too large or not a Calibre library" }
+                    }
+                }
+            },
+            "/api/import/calibre/library": {
+                "post": {
+                    "summary": "Sync the server's Calibre library",
+                    "description": "Sync the library at CALIBRE_LIBRARY_PATH into your library (admin only)",
+                    "tags": ["Import"],
+                    "responses": {
+                        "200": { "description": "Sync summary" },
+                        "403": { "description": "Not an admin" },
+                        "404": { "description": "No Calibre library configured" }
+                    }
+                }
+            },
+            "/api/import/panicless": {
+                "post": {
+                    "summary": "Restore an archive",
+                    "description": "Import an archive written by /api/export into your library. Books are matched by ISBN, then title and author",
+                    "tags": ["Import"],
+                    "parameters": [
+                        {
+                            "name": "on_conflict",
+                            "in": "query",
+                            "schema": { "type": "string", "enum": ["skip", "overwrite", "duplicate"], "default": "skip" }
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "file": { "type": "string", "format": "binary" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": { "description": "Import summary" },
+                        "400": { "description": "Invalid or unsupported archive" }
+                    }
+                }
             }
         }
     });
@@ -120,10 +120,10 @@ pub async fn create_reading(
          RETURNING id, user_id, book_id, start_date, end_date, rating, notes, created_at, updated_at"
     )
     .bind(claims.sub)
-    .bind(&payload.book_id)
-    .bind(&payload.start_date)
-    .bind(&payload.end_date)
-    .bind(&payload.rating)
+    .bind(payload.book_id)
+    .bind(payload.start_date)
+    .bind(payload.end_date)
+    .bind(payload.rating)
     .bind(&payload.notes)
     .fetch_one(&pool)
     .await
@@ -276,8 +276,8 @@ pub async fn complete_reading(
          WHERE id = $3
          RETURNING id, user_id, book_id, start_date, end_date, rating, notes, created_at, updated_at"
     )
-    .bind(&payload.end_date)
-    .bind(&payload.rating)
+    .bind(payload.end_date)
+    .bind(payload.rating)
     .bind(reading_id)
     .fetch_one(&pool)
     .await?;
@@ -0,0 +1,187 @@
+use axum::{
+    extract::{Multipart, Query, State},
+    Json,
+};
+use panicless_mcp_lib::{enrichment, isbn::Isbn};
+
+use crate::{
+    db::DbPool,
+    errors::{AppError, AppResult},
+    middleware::Claims,
+    models::{
+        book::BOOK_COLUMNS,
+        scan::{ScanQuery, ScanResult, ScannedIsbn, DRAFT_TAG},
+        Book,
+    },
+    services::barcode::scan_image,
+};
+
+/// Maximum size of one photo
+pub const MAX_PHOTO_SIZE: usize = 10 * 1024 * 1024; // 10 MB
+
+/// Photos one request may carry
+pub const MAX_SCAN_PHOTOS: usize = 20;
+
+/// Maximum size of a scan request
+pub const MAX_SCAN_SIZE: usize = 50 * 1024 * 1024; // 50 MB
+
+/// Read the ISBN barcodes on photos of books and find them in the library
+/// Each photo is sent as a "file" field; a batch repeats the field. With
+/// `?create=true` an ISBN no book has yet gets a draft book, named from the loaded
+/// Open Library data when it knows the ISBN, enriched like any other book and
+/// tagged "draft". A photo that cannot be read only fails its own result.
+/// POST /api/books/scan (multipart)
+pub async fn scan_books(
+    State(pool): State<DbPool>,
+    Query(query): Query<ScanQuery>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> AppResult<Json<Vec<ScanResult>>> {
+
+    let photos = extract_photos(&mut multipart).await?;
+
+    let mut results = Vec::with_capacity(photos.len());
+    for (file_name, data) in photos {
+        let barcodes = tokio::task::spawn_blocking(move || scan_image(&data))
+            .await
+            .map_err(|e| AppError::Internal(format!("Barcode scanning failed: {}", e)))?;
+
+        let barcodes = match barcodes {
+            Ok(barcodes) => barcodes,
+            Err(error) => {
+                results.push(ScanResult { file_name, barcodes: Vec::new(), isbns: Vec::new(), error: Some(error) });
+                continue;
+            }
+        };
+
+        // Other EAN-13s (a price code, a magazine) are reported but not looked up
+        let mut isbns = Vec::new();
+        for isbn in barcodes.iter().filter(|code| code.starts_with("978") || code.starts_with("979")) {
+            let Ok(isbn) = Isbn::parse(isbn) else {
+                continue;
+            };
+            isbns.push(scanned_isbn(&pool, claims.sub, isbn, query.create).await?);
+        }
+
+        let error = if barcodes.is_empty() {
+            Some("No barcode found".to_string())
+        } else if isbns.is_empty() {
+            Some("No ISBN barcode found".to_string())
+        } else {
+            None
+        };
+        results.push(ScanResult { file_name, barcodes, isbns, error });
+    }
+
+    Ok(Json(results))
+}
+
+/// The library's books with an ISBN, and a draft book when asked for and there are none
+async fn scanned_isbn(pool: &DbPool, user_id: i32, isbn: Isbn, create: bool) -> AppResult<ScannedIsbn> {
+    let isbn13 = isbn.to_string();
+
+    let books = sqlx::query_as::<_, Book>(&format!(
+        "SELECT {} FROM books
+         WHERE user_id = $1
+           AND id IN (SELECT book_id FROM book_identifiers WHERE id_type = 'isbn13' AND value = $2)
+         ORDER BY title, id",
+        BOOK_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(&isbn13)
+    .fetch_all(pool)
+    .await?;
+
+    let (draft, enrichment) = if create && books.is_empty() {
+        let (draft, enrichment) = create_draft(pool, user_id, &isbn).await?;
+        (Some(draft), enrichment)
+    } else {
+        (None, None)
+    };
+
+    Ok(ScannedIsbn { isbn: isbn.into(), books, draft, enrichment })
+}
+
+/// A book known only by its ISBN, filled in from the Open Library data
+async fn create_draft(
+    pool: &DbPool,
+    user_id: i32,
+    isbn: &Isbn,
+) -> AppResult<(Book, Option<enrichment::BookEnrichment>)> {
+    let isbn13 = isbn.to_string();
+    let edition = enrichment::edition_title(pool, &isbn13).await?;
+    let (title, author) = match edition {
+        Some(edition) => {
+            let author = (!edition.authors.is_empty()).then(|| edition.authors.join(", "));
+            (edition.title, author)
+        }
+        None => (format!("ISBN {}", isbn.hyphenated()), None),
+    };
+
+    let book = sqlx::query_as::<_, Book>(&format!(
+        "INSERT INTO books (user_id, title, author, isbn, tags)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING {}",
+        BOOK_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(&title)
+    .bind(&author)
+    .bind(&isbn13)
+    .bind(vec![DRAFT_TAG.to_string()])
+    .fetch_one(pool)
+    .await?;
+
+    let enrichment = enrichment::enrich_book(pool, user_id, book.id).await?;
+
+    // Enrichment changed the row; return it as stored
+    let book = if enrichment.as_ref().is_some_and(|enrichment| !enrichment.fields.is_empty()) {
+        sqlx::query_as::<_, Book>(&format!("SELECT {} FROM books WHERE id = $1", BOOK_COLUMNS))
+            .bind(book.id)
+            .fetch_one(pool)
+            .await?
+    } else {
+        book
+    };
+
+    Ok((book, enrichment))
+}
+
+/// The "file" fields of a scan request with their file names
+async fn extract_photos(multipart: &mut Multipart) -> AppResult<Vec<(Option<String>, Vec<u8>)>> {
+    let mut photos = Vec::new();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::Validation(format!("Failed to read multipart field: {}", e)))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        if photos.len() == MAX_SCAN_PHOTOS {
+            return Err(AppError::Validation(format!(
+                "At most {} photos can be scanned at once",
+                MAX_SCAN_PHOTOS
+            )));
+        }
+
+        let file_name = field.file_name().map(str::to_string);
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::Validation(format!("Failed to read file data: {}", e)))?;
+        if data.len() > MAX_PHOTO_SIZE {
+            return Err(AppError::Validation(format!(
+                "Photos are limited to {} MB each",
+                MAX_PHOTO_SIZE / 1024 / 1024
+            )));
+        }
+        photos.push((file_name, data.to_vec()));
+    }
+
+    if photos.is_empty() {
+        return Err(AppError::Validation("No file provided in request".to_string()));
+    }
+    Ok(photos)
+}
@@ -0,0 +1,396 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::Utc;
+
+use crate::{
+    config::Config,
+    crypto::hash_token,
+    db::DbPool,
+    errors::{AppError, AppResult},
+    middleware::{auth::generate_jwt, Claims, ClientInfo},
+    models::{
+        session::{Session, SessionResponse},
+        user::{AuthResponse, User},
+    },
+};
+
+/// Start a new session for the user and issue its first token pair
+pub async fn start_session(
+    pool: &DbPool,
+    config: &Config,
+    user: User,
+    client: &ClientInfo,
+) -> AppResult<AuthResponse> {
+    if user.is_disabled() {
+        return Err(AppError::Authorization("Account is disabled".to_string()));
+    }
+
+    // Opportunistic cleanup of the user's long-dead sessions
+    sqlx::query(
+        "DELETE FROM user_sessions WHERE user_id = $1 AND expires_at < NOW() - INTERVAL '30 days'"
+    )
+    .bind(user.id)
+    .execute(pool)
+    .await?;
+
+    // The row needs an id before the refresh token (which embeds it) can be
+    // minted, so it is created with a placeholder hash and updated right after
+    let mut tx = pool.begin().await?;
+
+    let placeholder = hash_token(&format!("pending-{:032x}", rand::random::<u128>()));
+    let session_id = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO user_sessions (user_id, refresh_token_hash, user_agent, ip_address, expires_at)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id"
+    )
+    .bind(user.id)
+    .bind(&placeholder)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .bind(Utc::now() + chrono::Duration::seconds(config.jwt_refresh_token_expiry))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let (access_token, refresh_token) = generate_token_pair(config, &user, session_id)?;
+
+    sqlx::query("UPDATE user_sessions SET refresh_token_hash = $1 WHERE id = $2")
+        .bind(hash_token(&refresh_token))
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(AuthResponse {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: config.jwt_access_token_expiry,
+        user: user.into(),
+    })
+}
+
+/// Rotate the refresh token of a session
+/// The presented token must be the session's current one; presenting an
+/// already-rotated token means it leaked, so the whole session is revoked.
+pub async fn rotate_session(
+    pool: &DbPool,
+    config: &Config,
+    refresh_token: &str,
+    claims: &Claims,
+    client: &ClientInfo,
+) -> AppResult<AuthResponse> {
+    let session_id = claims.sid.ok_or_else(|| {
+        AppError::Authentication("Session expired, please log in again".to_string())
+    })?;
+
+    let session = sqlx::query_as::<_, Session>(
+        "SELECT id, user_id, refresh_token_hash, user_agent, ip_address, created_at, last_used_at, expires_at, revoked_at, revoked_reason
+         FROM user_sessions WHERE id = $1 AND user_id = $2"
+    )
+    .bind(session_id)
+    .bind(claims.sub)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::Authentication("Session not found".to_string()))?;
+
+    if session.revoked_at.is_some() || session.expires_at < Utc::now() {
+        return Err(AppError::Authentication("Session has been revoked or expired".to_string()));
+    }
+
+    let presented_hash = hash_token(refresh_token);
+    if session.refresh_token_hash != presented_hash {
+        revoke_for_reuse(pool, &session).await?;
+        return Err(AppError::Authentication(
+            "Refresh token reuse detected, session revoked".to_string(),
+        ));
+    }
+
+    // Fetch user to ensure they still exist
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+    )
+    .bind(claims.sub)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::Authentication("User not found".to_string()))?;
+
+    if user.is_disabled() {
+        return Err(AppError::Authorization("Account is disabled".to_string()));
+    }
+
+    let (access_token, new_refresh_token) = generate_token_pair(config, &user, session.id)?;
+
+    // Compare-and-swap on the old hash: of two concurrent refreshes with the
+    // same token only one wins, the other is treated as reuse
+    let rotated = sqlx::query(
+        "UPDATE user_sessions
+         SET refresh_token_hash = $1, last_used_at = NOW(), expires_at = $2,
+             user_agent = COALESCE($3, user_agent), ip_address = COALESCE($4, ip_address)
+         WHERE id = $5 AND refresh_token_hash = $6 AND revoked_at IS NULL"
+    )
+    .bind(hash_token(&new_refresh_token))
+    .bind(Utc::now() + chrono::Duration::seconds(config.jwt_refresh_token_expiry))
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .bind(session.id)
+    .bind(&presented_hash)
+    .execute(pool)
+    .await?;
+
+    if rotated.rows_affected() == 0 {
+        revoke_for_reuse(pool, &session).await?;
+        return Err(AppError::Authentication(
+            "Refresh token reuse detected, session revoked".to_string(),
+        ));
+    }
+
+    Ok(AuthResponse {
+        access_token,
+        refresh_token: new_refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: config.jwt_access_token_expiry,
+        user: user.into(),
+    })
+}
+
+/// Revoke a single session; returns false if it was not found or already revoked
+pub async fn revoke_session_by_id(
+    pool: &DbPool,
+    user_id: i32,
+    session_id: i32,
+    reason: &str,
+) -> AppResult<bool> {
+    let result = sqlx::query(
+        "UPDATE user_sessions SET revoked_at = NOW(), revoked_reason = $1
+         WHERE id = $2 AND user_id = $3 AND revoked_at IS NULL"
+    )
+    .bind(reason)
+    .bind(session_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke every active session of a user, optionally keeping one
+pub async fn revoke_all_sessions(
+    pool: &DbPool,
+    user_id: i32,
+    except_session_id: Option<i32>,
+    reason: &str,
+) -> AppResult<u64> {
+    let result = sqlx::query(
+        "UPDATE user_sessions SET revoked_at = NOW(), revoked_reason = $1
+         WHERE user_id = $2 AND revoked_at IS NULL AND ($3::INTEGER IS NULL OR id <> $3)"
+    )
+    .bind(reason)
+    .bind(user_id)
+    .bind(except_session_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn revoke_for_reuse(pool: &DbPool, session: &Session) -> AppResult<()> {
+    tracing::warn!(
+        "Refresh token reuse detected for session {} of user {}, revoking session",
+        session.id,
+        session.user_id
+    );
+    revoke_session_by_id(pool, session.user_id, session.id, "reuse_detected").await?;
+    Ok(())
+}
+
+fn generate_token_pair(config: &Config, user: &User, session_id: i32) -> AppResult<(String, String)> {
+    let access_claims = Claims::new_access_token(
+        user.id,
+        user.username.clone(),
+        user.role.clone(),
+        session_id,
+        config.jwt_access_token_expiry,
+    );
+    let refresh_claims = Claims::new_refresh_token(
+        user.id,
+        user.username.clone(),
+        session_id,
+        config.jwt_refresh_token_expiry,
+    );
+
+    let access_token = generate_jwt(&access_claims, &config.jwt_secret)?;
+    let refresh_token = generate_jwt(&refresh_claims, &config.jwt_secret)?;
+
+    Ok((access_token, refresh_token))
+}
+
+/// List the active sessions of the authenticated user
+/// GET /api/auth/sessions
+pub async fn list_sessions(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> AppResult<Json<Vec<SessionResponse>>> {
+    let sessions = sqlx::query_as::<_, Session>(
+        "SELECT id, user_id, refresh_token_hash, user_agent, ip_address, created_at, last_used_at, expires_at, revoked_at, revoked_reason
+         FROM user_sessions
+         WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+         ORDER BY last_used_at DESC"
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await?;
+
+    let responses = sessions
+        .into_iter()
+        .map(|s| SessionResponse::from_session(s, claims.sid))
+        .collect();
+
+    Ok(Json(responses))
+}
+
+/// Revoke one of the authenticated user's sessions
+/// DELETE /api/auth/sessions/:id
+pub async fn revoke_session(
+    State(pool): State<DbPool>,
+    Path(session_id): Path<i32>,
+    claims: Claims,
+) -> AppResult<Json<serde_json::Value>> {
+    if !revoke_session_by_id(&pool, claims.sub, session_id, "revoked").await? {
+        return Err(AppError::NotFound("Session not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "Session revoked successfully"
+    })))
+}
+
+/// Revoke every session of the authenticated user except the current one
+/// DELETE /api/auth/sessions
+pub async fn revoke_other_sessions(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> AppResult<Json<serde_json::Value>> {
+    let revoked = revoke_all_sessions(&pool, claims.sub, claims.sid, "revoked").await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Other sessions revoked successfully",
+        "revoked": revoked
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handlers::auth::logout,
+        middleware::auth::{verify_jwt, verify_session_active},
+        models::session::RefreshRequest,
+    };
+
+    async fn create_user(pool: &DbPool, username: &str) -> User {
+        sqlx::query_as::<_, User>(
+            "INSERT INTO users (username, email, password_hash) VALUES ($1, $1 || '@example.com', '')
+             RETURNING id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at"
+        )
+        .bind(username)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    fn claims_of(token: &str, config: &Config) -> Claims {
+        verify_jwt(token, &config.jwt_secret).unwrap()
+    }
+
+    async fn revoked_reason(pool: &DbPool, session_id: i32) -> Option<String> {
+        sqlx::query_scalar("SELECT revoked_reason FROM user_sessions WHERE id = $1")
+            .bind(session_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_reused_refresh_token_revokes_session(pool: DbPool) {
+        let config = Config::for_tests();
+        let client = ClientInfo::default();
+        let user = create_user(&pool, "reader").await;
+
+        let first = start_session(&pool, &config, user, &client).await.unwrap();
+        let first_claims = claims_of(&first.refresh_token, &config);
+        let session_id = first_claims.sid.unwrap();
+
+        let second = rotate_session(&pool, &config, &first.refresh_token, &first_claims, &client).await.unwrap();
+        assert_ne!(second.refresh_token, first.refresh_token);
+        let access_claims = claims_of(&second.access_token, &config);
+        assert_eq!(access_claims.sid, Some(session_id));
+        verify_session_active(&pool, session_id, access_claims.sub).await.unwrap();
+
+        // The rotated-out token comes back: it leaked, so the session goes
+        let reused = rotate_session(&pool, &config, &first.refresh_token, &first_claims, &client).await;
+        assert!(matches!(reused, Err(AppError::Authentication(_))));
+        assert_eq!(revoked_reason(&pool, session_id).await.as_deref(), Some("reuse_detected"));
+
+        // Along with everything issued to it since
+        let second_claims = claims_of(&second.refresh_token, &config);
+        assert!(rotate_session(&pool, &config, &second.refresh_token, &second_claims, &client).await.is_err());
+        assert!(verify_session_active(&pool, session_id, access_claims.sub).await.is_err());
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_logout_rejects_access_token(pool: DbPool) {
+        let config = Config::for_tests();
+        let user = create_user(&pool, "reader").await;
+
+        let auth = start_session(&pool, &config, user, &ClientInfo::default()).await.unwrap();
+        let access_claims = claims_of(&auth.access_token, &config);
+        let session_id = access_claims.sid.unwrap();
+        verify_session_active(&pool, session_id, access_claims.sub).await.unwrap();
+
+        let _ = logout(
+            State(pool.clone()),
+            State(config.clone()),
+            Json(RefreshRequest { refresh_token: auth.refresh_token.clone() }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(revoked_reason(&pool, session_id).await.as_deref(), Some("logout"));
+        // The access token has not expired, but its session is gone
+        assert!(verify_session_active(&pool, session_id, access_claims.sub).await.is_err());
+        let refresh_claims = claims_of(&auth.refresh_token, &config);
+        assert!(rotate_session(&pool, &config, &auth.refresh_token, &refresh_claims, &ClientInfo::default()).await.is_err());
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_revoke_all_sessions(pool: DbPool) {
+        let config = Config::for_tests();
+        let client = ClientInfo::default();
+        let user = create_user(&pool, "reader").await;
+        let other = create_user(&pool, "other").await;
+        let user_id = user.id;
+
+        let mut sessions = Vec::new();
+        for _ in 0..3 {
+            let auth = start_session(&pool, &config, user.clone(), &client).await.unwrap();
+            sessions.push(claims_of(&auth.access_token, &config).sid.unwrap());
+        }
+        let other_auth = start_session(&pool, &config, other.clone(), &client).await.unwrap();
+        let other_session = claims_of(&other_auth.access_token, &config).sid.unwrap();
+
+        // Signing out everywhere else keeps the current session
+        assert_eq!(revoke_all_sessions(&pool, user_id, Some(sessions[0]), "revoked").await.unwrap(), 2);
+        verify_session_active(&pool, sessions[0], user_id).await.unwrap();
+        for &session_id in &sessions[1..] {
+            assert!(verify_session_active(&pool, session_id, user_id).await.is_err());
+        }
+
+        // Already revoked sessions are not counted again, and other users keep theirs
+        assert_eq!(revoke_all_sessions(&pool, user_id, None, "revoked").await.unwrap(), 1);
+        assert!(verify_session_active(&pool, sessions[0], user_id).await.is_err());
+        verify_session_active(&pool, other_session, other.id).await.unwrap();
+    }
+}
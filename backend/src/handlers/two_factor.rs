@@ -0,0 +1,313 @@
+use axum::{extract::State, Json};
+use chrono::Utc;
+
+use crate::{
+    config::Config,
+    crypto::{hash_token, totp, TokenCrypto},
+    db::DbPool,
+    errors::{AppError, AppResult},
+    handlers::{auth::verify_password, sessions::start_session},
+    middleware::{auth::verify_jwt, Claims, ClientInfo},
+    models::{
+        two_factor::{
+            DisableTwoFactorRequest, MfaVerifyRequest, RecoveryCodesResponse, TotpCodeRequest,
+            TotpSetupResponse, TwoFactorStatusResponse, UserTotp,
+        },
+        user::{AuthResponse, User},
+    },
+    rate_limit::RateLimiter,
+};
+
+/// Number of recovery codes generated at a time
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Get the two-factor status of the authenticated user
+/// GET /api/auth/2fa
+pub async fn get_two_factor_status(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> AppResult<Json<TwoFactorStatusResponse>> {
+    let enrollment = fetch_totp(&pool, claims.sub).await?;
+
+    let recovery_codes_remaining = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM user_recovery_codes WHERE user_id = $1 AND used_at IS NULL"
+    )
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(TwoFactorStatusResponse {
+        enabled: enrollment.as_ref().is_some_and(|t| t.enabled_at.is_some()),
+        pending: enrollment.as_ref().is_some_and(|t| t.enabled_at.is_none()),
+        recovery_codes_remaining,
+    }))
+}
+
+/// Start TOTP enrollment with a new secret
+/// POST /api/auth/2fa/setup
+/// Replaces any pending (unconfirmed) enrollment
+pub async fn setup_two_factor(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+) -> AppResult<Json<TotpSetupResponse>> {
+    if let Some(existing) = fetch_totp(&pool, claims.sub).await? {
+        if existing.enabled_at.is_some() {
+            return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+        }
+    }
+
+    let secret = totp::base32_encode(&totp::generate_secret());
+    let encrypted_secret = TokenCrypto::from_config(&config)?.encrypt(&secret)?;
+
+    sqlx::query(
+        "INSERT INTO user_totp (user_id, encrypted_secret)
+         VALUES ($1, $2)
+         ON CONFLICT (user_id) DO UPDATE
+         SET encrypted_secret = EXCLUDED.encrypted_secret, last_used_step = NULL, created_at = NOW()
+         WHERE user_totp.enabled_at IS NULL"
+    )
+    .bind(claims.sub)
+    .bind(&encrypted_secret)
+    .execute(&pool)
+    .await?;
+
+    Ok(Json(TotpSetupResponse {
+        otpauth_uri: totp::otpauth_uri(&config.totp_issuer, &claims.username, &secret),
+        secret,
+    }))
+}
+
+/// Confirm enrollment with a code from the authenticator
+/// POST /api/auth/2fa/enable
+/// Returns the initial recovery codes
+pub async fn enable_two_factor(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Json(payload): Json<TotpCodeRequest>,
+) -> AppResult<Json<RecoveryCodesResponse>> {
+    let enrollment = fetch_totp(&pool, claims.sub)
+        .await?
+        .ok_or_else(|| AppError::Validation("Two-factor setup has not been started".to_string()))?;
+
+    if enrollment.enabled_at.is_some() {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let step = verify_totp_code(&config, &enrollment, &payload.code)?
+        .ok_or_else(|| AppError::Validation("Invalid verification code".to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE user_totp SET enabled_at = NOW(), last_used_step = $1 WHERE user_id = $2"
+    )
+    .bind(step)
+    .bind(claims.sub)
+    .execute(&mut *tx)
+    .await?;
+
+    let recovery_codes = replace_recovery_codes(&mut tx, claims.sub).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Turn off two-factor authentication
+/// POST /api/auth/2fa/disable
+/// Requires the password and a current code (or a recovery code)
+pub async fn disable_two_factor(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Json(payload): Json<DisableTwoFactorRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let password_hash = sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE id = $1")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if !verify_password(&payload.password, &password_hash)? {
+        return Err(AppError::Authentication("Password is incorrect".to_string()));
+    }
+
+    verify_second_factor(&pool, &config, claims.sub, &payload.code).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Two-factor authentication disabled"
+    })))
+}
+
+/// Replace all recovery codes with a fresh set
+/// POST /api/auth/2fa/recovery-codes
+pub async fn regenerate_recovery_codes(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    claims: Claims,
+    Json(payload): Json<TotpCodeRequest>,
+) -> AppResult<Json<RecoveryCodesResponse>> {
+    verify_second_factor(&pool, &config, claims.sub, &payload.code).await?;
+
+    let mut tx = pool.begin().await?;
+    let recovery_codes = replace_recovery_codes(&mut tx, claims.sub).await?;
+    tx.commit().await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Complete a two-factor login with the challenge token from /api/auth/login
+/// POST /api/auth/2fa/verify
+pub async fn verify_two_factor(
+    State(pool): State<DbPool>,
+    State(config): State<Config>,
+    State(rate_limiter): State<RateLimiter>,
+    client: ClientInfo,
+    Json(payload): Json<MfaVerifyRequest>,
+) -> AppResult<Json<AuthResponse>> {
+    let claims = verify_jwt(&payload.challenge_token, &config.jwt_secret)?;
+
+    if claims.token_type != "mfa_challenge" {
+        return Err(AppError::Authentication("Invalid token type".to_string()));
+    }
+
+    // Six digits are guessable without a lockout
+    let lockout_key = format!("mfa:user:{}", claims.sub);
+    rate_limiter.ensure_not_locked(&lockout_key).await?;
+
+    if let Err(e) = verify_second_factor(&pool, &config, claims.sub, &payload.code).await {
+        rate_limiter.record_failure(&lockout_key).await;
+        return Err(e);
+    }
+    rate_limiter.clear_failures(&lockout_key).await;
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+    )
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::Authentication("User not found".to_string()))?;
+
+    let response = start_session(&pool, &config, user, &client).await?;
+
+    Ok(Json(response))
+}
+
+/// Whether the user has confirmed TOTP enrollment
+pub async fn two_factor_enabled(pool: &DbPool, user_id: i32) -> AppResult<bool> {
+    let enabled = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL)"
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(enabled)
+}
+
+/// Check a TOTP code or, failing that, consume a recovery code
+/// Accepted TOTP steps are recorded so a code cannot be used twice
+pub async fn verify_second_factor(
+    pool: &DbPool,
+    config: &Config,
+    user_id: i32,
+    code: &str,
+) -> AppResult<()> {
+    let enrollment = fetch_totp(pool, user_id)
+        .await?
+        .filter(|t| t.enabled_at.is_some())
+        .ok_or_else(|| AppError::Validation("Two-factor authentication is not enabled".to_string()))?;
+
+    if let Some(step) = verify_totp_code(config, &enrollment, code)? {
+        // Compare-and-swap so two requests cannot both use the same step
+        let result = sqlx::query(
+            "UPDATE user_totp SET last_used_step = $1
+             WHERE user_id = $2 AND (last_used_step IS NULL OR last_used_step < $1)"
+        )
+        .bind(step)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 1 {
+            return Ok(());
+        }
+    }
+
+    let normalized = totp::normalize_recovery_code(code);
+    if !normalized.is_empty() {
+        let result = sqlx::query(
+            "UPDATE user_recovery_codes SET used_at = NOW()
+             WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .bind(hash_token(&normalized))
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 1 {
+            tracing::info!("Recovery code used by user {}", user_id);
+            return Ok(());
+        }
+    }
+
+    Err(AppError::Authentication("Invalid verification code".to_string()))
+}
+
+async fn fetch_totp(pool: &DbPool, user_id: i32) -> AppResult<Option<UserTotp>> {
+    let enrollment = sqlx::query_as::<_, UserTotp>(
+        "SELECT user_id, encrypted_secret, enabled_at, last_used_step, created_at FROM user_totp WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(enrollment)
+}
+
+/// Returns the matching time step, if the code is valid and not replayed
+fn verify_totp_code(config: &Config, enrollment: &UserTotp, code: &str) -> AppResult<Option<i64>> {
+    let secret_base32 = TokenCrypto::from_config(config)?.decrypt(&enrollment.encrypted_secret)?;
+    let secret = totp::base32_decode(&secret_base32)
+        .ok_or_else(|| AppError::Internal("Stored TOTP secret is not valid base32".to_string()))?;
+
+    Ok(totp::verify_code(&secret, code, Utc::now().timestamp(), enrollment.last_used_step))
+}
+
+async fn replace_recovery_codes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+) -> AppResult<Vec<String>> {
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| totp::generate_recovery_code())
+        .collect();
+
+    for code in &codes {
+        sqlx::query("INSERT INTO user_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(hash_token(&totp::normalize_recovery_code(code)))
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok(codes)
+}
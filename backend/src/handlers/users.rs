@@ -5,10 +5,20 @@ use axum::{
 use validator::Validate;
 
 use crate::{
+    config::Config,
+    covers::SharedCoverStore,
     db::DbPool,
     errors::{AppError, AppResult},
+    handlers::{
+        account::send_verification_email,
+        auth::{hash_password, verify_password},
+        covers::delete_cover_files,
+        sessions::revoke_all_sessions,
+    },
+    mail::{EmailMessage, SharedMailer},
     middleware::Claims,
-    models::user::{UpdateUser, User, UserResponse},
+    models::cover::{BookCover, BOOK_COVER_COLUMNS},
+    models::user::{ChangePasswordRequest, UpdateUser, User, UserResponse},
 };
 
 pub async fn get_user(
@@ -22,7 +32,7 @@ pub async fn get_user(
     }
 
     let user = sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, full_name, created_at, updated_at FROM users WHERE id = $1"
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
     )
     .bind(user_id)
     .fetch_optional(&pool)
@@ -34,6 +44,8 @@ pub async fn get_user(
 
 pub async fn update_user(
     State(pool): State<DbPool>,
+    State(config): State<Config>,
+    State(mailer): State<SharedMailer>,
     Path(user_id): Path<i32>,
     claims: Claims,
     Json(payload): Json<UpdateUser>,
@@ -50,7 +62,11 @@ pub async fn update_user(
     let mut param_count = 1;
 
     if payload.email.is_some() {
-        query.push_str(&format!(", email = ${}", param_count));
+        // A changed address must be verified again (SET sees the old email)
+        query.push_str(&format!(
+            ", email_verified_at = CASE WHEN email = ${0} THEN email_verified_at ELSE NULL END, email = ${0}",
+            param_count
+        ));
         param_count += 1;
     }
     if payload.full_name.is_some() {
@@ -58,7 +74,7 @@ pub async fn update_user(
         param_count += 1;
     }
 
-    query.push_str(&format!(" WHERE id = ${} RETURNING id, username, email, password_hash, full_name, created_at, updated_at", param_count));
+    query.push_str(&format!(" WHERE id = ${} RETURNING id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at", param_count));
 
     let mut query_builder = sqlx::query_as::<_, User>(&query);
 
@@ -76,11 +92,66 @@ pub async fn update_user(
         .await?
         .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
+    if payload.email.is_some() && user.email_verified_at.is_none() {
+        if let Err(e) = send_verification_email(&pool, &config, mailer.as_ref(), &user).await {
+            tracing::error!("Failed to send verification email to user {}: {}", user.id, e);
+        }
+    }
+
     Ok(Json(user.into()))
 }
 
+/// Change the password, given the current one
+/// POST /api/users/:id/password
+/// Signs out every other session of the user
+pub async fn change_password(
+    State(pool): State<DbPool>,
+    State(mailer): State<SharedMailer>,
+    Path(user_id): Path<i32>,
+    claims: Claims,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if claims.sub != user_id {
+        return Err(AppError::Authorization("Access denied".to_string()));
+    }
+
+    payload.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, full_name, email_verified_at, role, disabled_at, created_at, updated_at FROM users WHERE id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if !verify_password(&payload.current_password, &user.password_hash)? {
+        return Err(AppError::Authentication("Current password is incorrect".to_string()));
+    }
+
+    let password_hash = hash_password(&payload.new_password)?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+        .bind(&password_hash)
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+
+    revoke_all_sessions(&pool, user_id, claims.sid, "password_changed").await?;
+
+    if let Err(e) = mailer.send(&EmailMessage::password_changed(&user.email, &user.username)).await {
+        tracing::error!("Failed to send password change notice to user {}: {}", user.id, e);
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": "Password changed successfully"
+    })))
+}
+
 pub async fn delete_user(
     State(pool): State<DbPool>,
+    State(covers): State<SharedCoverStore>,
     Path(user_id): Path<i32>,
     claims: Claims,
 ) -> AppResult<Json<serde_json::Value>> {
@@ -88,15 +159,31 @@ pub async fn delete_user(
         return Err(AppError::Authorization("Access denied".to_string()));
     }
 
+    let mut tx = pool.begin().await?;
+
+    let book_covers = sqlx::query_as::<_, BookCover>(&format!(
+        "DELETE FROM book_covers WHERE book_id IN (SELECT id FROM books WHERE user_id = $1) RETURNING {}",
+        BOOK_COVER_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
     let result = sqlx::query("DELETE FROM users WHERE id = $1")
         .bind(user_id)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound("User not found".to_string()));
     }
 
+    tx.commit().await?;
+
+    for cover in &book_covers {
+        delete_cover_files(covers.as_ref(), cover).await;
+    }
+
     Ok(Json(serde_json::json!({
         "message": "User deleted successfully"
     })))
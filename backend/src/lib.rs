@@ -1,10 +1,16 @@
+#![recursion_limit = "256"]
+
+pub mod chat;
 pub mod config;
+pub mod covers;
 pub mod crypto;
 pub mod db;
 pub mod errors;
 pub mod handlers;
+pub mod mail;
 pub mod middleware;
 pub mod models;
+pub mod rate_limit;
 pub mod routes;
 pub mod services;
 
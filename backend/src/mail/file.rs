@@ -0,0 +1,102 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use chrono::Utc;
+
+use super::{EmailMessage, Mailer};
+use crate::errors::{AppError, AppResult};
+
+/// Mailer that only logs messages
+/// Default transport for development; links can be copied from the log
+pub struct LogMailer;
+
+#[async_trait::async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: &EmailMessage) -> AppResult<()> {
+        tracing::info!(
+            "Email to {} ({}):\n{}",
+            message.to,
+            message.subject,
+            message.body
+        );
+        Ok(())
+    }
+}
+
+/// Mailer that writes each message to its own file in a directory
+/// Useful in tests and staging to inspect what would have been sent
+pub struct FileMailer {
+    dir: PathBuf,
+    counter: AtomicU64,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Render a message in RFC 5322 style, without a Date or Message-ID
+    pub fn render(message: &EmailMessage) -> String {
+        format!(
+            "To: {}\nSubject: {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+            message.to, message.subject, message.body
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: &EmailMessage) -> AppResult<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to create mail directory: {}", e)))?;
+
+        let sequence = self.counter.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.join(format!(
+            "{}-{:04}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            sequence
+        ));
+
+        tokio::fs::write(&path, Self::render(message))
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to write email: {}", e)))?;
+
+        tracing::debug!("Email to {} written to {}", message.to, path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_mailer_writes_one_file_per_message() {
+        let dir = std::env::temp_dir().join(format!("panicless-mail-{:016x}", rand::random::<u64>()));
+        let mailer = FileMailer::new(&dir);
+
+        let first = EmailMessage::password_reset("a@example.com", "alice", "http://x/reset?token=abc", 60);
+        let second = EmailMessage::password_changed("a@example.com", "alice");
+        mailer.send(&first).await.unwrap();
+        mailer.send(&second).await.unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 2);
+
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.starts_with("To: a@example.com\nSubject: Reset your password\n"));
+        assert!(content.contains("http://x/reset?token=abc"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
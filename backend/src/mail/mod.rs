@@ -0,0 +1,84 @@
+pub mod file;
+pub mod smtp;
+
+pub use file::{FileMailer, LogMailer};
+pub use smtp::SmtpMailer;
+
+use std::sync::Arc;
+
+use crate::{config::Config, errors::AppResult};
+
+/// An outgoing plain-text email
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers outgoing email
+/// Implementations must not fail silently: a returned Ok means the message
+/// was handed to the transport
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> AppResult<()>;
+}
+
+/// Shared mailer handle stored in the application state
+pub type SharedMailer = Arc<dyn Mailer>;
+
+/// Build the mailer selected by MAIL_TRANSPORT
+pub fn from_config(config: &Config) -> AppResult<SharedMailer> {
+    let mailer: SharedMailer = match config.mail_transport.as_str() {
+        "smtp" => Arc::new(SmtpMailer::new(config)?),
+        "file" => Arc::new(FileMailer::new(&config.mail_file_dir)),
+        _ => Arc::new(LogMailer),
+    };
+    Ok(mailer)
+}
+
+impl EmailMessage {
+    pub fn email_verification(to: &str, username: &str, link: &str, expires_in_hours: i64) -> Self {
+        EmailMessage {
+            to: to.to_string(),
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Hello {},\n\n\
+                 Please confirm your email address for Panicless Library by opening this link:\n\n\
+                 {}\n\n\
+                 The link expires in {} hours. If you did not request this, you can ignore this email.\n",
+                username, link, expires_in_hours
+            ),
+        }
+    }
+
+    pub fn password_reset(to: &str, username: &str, link: &str, expires_in_minutes: i64) -> Self {
+        EmailMessage {
+            to: to.to_string(),
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Hello {},\n\n\
+                 A password reset was requested for your Panicless Library account. \
+                 To choose a new password, open this link:\n\n\
+                 {}\n\n\
+                 The link expires in {} minutes and can be used once. \
+                 If you did not request a reset, you can ignore this email.\n",
+                username, link, expires_in_minutes
+            ),
+        }
+    }
+
+    pub fn password_changed(to: &str, username: &str) -> Self {
+        EmailMessage {
+            to: to.to_string(),
+            subject: "Your password was changed".to_string(),
+            body: format!(
+                "Hello {},\n\n\
+                 The password of your Panicless Library account was just changed \
+                 and all other sessions were signed out.\n\n\
+                 If this was not you, reset your password immediately.\n",
+                username
+            ),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use panicless_backend::{create_pool, create_router, Config};
+use std::net::SocketAddr;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -28,8 +29,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     panicless_backend::db::test_connection(&pool).await?;
     tracing::info!("Database connection test successful");
 
+    // Create mailer
+    let mailer = panicless_backend::mail::from_config(&config)?;
+    tracing::info!("Mail transport: {}", config.mail_transport);
+
+    // Create cover storage
+    let covers = panicless_backend::covers::from_config(&config)?;
+    tracing::info!("Cover storage: {}", config.cover_storage);
+
+    // Imports this instance was running when it stopped cannot be resumed
+    let interrupted = panicless_backend::services::import_jobs::fail_interrupted_jobs(&pool, &config.instance_id).await?;
+    if interrupted > 0 {
+        tracing::warn!("Marked {} interrupted import jobs as failed", interrupted);
+    }
+
+    // Re-check stored connector keys in the background
+    panicless_backend::services::connector_verification::spawn_periodic_verification(pool.clone(), config.clone());
+
     // Create router
-    let app = create_router(pool, config.clone());
+    let app = create_router(pool, config.clone(), mailer, covers);
     tracing::info!("Router created with all endpoints");
 
     // Start server
@@ -40,7 +58,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("📚 API available at: http://{}/api", config.server_address());
     tracing::info!("💚 Health check at: http://{}/health", config.server_address());
 
-    axum::serve(listener, app)
+    // Connect info gives handlers the peer address (sessions, rate limiting)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
 
     Ok(())
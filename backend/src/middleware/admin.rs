@@ -0,0 +1,58 @@
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+
+use super::{Claims, ClientInfo};
+use crate::{db::DbPool, errors::AppError};
+
+/// An authenticated administrator
+/// Requires a session access token with the admin role, and re-checks the
+/// role in the database so a demotion takes effect immediately
+#[derive(Debug, Clone)]
+pub struct AdminUser {
+    pub claims: Claims,
+    pub ip_address: Option<String>,
+}
+
+impl AdminUser {
+    pub fn id(&self) -> i32 {
+        self.claims.sub
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+    DbPool: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+
+        if claims.role.as_deref() != Some("admin") {
+            return Err(AppError::Authorization("Administrator access required".to_string()));
+        }
+
+        let pool = DbPool::from_ref(state);
+        let is_admin = sqlx::query_scalar::<_, bool>(
+            "SELECT role = 'admin' AND disabled_at IS NULL FROM users WHERE id = $1"
+        )
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await?
+        .unwrap_or(false);
+
+        if !is_admin {
+            return Err(AppError::Authorization("Administrator access required".to_string()));
+        }
+
+        let ClientInfo { ip_address, .. } = ClientInfo::from_request_parts(parts, state)
+            .await
+            .unwrap_or_default();
+
+        Ok(AdminUser { claims, ip_address })
+    }
+}
@@ -17,11 +17,17 @@ pub struct Claims {
     pub username: String,   // Username
     pub exp: usize,         // Expiration time
     pub iat: usize,         // Issued at
-    pub token_type: String, // "access" or "refresh"
+    pub token_type: String, // "access", "refresh" or "mfa_challenge"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<i32>,   // Session id (user_sessions), absent for OAuth tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // Unique token id, makes every rotated refresh token distinct
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>, // "admin" for administrators; only set on session access tokens
 }
 
 impl Claims {
-    pub fn new_access_token(user_id: i32, username: String, expiry: i64) -> Self {
+    pub fn new_access_token(user_id: i32, username: String, role: String, session_id: i32, expiry: i64) -> Self {
         let now = chrono::Utc::now().timestamp() as usize;
         Self {
             sub: user_id,
@@ -29,10 +35,13 @@ impl Claims {
             exp: (now as i64 + expiry) as usize,
             iat: now,
             token_type: "access".to_string(),
+            sid: Some(session_id),
+            jti: None,
+            role: Some(role),
         }
     }
 
-    pub fn new_refresh_token(user_id: i32, username: String, expiry: i64) -> Self {
+    pub fn new_refresh_token(user_id: i32, username: String, session_id: i32, expiry: i64) -> Self {
         let now = chrono::Utc::now().timestamp() as usize;
         Self {
             sub: user_id,
@@ -40,6 +49,25 @@ impl Claims {
             exp: (now as i64 + expiry) as usize,
             iat: now,
             token_type: "refresh".to_string(),
+            sid: Some(session_id),
+            jti: Some(format!("{:032x}", rand::random::<u128>())),
+            role: None,
+        }
+    }
+
+    /// Short-lived token proving the password step of a two-factor login
+    /// Only accepted by the second-factor verification endpoint
+    pub fn new_mfa_challenge(user_id: i32, username: String, expiry: i64) -> Self {
+        let now = chrono::Utc::now().timestamp() as usize;
+        Self {
+            sub: user_id,
+            username,
+            exp: (now as i64 + expiry) as usize,
+            iat: now,
+            token_type: "mfa_challenge".to_string(),
+            sid: None,
+            jti: Some(format!("{:032x}", rand::random::<u128>())),
+            role: None,
         }
     }
 }
@@ -89,6 +117,11 @@ pub async fn auth_middleware(
             if jwt_claims.token_type != "access" {
                 return Err(AppError::Authentication("Invalid token type".to_string()));
             }
+            // Access tokens die with their session (logout, revocation)
+            match jwt_claims.sid {
+                Some(session_id) => verify_session_active(&pool, session_id, jwt_claims.sub).await?,
+                None => verify_user_enabled(&pool, jwt_claims.sub).await?,
+            }
             tracing::debug!("Authenticated with JWT token for user_id={}", jwt_claims.sub);
             jwt_claims
         }
@@ -115,6 +148,42 @@ pub async fn auth_middleware(
     Ok(next.run(request).await)
 }
 
+/// Check that a session exists, belongs to the user and was neither revoked nor expired,
+/// and that the account is not disabled
+pub async fn verify_session_active(pool: &PgPool, session_id: i32, user_id: i32) -> AppResult<()> {
+    let active = sqlx::query_scalar::<_, bool>(
+        "SELECT s.revoked_at IS NULL AND s.expires_at > NOW() AND u.disabled_at IS NULL
+         FROM user_sessions s JOIN users u ON u.id = s.user_id
+         WHERE s.id = $1 AND s.user_id = $2"
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(false);
+
+    if !active {
+        return Err(AppError::Authentication("Session has been revoked or expired".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Check that the user exists and is not disabled
+async fn verify_user_enabled(pool: &PgPool, user_id: i32) -> AppResult<()> {
+    let enabled = sqlx::query_scalar::<_, bool>("SELECT disabled_at IS NULL FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(false);
+
+    if !enabled {
+        return Err(AppError::Authentication("Account is disabled".to_string()));
+    }
+
+    Ok(())
+}
+
 /// Verify OAuth access token by looking it up in the database
 async fn verify_oauth_token(pool: &PgPool, token: &str) -> AppResult<Claims> {
     // Look up token in database
@@ -135,10 +204,11 @@ async fn verify_oauth_token(pool: &PgPool, token: &str) -> AppResult<Claims> {
     }
 
     // Get user info
-    let user = sqlx::query("SELECT username FROM users WHERE id = $1")
+    let user = sqlx::query("SELECT username FROM users WHERE id = $1 AND disabled_at IS NULL")
         .bind(user_id)
-        .fetch_one(pool)
-        .await?;
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::Authentication("Account is disabled".to_string()))?;
 
     let username: String = user.get("username");
 
@@ -152,6 +222,9 @@ async fn verify_oauth_token(pool: &PgPool, token: &str) -> AppResult<Claims> {
         exp,
         iat: now,
         token_type: "access".to_string(),
+        sid: None,
+        jti: None,
+        role: None,
     })
 }
 
@@ -0,0 +1,151 @@
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts, HeaderMap},
+};
+use ipnet::IpNet;
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+/// Maximum stored length of a User-Agent string
+const MAX_USER_AGENT_LEN: usize = 500;
+
+/// Information about the client that sent a request
+/// Used to label sessions and to key rate limits
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Reverse proxies whose forwarding headers are believed (TRUSTED_PROXIES)
+/// Added to every request as an extension by the router.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Arc<Vec<IpNet>>);
+
+impl TrustedProxies {
+    pub fn new(networks: Vec<IpNet>) -> Self {
+        Self(Arc::new(networks))
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(&ip))
+    }
+}
+
+impl ClientInfo {
+    pub fn from_parts(headers: &HeaderMap, peer: Option<SocketAddr>, trusted: &TrustedProxies) -> Self {
+        let user_agent = headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+
+        Self {
+            user_agent,
+            ip_address: client_ip(headers, peer, trusted),
+        }
+    }
+}
+
+/// Determine the client IP address
+/// The TCP peer address, unless the peer is a trusted proxy: then the nearest
+/// X-Forwarded-For hop that is not itself a trusted proxy, or X-Real-IP (set by
+/// the nginx frontend proxy). Headers from anyone else are client-controlled and ignored.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trusted: &TrustedProxies) -> Option<String> {
+    let peer = peer?.ip();
+    if !trusted.contains(peer) {
+        return Some(peer.to_string());
+    }
+
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+
+    if forwarded.is_empty() {
+        let real_ip = headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<IpAddr>().ok());
+        return Some(real_ip.unwrap_or(peer).to_string());
+    }
+
+    // Each proxy appends the address it got the request from; walk back until
+    // a hop is not one of ours. Hops further left could be made up.
+    let mut client = peer;
+    for hop in forwarded.iter().rev() {
+        let Ok(ip) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !trusted.contains(ip) {
+            break;
+        }
+    }
+    Some(client.to_string())
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+
+        let trusted = parts.extensions.get::<TrustedProxies>().cloned().unwrap_or_default();
+
+        Ok(ClientInfo::from_parts(&parts.headers, peer, &trusted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trusted(networks: &str) -> TrustedProxies {
+        TrustedProxies::new(networks.split(',').map(|n| n.parse().unwrap()).collect())
+    }
+
+    #[test]
+    fn test_client_ip_reads_headers_from_trusted_proxy() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        headers.insert("x-real-ip", "10.0.0.1".parse().unwrap());
+        let peer: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        // Trusted proxies are skipped from the nearest hop back
+        let proxies = trusted("127.0.0.1/32,10.0.0.0/8");
+        assert_eq!(client_ip(&headers, Some(peer), &proxies), Some("203.0.113.7".to_string()));
+
+        // An untrusted hop stops the walk, whatever the client wrote before it
+        let proxies = trusted("127.0.0.1/32");
+        assert_eq!(client_ip(&headers, Some(peer), &proxies), Some("10.0.0.1".to_string()));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", "198.51.100.4".parse().unwrap());
+        assert_eq!(client_ip(&headers, Some(peer), &proxies), Some("198.51.100.4".to_string()));
+    }
+
+    #[test]
+    fn test_client_ip_ignores_headers_from_others() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7".parse().unwrap());
+        headers.insert("x-real-ip", "203.0.113.8".parse().unwrap());
+        let peer: SocketAddr = "192.168.1.20:4000".parse().unwrap();
+
+        assert_eq!(client_ip(&headers, Some(peer), &TrustedProxies::default()), Some("192.168.1.20".to_string()));
+        assert_eq!(client_ip(&headers, Some(peer), &trusted("10.0.0.0/8")), Some("192.168.1.20".to_string()));
+        assert_eq!(client_ip(&headers, None, &TrustedProxies::default()), None);
+    }
+}
@@ -1,3 +1,8 @@
+pub mod admin;
 pub mod auth;
+pub mod client;
+pub mod rate_limit;
 
+pub use admin::AdminUser;
 pub use auth::{Claims, auth_middleware};
+pub use client::{ClientInfo, TrustedProxies};
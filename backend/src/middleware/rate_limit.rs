@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use super::{Claims, ClientInfo};
+use crate::{
+    errors::AppError,
+    rate_limit::{Policy, RateLimiter},
+};
+
+/// Route groups with their own rate limit policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    Auth,
+    OAuth,
+    Api,
+    Import,
+}
+
+impl RouteGroup {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::OAuth => "oauth",
+            Self::Api => "api",
+            Self::Import => "import",
+        }
+    }
+
+    fn policy(&self, limiter: &RateLimiter) -> Policy {
+        match self {
+            Self::Auth => limiter.policies.auth,
+            Self::OAuth => limiter.policies.oauth,
+            Self::Api => limiter.policies.api,
+            Self::Import => limiter.policies.import,
+        }
+    }
+}
+
+/// State of one rate limit layer
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    pub limiter: RateLimiter,
+    pub group: RouteGroup,
+}
+
+/// Rate limit requests of a route group
+/// Authenticated requests (claims set by auth_middleware, which must run
+/// first) are counted per user, everything else per client IP
+pub async fn rate_limit_middleware(
+    State(layer): State<RateLimitLayer>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = match request.extensions().get::<Claims>() {
+        Some(claims) => format!("{}:user:{}", layer.group.name(), claims.sub),
+        None => format!(
+            "{}:ip:{}",
+            layer.group.name(),
+            client.ip_address.as_deref().unwrap_or("unknown")
+        ),
+    };
+
+    layer.limiter.check(&key, &layer.group.policy(&layer.limiter)).await?;
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+/// Who may create an account through /api/auth/register
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationPolicy {
+    Open,
+    InviteOnly,
+    Closed,
+}
+
+impl RegistrationPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::InviteOnly => "invite_only",
+            Self::Closed => "closed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(Self::Open),
+            "invite_only" => Some(Self::InviteOnly),
+            "closed" => Some(Self::Closed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrationSettings {
+    pub registration_policy: RegistrationPolicy,
+}
+
+/// User as listed to administrators, with usage counters
+#[derive(Debug, Serialize, FromRow)]
+pub struct AdminUserSummary {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub full_name: Option<String>,
+    pub role: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub disabled_reason: Option<String>,
+    pub two_factor_enabled: bool,
+    pub book_count: i64,
+    pub reading_count: i64,
+    pub active_sessions: i64,
+    pub last_active_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUserQuery {
+    pub search: Option<String>,
+    pub role: Option<String>,
+    pub disabled: Option<bool>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DisableUserRequest {
+    #[validate(length(max = 500))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: String,
+}
+
+/// Instance-wide counters
+#[derive(Debug, Serialize, FromRow)]
+pub struct InstanceStats {
+    pub users_total: i64,
+    pub users_admin: i64,
+    pub users_disabled: i64,
+    pub users_unverified: i64,
+    pub users_active_30d: i64,
+    pub books_total: i64,
+    pub readings_total: i64,
+    pub readings_completed: i64,
+    pub connectors_enabled: i64,
+    pub sessions_active: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct InviteCode {
+    pub id: i32,
+    pub code_hint: String,
+    pub created_by: Option<i32>,
+    pub email: Option<String>,
+    pub max_uses: i32,
+    pub use_count: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateInviteRequest {
+    #[validate(email)]
+    pub email: Option<String>,
+
+    #[validate(range(min = 1, max = 1000))]
+    pub max_uses: Option<i32>,
+
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+}
+
+/// A new invite; the code is only returned here
+#[derive(Debug, Serialize)]
+pub struct CreatedInviteResponse {
+    pub code: String,
+    pub invite: InviteCode,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub admin_user_id: Option<i32>,
+    pub admin_username: String,
+    pub action: String,
+    pub target_user_id: Option<i32>,
+    pub details: serde_json::Value,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub action: Option<String>,
+    pub target_user_id: Option<i32>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
@@ -3,6 +3,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use validator::Validate;
 
+use crate::models::identifier::IdentifierType;
+
+/// Columns of `books` selected into `Book`
+pub const BOOK_COLUMNS: &str = "id, user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, series, series_index, tags, format, original_publication_year, owned_copies, goodreads_id, calibre_uuid, created_at, updated_at";
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Book {
     pub id: i32,
@@ -17,6 +22,18 @@ pub struct Book {
     pub language: Option<String>,
     pub description: Option<String>,
     pub cover_image_url: Option<String>,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+    pub tags: Vec<String>,
+    /// Binding or format of the edition, e.g. "Paperback"
+    pub format: Option<String>,
+    /// Year the work was first published; `publication_year` is the edition's
+    pub original_publication_year: Option<i32>,
+    pub owned_copies: Option<i32>,
+    /// Set on books imported from Goodreads
+    pub goodreads_id: Option<String>,
+    /// Set on books synced from a Calibre library
+    pub calibre_uuid: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -51,6 +68,23 @@ pub struct CreateBook {
 
     #[validate(length(max = 500), url)]
     pub cover_image_url: Option<String>,
+
+    #[validate(length(max = 255))]
+    pub series: Option<String>,
+
+    #[validate(range(min = 0.0))]
+    pub series_index: Option<f64>,
+
+    pub tags: Option<Vec<String>>,
+
+    #[validate(length(max = 50))]
+    pub format: Option<String>,
+
+    #[validate(range(min = 1000, max = 9999))]
+    pub original_publication_year: Option<i32>,
+
+    #[validate(range(min = 0))]
+    pub owned_copies: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -83,6 +117,23 @@ pub struct UpdateBook {
 
     #[validate(length(max = 500), url)]
     pub cover_image_url: Option<String>,
+
+    #[validate(length(max = 255))]
+    pub series: Option<String>,
+
+    #[validate(range(min = 0.0))]
+    pub series_index: Option<f64>,
+
+    pub tags: Option<Vec<String>>,
+
+    #[validate(length(max = 50))]
+    pub format: Option<String>,
+
+    #[validate(range(min = 1000, max = 9999))]
+    pub original_publication_year: Option<i32>,
+
+    #[validate(range(min = 0))]
+    pub owned_copies: Option<i32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -116,6 +167,10 @@ pub struct AdvancedBookSearchQuery {
     pub language: Option<String>,
     pub publisher: Option<String>,
     pub description: Option<String>,
+    /// Any identifier the book carries; see `identifier_type`
+    pub identifier: Option<String>,
+    /// Type of `identifier`; without one, every type the value is valid for
+    pub identifier_type: Option<IdentifierType>,
     pub page: Option<i64>,
     pub limit: Option<i64>,
 }
@@ -131,6 +186,8 @@ impl Default for AdvancedBookSearchQuery {
             language: None,
             publisher: None,
             description: None,
+            identifier: None,
+            identifier_type: None,
             page: Some(1),
             limit: Some(20),
         }
@@ -0,0 +1,82 @@
+use serde::Deserialize;
+
+use crate::chat::ChatMessage;
+
+/// A prior turn of the conversation, as kept by the client
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatTurn {
+    /// "user" or "assistant"
+    pub role: String,
+    pub content: String,
+}
+
+/// Request payload for POST /api/chat
+#[derive(Debug, Deserialize)]
+pub struct ChatRequest {
+    /// Conversation so far; the last turn must come from the user
+    pub messages: Vec<ChatTurn>,
+    /// Connector to use; defaults to the most recently used active one
+    pub provider: Option<String>,
+}
+
+impl ChatRequest {
+    /// Convert the client's transcript into provider-neutral messages
+    pub fn to_messages(&self) -> Result<Vec<ChatMessage>, String> {
+        match self.messages.last() {
+            None => return Err("At least one message is required".to_string()),
+            Some(turn) if turn.role != "user" => {
+                return Err("The last message must come from the user".to_string())
+            }
+            _ => {}
+        }
+
+        self.messages
+            .iter()
+            .map(|turn| {
+                if turn.content.trim().is_empty() {
+                    return Err("Messages cannot be empty".to_string());
+                }
+                match turn.role.as_str() {
+                    "user" => Ok(ChatMessage::User { text: turn.content.clone() }),
+                    "assistant" => Ok(ChatMessage::Assistant {
+                        text: turn.content.clone(),
+                        tool_calls: Vec::new(),
+                    }),
+                    other => Err(format!(
+                        "Invalid role '{}'. Must be one of: user, assistant",
+                        other
+                    )),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(role: &str, content: &str) -> ChatTurn {
+        ChatTurn { role: role.to_string(), content: content.to_string() }
+    }
+
+    #[test]
+    fn test_to_messages() {
+        let request = ChatRequest {
+            messages: vec![turn("user", "hi"), turn("assistant", "hello"), turn("user", "books?")],
+            provider: None,
+        };
+        let messages = request.to_messages().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2], ChatMessage::User { text: "books?".to_string() });
+    }
+
+    #[test]
+    fn test_to_messages_rejects_invalid_transcripts() {
+        let request = |messages| ChatRequest { messages, provider: None };
+        assert!(request(vec![]).to_messages().is_err());
+        assert!(request(vec![turn("user", "hi"), turn("assistant", "hello")]).to_messages().is_err());
+        assert!(request(vec![turn("system", "x"), turn("user", "hi")]).to_messages().is_err());
+        assert!(request(vec![turn("user", "  ")]).to_messages().is_err());
+    }
+}
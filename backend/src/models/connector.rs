@@ -2,6 +2,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+/// Columns selected into `Connector`
+pub const CONNECTOR_COLUMNS: &str =
+    "id, user_id, provider, encrypted_token, base_url, model, is_active, last_used_at, status, status_detail, verified_at, created_at, updated_at";
+
 /// Represents a connector for an AI provider with encrypted token
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Connector {
@@ -9,10 +13,19 @@ pub struct Connector {
     pub user_id: i32,
     pub provider: String,
     /// Encrypted token - never sent to client in full
+    /// None for custom connectors whose server needs no key
     #[serde(skip_serializing)]
-    pub encrypted_token: String,
+    pub encrypted_token: Option<String>,
+    /// Endpoint of a custom (OpenAI-compatible) connector
+    pub base_url: Option<String>,
+    /// Model override; required for custom connectors
+    pub model: Option<String>,
     pub is_active: bool,
     pub last_used_at: Option<DateTime<Utc>>,
+    /// Outcome of the last key check, see `ConnectorStatus`
+    pub status: String,
+    pub status_detail: Option<String>,
+    pub verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -22,8 +35,14 @@ pub struct Connector {
 pub struct ConnectorResponse {
     pub id: i32,
     pub provider: String,
+    pub has_api_key: bool,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
     pub is_active: bool,
     pub last_used_at: Option<DateTime<Utc>>,
+    pub status: String,
+    pub status_detail: Option<String>,
+    pub verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -33,8 +52,14 @@ impl From<Connector> for ConnectorResponse {
         ConnectorResponse {
             id: connector.id,
             provider: connector.provider,
+            has_api_key: connector.encrypted_token.is_some(),
+            base_url: connector.base_url,
+            model: connector.model,
             is_active: connector.is_active,
             last_used_at: connector.last_used_at,
+            status: connector.status,
+            status_detail: connector.status_detail,
+            verified_at: connector.verified_at,
             created_at: connector.created_at,
             updated_at: connector.updated_at,
         }
@@ -44,10 +69,81 @@ impl From<Connector> for ConnectorResponse {
 /// Request payload to create or update a connector
 #[derive(Debug, Deserialize)]
 pub struct CreateConnectorRequest {
-    /// One of: "anthropic", "gemini", "chatgpt"
+    /// One of: "anthropic", "gemini", "chatgpt", "custom"
     pub provider: String,
     /// API token/key for the provider (plaintext, will be encrypted before storage)
-    pub api_token: String,
+    /// Required except for custom connectors
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// Base URL of an OpenAI-compatible server, e.g. http://localhost:11434 (custom only)
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Model to use; required for custom connectors
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Connector fields checked and normalized for storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectorSettings {
+    pub api_token: Option<String>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+}
+
+impl CreateConnectorRequest {
+    /// Check the fields the provider needs
+    /// `allowed_hosts` restricts custom base URLs; empty allows any host
+    pub fn validate(&self, allowed_hosts: &[String]) -> Result<ConnectorSettings, String> {
+        validate_provider(&self.provider)?;
+
+        let non_empty = |value: &Option<String>| {
+            value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+        };
+        let api_token = non_empty(&self.api_token);
+        let model = non_empty(&self.model);
+        if model.as_ref().is_some_and(|m| m.len() > 255) {
+            return Err("Model name must be at most 255 characters".to_string());
+        }
+
+        if self.provider != "custom" {
+            if api_token.is_none() {
+                return Err("API token cannot be empty".to_string());
+            }
+            if non_empty(&self.base_url).is_some() {
+                return Err("base_url is only supported for custom connectors".to_string());
+            }
+            return Ok(ConnectorSettings { api_token, base_url: None, model });
+        }
+
+        let base_url = non_empty(&self.base_url)
+            .ok_or_else(|| "base_url is required for custom connectors".to_string())?;
+        let base_url = normalize_base_url(&base_url, allowed_hosts)?;
+        if model.is_none() {
+            return Err("model is required for custom connectors".to_string());
+        }
+
+        Ok(ConnectorSettings { api_token, base_url: Some(base_url), model })
+    }
+}
+
+/// Validate an OpenAI-compatible base URL and strip a trailing `/v1`
+/// (the adapter appends `/v1/...` itself)
+pub fn normalize_base_url(raw: &str, allowed_hosts: &[String]) -> Result<String, String> {
+    let url = reqwest::Url::parse(raw).map_err(|_| format!("Invalid base_url '{}'", raw))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("base_url must use http or https".to_string());
+    }
+    if url.query().is_some() || url.fragment().is_some() || !url.username().is_empty() {
+        return Err("base_url must not contain credentials, a query or a fragment".to_string());
+    }
+    let host = url.host_str().unwrap_or_default();
+    if !allowed_hosts.is_empty() && !allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)) {
+        return Err(format!("Host '{}' is not allowed for custom connectors", host));
+    }
+
+    let normalized = url.as_str().trim_end_matches('/');
+    Ok(normalized.strip_suffix("/v1").unwrap_or(normalized).to_string())
 }
 
 /// Request payload to update a connector's active status
@@ -59,12 +155,48 @@ pub struct UpdateConnectorRequest {
     pub is_active: Option<bool>,
 }
 
+/// Result of checking a connector's key against its provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectorStatus {
+    /// Never checked, or the key changed since the last check
+    Unverified,
+    Valid,
+    /// Rejected by the provider (revoked or mistyped key)
+    Invalid,
+    RateLimited,
+    /// The provider could not be reached or answered with an unexpected error
+    Unreachable,
+}
+
+impl ConnectorStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unverified => "unverified",
+            Self::Valid => "valid",
+            Self::Invalid => "invalid",
+            Self::RateLimited => "rate_limited",
+            Self::Unreachable => "unreachable",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "unverified" => Some(Self::Unverified),
+            "valid" => Some(Self::Valid),
+            "invalid" => Some(Self::Invalid),
+            "rate_limited" => Some(Self::RateLimited),
+            "unreachable" => Some(Self::Unreachable),
+            _ => None,
+        }
+    }
+}
+
 /// Validate that the provider is one of the supported providers
 pub fn validate_provider(provider: &str) -> Result<(), String> {
     match provider {
-        "anthropic" | "gemini" | "chatgpt" => Ok(()),
+        "anthropic" | "gemini" | "chatgpt" | "custom" => Ok(()),
         _ => Err(format!(
-            "Invalid provider '{}'. Must be one of: anthropic, gemini, chatgpt",
+            "Invalid provider '{}'. Must be one of: anthropic, gemini, chatgpt, custom",
             provider
         )),
     }
@@ -79,6 +211,7 @@ mod tests {
         assert!(validate_provider("anthropic").is_ok());
         assert!(validate_provider("gemini").is_ok());
         assert!(validate_provider("chatgpt").is_ok());
+        assert!(validate_provider("custom").is_ok());
     }
 
     #[test]
@@ -94,9 +227,14 @@ mod tests {
             id: 1,
             user_id: 42,
             provider: "anthropic".to_string(),
-            encrypted_token: "secret_encrypted_token".to_string(),
+            encrypted_token: Some("secret_encrypted_token".to_string()),
+            base_url: None,
+            model: None,
             is_active: true,
             last_used_at: None,
+            status: "valid".to_string(),
+            status_detail: None,
+            verified_at: Some(Utc::now()),
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -104,6 +242,64 @@ mod tests {
         let response: ConnectorResponse = connector.into();
         assert_eq!(response.id, 1);
         assert_eq!(response.provider, "anthropic");
-        assert_eq!(response.is_active, true);
+        assert!(response.is_active);
+        assert_eq!(response.status, "valid");
+        assert!(response.has_api_key);
+    }
+
+    fn request(provider: &str, api_token: Option<&str>, base_url: Option<&str>, model: Option<&str>) -> CreateConnectorRequest {
+        CreateConnectorRequest {
+            provider: provider.to_string(),
+            api_token: api_token.map(str::to_string),
+            base_url: base_url.map(str::to_string),
+            model: model.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_validate_hosted_provider_request() {
+        let settings = request("anthropic", Some(" sk-1 "), None, None).validate(&[]).unwrap();
+        assert_eq!(settings.api_token.as_deref(), Some("sk-1"));
+        assert!(request("anthropic", Some("  "), None, None).validate(&[]).is_err());
+        assert!(request("chatgpt", Some("sk"), Some("http://evil.example"), None).validate(&[]).is_err());
+    }
+
+    #[test]
+    fn test_validate_custom_request() {
+        let settings = request("custom", None, Some("http://localhost:11434/v1/"), Some("llama3.1"))
+            .validate(&[])
+            .unwrap();
+        assert_eq!(settings.api_token, None);
+        assert_eq!(settings.base_url.as_deref(), Some("http://localhost:11434"));
+        assert_eq!(settings.model.as_deref(), Some("llama3.1"));
+
+        assert!(request("custom", None, None, Some("llama3.1")).validate(&[]).is_err());
+        assert!(request("custom", None, Some("http://localhost:11434"), None).validate(&[]).is_err());
+        assert!(request("custom", None, Some("ftp://localhost"), Some("m")).validate(&[]).is_err());
+        assert!(request("custom", None, Some("http://user:pw@localhost"), Some("m")).validate(&[]).is_err());
+    }
+
+    #[test]
+    fn test_normalize_base_url_allowed_hosts() {
+        let allowed = vec!["ollama.internal".to_string()];
+        assert_eq!(
+            normalize_base_url("https://Ollama.internal/api", &allowed).unwrap(),
+            "https://ollama.internal/api"
+        );
+        assert!(normalize_base_url("http://169.254.169.254", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_connector_status_round_trip() {
+        for status in [
+            ConnectorStatus::Unverified,
+            ConnectorStatus::Valid,
+            ConnectorStatus::Invalid,
+            ConnectorStatus::RateLimited,
+            ConnectorStatus::Unreachable,
+        ] {
+            assert_eq!(ConnectorStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(ConnectorStatus::parse("revoked"), None);
     }
 }
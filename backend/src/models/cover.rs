@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::covers::CoverSize;
+
+/// Columns of `book_covers`, in `BookCover` field order
+pub const BOOK_COVER_COLUMNS: &str =
+    "book_id, storage_prefix, content_type, width, height, byte_size, sha256, uploaded_at";
+
+#[derive(Debug, Clone, FromRow)]
+pub struct BookCover {
+    pub book_id: i32,
+    pub storage_prefix: String,
+    /// Media type of the original
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub byte_size: i32,
+    pub sha256: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl BookCover {
+    /// Changes with every upload; used in cover URLs and ETags
+    pub fn version(&self) -> &str {
+        &self.sha256[..16]
+    }
+
+    /// Storage key of one of the cover's files
+    pub fn key(&self, size: CoverSize) -> String {
+        format!("{}/{}", self.storage_prefix, cover_file_name(size, &self.content_type))
+    }
+
+    /// Media type a size is served with; resized variants are JPEGs
+    pub fn size_content_type(&self, size: CoverSize) -> &str {
+        match size {
+            CoverSize::Original => &self.content_type,
+            _ => "image/jpeg",
+        }
+    }
+
+    /// Keys of all the cover's files
+    pub fn keys(&self) -> Vec<String> {
+        [CoverSize::Original, CoverSize::Medium, CoverSize::Thumbnail]
+            .into_iter()
+            .map(|size| self.key(size))
+            .collect()
+    }
+
+    /// URL the book's `cover_image_url` is set to
+    /// The version makes it change with each upload, so it can be cached for good.
+    pub fn url(&self) -> String {
+        format!("/api/books/{}/cover?v={}", self.book_id, self.version())
+    }
+}
+
+/// Whether a `cover_image_url` points at a cover uploaded to this server
+/// Such URLs name a book ID and need the owner's session, so they mean nothing in
+/// another account or on another server.
+pub fn is_uploaded_cover_url(url: &str) -> bool {
+    url.strip_prefix("/api/books/")
+        .and_then(|rest| rest.split_once('/'))
+        .is_some_and(|(book_id, rest)| {
+            !book_id.is_empty()
+                && book_id.bytes().all(|b| b.is_ascii_digit())
+                && (rest == "cover" || rest.starts_with("cover?"))
+        })
+}
+
+/// File name of a cover size, e.g. "original.png" or "thumbnail.jpg"
+pub fn cover_file_name(size: CoverSize, content_type: &str) -> String {
+    let extension = match (size, content_type) {
+        (CoverSize::Original, "image/png") => "png",
+        (CoverSize::Original, "image/webp") => "webp",
+        (CoverSize::Original, "image/gif") => "gif",
+        _ => "jpg",
+    };
+    format!("{}.{}", size.as_str(), extension)
+}
+
+/// An uploaded cover as returned by the API
+#[derive(Debug, Serialize)]
+pub struct CoverInfo {
+    pub book_id: i32,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub byte_size: i32,
+    pub url: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl From<&BookCover> for CoverInfo {
+    fn from(cover: &BookCover) -> Self {
+        CoverInfo {
+            book_id: cover.book_id,
+            content_type: cover.content_type.clone(),
+            width: cover.width,
+            height: cover.height,
+            byte_size: cover.byte_size,
+            url: cover.url(),
+            uploaded_at: cover.uploaded_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoverQuery {
+    /// thumbnail, medium or original (default)
+    #[serde(default)]
+    pub size: CoverSize,
+    /// Version from the cover URL; when current, the response may be cached for good
+    pub v: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_uploaded_cover_url() {
+        assert!(is_uploaded_cover_url("/api/books/12/cover?v=9f86d081884c7d65"));
+        assert!(is_uploaded_cover_url("/api/books/12/cover"));
+        assert!(!is_uploaded_cover_url("https://covers.openlibrary.org/b/id/12-L.jpg"));
+        assert!(!is_uploaded_cover_url("/api/books/12/covers"));
+        assert!(!is_uploaded_cover_url("/api/books//cover"));
+    }
+}
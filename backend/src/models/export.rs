@@ -0,0 +1,180 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Version of the library export format written by this build
+/// 1: profile, books and readings
+/// 2: adds connectors (metadata only)
+/// 3: adds book series and tags
+/// 4: adds highlights
+/// 5: adds book format, original publication year, owned copies and Goodreads ID
+/// 6: adds book identifiers
+pub const EXPORT_FORMAT_VERSION: u32 = 6;
+
+/// A user's library as a self-contained JSON document
+/// IDs are only meaningful within the document: readings and highlights point
+/// at books through `book_id`, and all get new IDs when imported.
+/// Sections added by later versions default to empty, so older documents still load.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LibraryExport {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub user: ExportedUser,
+    pub books: Vec<ExportedBook>,
+    pub readings: Vec<ExportedReading>,
+    #[serde(default)]
+    pub connectors: Vec<ExportedConnector>,
+    #[serde(default)]
+    pub highlights: Vec<ExportedHighlight>,
+    /// Identifiers besides the ones books derive from their ISBN, Goodreads ID and Calibre UUID
+    #[serde(default)]
+    pub identifiers: Vec<ExportedIdentifier>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedUser {
+    pub username: String,
+    pub email: String,
+    pub full_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExportedBook {
+    pub id: i32,
+    pub title: String,
+    pub author: Option<String>,
+    pub edition: Option<String>,
+    pub isbn: Option<String>,
+    pub publication_year: Option<i32>,
+    pub publisher: Option<String>,
+    pub pages: Option<i32>,
+    pub language: Option<String>,
+    pub description: Option<String>,
+    pub cover_image_url: Option<String>,
+    #[serde(default)]
+    pub series: Option<String>,
+    #[serde(default)]
+    pub series_index: Option<f64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub original_publication_year: Option<i32>,
+    #[serde(default)]
+    pub owned_copies: Option<i32>,
+    #[serde(default)]
+    pub goodreads_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExportedIdentifier {
+    pub book_id: i32,
+    pub id_type: String,
+    pub value: String,
+    /// "manual", "import" or "calibre"
+    pub source: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExportedReading {
+    pub id: i32,
+    pub book_id: i32,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub rating: Option<i32>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExportedHighlight {
+    pub id: i32,
+    pub book_id: i32,
+    pub reading_id: Option<i32>,
+    pub text: Option<String>,
+    pub note: Option<String>,
+    pub location_start: Option<i32>,
+    pub location_end: Option<i32>,
+    pub page: Option<i32>,
+    pub highlighted_at: Option<DateTime<Utc>>,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A connector without its API key, which never leaves the server
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExportedConnector {
+    pub provider: String,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What to do with books of the export that are already in the library
+/// (same ISBN, or same title and author)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the existing book; add only readings it does not have yet
+    #[default]
+    Skip,
+    /// Replace the existing book's details and matching readings with the export's
+    Overwrite,
+    /// Import every book as a new one
+    Duplicate,
+}
+
+impl ConflictPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Overwrite => "overwrite",
+            Self::Duplicate => "duplicate",
+        }
+    }
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "duplicate" => Ok(Self::Duplicate),
+            _ => Err(format!(
+                "Unknown conflict policy '{}' (expected skip, overwrite or duplicate)",
+                value
+            )),
+        }
+    }
+}
+
+/// What an import added to the library
+#[derive(Debug, Default, Serialize)]
+pub struct LibraryImportSummary {
+    pub books_imported: usize,
+    /// Existing books overwritten by the export
+    pub books_updated: usize,
+    /// Existing books left unchanged
+    pub books_skipped: usize,
+    pub readings_imported: usize,
+    pub readings_updated: usize,
+    /// Readings the library already had (same book and start date)
+    pub readings_skipped: usize,
+    pub highlights_imported: usize,
+    /// Highlights the library already had (same book, text and note)
+    pub highlights_skipped: usize,
+    /// Identifiers added to books; the ones books already had are not counted
+    pub identifiers_imported: usize,
+    pub connectors_imported: usize,
+    /// Providers whose connector must be re-created with a new API key
+    pub connectors_without_key: Vec<String>,
+    /// The profile's full name was taken from the export
+    pub profile_updated: bool,
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+/// Columns of `highlights`, in `Highlight` field order
+pub const HIGHLIGHT_COLUMNS: &str =
+    "id, user_id, book_id, reading_id, text, note, location_start, location_end, page, highlighted_at, source, created_at, updated_at";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Highlight {
+    pub id: i32,
+    pub user_id: i32,
+    pub book_id: i32,
+    pub reading_id: Option<i32>,
+    /// Highlighted passage; None for a note on its own
+    pub text: Option<String>,
+    pub note: Option<String>,
+    pub location_start: Option<i32>,
+    pub location_end: Option<i32>,
+    pub page: Option<i32>,
+    pub highlighted_at: Option<DateTime<Utc>>,
+    /// "manual" or "kindle"
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct HighlightWithBook {
+    pub id: i32,
+    pub user_id: i32,
+    pub book_id: i32,
+    pub reading_id: Option<i32>,
+    pub text: Option<String>,
+    pub note: Option<String>,
+    pub location_start: Option<i32>,
+    pub location_end: Option<i32>,
+    pub page: Option<i32>,
+    pub highlighted_at: Option<DateTime<Utc>>,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub book_title: String,
+    pub book_author: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateHighlight {
+    pub book_id: i32,
+
+    pub reading_id: Option<i32>,
+
+    #[validate(length(min = 1))]
+    pub text: Option<String>,
+
+    #[validate(length(min = 1))]
+    pub note: Option<String>,
+
+    #[validate(range(min = 0))]
+    pub location_start: Option<i32>,
+
+    #[validate(range(min = 0))]
+    pub location_end: Option<i32>,
+
+    #[validate(range(min = 1))]
+    pub page: Option<i32>,
+
+    pub highlighted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateHighlight {
+    pub reading_id: Option<i32>,
+
+    #[validate(length(min = 1))]
+    pub text: Option<String>,
+
+    #[validate(length(min = 1))]
+    pub note: Option<String>,
+
+    #[validate(range(min = 0))]
+    pub location_start: Option<i32>,
+
+    #[validate(range(min = 0))]
+    pub location_end: Option<i32>,
+
+    #[validate(range(min = 1))]
+    pub page: Option<i32>,
+
+    pub highlighted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HighlightQuery {
+    /// Case-insensitive search in the passage, the note and the book title
+    pub q: Option<String>,
+    pub book_id: Option<i32>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KindleImportQuery {
+    /// Create books for clippings that match no book of the library
+    #[serde(default)]
+    pub create_books: bool,
+}
+
+/// What a Kindle clippings import added to the library
+#[derive(Debug, Default, Serialize)]
+pub struct KindleImportSummary {
+    /// Highlights and notes in the file, after merging edited highlights
+    pub clippings_found: usize,
+    pub highlights_imported: usize,
+    /// Existing highlights replaced by a longer or edited version
+    pub highlights_updated: usize,
+    /// Highlights the library already had
+    pub highlights_skipped: usize,
+    pub books_created: usize,
+    /// Books of the file that match no book of the library
+    pub unmatched_books: Vec<UnmatchedBook>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnmatchedBook {
+    pub title: String,
+    pub author: Option<String>,
+    pub highlights: usize,
+}
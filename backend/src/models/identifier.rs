@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use panicless_mcp_lib::isbn::Isbn;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Columns of `book_identifiers`, in `BookIdentifier` field order
+pub const BOOK_IDENTIFIER_COLUMNS: &str = "id, book_id, id_type, value, source, created_at";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BookIdentifier {
+    pub id: i32,
+    pub book_id: i32,
+    /// One of the `IdentifierType` names, e.g. "isbn13"
+    pub id_type: String,
+    /// Normalized value
+    pub value: String,
+    /// "book" for identifiers derived from the book's isbn, goodreads_id and
+    /// calibre_uuid, otherwise "manual", "import" or "calibre"
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Kinds of external identifiers a book can carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentifierType {
+    Isbn10,
+    Isbn13,
+    /// Amazon Standard Identification Number
+    Asin,
+    Goodreads,
+    OpenlibraryWork,
+    OpenlibraryEdition,
+    CalibreUuid,
+    /// WorldCat number
+    Oclc,
+    /// Library of Congress Control Number
+    Lccn,
+}
+
+impl IdentifierType {
+    pub const ALL: [IdentifierType; 9] = [
+        Self::Isbn10,
+        Self::Isbn13,
+        Self::Asin,
+        Self::Goodreads,
+        Self::OpenlibraryWork,
+        Self::OpenlibraryEdition,
+        Self::CalibreUuid,
+        Self::Oclc,
+        Self::Lccn,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Isbn10 => "isbn10",
+            Self::Isbn13 => "isbn13",
+            Self::Asin => "asin",
+            Self::Goodreads => "goodreads",
+            Self::OpenlibraryWork => "openlibrary_work",
+            Self::OpenlibraryEdition => "openlibrary_edition",
+            Self::CalibreUuid => "calibre_uuid",
+            Self::Oclc => "oclc",
+            Self::Lccn => "lccn",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == value)
+    }
+
+    /// The canonical form of an identifier, or why it is not one of this type
+    /// Common spellings are accepted: hyphenated ISBNs, Goodreads and Open Library
+    /// URLs, "ocm"/"(OCoLC)" prefixed OCLC numbers and hyphenated LCCNs.
+    pub fn normalize(&self, value: &str) -> Result<String, String> {
+        let value = value.trim();
+        let invalid = || format!("'{}' is not a valid {} identifier", value, self.as_str());
+
+        let normalized = match self {
+            Self::Isbn10 | Self::Isbn13 => {
+                let cleaned: String = value.chars().filter(|c| !matches!(c, '-' | ' ')).collect::<String>().to_uppercase();
+                let length = if *self == Self::Isbn10 { 10 } else { 13 };
+                if cleaned.len() == length {
+                    Isbn::parse(&cleaned)?;
+                }
+                (cleaned.len() == length).then_some(cleaned)
+            }
+            Self::Asin => {
+                let cleaned = value.to_uppercase();
+                (cleaned.len() == 10 && cleaned.chars().all(|c| c.is_ascii_alphanumeric())).then_some(cleaned)
+            }
+            Self::Goodreads => {
+                // https://www.goodreads.com/book/show/234225.Dune -> 234225
+                let digits: String = last_segment(value).chars().take_while(char::is_ascii_digit).collect();
+                (!digits.is_empty() && digits.len() <= 20).then_some(digits)
+            }
+            Self::OpenlibraryWork | Self::OpenlibraryEdition => {
+                let suffix = if *self == Self::OpenlibraryWork { 'W' } else { 'M' };
+                let cleaned = last_segment(value).to_uppercase();
+                let valid = cleaned
+                    .strip_prefix("OL")
+                    .and_then(|rest| rest.strip_suffix(suffix))
+                    .is_some_and(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()));
+                valid.then_some(cleaned)
+            }
+            Self::CalibreUuid => {
+                let cleaned = value.to_lowercase();
+                let groups: Vec<&str> = cleaned.split('-').collect();
+                let valid = groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+                    && groups.iter().all(|g| g.chars().all(|c| c.is_ascii_hexdigit()));
+                valid.then_some(cleaned)
+            }
+            Self::Oclc => {
+                let lower = value.to_lowercase();
+                let lower = lower.strip_prefix("(ocolc)").unwrap_or(&lower).trim();
+                let number = ["ocm", "ocn", "on"]
+                    .iter()
+                    .find_map(|prefix| lower.strip_prefix(prefix))
+                    .unwrap_or(lower)
+                    .trim_start_matches('0');
+                (!number.is_empty() && number.len() <= 20 && number.chars().all(|c| c.is_ascii_digit()))
+                    .then(|| number.to_string())
+            }
+            Self::Lccn => normalize_lccn(value),
+        };
+
+        normalized.ok_or_else(invalid)
+    }
+
+    /// The type and value an identifier is stored as: normalized, with ISBN-10s
+    /// converted to the ISBN-13 books carry
+    pub fn canonicalize(&self, value: &str) -> Result<(IdentifierType, String), String> {
+        let value = self.normalize(value)?;
+        match self {
+            Self::Isbn10 => Ok((Self::Isbn13, Isbn::parse(&value)?.to_string())),
+            _ => Ok((*self, value)),
+        }
+    }
+}
+
+/// The ISBN-13 identifier an ISBN-10 or ISBN-13 stands for, if it is a valid one
+pub fn isbn_identifier(isbn: &str) -> Option<(IdentifierType, String)> {
+    Isbn::parse(isbn).ok().map(|isbn| (IdentifierType::Isbn13, isbn.to_string()))
+}
+
+/// The (type, value) pairs a searched identifier may stand for
+/// Without a type, every type the value is valid for.
+pub fn identifier_candidates(id_type: Option<IdentifierType>, value: &str) -> Result<Vec<(IdentifierType, String)>, String> {
+    if let Some(id_type) = id_type {
+        return Ok(vec![id_type.canonicalize(value)?]);
+    }
+
+    let mut candidates: Vec<_> = IdentifierType::ALL
+        .into_iter()
+        .filter_map(|id_type| id_type.canonicalize(value).ok())
+        .collect();
+    candidates.dedup();
+    if candidates.is_empty() {
+        return Err(format!("'{}' is not a valid identifier of any type", value.trim()));
+    }
+    Ok(candidates)
+}
+
+/// Last path segment of a URL or path, or the value itself
+fn last_segment(value: &str) -> &str {
+    value.trim_end_matches('/').rsplit('/').next().unwrap_or(value)
+}
+
+/// LCCN normalization as the Library of Congress defines it: no blanks, nothing
+/// after a slash, and a hyphenated serial zero-padded to six digits
+/// ("n 78-890351" -> "n78890351", "2001-1234" -> "2001001234")
+fn normalize_lccn(value: &str) -> Option<String> {
+    let cleaned: String = value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+    let cleaned = cleaned.split('/').next().unwrap_or_default();
+    let normalized = match cleaned.split_once('-') {
+        Some((prefix, serial)) if !serial.is_empty() && serial.len() <= 6 => format!("{}{:0>6}", prefix, serial),
+        Some(_) => return None,
+        None => cleaned.to_string(),
+    };
+
+    let letters = normalized.chars().take_while(char::is_ascii_lowercase).count();
+    let digits = &normalized[letters..];
+    let valid = letters <= 3 && matches!(digits.len(), 8 | 10) && digits.chars().all(|c| c.is_ascii_digit());
+    valid.then_some(normalized)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateIdentifier {
+    pub id_type: IdentifierType,
+    pub value: String,
+}
+
+/// GET /api/books/lookup?type=isbn13&value=978-0-441-01359-3
+/// Without a type, the value is matched against every type it is valid for.
+#[derive(Debug, Deserialize)]
+pub struct IdentifierLookupQuery {
+    #[serde(rename = "type")]
+    pub id_type: Option<IdentifierType>,
+    pub value: String,
+}
+
+/// GET /api/isbn/:isbn
+#[derive(Debug, Serialize)]
+pub struct IsbnInfo {
+    /// The form books store
+    pub isbn13: String,
+    /// None for 979 ISBNs
+    pub isbn10: Option<String>,
+    /// e.g. "978-0-441-01359-3"
+    pub hyphenated: String,
+}
+
+impl From<Isbn> for IsbnInfo {
+    fn from(isbn: Isbn) -> Self {
+        IsbnInfo {
+            isbn10: isbn.to_isbn10(),
+            hyphenated: isbn.hyphenated(),
+            isbn13: isbn.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_type_round_trip() {
+        for id_type in IdentifierType::ALL {
+            assert_eq!(IdentifierType::parse(id_type.as_str()), Some(id_type));
+        }
+        assert_eq!(IdentifierType::parse("isbn"), None);
+    }
+
+    #[test]
+    fn test_normalize_isbns_and_asin() {
+        assert_eq!(IdentifierType::Isbn13.normalize("978-0-441-01359-3"), Ok("9780441013593".to_string()));
+        assert_eq!(IdentifierType::Isbn10.normalize("0 804 42957 x"), Ok("080442957X".to_string()));
+        assert!(IdentifierType::Isbn10.normalize("9780441013593").is_err());
+        assert!(IdentifierType::Isbn13.normalize("97804410135X3").is_err());
+        assert_eq!(IdentifierType::Asin.normalize("b00b7nptr0"), Ok("B00B7NPTR0".to_string()));
+        assert!(IdentifierType::Asin.normalize("B00B7NPT").is_err());
+        assert!(IdentifierType::Isbn10.normalize("0441013598").unwrap_err().contains("check digit"));
+        assert_eq!(
+            IdentifierType::Isbn10.canonicalize("0-441-01359-7"),
+            Ok((IdentifierType::Isbn13, "9780441013593".to_string()))
+        );
+        assert_eq!(isbn_identifier("0-441-01359-7"), Some((IdentifierType::Isbn13, "9780441013593".to_string())));
+        assert_eq!(isbn_identifier("b1a2c3d4"), None);
+    }
+
+    #[test]
+    fn test_identifier_candidates() {
+        let candidates = identifier_candidates(None, "0441013597").unwrap();
+        assert_eq!(
+            candidates.iter().map(|(id_type, _)| *id_type).collect::<Vec<_>>(),
+            vec![
+                IdentifierType::Isbn13,
+                IdentifierType::Asin,
+                IdentifierType::Goodreads,
+                IdentifierType::Oclc,
+                IdentifierType::Lccn,
+            ]
+        );
+        assert_eq!(
+            identifier_candidates(Some(IdentifierType::Isbn13), "978-0-441-01359-3").unwrap(),
+            vec![(IdentifierType::Isbn13, "9780441013593".to_string())]
+        );
+        assert!(identifier_candidates(None, "not an id").is_err());
+    }
+
+    #[test]
+    fn test_normalize_site_identifiers() {
+        assert_eq!(
+            IdentifierType::Goodreads.normalize("https://www.goodreads.com/book/show/234225.Dune"),
+            Ok("234225".to_string())
+        );
+        assert_eq!(IdentifierType::Goodreads.normalize("44767458-dune"), Ok("44767458".to_string()));
+        assert!(IdentifierType::Goodreads.normalize("dune").is_err());
+        assert_eq!(
+            IdentifierType::OpenlibraryWork.normalize("https://openlibrary.org/works/ol893415w/"),
+            Ok("OL893415W".to_string())
+        );
+        assert!(IdentifierType::OpenlibraryWork.normalize("OL7353617M").is_err());
+        assert_eq!(IdentifierType::OpenlibraryEdition.normalize("/books/OL7353617M"), Ok("OL7353617M".to_string()));
+        assert_eq!(
+            IdentifierType::CalibreUuid.normalize("7C3A0E1D-93B0-4A4C-9D54-0D5F0F0E8B11"),
+            Ok("7c3a0e1d-93b0-4a4c-9d54-0d5f0f0e8b11".to_string())
+        );
+        assert!(IdentifierType::CalibreUuid.normalize("7c3a0e1d93b04a4c9d540d5f0f0e8b11").is_err());
+    }
+
+    #[test]
+    fn test_normalize_library_identifiers() {
+        assert_eq!(IdentifierType::Oclc.normalize("(OCoLC)ocm00123456"), Ok("123456".to_string()));
+        assert_eq!(IdentifierType::Oclc.normalize("ocm00123456"), Ok("123456".to_string()));
+        assert_eq!(IdentifierType::Oclc.normalize("(OCoLC)123456"), Ok("123456".to_string()));
+        assert!(IdentifierType::Oclc.normalize("abc").is_err());
+        assert_eq!(IdentifierType::Lccn.normalize("n 78-890351"), Ok("n78890351".to_string()));
+        assert_eq!(IdentifierType::Lccn.normalize("2001-1234"), Ok("2001001234".to_string()));
+        assert_eq!(IdentifierType::Lccn.normalize("85-2 /AC/r932"), Ok("85000002".to_string()));
+        assert!(IdentifierType::Lccn.normalize("12-3456789").is_err());
+    }
+}
@@ -1,8 +1,11 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use crate::models::identifier::IdentifierType;
+
 /// Represents a single row from the Goodreads CSV export
-#[derive(Debug, Deserialize)]
+/// Fields are in Goodreads' column order, which the CSV export relies on.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct GoodreadsRecord {
     #[serde(rename = "Book Id")]
     pub book_id: String,
@@ -86,16 +89,31 @@ pub struct TransformedBook {
     pub publication_year: Option<i32>,
     pub publisher: Option<String>,
     pub pages: Option<i32>,
+    pub language: Option<String>,
+    /// Binding or format of the edition
+    pub format: Option<String>,
+    pub original_publication_year: Option<i32>,
+    pub owned_copies: Option<i32>,
+    pub goodreads_id: Option<String>,
+    /// Identifiers besides `isbn` and `goodreads_id`, e.g. every ISBN the row lists
+    pub identifiers: Vec<(IdentifierType, String)>,
+    /// Shelves and tags besides the exclusive shelf
+    pub tags: Vec<String>,
     pub shelf: String,
     pub rating: Option<i32>,
     pub notes: Option<String>,
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
+    /// Times the book was finished, when the site counts them; the reading
+    /// above is the latest
+    pub read_count: Option<i32>,
 }
 
 /// Response for the import operation
 #[derive(Debug, Serialize)]
 pub struct ImportResponse {
+    /// Import job that recorded the changes, for reverting them
+    pub job_id: i32,
     pub summary: ImportSummary,
     pub successes: Vec<ImportSuccess>,
     pub errors: Vec<ImportError>,
@@ -109,6 +127,8 @@ pub struct ImportSummary {
     pub failed_imports: usize,
     pub books_created: usize,
     pub books_updated: usize,
+    /// Rows that matched a book with nothing new, or that the user chose to skip
+    pub books_skipped: usize,
     pub readings_created: usize,
 }
 
@@ -116,7 +136,8 @@ pub struct ImportSummary {
 #[derive(Debug, Serialize)]
 pub struct ImportSuccess {
     pub row_number: usize,
-    pub book_id: i32,
+    /// None for a skipped row that matched no book
+    pub book_id: Option<i32>,
     pub book_title: String,
     pub operation: String,
 }
@@ -133,13 +154,127 @@ pub struct ImportError {
 #[derive(Debug)]
 pub enum BookImportResult {
     Success {
-        book_id: i32,
+        book_id: Option<i32>,
         book_title: String,
         operation: String,
-        reading_created: bool,
+        readings_created: usize,
     },
     Failure {
         book_title: Option<String>,
         error: String,
     },
 }
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportQuery {
+    /// Parse and match without writing; returns an `ImportPreview`
+    #[serde(default)]
+    pub dry_run: bool,
+    /// How rows matching a book of the library change it
+    #[serde(default)]
+    pub merge: MergePolicy,
+}
+
+/// How an import treats the fields of books already in the library
+/// Imported empty values never clear a field, and shelves are added to the tags
+/// unless the policy is `KeepLocal`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    /// Only fill in fields the book does not have yet
+    #[default]
+    FillEmpty,
+    /// Replace fields with the imported values
+    Overwrite,
+    /// Leave the book as it is; only readings are added
+    KeepLocal,
+}
+
+impl std::str::FromStr for MergePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "fill_empty" => Ok(Self::FillEmpty),
+            "overwrite" => Ok(Self::Overwrite),
+            "keep_local" => Ok(Self::KeepLocal),
+            other => Err(format!(
+                "Unknown merge policy '{}' (expected fill_empty, overwrite or keep_local)",
+                other
+            )),
+        }
+    }
+}
+
+/// What an import does with one row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Skip,
+}
+
+/// How a row was matched to a book of the library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchedBy {
+    Isbn,
+    /// Another identifier in common, such as the Goodreads ID
+    Identifier,
+    TitleAuthor,
+    /// Chosen by the user in a decision
+    User,
+}
+
+/// The user's choice for one row, overriding the proposed action
+/// `{"row_number": 5, "action": "update", "book_id": 42}` merges row 5 into book 42.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RowDecision {
+    pub row_number: usize,
+    pub action: ImportAction,
+    /// Book to merge into; required for "update"
+    pub book_id: Option<i32>,
+}
+
+/// A book field the import would change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub current: serde_json::Value,
+    pub proposed: serde_json::Value,
+}
+
+/// Proposed action for one row of a dry run
+#[derive(Debug, Serialize)]
+pub struct PreviewRow {
+    pub row_number: usize,
+    pub book_title: String,
+    pub action: ImportAction,
+    /// Book the row would update, if any
+    pub book_id: Option<i32>,
+    pub matched_by: Option<MatchedBy>,
+    /// Fields an update would change; empty when creating
+    pub changes: Vec<FieldChange>,
+    pub creates_reading: bool,
+    /// Finished readings added to match the site's read count
+    pub extra_readings: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewSummary {
+    pub total_rows: usize,
+    pub to_create: usize,
+    pub to_update: usize,
+    pub to_skip: usize,
+    pub failed_rows: usize,
+    pub readings_to_create: usize,
+}
+
+/// Response of an import with `dry_run=true`; nothing has been written
+#[derive(Debug, Serialize)]
+pub struct ImportPreview {
+    pub summary: PreviewSummary,
+    pub rows: Vec<PreviewRow>,
+    pub errors: Vec<ImportError>,
+}
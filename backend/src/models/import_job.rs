@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Columns of `import_jobs` without the stored results, in `ImportJob` field order
+pub const IMPORT_JOB_COLUMNS: &str =
+    "id, user_id, source, file_name, status, total_rows, processed_rows, books_created, books_updated, \
+     books_skipped, readings_created, failed_rows, cancel_requested, error, started_at, finished_at, reverted_at, \
+     created_at, updated_at";
+
+/// A background import and its progress
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ImportJob {
+    pub id: i32,
+    pub user_id: i32,
+    /// "goodreads", "storygraph" or "librarything"
+    pub source: String,
+    pub file_name: Option<String>,
+    /// "queued", "running", "completed", "failed", "cancelled" or "reverted"
+    pub status: String,
+    pub total_rows: i32,
+    pub processed_rows: i32,
+    pub books_created: i32,
+    pub books_updated: i32,
+    pub books_skipped: i32,
+    pub readings_created: i32,
+    pub failed_rows: i32,
+    pub cancel_requested: bool,
+    /// Why the whole job failed
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub reverted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ImportJob {
+    /// Only queued and running jobs still change
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.status.as_str(), "queued" | "running")
+    }
+}
+
+/// A job with its per-row results (an `ImportResponse`), once it has finished
+#[derive(Debug, Serialize)]
+pub struct ImportJobDetails {
+    #[serde(flatten)]
+    pub job: ImportJob,
+    pub result: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportJobQuery {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Book fields an import writes, as they were before it
+/// Fields added after the first change records default to empty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct BookSnapshot {
+    pub title: String,
+    pub author: Option<String>,
+    pub isbn: Option<String>,
+    pub publication_year: Option<i32>,
+    #[serde(default)]
+    pub original_publication_year: Option<i32>,
+    pub publisher: Option<String>,
+    pub pages: Option<i32>,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub owned_copies: Option<i32>,
+    #[serde(default)]
+    pub goodreads_id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Columns of `books` selected into `BookSnapshot`
+pub const BOOK_SNAPSHOT_COLUMNS: &str = "title, author, isbn, publication_year, original_publication_year, publisher, \
+     pages, language, format, owned_copies, goodreads_id, tags";
+
+/// What reverting an import undid
+#[derive(Debug, Default, Serialize)]
+pub struct RevertSummary {
+    pub books_deleted: usize,
+    pub books_restored: usize,
+    pub readings_deleted: usize,
+    /// Rows left alone because they changed after the import
+    pub conflicts: Vec<RevertConflict>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevertConflict {
+    /// "book" or "reading"
+    pub entity: &'static str,
+    pub entity_id: i32,
+    /// Title of the book, or of the reading's book
+    pub title: String,
+    pub reason: String,
+}
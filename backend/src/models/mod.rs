@@ -1,10 +1,24 @@
 pub mod user;
 pub mod book;
 pub mod reading;
+pub mod highlight;
 pub mod import;
+pub mod import_job;
+pub mod identifier;
+pub mod cover;
+pub mod scan;
 pub mod connector;
+pub mod session;
+pub mod two_factor;
+pub mod admin;
+pub mod oauth_client;
+pub mod export;
+pub mod chat;
 
 pub use user::User;
 pub use book::Book;
 pub use reading::Reading;
+pub use highlight::Highlight;
 pub use connector::Connector;
+pub use session::Session;
+pub use oauth_client::OAuthClient;
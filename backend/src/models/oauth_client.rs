@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// An OAuth2 client registered in the database
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OAuthClient {
+    pub id: i32,
+    pub client_id: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: String,
+    pub redirect_uris: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl OAuthClient {
+    /// Whether the client may redirect to this URI (no registered URIs allows any)
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.is_empty() || self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+}
@@ -0,0 +1,39 @@
+use panicless_mcp_lib::enrichment::BookEnrichment;
+use serde::{Deserialize, Serialize};
+
+use super::{identifier::IsbnInfo, Book};
+
+/// Tag given to books created from a scan, until the user has looked them over
+pub const DRAFT_TAG: &str = "draft";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ScanQuery {
+    /// Create a draft book for each ISBN not yet in the library
+    #[serde(default)]
+    pub create: bool,
+}
+
+/// What was read from one uploaded photo
+#[derive(Debug, Serialize)]
+pub struct ScanResult {
+    /// Name the photo was uploaded with
+    pub file_name: Option<String>,
+    /// Every EAN-13 barcode read, ISBN or not
+    pub barcodes: Vec<String>,
+    pub isbns: Vec<ScannedIsbn>,
+    /// Why nothing could be read, when nothing was
+    pub error: Option<String>,
+}
+
+/// An ISBN read from a photo and the books that have it
+#[derive(Debug, Serialize)]
+pub struct ScannedIsbn {
+    #[serde(flatten)]
+    pub isbn: IsbnInfo,
+    /// Books of the library with this ISBN
+    pub books: Vec<Book>,
+    /// The draft book created for it, with `?create=true` when no book had it
+    pub draft: Option<Book>,
+    /// How the draft was filled from the Open Library data
+    pub enrichment: Option<BookEnrichment>,
+}
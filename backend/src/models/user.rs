@@ -11,6 +11,9 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub full_name: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub role: String,
+    pub disabled_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -28,6 +31,9 @@ pub struct CreateUser {
 
     #[validate(length(max = 100))]
     pub full_name: Option<String>,
+
+    /// Required when the registration policy is invite-only
+    pub invite_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -51,6 +57,8 @@ pub struct UserResponse {
     pub username: String,
     pub email: String,
     pub full_name: Option<String>,
+    pub email_verified: bool,
+    pub role: String,
     pub created_at: DateTime<Utc>,
 }
 
@@ -61,6 +69,8 @@ impl From<User> for UserResponse {
             username: user.username,
             email: user.email,
             full_name: user.full_name,
+            email_verified: user.email_verified_at.is_some(),
+            role: user.role,
             created_at: user.created_at,
         }
     }
@@ -74,3 +84,43 @@ pub struct UpdateUser {
     #[validate(length(max = 100))]
     pub full_name: Option<String>,
 }
+
+impl User {
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
+}
+
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ADMIN: &str = "admin";
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use super::{gcra, register_failure, Decision, FailureState, LockoutPolicy, Policy, RateLimitStore};
+use crate::errors::AppResult;
+
+/// Prune stale entries every this many checks
+const PRUNE_EVERY: u64 = 1024;
+/// Failure counters idle for longer than this are dropped when pruning
+const FAILURE_RETENTION_MS: i64 = 24 * 3_600_000;
+
+/// In-process store; limits are per instance
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, i64>>,
+    failures: Mutex<HashMap<String, FailureState>>,
+    checks: AtomicU64,
+}
+
+impl MemoryStore {
+    fn prune(&self, now_ms: i64) {
+        self.buckets.lock().unwrap().retain(|_, tat| *tat > now_ms);
+        self.failures
+            .lock()
+            .unwrap()
+            .retain(|_, state| now_ms - state.last_failure_ms < FAILURE_RETENTION_MS);
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn check(&self, key: &str, policy: &Policy, now_ms: i64) -> AppResult<Decision> {
+        if self.checks.fetch_add(1, Ordering::Relaxed) % PRUNE_EVERY == PRUNE_EVERY - 1 {
+            self.prune(now_ms);
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let (decision, new_tat) = gcra(buckets.get(key).copied(), now_ms, policy);
+        if let Some(tat) = new_tat {
+            buckets.insert(key.to_string(), tat);
+        }
+
+        Ok(decision)
+    }
+
+    async fn failure_state(&self, key: &str) -> AppResult<Option<FailureState>> {
+        Ok(self.failures.lock().unwrap().get(key).copied())
+    }
+
+    async fn record_failure(&self, key: &str, policy: &LockoutPolicy, now_ms: i64) -> AppResult<FailureState> {
+        let mut failures = self.failures.lock().unwrap();
+        let state = register_failure(failures.get(key).copied(), now_ms, policy);
+        failures.insert(key.to_string(), state);
+        Ok(state)
+    }
+
+    async fn clear_failures(&self, key: &str) -> AppResult<()> {
+        self.failures.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_buckets_are_independent_per_key() {
+        let store = MemoryStore::default();
+        let policy = Policy::per_minute(1);
+
+        assert!(store.check("login:ip:a", &policy, 0).await.unwrap().allowed);
+        assert!(!store.check("login:ip:a", &policy, 0).await.unwrap().allowed);
+        assert!(store.check("login:ip:b", &policy, 0).await.unwrap().allowed);
+    }
+
+    #[tokio::test]
+    async fn test_failures_cleared() {
+        let store = MemoryStore::default();
+        let policy = LockoutPolicy { threshold: 1, base_ms: 1_000, max_ms: 10_000 };
+
+        let state = store.record_failure("user:alice", &policy, 0).await.unwrap();
+        assert_eq!(state.locked_until_ms, Some(1_000));
+        store.clear_failures("user:alice").await.unwrap();
+        assert_eq!(store.failure_state("user:alice").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,381 @@
+pub mod memory;
+pub mod postgres;
+
+pub use memory::MemoryStore;
+pub use postgres::PostgresStore;
+
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::{
+    config::Config,
+    db::DbPool,
+    errors::{AppError, AppResult},
+};
+
+/// Allows `limit` requests per `period_ms`, all of which may arrive as a burst
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub limit: u32,
+    pub period_ms: i64,
+}
+
+impl Policy {
+    pub fn per_minute(limit: u32) -> Self {
+        Self { limit, period_ms: 60_000 }
+    }
+
+    pub fn per_hour(limit: u32) -> Self {
+        Self { limit, period_ms: 3_600_000 }
+    }
+
+    /// Time one request "costs"
+    fn emission_interval(&self) -> i64 {
+        (self.period_ms / self.limit.max(1) as i64).max(1)
+    }
+}
+
+/// Outcome of a rate limit check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub retry_after_ms: i64,
+}
+
+/// Generic cell rate algorithm (a token bucket keeping a single timestamp)
+/// Takes the stored theoretical arrival time and returns the decision with the
+/// new value to store (None when the request is rejected and nothing changes).
+/// A limit of 0 disables the policy.
+pub fn gcra(stored_tat: Option<i64>, now_ms: i64, policy: &Policy) -> (Decision, Option<i64>) {
+    if policy.limit == 0 {
+        return (Decision { allowed: true, retry_after_ms: 0 }, None);
+    }
+
+    let interval = policy.emission_interval();
+    let tolerance = policy.period_ms - interval;
+    let tat = stored_tat.map_or(now_ms, |t| t.max(now_ms));
+
+    if tat - now_ms > tolerance {
+        let retry_after_ms = tat - now_ms - tolerance;
+        (Decision { allowed: false, retry_after_ms }, None)
+    } else {
+        (Decision { allowed: true, retry_after_ms: 0 }, Some(tat + interval))
+    }
+}
+
+/// Progressive lockout after repeated failures (e.g. wrong passwords)
+/// The first lock lasts `base_ms` and doubles with every further failure, up to `max_ms`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    pub threshold: u32,
+    pub base_ms: i64,
+    pub max_ms: i64,
+}
+
+/// Consecutive failures recorded for a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailureState {
+    pub count: u32,
+    pub last_failure_ms: i64,
+    pub locked_until_ms: Option<i64>,
+}
+
+/// Records one more failure
+/// Failures older than the maximum lock duration are forgotten.
+/// A threshold of 0 disables locking.
+pub fn register_failure(previous: Option<FailureState>, now_ms: i64, policy: &LockoutPolicy) -> FailureState {
+    let count = match previous {
+        Some(state) if now_ms - state.last_failure_ms < policy.max_ms => state.count.saturating_add(1),
+        _ => 1,
+    };
+
+    let locked_until_ms = (policy.threshold > 0 && count >= policy.threshold).then(|| {
+        let doublings = (count - policy.threshold).min(32);
+        let duration = policy.base_ms.saturating_mul(1i64 << doublings).min(policy.max_ms);
+        now_ms + duration
+    });
+
+    FailureState {
+        count,
+        last_failure_ms: now_ms,
+        locked_until_ms,
+    }
+}
+
+/// Remaining lock time, if the key is locked
+pub fn lock_remaining(state: Option<FailureState>, now_ms: i64) -> Option<i64> {
+    state
+        .and_then(|s| s.locked_until_ms)
+        .map(|until| until - now_ms)
+        .filter(|remaining| *remaining > 0)
+}
+
+/// Storage for buckets and failure counters
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Take one request from the bucket at `key`
+    async fn check(&self, key: &str, policy: &Policy, now_ms: i64) -> AppResult<Decision>;
+
+    async fn failure_state(&self, key: &str) -> AppResult<Option<FailureState>>;
+
+    async fn record_failure(&self, key: &str, policy: &LockoutPolicy, now_ms: i64) -> AppResult<FailureState>;
+
+    async fn clear_failures(&self, key: &str) -> AppResult<()>;
+}
+
+/// Rate limit policies per route group
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitPolicies {
+    /// Login, refresh, 2FA and password reset endpoints, per IP
+    pub auth: Policy,
+    /// OAuth token endpoint, per IP
+    pub oauth: Policy,
+    /// Authenticated API, per user
+    pub api: Policy,
+    /// Goodreads import, per user
+    pub import: Policy,
+}
+
+/// Shared rate limiter handle stored in the application state
+/// Store errors never fail a request: they are logged and the request goes on
+/// unlimited, so an outage of the Postgres backend does not stop every login.
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    enabled: bool,
+    pub policies: RateLimitPolicies,
+    pub lockout: LockoutPolicy,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn RateLimitStore>, enabled: bool, policies: RateLimitPolicies, lockout: LockoutPolicy) -> Self {
+        Self {
+            store,
+            enabled,
+            policies,
+            lockout,
+        }
+    }
+
+    /// Build the limiter selected by RATE_LIMIT_BACKEND
+    pub fn from_config(config: &Config, pool: &DbPool) -> Self {
+        let store: Arc<dyn RateLimitStore> = match config.rate_limit_backend.as_str() {
+            "postgres" => Arc::new(PostgresStore::new(pool.clone())),
+            _ => Arc::new(MemoryStore::default()),
+        };
+
+        let policies = RateLimitPolicies {
+            auth: Policy::per_minute(config.rate_limit_auth_per_minute),
+            oauth: Policy::per_minute(config.rate_limit_oauth_per_minute),
+            api: Policy::per_minute(config.rate_limit_api_per_minute),
+            import: Policy::per_hour(config.rate_limit_import_per_hour),
+        };
+
+        let lockout = LockoutPolicy {
+            threshold: config.login_lockout_threshold,
+            base_ms: config.login_lockout_base_seconds * 1000,
+            max_ms: config.login_lockout_max_seconds * 1000,
+        };
+
+        Self::new(store, config.rate_limit_enabled, policies, lockout)
+    }
+
+    /// Take one request from the bucket, failing with RateLimited when empty
+    pub async fn check(&self, key: &str, policy: &Policy) -> AppResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        match self.store.check(key, policy, now_ms()).await {
+            Ok(decision) if !decision.allowed => {
+                tracing::warn!("Rate limit exceeded for {}", key);
+                Err(AppError::RateLimited {
+                    message: "Rate limit exceeded, please slow down".to_string(),
+                    retry_after: retry_after_secs(decision.retry_after_ms),
+                })
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::error!("Rate limit check failed for {}: {}", key, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Fail with RateLimited while the key is locked out
+    pub async fn ensure_not_locked(&self, key: &str) -> AppResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let state = match self.store.failure_state(key).await {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::error!("Lockout check failed for {}: {}", key, e);
+                return Ok(());
+            }
+        };
+        if let Some(remaining) = lock_remaining(state, now_ms()) {
+            return Err(AppError::RateLimited {
+                message: "Too many failed attempts, account temporarily locked".to_string(),
+                retry_after: retry_after_secs(remaining),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Count a failed attempt, possibly locking the key
+    pub async fn record_failure(&self, key: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        let state = match self.store.record_failure(key, &self.lockout, now_ms()).await {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::error!("Recording a failed attempt failed for {}: {}", key, e);
+                return;
+            }
+        };
+        if let Some(until) = state.locked_until_ms {
+            tracing::warn!(
+                "Locking {} for {}s after {} failed attempts",
+                key,
+                retry_after_secs(until - state.last_failure_ms),
+                state.count
+            );
+        }
+    }
+
+    /// Forget failed attempts after a success
+    pub async fn clear_failures(&self, key: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        if let Err(e) = self.store.clear_failures(key).await {
+            tracing::error!("Clearing failed attempts failed for {}: {}", key, e);
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// Retry-After is in whole seconds, rounded up
+fn retry_after_secs(ms: i64) -> u64 {
+    ((ms.max(0) + 999) / 1000).max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcra_allows_burst_then_limits() {
+        let policy = Policy::per_minute(3);
+        let mut tat = None;
+
+        for _ in 0..3 {
+            let (decision, new_tat) = gcra(tat, 0, &policy);
+            assert!(decision.allowed);
+            tat = new_tat;
+        }
+
+        let (decision, new_tat) = gcra(tat, 0, &policy);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_ms, 20_000);
+        assert_eq!(new_tat, None);
+
+        // One request is replenished every 20 seconds
+        let (decision, _) = gcra(tat, 20_000, &policy);
+        assert!(decision.allowed);
+    }
+
+    #[test]
+    fn test_gcra_zero_limit_disables_policy() {
+        let (decision, new_tat) = gcra(Some(i64::MAX / 2), 0, &Policy::per_minute(0));
+        assert!(decision.allowed);
+        assert_eq!(new_tat, None);
+    }
+
+    #[test]
+    fn test_progressive_lockout() {
+        let policy = LockoutPolicy { threshold: 3, base_ms: 60_000, max_ms: 600_000 };
+
+        let first = register_failure(None, 0, &policy);
+        let second = register_failure(Some(first), 1_000, &policy);
+        assert_eq!(second.locked_until_ms, None);
+
+        let third = register_failure(Some(second), 2_000, &policy);
+        assert_eq!(third.locked_until_ms, Some(62_000));
+        assert_eq!(lock_remaining(Some(third), 2_000), Some(60_000));
+        assert_eq!(lock_remaining(Some(third), 62_000), None);
+
+        let fourth = register_failure(Some(third), 62_000, &policy);
+        assert_eq!(fourth.locked_until_ms, Some(62_000 + 120_000));
+
+        // Doubling is capped at the maximum
+        let mut state = fourth;
+        for _ in 0..10 {
+            state = register_failure(Some(state), state.last_failure_ms + 1, &policy);
+        }
+        assert_eq!(state.locked_until_ms, Some(state.last_failure_ms + 600_000));
+
+        // Failures are forgotten after a quiet period
+        let fresh = register_failure(Some(state), state.last_failure_ms + 600_000, &policy);
+        assert_eq!(fresh.count, 1);
+        assert_eq!(fresh.locked_until_ms, None);
+    }
+
+    #[test]
+    fn test_retry_after_rounds_up() {
+        assert_eq!(retry_after_secs(1), 1);
+        assert_eq!(retry_after_secs(1_000), 1);
+        assert_eq!(retry_after_secs(1_001), 2);
+        assert_eq!(retry_after_secs(-5), 1);
+    }
+
+    /// A store whose backend is down
+    struct UnavailableStore;
+
+    #[async_trait::async_trait]
+    impl RateLimitStore for UnavailableStore {
+        async fn check(&self, _: &str, _: &Policy, _: i64) -> AppResult<Decision> {
+            Err(AppError::Internal("store unavailable".to_string()))
+        }
+
+        async fn failure_state(&self, _: &str) -> AppResult<Option<FailureState>> {
+            Err(AppError::Internal("store unavailable".to_string()))
+        }
+
+        async fn record_failure(&self, _: &str, _: &LockoutPolicy, _: i64) -> AppResult<FailureState> {
+            Err(AppError::Internal("store unavailable".to_string()))
+        }
+
+        async fn clear_failures(&self, _: &str) -> AppResult<()> {
+            Err(AppError::Internal("store unavailable".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_errors_let_requests_through() {
+        let policy = Policy::per_minute(1);
+        let limiter = RateLimiter::new(
+            Arc::new(UnavailableStore),
+            true,
+            RateLimitPolicies { auth: policy, oauth: policy, api: policy, import: policy },
+            LockoutPolicy { threshold: 1, base_ms: 60_000, max_ms: 600_000 },
+        );
+
+        for _ in 0..3 {
+            limiter.check("ip:127.0.0.1", &policy).await.unwrap();
+            limiter.ensure_not_locked("login:user:alice").await.unwrap();
+            limiter.record_failure("login:user:alice").await;
+        }
+        limiter.clear_failures("login:user:alice").await;
+    }
+}
@@ -0,0 +1,117 @@
+use sqlx::Row;
+
+use super::{gcra, register_failure, Decision, FailureState, LockoutPolicy, Policy, RateLimitStore};
+use crate::{db::DbPool, errors::AppResult};
+
+/// Shared store for multi-instance deployments
+/// Rows are locked with SELECT ... FOR UPDATE so concurrent instances see
+/// a consistent bucket
+pub struct PostgresStore {
+    pool: DbPool,
+}
+
+impl PostgresStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for PostgresStore {
+    async fn check(&self, key: &str, policy: &Policy, now_ms: i64) -> AppResult<Decision> {
+        // Roughly one check in a thousand clears out drained buckets
+        if rand::random::<u16>().is_multiple_of(1000) {
+            sqlx::query("DELETE FROM rate_limit_buckets WHERE tat_ms < $1")
+                .bind(now_ms)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        // A bucket whose TAT is "now" is equivalent to a full one
+        sqlx::query("INSERT INTO rate_limit_buckets (key, tat_ms) VALUES ($1, $2) ON CONFLICT (key) DO NOTHING")
+            .bind(key)
+            .bind(now_ms)
+            .execute(&mut *tx)
+            .await?;
+
+        let stored_tat = sqlx::query_scalar::<_, i64>("SELECT tat_ms FROM rate_limit_buckets WHERE key = $1 FOR UPDATE")
+            .bind(key)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let (decision, new_tat) = gcra(Some(stored_tat), now_ms, policy);
+        if let Some(tat) = new_tat {
+            sqlx::query("UPDATE rate_limit_buckets SET tat_ms = $1 WHERE key = $2")
+                .bind(tat)
+                .bind(key)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(decision)
+    }
+
+    async fn failure_state(&self, key: &str) -> AppResult<Option<FailureState>> {
+        let row = sqlx::query(
+            "SELECT failure_count, last_failure_ms, locked_until_ms FROM login_failures WHERE key = $1"
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| FailureState {
+            count: r.get::<i32, _>("failure_count") as u32,
+            last_failure_ms: r.get("last_failure_ms"),
+            locked_until_ms: r.get("locked_until_ms"),
+        }))
+    }
+
+    async fn record_failure(&self, key: &str, policy: &LockoutPolicy, now_ms: i64) -> AppResult<FailureState> {
+        let mut tx = self.pool.begin().await?;
+
+        let previous = sqlx::query(
+            "SELECT failure_count, last_failure_ms, locked_until_ms FROM login_failures WHERE key = $1 FOR UPDATE"
+        )
+        .bind(key)
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|r| FailureState {
+            count: r.get::<i32, _>("failure_count") as u32,
+            last_failure_ms: r.get("last_failure_ms"),
+            locked_until_ms: r.get("locked_until_ms"),
+        });
+
+        let state = register_failure(previous, now_ms, policy);
+
+        sqlx::query(
+            "INSERT INTO login_failures (key, failure_count, last_failure_ms, locked_until_ms)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (key) DO UPDATE
+             SET failure_count = EXCLUDED.failure_count,
+                 last_failure_ms = EXCLUDED.last_failure_ms,
+                 locked_until_ms = EXCLUDED.locked_until_ms"
+        )
+        .bind(key)
+        .bind(state.count as i32)
+        .bind(state.last_failure_ms)
+        .bind(state.locked_until_ms)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(state)
+    }
+
+    async fn clear_failures(&self, key: &str) -> AppResult<()> {
+        sqlx::query("DELETE FROM login_failures WHERE key = $1")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,9 +1,9 @@
 use axum::{
-    extract::FromRef,
+    extract::{DefaultBodyLimit, FromRef},
     middleware,
     response::IntoResponse,
     routing::{delete, get, patch, post, put},
-    Router,
+    Extension, Router,
     http::StatusCode,
 };
 use tower_http::{
@@ -15,15 +15,25 @@ use tracing::Level;
 
 use crate::{
     config::Config,
+    covers::SharedCoverStore,
     db::DbPool,
     handlers,
-    middleware::auth::auth_middleware,
+    mail::SharedMailer,
+    middleware::{
+        auth::auth_middleware,
+        rate_limit::{rate_limit_middleware, RateLimitLayer, RouteGroup},
+        TrustedProxies,
+    },
+    rate_limit::RateLimiter,
 };
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: DbPool,
     pub config: Config,
+    pub mailer: SharedMailer,
+    pub covers: SharedCoverStore,
+    pub rate_limiter: RateLimiter,
 }
 
 impl FromRef<AppState> for DbPool {
@@ -38,44 +48,145 @@ impl FromRef<AppState> for Config {
     }
 }
 
-pub fn create_router(pool: DbPool, config: Config) -> Router {
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.rate_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedMailer {
+    fn from_ref(state: &AppState) -> Self {
+        state.mailer.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedCoverStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.covers.clone()
+    }
+}
+
+pub fn create_router(pool: DbPool, config: Config, mailer: SharedMailer, covers: SharedCoverStore) -> Router {
     let state = AppState {
+        rate_limiter: RateLimiter::from_config(&config, &pool),
         pool,
         config: config.clone(),
+        mailer,
+        covers,
     };
 
     // Configure CORS
     let cors = CorsLayer::permissive(); // TODO: Restrict in production using config.cors_allowed_origins
 
+    let rate_limit = |group: RouteGroup| {
+        middleware::from_fn_with_state(
+            RateLimitLayer { limiter: state.rate_limiter.clone(), group },
+            rate_limit_middleware,
+        )
+    };
+
     // Public routes (no authentication required)
-    let public_routes = Router::new()
+    // Credential-handling endpoints are rate limited per client IP
+    let auth_routes = Router::new()
         .route("/api/auth/register", post(handlers::register))
         .route("/api/auth/login", post(handlers::login))
         .route("/api/auth/refresh", post(handlers::refresh))
-        // OAuth2 token endpoint (no auth required, uses client credentials)
+        .route("/api/auth/logout", post(handlers::logout))
+        .route("/api/auth/password/forgot", post(handlers::forgot_password))
+        .route("/api/auth/password/reset", post(handlers::reset_password))
+        .route("/api/auth/verify-email", post(handlers::verify_email))
+        .route("/api/auth/2fa/verify", post(handlers::verify_two_factor))
+        .route("/api/auth/registration", get(handlers::get_registration_settings))
+        .layer(rate_limit(RouteGroup::Auth));
+
+    // OAuth2 token endpoint (no auth required, uses client credentials)
+    let oauth_token_routes = Router::new()
         .route("/oauth/token", post(handlers::token))
+        .layer(rate_limit(RouteGroup::OAuth));
+
+    let public_routes = Router::new()
+        .merge(auth_routes)
+        .merge(oauth_token_routes)
         // OAuth2 Discovery endpoints (RFC 8414, RFC 8707)
         .route("/.well-known/oauth-authorization-server", get(handlers::authorization_server_metadata))
-        .route("/.well-known/oauth-protected-resource", get(handlers::protected_resource_metadata));
+        .route("/.well-known/oauth-protected-resource", get(handlers::protected_resource_metadata))
+        // OpenID Connect Discovery and signing keys
+        .route("/.well-known/openid-configuration", get(handlers::openid_configuration))
+        .route("/oauth/jwks", get(handlers::jwks));
+
+    // Expensive endpoints with their own, stricter per-user limit
+    let import_routes = Router::new()
+        .route("/api/import/goodreads/csv", post(handlers::import_goodreads_csv))
+        .route("/api/import/storygraph/csv", post(handlers::import_storygraph_csv))
+        .route("/api/import/librarything", post(handlers::import_librarything))
+        .route(
+            "/api/import/calibre",
+            post(handlers::import_calibre).layer(DefaultBodyLimit::max(handlers::import::MAX_CALIBRE_SIZE)),
+        )
+        .route("/api/import/calibre/library", post(handlers::import_calibre_library))
+        .route("/api/import/kindle", post(handlers::import_kindle_clippings))
+        .route(
+            "/api/import/jobs",
+            post(handlers::create_import_job)
+                .layer(DefaultBodyLimit::max(handlers::import_jobs::MAX_JOB_FILE_SIZE)),
+        )
+        .route(
+            "/api/import/panicless",
+            post(handlers::import_panicless_archive)
+                .layer(DefaultBodyLimit::max(handlers::import::MAX_ARCHIVE_SIZE)),
+        )
+        .route("/api/export", get(handlers::export_account))
+        .route("/api/export/goodreads.csv", get(handlers::export_goodreads_csv))
+        .layer(rate_limit(RouteGroup::Import));
 
     // Protected routes (authentication required)
     let protected_routes = Router::new()
         // Auth
         .route("/api/auth/me", get(handlers::get_current_user))
+        .route("/api/auth/sessions", get(handlers::list_sessions))
+        .route("/api/auth/sessions", delete(handlers::revoke_other_sessions))
+        .route("/api/auth/sessions/:id", delete(handlers::revoke_session))
+        .route("/api/auth/verify-email/resend", post(handlers::resend_verification))
+        .route("/api/auth/2fa", get(handlers::get_two_factor_status))
+        .route("/api/auth/2fa/setup", post(handlers::setup_two_factor))
+        .route("/api/auth/2fa/enable", post(handlers::enable_two_factor))
+        .route("/api/auth/2fa/disable", post(handlers::disable_two_factor))
+        .route("/api/auth/2fa/recovery-codes", post(handlers::regenerate_recovery_codes))
         // OAuth2 authorize endpoint (requires authentication)
         .route("/oauth/authorize", post(handlers::authorize))
+        // OpenID Connect UserInfo (accepts OAuth access tokens)
+        .route("/oauth/userinfo", get(handlers::userinfo).post(handlers::userinfo))
         // Users
         .route("/api/users/:id", get(handlers::get_user))
         .route("/api/users/:id", put(handlers::update_user))
         .route("/api/users/:id", delete(handlers::delete_user))
+        .route("/api/users/:id/password", post(handlers::change_password))
         // Books
         .route("/api/books", get(handlers::list_books))
         .route("/api/books/search/advanced", get(handlers::advanced_search_books))
+        .route("/api/books/lookup", get(handlers::lookup_books))
+        .route("/api/isbn/:isbn", get(handlers::isbn_info))
         .route("/api/books", post(handlers::create_book))
+        .route("/api/books/enrich", post(handlers::enrich_library))
+        .route(
+            "/api/books/scan",
+            post(handlers::scan_books).layer(DefaultBodyLimit::max(handlers::scan::MAX_SCAN_SIZE)),
+        )
         .route("/api/books/:id", get(handlers::get_book))
         .route("/api/books/:id", put(handlers::update_book))
         .route("/api/books/:id", delete(handlers::delete_book))
         .route("/api/books/:id/readings", get(handlers::get_book_readings))
+        .route("/api/books/:id/highlights", get(handlers::get_book_highlights))
+        .route("/api/books/:id/identifiers", get(handlers::list_book_identifiers))
+        .route("/api/books/:id/identifiers", post(handlers::create_book_identifier))
+        .route("/api/books/:id/identifiers/:identifier_id", delete(handlers::delete_book_identifier))
+        .route("/api/books/:id/enrich", post(handlers::enrich_book))
+        .route("/api/books/:id/provenance", get(handlers::get_book_provenance))
+        .route("/api/books/:id/cover", get(handlers::get_cover).delete(handlers::delete_cover))
+        .route(
+            "/api/books/:id/cover",
+            post(handlers::upload_cover).layer(DefaultBodyLimit::max(handlers::covers::MAX_COVER_SIZE)),
+        )
         // Readings
         .route("/api/readings", get(handlers::list_readings))
         .route("/api/readings", post(handlers::create_reading))
@@ -84,17 +195,50 @@ pub fn create_router(pool: DbPool, config: Config) -> Router {
         .route("/api/readings/:id", delete(handlers::delete_reading))
         .route("/api/readings/:id/complete", patch(handlers::complete_reading))
         .route("/api/readings/stats", get(handlers::get_reading_stats))
+        // Highlights
+        .route("/api/highlights", get(handlers::list_highlights))
+        .route("/api/highlights", post(handlers::create_highlight))
+        .route("/api/highlights/:id", get(handlers::get_highlight))
+        .route("/api/highlights/:id", put(handlers::update_highlight))
+        .route("/api/highlights/:id", delete(handlers::delete_highlight))
         // Connectors
         .route("/api/connectors", get(handlers::list_connectors))
         .route("/api/connectors", post(handlers::create_or_update_connector))
         .route("/api/connectors/:provider", get(handlers::get_connector))
         .route("/api/connectors/:provider", delete(handlers::delete_connector))
         .route("/api/connectors/:provider/toggle", patch(handlers::toggle_connector))
+        .route("/api/connectors/:provider/verify", post(handlers::verify_connector_key))
+        // Chat with the library tools through the user's connector
+        .route("/api/chat", post(handlers::chat))
         // Import
-        .route("/api/import/goodreads/csv", post(handlers::import_goodreads_csv))
+        .merge(import_routes)
+        .route("/api/import/jobs", get(handlers::list_import_jobs))
+        .route("/api/import/jobs/:id", get(handlers::get_import_job))
+        .route("/api/import/jobs/:id/events", get(handlers::import_job_events))
+        .route("/api/import/jobs/:id/cancel", post(handlers::cancel_import_job))
+        .route("/api/import/jobs/:id/revert", post(handlers::revert_import_job))
+        // Admin routes (admin role checked by the AdminUser extractor)
+        .route("/api/admin/users", get(handlers::list_users))
+        .route("/api/admin/users/:id", get(handlers::get_user_details))
+        .route("/api/admin/users/:id/disable", post(handlers::disable_user))
+        .route("/api/admin/users/:id/enable", post(handlers::enable_user))
+        .route("/api/admin/users/:id/role", put(handlers::update_user_role))
+        .route("/api/admin/users/:id/sessions/revoke", post(handlers::revoke_user_sessions))
+        .route("/api/admin/users/:id/password-reset", post(handlers::send_user_password_reset))
+        .route("/api/admin/users/:id/verification-email", post(handlers::send_user_verification))
+        .route("/api/admin/users/:id/2fa", delete(handlers::reset_user_two_factor))
+        .route("/api/admin/stats", get(handlers::get_instance_stats))
+        .route("/api/admin/settings/registration", get(handlers::get_admin_registration_settings))
+        .route("/api/admin/settings/registration", put(handlers::update_registration_settings))
+        .route("/api/admin/invites", get(handlers::list_invites))
+        .route("/api/admin/invites", post(handlers::create_invite))
+        .route("/api/admin/invites/:id", delete(handlers::revoke_invite))
+        .route("/api/admin/audit", get(handlers::list_audit_log))
         // MCP endpoints (HTTP/SSE for remote access)
         .route("/mcp", get(handlers::handle_mcp_sse))
         .route("/mcp", post(handlers::handle_mcp_sse_post))
+        // Per-user limit on the whole API; runs after authentication
+        .layer(rate_limit(RouteGroup::Api))
         // Apply authentication middleware to all protected routes
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -124,6 +268,7 @@ pub fn create_router(pool: DbPool, config: Config) -> Router {
         .fallback(fallback_handler)
         .layer(trace_layer)
         .layer(cors)
+        .layer(Extension(TrustedProxies::new(config.trusted_proxies.clone())))
         .with_state(state)
 }
 
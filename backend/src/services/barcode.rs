@@ -0,0 +1,336 @@
+//! Reading EAN-13 barcodes from photos
+//!
+//! The back of a book carries its ISBN-13 as an EAN-13 symbol. Rows across the image
+//! are binarized and split into runs of bars and spaces, and the runs are matched
+//! against the EAN-13 layout: guard, six digits, middle guard, six digits, guard.
+//! Rows are read both ways, in the photo and turned a quarter, so upside-down and
+//! sideways photos are read too. A symbol is only accepted with a valid check digit.
+
+use image::{imageops, DynamicImage, GrayImage, ImageFormat};
+
+use crate::covers::{decode_limited, DecodeError};
+
+/// Photo formats, as listed in the error for other files
+const PHOTO_FORMATS: [ImageFormat; 4] = [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP, ImageFormat::Gif];
+
+/// Photos are scaled down to this before scanning; a barcode filling a fifth of the
+/// width still has two pixels per bar
+const SCAN_DIMENSION: u32 = 2400;
+
+/// Rows read across the image in each orientation
+const SCAN_ROWS: u32 = 64;
+
+/// Bar and space widths (in modules) of the L-code digits
+/// G codes are the same widths reversed; R codes are the L widths starting with a bar.
+const DIGIT_PATTERNS: [[u32; 4]; 10] = [
+    [3, 2, 1, 1],
+    [2, 2, 2, 1],
+    [2, 1, 2, 2],
+    [1, 4, 1, 1],
+    [1, 1, 3, 2],
+    [1, 2, 3, 1],
+    [1, 1, 1, 4],
+    [1, 3, 1, 2],
+    [1, 2, 1, 3],
+    [3, 1, 1, 2],
+];
+
+/// Which of the six left digits use G codes, for each first digit (highest bit first)
+const FIRST_DIGIT_PARITIES: [u8; 10] = [0x00, 0x0B, 0x0D, 0x0E, 0x13, 0x19, 0x1C, 0x15, 0x16, 0x1A];
+
+const GUARD: [u32; 3] = [1, 1, 1];
+const MIDDLE_GUARD: [u32; 5] = [1, 1, 1, 1, 1];
+
+/// Runs from the start guard to the end guard: 3 + 6×4 + 5 + 6×4 + 3
+const SYMBOL_RUNS: usize = 59;
+
+/// Mean deviation from a pattern, as a share of its width, still accepted
+const MAX_AVG_VARIANCE: f32 = 0.42;
+
+/// Deviation of a single bar or space, in modules, still accepted
+const MAX_INDIVIDUAL_VARIANCE: f32 = 0.7;
+
+/// Lowest difference between light and dark, out of 255, for a row to be read
+const MIN_CONTRAST: u8 = 40;
+
+/// Decode a photo and read the EAN-13 barcodes in it
+/// Returns the 13-digit codes found, each once, in the order they were found.
+/// Takes a while on large photos; call it from a blocking task.
+pub fn scan_image(data: &[u8]) -> Result<Vec<String>, String> {
+    let (_, image) = decode_limited(data, &PHOTO_FORMATS).map_err(|e| match e {
+        DecodeError::Format => "Photos must be JPEG, PNG, WebP or GIF images".to_string(),
+        DecodeError::Image(e) => format!("The photo could not be read: {}", e),
+    })?;
+
+    Ok(decode_ean13(&image))
+}
+
+/// The EAN-13 codes in an image, each once, in the order they were found
+pub fn decode_ean13(image: &DynamicImage) -> Vec<String> {
+    let image = if image.width() > SCAN_DIMENSION || image.height() > SCAN_DIMENSION {
+        image.resize(SCAN_DIMENSION, SCAN_DIMENSION, imageops::FilterType::Triangle)
+    } else {
+        image.clone()
+    };
+    let gray = image.to_luma8();
+    let turned = imageops::rotate90(&gray);
+
+    let mut codes = Vec::new();
+    for gray in [&gray, &turned] {
+        for row in scan_rows(gray) {
+            let Some(bars) = binarize(&row) else {
+                continue;
+            };
+            let reversed: Vec<bool> = bars.iter().rev().copied().collect();
+            for bars in [bars, reversed] {
+                for code in decode_row(&runs(&bars)) {
+                    if !codes.contains(&code) {
+                        codes.push(code);
+                    }
+                }
+            }
+        }
+    }
+    codes
+}
+
+/// Luma of rows spread evenly over the image, the middle one first
+fn scan_rows(gray: &GrayImage) -> Vec<Vec<u8>> {
+    let height = gray.height();
+    let rows = SCAN_ROWS.min(height);
+    let mut ys: Vec<u32> = (0..rows).map(|i| (2 * i + 1) * height / (2 * rows)).collect();
+    ys.sort_by_key(|y| y.abs_diff(height / 2));
+    ys.dedup();
+
+    ys.into_iter()
+        .map(|y| (0..gray.width()).map(|x| gray.get_pixel(x, y).0[0]).collect())
+        .collect()
+}
+
+/// Dark (true) and light pixels of a row
+/// The threshold sits halfway between the row's darker and lighter tenth, which
+/// tolerates a few specks and some uneven lighting. None for rows without contrast.
+fn binarize(row: &[u8]) -> Option<Vec<bool>> {
+    let mut sorted = row.to_vec();
+    sorted.sort_unstable();
+    let dark = sorted[sorted.len() / 10];
+    let light = sorted[sorted.len() * 9 / 10];
+    if light.saturating_sub(dark) < MIN_CONTRAST {
+        return None;
+    }
+    let threshold = (dark as u16 + light as u16) / 2;
+    Some(row.iter().map(|&luma| (luma as u16) < threshold).collect())
+}
+
+/// Lengths of alternating light and dark runs, starting with a light one
+/// The first run is empty when the row starts dark.
+fn runs(bars: &[bool]) -> Vec<u32> {
+    let mut runs = vec![0];
+    let mut dark = false;
+    for &pixel in bars {
+        if pixel != dark {
+            runs.push(0);
+            dark = pixel;
+        }
+        *runs.last_mut().expect("runs starts non-empty") += 1;
+    }
+    runs
+}
+
+/// Valid EAN-13 symbols in a row's runs, read left to right
+fn decode_row(runs: &[u32]) -> Vec<String> {
+    let mut codes = Vec::new();
+    // Dark runs are at odd indexes
+    let mut start = 1;
+    while start + SYMBOL_RUNS <= runs.len() {
+        if let Some(code) = decode_at(runs, start) {
+            codes.push(code);
+            start += SYMBOL_RUNS;
+        } else {
+            start += 2;
+        }
+    }
+    codes
+}
+
+/// The symbol whose start guard is the dark run at `start`, if there is one
+fn decode_at(runs: &[u32], start: usize) -> Option<String> {
+    let symbol = &runs[start..start + SYMBOL_RUNS];
+    let module = symbol.iter().sum::<u32>() as f32 / 95.0;
+
+    // Light margins at least as wide as the guards on both sides; a symbol cut off
+    // by the edge of the photo is not trusted
+    let end_margin = *runs.get(start + SYMBOL_RUNS)?;
+    if (runs[start - 1] as f32) < 3.0 * module || (end_margin as f32) < 3.0 * module {
+        return None;
+    }
+
+    let matches = |counters: &[u32], pattern: &[u32]| {
+        let width = counters.iter().sum::<u32>() as f32;
+        let modules = pattern.iter().sum::<u32>() as f32;
+        // Each part of the symbol should be about as wide as its share of it
+        if width < modules || (width / modules - module).abs() > 0.4 * module {
+            return None;
+        }
+        pattern_variance(counters, pattern).filter(|variance| *variance < MAX_AVG_VARIANCE)
+    };
+
+    matches(&symbol[0..3], &GUARD)?;
+    matches(&symbol[27..32], &MIDDLE_GUARD)?;
+    matches(&symbol[56..59], &GUARD)?;
+
+    let mut digits = Vec::with_capacity(13);
+    let mut parities = 0u8;
+    for i in 0..6 {
+        let counters = &symbol[3 + 4 * i..7 + 4 * i];
+        let (digit, g_code) = (0..10)
+            .flat_map(|digit| {
+                let l = DIGIT_PATTERNS[digit];
+                let g = [l[3], l[2], l[1], l[0]];
+                [
+                    matches(counters, &l).map(|variance| (variance, digit, false)),
+                    matches(counters, &g).map(|variance| (variance, digit, true)),
+                ]
+            })
+            .flatten()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, digit, g_code)| (digit, g_code))?;
+        digits.push(digit as u8);
+        parities = (parities << 1) | g_code as u8;
+    }
+    for i in 0..6 {
+        let counters = &symbol[32 + 4 * i..36 + 4 * i];
+        let digit = (0..10)
+            .filter_map(|digit| matches(counters, &DIGIT_PATTERNS[digit]).map(|variance| (variance, digit)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, digit)| digit)?;
+        digits.push(digit as u8);
+    }
+
+    let first = FIRST_DIGIT_PARITIES.iter().position(|&p| p == parities)?;
+    digits.insert(0, first as u8);
+
+    let code: String = digits.iter().map(|d| char::from(b'0' + d)).collect();
+    ean13_check_digit(&code[..12]).filter(|check| *check == digits[12]).map(|_| code)
+}
+
+/// Mean deviation of runs from a pattern, as a share of their width
+/// None when a single run is too far off.
+fn pattern_variance(counters: &[u32], pattern: &[u32]) -> Option<f32> {
+    let width = counters.iter().sum::<u32>() as f32;
+    let unit = width / pattern.iter().sum::<u32>() as f32;
+    let mut variance = 0.0;
+    for (&counter, &modules) in counters.iter().zip(pattern) {
+        let deviation = (counter as f32 - modules as f32 * unit).abs();
+        if deviation > MAX_INDIVIDUAL_VARIANCE * unit {
+            return None;
+        }
+        variance += deviation;
+    }
+    Some(variance / width)
+}
+
+/// Check digit of the first twelve digits of an EAN-13
+fn ean13_check_digit(digits: &str) -> Option<u8> {
+    let mut sum = 0;
+    for (i, c) in digits.chars().enumerate() {
+        let digit = c.to_digit(10)?;
+        sum += if i % 2 == 0 { digit } else { 3 * digit };
+    }
+    Some(((10 - sum % 10) % 10) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma};
+
+    /// Modules of an EAN-13 symbol, true for bars
+    fn modules(code: &str) -> Vec<bool> {
+        let digits: Vec<usize> = code.chars().map(|c| c.to_digit(10).unwrap() as usize).collect();
+        let mut modules = Vec::new();
+        let mut push = |widths: &[u32], start_dark: bool| {
+            let mut dark = start_dark;
+            for &width in widths {
+                modules.extend(std::iter::repeat_n(dark, width as usize));
+                dark = !dark;
+            }
+        };
+
+        push(&GUARD, true);
+        for (i, &digit) in digits[1..7].iter().enumerate() {
+            let l = DIGIT_PATTERNS[digit];
+            if FIRST_DIGIT_PARITIES[digits[0]] & (0x20 >> i) != 0 {
+                push(&[l[3], l[2], l[1], l[0]], false);
+            } else {
+                push(&l, false);
+            }
+        }
+        push(&MIDDLE_GUARD, false);
+        for &digit in &digits[7..] {
+            push(&DIGIT_PATTERNS[digit], true);
+        }
+        push(&GUARD, true);
+        modules
+    }
+
+    /// A barcode on a light background, `scale` pixels a module
+    fn render(code: &str, scale: u32) -> GrayImage {
+        let modules = modules(code);
+        let margin = 20 * scale;
+        let width = modules.len() as u32 * scale + 2 * margin;
+        ImageBuffer::from_fn(width, 40 * scale, |x, y| {
+            let module = x.checked_sub(margin).map(|x| (x / scale) as usize);
+            let inside = y > 5 * scale && y < 35 * scale;
+            match module.and_then(|m| modules.get(m)) {
+                Some(true) if inside => Luma([30u8]),
+                _ => Luma([235u8]),
+            }
+        })
+    }
+
+    #[test]
+    fn test_reads_rendered_barcode() {
+        let image = DynamicImage::ImageLuma8(render("9780441013593", 3));
+        assert_eq!(decode_ean13(&image), vec!["9780441013593"]);
+
+        // A first digit other than 9 uses other G-code parities
+        let image = DynamicImage::ImageLuma8(render("4006381333931", 2));
+        assert_eq!(decode_ean13(&image), vec!["4006381333931"]);
+    }
+
+    #[test]
+    fn test_reads_turned_and_uneven_barcodes() {
+        let image = DynamicImage::ImageLuma8(render("9781857988826", 3));
+        assert_eq!(decode_ean13(&image.rotate90()), vec!["9781857988826"]);
+        assert_eq!(decode_ean13(&image.rotate180()), vec!["9781857988826"]);
+
+        // Scaled to a width that is no whole number of pixels a module, with a
+        // shadow across it
+        let image = image.resize_exact(image.width() * 7 / 9, image.height(), imageops::FilterType::Triangle);
+        let mut gray = image.to_luma8();
+        let width = gray.width();
+        for (x, _, pixel) in gray.enumerate_pixels_mut() {
+            pixel.0[0] = pixel.0[0].saturating_sub((50 * x / width) as u8);
+        }
+        assert_eq!(decode_ean13(&DynamicImage::ImageLuma8(gray)), vec!["9781857988826"]);
+    }
+
+    #[test]
+    fn test_rejects_wrong_check_digit_and_blank_images() {
+        let image = DynamicImage::ImageLuma8(render("9780441013594", 3));
+        assert!(decode_ean13(&image).is_empty());
+
+        let blank = DynamicImage::ImageLuma8(ImageBuffer::from_pixel(400, 300, Luma([200u8])));
+        assert!(decode_ean13(&blank).is_empty());
+
+        assert!(scan_image(b"not an image").unwrap_err().contains("JPEG, PNG"));
+    }
+
+    #[test]
+    fn test_ean13_check_digit() {
+        assert_eq!(ean13_check_digit("978044101359"), Some(3));
+        assert_eq!(ean13_check_digit("400638133393"), Some(1));
+        assert_eq!(ean13_check_digit("97804410135x"), None);
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor};
+
+use crate::{errors::AppResult, models::identifier::IdentifierType};
+
+/// Every identifier of a user's books as (book id, type, value)
+pub async fn load_user_identifiers<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: i32,
+) -> AppResult<Vec<(i32, IdentifierType, String)>> {
+    let rows = sqlx::query_as::<_, (i32, String, String)>(
+        "SELECT i.book_id, i.id_type, i.value
+         FROM book_identifiers i
+         JOIN books b ON i.book_id = b.id
+         WHERE b.user_id = $1
+         ORDER BY i.id",
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(book_id, id_type, value)| Some((book_id, IdentifierType::parse(&id_type)?, value)))
+        .collect())
+}
+
+/// Add normalized identifiers to a book, skipping the ones it already has
+/// Returns the ID and creation time of each identifier added.
+pub async fn add_identifiers(
+    conn: &mut PgConnection,
+    book_id: i32,
+    identifiers: &[(IdentifierType, String)],
+    source: &str,
+) -> AppResult<Vec<(i32, DateTime<Utc>)>> {
+    let mut added = Vec::new();
+    for (id_type, value) in identifiers {
+        let row = sqlx::query_as::<_, (i32, DateTime<Utc>)>(
+            "INSERT INTO book_identifiers (book_id, id_type, value, source)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (book_id, id_type, value) DO NOTHING
+             RETURNING id, created_at",
+        )
+        .bind(book_id)
+        .bind(id_type.as_str())
+        .bind(value)
+        .bind(source)
+        .fetch_optional(&mut *conn)
+        .await?;
+        added.extend(row);
+    }
+    Ok(added)
+}
@@ -0,0 +1,709 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteConnection},
+    ConnectOptions, Connection,
+};
+
+use crate::{
+    db::DbPool,
+    errors::AppResult,
+    models::identifier::{isbn_identifier, IdentifierType},
+    services::{
+        book_identifiers::{add_identifiers, load_user_identifiers},
+        goodreads_parser::parse_year,
+        importer::isbn_or_none,
+        library_export::BookIndex,
+    },
+};
+
+/// First bytes of every SQLite database file
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Longest reading a Calibre database may take
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// SQLite instructions run between checks of the read deadline
+const PROGRESS_CHECK_OPS: i32 = 10_000;
+
+/// Tables of Calibre's schema the library query reads
+const CALIBRE_TABLES: [&str; 13] = [
+    "books",
+    "authors",
+    "books_authors_link",
+    "series",
+    "books_series_link",
+    "tags",
+    "books_tags_link",
+    "publishers",
+    "books_publishers_link",
+    "languages",
+    "books_languages_link",
+    "identifiers",
+    "comments",
+];
+
+/// A book as described by a Calibre library
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CalibreBook {
+    /// Stable identity across syncs
+    pub uuid: String,
+    /// Calibre's own numeric ID, used for cover URLs
+    pub calibre_id: Option<i64>,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+    pub tags: Vec<String>,
+    pub isbn: Option<String>,
+    /// Identifiers besides the ISBN and UUID: ASIN, Goodreads, Open Library, OCLC, LCCN
+    pub identifiers: Vec<(IdentifierType, String)>,
+    pub publisher: Option<String>,
+    pub publication_year: Option<i32>,
+    /// ISO 639 code as stored by Calibre ("eng")
+    pub language: Option<String>,
+    /// Plain text; Calibre keeps comments as HTML
+    pub description: Option<String>,
+    pub has_cover: bool,
+    /// Calibre's last_modified timestamp, compared as text
+    pub last_modified: Option<String>,
+}
+
+/// What a sync changed in the library
+#[derive(Debug, Default, Serialize)]
+pub struct CalibreSyncSummary {
+    pub books_found: usize,
+    pub books_created: usize,
+    pub books_updated: usize,
+    /// Existing books (same identifier, or same title and author) now tied to their Calibre book
+    pub books_linked: usize,
+    /// Books Calibre has not modified since the last sync
+    pub books_unchanged: usize,
+}
+
+#[derive(sqlx::FromRow)]
+struct CalibreRow {
+    id: i64,
+    uuid: Option<String>,
+    title: String,
+    series_index: Option<f64>,
+    pubdate: Option<String>,
+    has_cover: Option<bool>,
+    last_modified: Option<String>,
+    authors: Option<String>,
+    series: Option<String>,
+    tags: Option<String>,
+    publisher: Option<String>,
+    language: Option<String>,
+    isbn: Option<String>,
+    identifiers: Option<String>,
+    comments: Option<String>,
+}
+
+/// Read every book of a Calibre `metadata.db`
+/// The database is opened read-only, so a running Calibre is not disturbed.
+pub async fn read_metadata_db(path: &Path) -> Result<Vec<CalibreBook>, String> {
+    read_metadata_db_within(path, READ_TIMEOUT).await
+}
+
+async fn read_metadata_db_within(path: &Path, timeout: Duration) -> Result<Vec<CalibreBook>, String> {
+    let mut conn: SqliteConnection = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .connect()
+        .await
+        .map_err(|e| format!("Failed to open Calibre database: {}", e))?;
+
+    check_schema(&mut conn).await?;
+
+    // SQLite itself is interrupted at the deadline; giving up on the query alone
+    // would leave its worker thread running
+    let deadline = Instant::now() + timeout;
+    conn.lock_handle()
+        .await
+        .map_err(|e| format!("Failed to open Calibre database: {}", e))?
+        .set_progress_handler(PROGRESS_CHECK_OPS, move || Instant::now() < deadline);
+    let too_long = || format!("Reading the Calibre database took longer than {} seconds", timeout.as_secs());
+
+    // Lists are joined with the unit separator, which never appears in names
+    let query = sqlx::query_as::<_, CalibreRow>(
+        "SELECT b.id, b.uuid, b.title, b.series_index, CAST(b.pubdate AS TEXT) AS pubdate, b.has_cover,
+                CAST(b.last_modified AS TEXT) AS last_modified,
+                (SELECT group_concat(a.name, char(31)) FROM books_authors_link l JOIN authors a ON a.id = l.author WHERE l.book = b.id) AS authors,
+                (SELECT s.name FROM books_series_link l JOIN series s ON s.id = l.series WHERE l.book = b.id) AS series,
+                (SELECT group_concat(t.name, char(31)) FROM books_tags_link l JOIN tags t ON t.id = l.tag WHERE l.book = b.id) AS tags,
+                (SELECT p.name FROM books_publishers_link l JOIN publishers p ON p.id = l.publisher WHERE l.book = b.id) AS publisher,
+                (SELECT g.lang_code FROM books_languages_link l JOIN languages g ON g.id = l.lang_code
+                  WHERE l.book = b.id ORDER BY l.item_order LIMIT 1) AS language,
+                (SELECT i.val FROM identifiers i WHERE i.book = b.id AND i.type = 'isbn') AS isbn,
+                (SELECT group_concat(scheme_value, char(31))
+                  FROM (SELECT i.type || ':' || i.val AS scheme_value FROM identifiers i WHERE i.book = b.id ORDER BY i.id)) AS identifiers,
+                (SELECT c.text FROM comments c WHERE c.book = b.id) AS comments
+         FROM books b
+         ORDER BY b.id"
+    )
+    .fetch_all(&mut conn);
+    let rows = match tokio::time::timeout(timeout, query).await {
+        Ok(Ok(rows)) => rows,
+        Ok(Err(_)) if Instant::now() >= deadline => return Err(too_long()),
+        Ok(Err(e)) => return Err(format!("Not a Calibre library database: {}", e)),
+        Err(_) => return Err(too_long()),
+    };
+
+    conn.close().await.ok();
+
+    let split = |list: Option<String>| -> Vec<String> {
+        list.map(|list| list.split('\u{1f}').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+            .unwrap_or_default()
+    };
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let mut tags = split(row.tags);
+            tags.sort();
+            let series = non_empty(row.series);
+            Some(CalibreBook {
+                uuid: non_empty(row.uuid)?,
+                calibre_id: Some(row.id),
+                title: row.title.trim().to_string(),
+                authors: split(row.authors),
+                series_index: series.as_ref().and(row.series_index),
+                series,
+                tags,
+                isbn: row.isbn.as_deref().and_then(isbn_or_none),
+                identifiers: split(row.identifiers)
+                    .iter()
+                    .filter_map(|identifier| identifier.split_once(':'))
+                    .filter_map(|(scheme, value)| calibre_identifier(scheme, value))
+                    .collect(),
+                publisher: non_empty(row.publisher),
+                publication_year: row.pubdate.as_deref().and_then(|date| date.get(..4)).and_then(parse_year),
+                language: non_empty(row.language),
+                description: row.comments.as_deref().and_then(html_to_text),
+                has_cover: row.has_cover.unwrap_or(false),
+                last_modified: non_empty(row.last_modified),
+            })
+        })
+        .collect())
+}
+
+/// Check that the tables the library query reads are plain tables
+/// An upload could define them as views, recursive or joining everything with
+/// everything, to keep the reader busy. Calibre's own views and triggers are left
+/// alone: the query reads none of those views, and reading fires no trigger.
+async fn check_schema(conn: &mut SqliteConnection) -> Result<(), String> {
+    let entries = sqlx::query_as::<_, (String, String, Option<String>)>(
+        "SELECT type, name, sql FROM sqlite_master WHERE type IN ('table', 'view')",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Not a Calibre library database: {}", e))?;
+
+    for table in CALIBRE_TABLES {
+        let entry = entries.iter().find(|(_, name, _)| name.eq_ignore_ascii_case(table));
+        // Virtual tables are listed as tables too, but created differently
+        let plain = |sql: &str| sql.trim_start().get(..12).is_some_and(|start| start.eq_ignore_ascii_case("CREATE TABLE"));
+        match entry {
+            Some((kind, _, Some(sql))) if kind == "table" && plain(sql) => {}
+            Some(_) => return Err(format!("Not a Calibre library database: {} is not a plain table", table)),
+            None => return Err(format!("Not a Calibre library database: no {} table", table)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Read an uploaded `metadata.db` or `metadata.opf`
+pub async fn read_upload(data: &[u8]) -> Result<Vec<CalibreBook>, String> {
+    if data.starts_with(SQLITE_MAGIC) {
+        // SQLite needs a file to open
+        let file = tempfile::NamedTempFile::new().map_err(|e| format!("Failed to store upload: {}", e))?;
+        tokio::fs::write(file.path(), data)
+            .await
+            .map_err(|e| format!("Failed to store upload: {}", e))?;
+        return read_metadata_db(file.path()).await;
+    }
+
+    let xml = std::str::from_utf8(data).map_err(|_| "Expected a Calibre metadata.db or metadata.opf file".to_string())?;
+    parse_opf(xml).map(|book| vec![book])
+}
+
+/// Read a Calibre library directory (or a single metadata.db / metadata.opf)
+/// Without a metadata.db, the metadata.opf files Calibre keeps next to each
+/// book (`Author/Title (id)/metadata.opf`) are read instead.
+pub async fn read_library(path: &Path) -> Result<Vec<CalibreBook>, String> {
+    if path.is_file() {
+        let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        return read_upload(&data).await;
+    }
+
+    let database = path.join("metadata.db");
+    if database.is_file() {
+        return read_metadata_db(&database).await;
+    }
+
+    let mut books = Vec::new();
+    for opf in find_opf_files(path)? {
+        let xml = fs::read_to_string(&opf).map_err(|e| format!("Failed to read {}: {}", opf.display(), e))?;
+        match parse_opf(&xml) {
+            Ok(book) => books.push(book),
+            Err(e) => tracing::warn!("Skipping {}: {}", opf.display(), e),
+        }
+    }
+    Ok(books)
+}
+
+fn find_opf_files(library: &Path) -> Result<Vec<PathBuf>, String> {
+    let read_dir = |dir: &Path| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))
+    };
+
+    let mut files = Vec::new();
+    for author in read_dir(library)?.flatten().filter(|entry| entry.path().is_dir()) {
+        for book in read_dir(&author.path())?.flatten() {
+            let opf = book.path().join("metadata.opf");
+            if opf.is_file() {
+                files.push(opf);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parse the `metadata.opf` Calibre writes for each book
+pub fn parse_opf(xml: &str) -> Result<CalibreBook, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|e| format!("Invalid metadata.opf: {}", e))?;
+    let metadata = document
+        .descendants()
+        .find(|node| node.has_tag_name("metadata"))
+        .ok_or("Invalid metadata.opf: no metadata element")?;
+
+    // OPF attributes are namespaced (opf:scheme); match on local names
+    let attribute = |node: roxmltree::Node, name: &str| {
+        node.attributes().find(|a| a.name() == name).map(|a| a.value().to_string())
+    };
+
+    let mut book = CalibreBook::default();
+    for node in metadata.children().filter(roxmltree::Node::is_element) {
+        let text = node.text().map(str::trim).filter(|text| !text.is_empty()).map(str::to_string);
+        match node.tag_name().name() {
+            "title" => book.title = text.unwrap_or_default(),
+            "creator" if attribute(node, "role").is_none_or(|role| role == "aut") => {
+                book.authors.extend(text);
+            }
+            "identifier" => {
+                let scheme = attribute(node, "scheme").unwrap_or_default().to_lowercase();
+                match scheme.as_str() {
+                    "uuid" => book.uuid = text.unwrap_or_default(),
+                    "calibre" => book.calibre_id = text.and_then(|id| id.parse().ok()),
+                    "isbn" => book.isbn = text.as_deref().and_then(isbn_or_none),
+                    scheme => book.identifiers.extend(text.and_then(|value| calibre_identifier(scheme, &value))),
+                }
+            }
+            "publisher" => book.publisher = text,
+            "date" => book.publication_year = text.as_deref().and_then(|date| date.get(..4)).and_then(parse_year),
+            "language" => book.language = text,
+            "subject" => book.tags.extend(text),
+            "description" => book.description = text.as_deref().and_then(html_to_text),
+            "meta" => match attribute(node, "name").as_deref() {
+                Some("calibre:series") => book.series = attribute(node, "content"),
+                Some("calibre:series_index") => {
+                    book.series_index = attribute(node, "content").and_then(|index| index.parse().ok())
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    book.has_cover = document
+        .descendants()
+        .any(|node| node.has_tag_name("reference") && attribute(node, "type").as_deref() == Some("cover"));
+    book.tags.sort();
+
+    if book.uuid.is_empty() {
+        return Err("metadata.opf has no Calibre UUID".to_string());
+    }
+    Ok(book)
+}
+
+/// The identifier a Calibre identifier scheme and value stand for
+/// Calibre names schemes after the sites its metadata sources use; the ISBN and
+/// UUID have fields of their own, and unknown schemes are dropped.
+fn calibre_identifier(scheme: &str, value: &str) -> Option<(IdentifierType, String)> {
+    let scheme = scheme.trim().to_lowercase();
+    let types: &[IdentifierType] = match scheme.as_str() {
+        "isbn" | "uuid" | "calibre" => return None,
+        "goodreads" => &[IdentifierType::Goodreads],
+        "oclc" => &[IdentifierType::Oclc],
+        "lccn" => &[IdentifierType::Lccn],
+        "openlibrary" | "olid" => &[IdentifierType::OpenlibraryEdition, IdentifierType::OpenlibraryWork],
+        // "amazon", "mobi-asin" and the per-country "amazon_de", "amazon_fr", ...
+        scheme if scheme.starts_with("amazon") || scheme.ends_with("asin") => &[IdentifierType::Asin],
+        _ => return None,
+    };
+    types
+        .iter()
+        .find_map(|id_type| id_type.normalize(value).ok().map(|value| (*id_type, value)))
+}
+
+/// Add or update the books of a Calibre library in a user's library
+/// Books are matched by Calibre UUID, then by any identifier in common, ISBN or
+/// title and author for books not synced before. Calibre is the source of truth
+/// for the fields and identifiers it has; fields it leaves empty keep their value.
+/// Books removed from Calibre are kept.
+pub async fn sync_calibre_books(
+    pool: &DbPool,
+    user_id: i32,
+    books: &[CalibreBook],
+    content_server_url: Option<&str>,
+) -> AppResult<CalibreSyncSummary> {
+    let mut tx = pool.begin().await?;
+    let mut summary = CalibreSyncSummary { books_found: books.len(), ..Default::default() };
+
+    let existing = sqlx::query_as::<_, (i32, String, Option<String>, Option<String>, Option<String>, Option<String>)>(
+        "SELECT id, title, author, isbn, calibre_uuid, calibre_last_modified FROM books WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut by_uuid: HashMap<String, (i32, Option<String>)> = HashMap::new();
+    let mut unlinked = BookIndex::default();
+    let mut linked_ids = HashSet::new();
+    for (id, title, author, isbn, calibre_uuid, last_modified) in existing {
+        match calibre_uuid {
+            Some(uuid) => {
+                by_uuid.insert(uuid, (id, last_modified));
+            }
+            None => unlinked.insert(id, isbn.as_deref(), &title, author.as_deref()),
+        }
+    }
+
+    let linked_books: HashSet<i32> = by_uuid.values().map(|(id, _)| *id).collect();
+    for (book_id, id_type, value) in load_user_identifiers(&mut *tx, user_id).await? {
+        if !linked_books.contains(&book_id) {
+            unlinked.insert_identifier(book_id, (id_type, value));
+        }
+    }
+
+    for book in books {
+        if book.title.is_empty() {
+            continue;
+        }
+
+        let author = (!book.authors.is_empty()).then(|| truncate(&book.authors.join(" & "), 255));
+        let cover_url = match (content_server_url, book.calibre_id) {
+            (Some(base), Some(id)) if book.has_cover => Some(format!("{}/get/cover/{}", base, id)),
+            _ => None,
+        };
+
+        let (target, linked) = match by_uuid.get(&book.uuid) {
+            Some((id, last_modified)) => {
+                if book.last_modified.is_some() && *last_modified == book.last_modified {
+                    summary.books_unchanged += 1;
+                    continue;
+                }
+                (Some(*id), false)
+            }
+            None => {
+                let isbn = book.isbn.as_deref().and_then(isbn_identifier);
+                let matched = unlinked
+                    .find_by_identifier(isbn.iter().chain(&book.identifiers))
+                    .or_else(|| unlinked.find(book.isbn.as_deref(), &book.title, author.as_deref()))
+                    .filter(|id| linked_ids.insert(*id));
+                (matched, matched.is_some())
+            }
+        };
+
+        let id = match target {
+            Some(id) => {
+                sqlx::query(
+                    "UPDATE books
+                     SET title = $3, author = COALESCE($4, author), isbn = COALESCE($5, isbn),
+                         publication_year = COALESCE($6, publication_year), publisher = COALESCE($7, publisher),
+                         language = COALESCE($8, language), description = COALESCE($9, description),
+                         cover_image_url = COALESCE($10, cover_image_url),
+                         series = $11, series_index = $12, tags = $13,
+                         calibre_uuid = $14, calibre_last_modified = $15, updated_at = NOW()
+                     WHERE id = $1 AND user_id = $2"
+                )
+                .bind(id)
+                .bind(user_id)
+                .bind(truncate(&book.title, 500))
+                .bind(&author)
+                .bind(&book.isbn)
+                .bind(book.publication_year)
+                .bind(book.publisher.as_deref().map(|p| truncate(p, 255)))
+                .bind(book.language.as_deref().map(|l| truncate(l, 50)))
+                .bind(&book.description)
+                .bind(&cover_url)
+                .bind(book.series.as_deref().map(|s| truncate(s, 255)))
+                .bind(book.series_index)
+                .bind(&book.tags)
+                .bind(&book.uuid)
+                .bind(&book.last_modified)
+                .execute(&mut *tx)
+                .await?;
+
+                if linked {
+                    summary.books_linked += 1;
+                } else {
+                    summary.books_updated += 1;
+                }
+                id
+            }
+            None => {
+                let id = sqlx::query_scalar::<_, i32>(
+                    "INSERT INTO books (user_id, title, author, isbn, publication_year, publisher, language, description,
+                                        cover_image_url, series, series_index, tags, calibre_uuid, calibre_last_modified)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                     RETURNING id"
+                )
+                .bind(user_id)
+                .bind(truncate(&book.title, 500))
+                .bind(&author)
+                .bind(&book.isbn)
+                .bind(book.publication_year)
+                .bind(book.publisher.as_deref().map(|p| truncate(p, 255)))
+                .bind(book.language.as_deref().map(|l| truncate(l, 50)))
+                .bind(&book.description)
+                .bind(&cover_url)
+                .bind(book.series.as_deref().map(|s| truncate(s, 255)))
+                .bind(book.series_index)
+                .bind(&book.tags)
+                .bind(&book.uuid)
+                .bind(&book.last_modified)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                summary.books_created += 1;
+                id
+            }
+        };
+
+        // Calibre's identifiers replace the ones an earlier sync stored
+        sqlx::query("DELETE FROM book_identifiers WHERE book_id = $1 AND source = 'calibre'")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        add_identifiers(&mut tx, id, &book.identifiers, "calibre").await?;
+
+        // Later duplicates in the same library update this book instead of linking another one
+        by_uuid.insert(book.uuid.clone(), (id, None));
+    }
+
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Cut a string to a column's length without splitting a character
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
+
+/// Plain text of Calibre's HTML comments: tags dropped, paragraphs and line breaks kept
+fn html_to_text(html: &str) -> Option<String> {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_lowercase();
+        if tag.starts_with("br") || tag == "/p" || tag == "/div" {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPF: &str = r#"<?xml version='1.0' encoding='utf-8'?>
+<package xmlns="http://www.idpf.org/2007/opf" unique-identifier="uuid_id" version="2.0">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+        <dc:identifier opf:scheme="calibre" id="calibre_id">42</dc:identifier>
+        <dc:identifier opf:scheme="uuid" id="uuid_id">7c3a0e1d-93b0-4a4c-9d54-0d5f0f0e8b11</dc:identifier>
+        <dc:title>The Left Hand of Darkness</dc:title>
+        <dc:creator opf:file-as="Le Guin, Ursula K." opf:role="aut">Ursula K. Le Guin</dc:creator>
+        <dc:creator opf:role="ill">Someone Else</dc:creator>
+        <dc:contributor opf:role="bkp">calibre (7.0.0)</dc:contributor>
+        <dc:date>1969-03-01T00:00:00+00:00</dc:date>
+        <dc:description>&lt;p&gt;Winter &amp;amp; ice.&lt;/p&gt;&lt;p&gt;Second&amp;nbsp;part&lt;/p&gt;</dc:description>
+        <dc:publisher>Ace</dc:publisher>
+        <dc:identifier opf:scheme="ISBN">978-0-441-47812-5</dc:identifier>
+        <dc:identifier opf:scheme="AMAZON">b000fbjcls</dc:identifier>
+        <dc:identifier opf:scheme="GOOGLE">Q6qpAAAAIAAJ</dc:identifier>
+        <dc:language>eng</dc:language>
+        <dc:subject>Science Fiction</dc:subject>
+        <dc:subject>Classics</dc:subject>
+        <meta name="calibre:series" content="Hainish Cycle"/>
+        <meta name="calibre:series_index" content="4.0"/>
+    </metadata>
+    <guide>
+        <reference type="cover" title="Cover" href="cover.jpg"/>
+    </guide>
+</package>"#;
+
+    #[test]
+    fn test_parse_opf() {
+        let book = parse_opf(OPF).unwrap();
+        assert_eq!(book.uuid, "7c3a0e1d-93b0-4a4c-9d54-0d5f0f0e8b11");
+        assert_eq!(book.calibre_id, Some(42));
+        assert_eq!(book.title, "The Left Hand of Darkness");
+        assert_eq!(book.authors, vec!["Ursula K. Le Guin"]);
+        assert_eq!(book.isbn.as_deref(), Some("9780441478125"));
+        assert_eq!(book.identifiers, vec![(IdentifierType::Asin, "B000FBJCLS".to_string())]);
+        assert_eq!(book.publication_year, Some(1969));
+        assert_eq!(book.series.as_deref(), Some("Hainish Cycle"));
+        assert_eq!(book.series_index, Some(4.0));
+        assert_eq!(book.tags, vec!["Classics", "Science Fiction"]);
+        assert_eq!(book.language.as_deref(), Some("eng"));
+        assert_eq!(book.description.as_deref(), Some("Winter & ice.\nSecond part"));
+        assert!(book.has_cover);
+
+        assert!(parse_opf(&OPF.replace("opf:scheme=\"uuid\"", "opf:scheme=\"other\"")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_metadata_db() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let options = SqliteConnectOptions::new().filename(file.path()).create_if_missing(true);
+        let mut conn = options.connect().await.unwrap();
+        // The parts of Calibre's schema the importer reads
+        for statement in [
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT NOT NULL, sort TEXT, timestamp TIMESTAMP,
+                pubdate TIMESTAMP, series_index REAL NOT NULL DEFAULT 1.0, uuid TEXT, has_cover BOOL DEFAULT 0,
+                last_modified TIMESTAMP NOT NULL)",
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL)",
+            "CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER)",
+            "CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT NOT NULL)",
+            "CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER, series INTEGER)",
+            "CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT NOT NULL)",
+            "CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER, tag INTEGER)",
+            "CREATE TABLE publishers (id INTEGER PRIMARY KEY, name TEXT NOT NULL)",
+            "CREATE TABLE books_publishers_link (id INTEGER PRIMARY KEY, book INTEGER, publisher INTEGER)",
+            "CREATE TABLE languages (id INTEGER PRIMARY KEY, lang_code TEXT NOT NULL)",
+            "CREATE TABLE books_languages_link (id INTEGER PRIMARY KEY, book INTEGER, lang_code INTEGER, item_order INTEGER)",
+            "CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT, val TEXT)",
+            "CREATE TABLE comments (id INTEGER PRIMARY KEY, book INTEGER, text TEXT)",
+            "INSERT INTO books VALUES (1, 'Dune', 'Dune', '2024-01-01 10:00:00+00:00', '1965-08-01 00:00:00+00:00', 1.0,
+                'aaaa-1111', 1, '2024-02-01 12:00:00.123456+00:00')",
+            "INSERT INTO books VALUES (2, 'Good Omens', 'Good Omens', '2024-01-01 10:00:00+00:00', '0101-01-01 00:00:00+00:00', 1.0,
+                'bbbb-2222', 0, '2024-02-02 12:00:00+00:00')",
+            "INSERT INTO authors VALUES (1, 'Frank Herbert'), (2, 'Terry Pratchett'), (3, 'Neil Gaiman')",
+            "INSERT INTO books_authors_link VALUES (1, 1, 1), (2, 2, 2), (3, 2, 3)",
+            "INSERT INTO series VALUES (1, 'Dune')",
+            "INSERT INTO books_series_link VALUES (1, 1, 1)",
+            "INSERT INTO tags VALUES (1, 'Sci-Fi'), (2, 'Classic')",
+            "INSERT INTO books_tags_link VALUES (1, 1, 1), (2, 1, 2)",
+            "INSERT INTO publishers VALUES (1, 'Chilton')",
+            "INSERT INTO books_publishers_link VALUES (1, 1, 1)",
+            "INSERT INTO languages VALUES (1, 'eng')",
+            "INSERT INTO books_languages_link VALUES (1, 1, 1, 0)",
+            "INSERT INTO identifiers VALUES (1, 1, 'isbn', '9780441013593'), (2, 1, 'goodreads', '234225'),
+                (3, 1, 'openlibrary', 'OL1532643W'), (4, 1, 'mobi-asin', 'B00B7NPRY8')",
+            "INSERT INTO comments VALUES (1, 1, '<div><p>Desert planet.</p></div>')",
+        ] {
+            sqlx::query(statement).execute(&mut conn).await.unwrap();
+        }
+        conn.close().await.unwrap();
+
+        let books = read_metadata_db(file.path()).await.unwrap();
+        assert_eq!(books.len(), 2);
+
+        let dune = &books[0];
+        assert_eq!(dune.uuid, "aaaa-1111");
+        assert_eq!(dune.calibre_id, Some(1));
+        assert_eq!(dune.authors, vec!["Frank Herbert"]);
+        assert_eq!((dune.series.as_deref(), dune.series_index), (Some("Dune"), Some(1.0)));
+        assert_eq!(dune.tags, vec!["Classic", "Sci-Fi"]);
+        assert_eq!(dune.isbn.as_deref(), Some("9780441013593"));
+        assert_eq!(
+            dune.identifiers,
+            vec![
+                (IdentifierType::Goodreads, "234225".to_string()),
+                (IdentifierType::OpenlibraryWork, "OL1532643W".to_string()),
+                (IdentifierType::Asin, "B00B7NPRY8".to_string()),
+            ]
+        );
+        assert_eq!(dune.publisher.as_deref(), Some("Chilton"));
+        assert_eq!(dune.publication_year, Some(1965));
+        assert_eq!(dune.language.as_deref(), Some("eng"));
+        assert_eq!(dune.description.as_deref(), Some("Desert planet."));
+        assert!(dune.has_cover);
+        assert_eq!(dune.last_modified.as_deref(), Some("2024-02-01 12:00:00.123456+00:00"));
+
+        let omens = &books[1];
+        assert_eq!(omens.authors, vec!["Terry Pratchett", "Neil Gaiman"]);
+        // Calibre's "undefined" date and missing series
+        assert_eq!((omens.publication_year, omens.series_index), (None, None));
+
+        // Uploads are recognised by their SQLite header
+        let uploaded = read_upload(&fs::read(file.path()).unwrap()).await.unwrap();
+        assert_eq!(uploaded, books);
+
+        // A read past its deadline is interrupted once SQLite next checks it
+        let mut conn = options.connect().await.unwrap();
+        sqlx::query(
+            "INSERT INTO books (id, title, last_modified)
+             WITH RECURSIVE n(id) AS (SELECT 3 UNION ALL SELECT id + 1 FROM n WHERE id < 1000)
+             SELECT id, 'Filler', '2024-01-01 10:00:00+00:00' FROM n",
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+        let err = read_metadata_db_within(file.path(), Duration::ZERO).await.unwrap_err();
+        assert!(err.contains("took longer"), "{}", err);
+
+        // Tables swapped for views never reach the library query
+        for statement in [
+            "DROP TABLE books_authors_link",
+            "CREATE VIEW books_authors_link AS
+                WITH RECURSIVE n(id) AS (SELECT 1 UNION ALL SELECT id + 1 FROM n)
+                SELECT id, 1 AS book, 1 AS author FROM n",
+        ] {
+            sqlx::query(statement).execute(&mut conn).await.unwrap();
+        }
+        conn.close().await.unwrap();
+        let err = read_metadata_db(file.path()).await.unwrap_err();
+        assert!(err.contains("books_authors_link is not a plain table"), "{}", err);
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(html_to_text("<p>One</p><p>Two<br/>Three &amp; four</p>").as_deref(), Some("One\nTwo\nThree & four"));
+        assert_eq!(html_to_text("<div></div>"), None);
+        assert_eq!(html_to_text("plain"), Some("plain".to_string()));
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use crate::{
+    chat::{self, Verification},
+    config::Config,
+    db::DbPool,
+    errors::AppResult,
+    models::connector::{Connector, ConnectorStatus, CONNECTOR_COLUMNS},
+};
+
+/// Outcome of one periodic re-verification pass
+#[derive(Debug, Default)]
+pub struct VerificationSummary {
+    pub checked: usize,
+    pub invalid: usize,
+    pub deactivated: usize,
+    /// Connectors whose key could not be decrypted or checked
+    pub failed: usize,
+}
+
+/// Check a connector's key with its provider and record the outcome
+/// With `deactivate_invalid`, a key the provider rejects also deactivates the connector.
+/// If the connector was changed while the check ran (a new key, endpoint or model),
+/// the result is discarded and the current row is returned unchanged.
+pub async fn verify_connector(
+    pool: &DbPool,
+    config: &Config,
+    connector: &Connector,
+    deactivate_invalid: bool,
+) -> AppResult<Connector> {
+    let provider = chat::provider_for(connector, config)?;
+
+    let verification = provider.verify().await;
+    record_verification(pool, connector, &verification, deactivate_invalid).await
+}
+
+async fn record_verification(
+    pool: &DbPool,
+    connector: &Connector,
+    verification: &Verification,
+    deactivate_invalid: bool,
+) -> AppResult<Connector> {
+    let updated = sqlx::query_as::<_, Connector>(&format!(
+        r#"
+        UPDATE connectors
+        SET status = $3,
+            status_detail = $4,
+            verified_at = NOW(),
+            is_active = CASE WHEN $5 AND $3 = 'invalid' THEN FALSE ELSE is_active END
+        WHERE id = $1
+          AND encrypted_token IS NOT DISTINCT FROM $2
+          AND base_url IS NOT DISTINCT FROM $6
+          AND model IS NOT DISTINCT FROM $7
+          AND updated_at = $8
+        RETURNING {}
+        "#,
+        CONNECTOR_COLUMNS
+    ))
+    .bind(connector.id)
+    .bind(&connector.encrypted_token)
+    .bind(verification.status.as_str())
+    .bind(verification.detail.as_deref())
+    .bind(deactivate_invalid)
+    .bind(&connector.base_url)
+    .bind(&connector.model)
+    .bind(connector.updated_at)
+    .fetch_optional(pool)
+    .await?;
+
+    match updated {
+        Some(connector) => Ok(connector),
+        None => {
+            let current = sqlx::query_as::<_, Connector>(&format!(
+                "SELECT {} FROM connectors WHERE id = $1",
+                CONNECTOR_COLUMNS
+            ))
+            .bind(connector.id)
+            .fetch_one(pool)
+            .await?;
+            Ok(current)
+        }
+    }
+}
+
+/// Re-check every active connector not verified within `max_age_hours`
+/// Keys the provider rejects are deactivated; rate limits and outages are only recorded.
+pub async fn reverify_stale_connectors(
+    pool: &DbPool,
+    config: &Config,
+    max_age_hours: u64,
+) -> AppResult<VerificationSummary> {
+    let connectors = sqlx::query_as::<_, Connector>(&format!(
+        r#"
+        SELECT {}
+        FROM connectors
+        WHERE is_active = TRUE
+          AND (verified_at IS NULL OR verified_at < NOW() - make_interval(hours => $1))
+        ORDER BY verified_at ASC NULLS FIRST
+        "#,
+        CONNECTOR_COLUMNS
+    ))
+    .bind(max_age_hours as i32)
+    .fetch_all(pool)
+    .await?;
+
+    let mut summary = VerificationSummary::default();
+    for connector in connectors {
+        summary.checked += 1;
+        match verify_connector(pool, config, &connector, true).await {
+            Ok(updated) => {
+                if ConnectorStatus::parse(&updated.status) == Some(ConnectorStatus::Invalid) {
+                    summary.invalid += 1;
+                    if connector.is_active && !updated.is_active {
+                        summary.deactivated += 1;
+                        tracing::info!(
+                            "Deactivated {} connector {} of user {}: key rejected by provider",
+                            updated.provider,
+                            updated.id,
+                            updated.user_id
+                        );
+                    }
+                }
+            }
+            Err(e) => {
+                summary.failed += 1;
+                tracing::warn!("Failed to verify connector {}: {}", connector.id, e);
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Re-verify connectors every CONNECTOR_VERIFY_INTERVAL_HOURS in the background
+/// An interval of 0 disables periodic verification.
+pub fn spawn_periodic_verification(pool: DbPool, config: Config) {
+    let hours = config.connector_verify_interval_hours;
+    if hours == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(hours * 3600));
+        loop {
+            ticker.tick().await;
+            match reverify_stale_connectors(&pool, &config, hours).await {
+                Ok(summary) if summary.checked > 0 => tracing::info!(
+                    "Re-verified {} connectors: {} invalid, {} deactivated, {} failed",
+                    summary.checked,
+                    summary.invalid,
+                    summary.deactivated,
+                    summary.failed
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::error!("Connector re-verification failed: {}", e),
+            }
+        }
+    });
+}
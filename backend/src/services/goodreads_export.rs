@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use csv::WriterBuilder;
+use panicless_mcp_lib::isbn::Isbn;
+
+use crate::models::{
+    export::{ExportedBook, ExportedReading},
+    import::GoodreadsRecord,
+};
+
+/// Goodreads date format, also read back by the importer
+const DATE_FORMAT: &str = "%Y/%m/%d";
+
+/// Separator the importer puts between a review and private notes
+const NOTES_SEPARATOR: &str = "\n\n---\n\n";
+
+/// Write books and their readings as a Goodreads library export
+/// One row per book, with the columns `GoodreadsRecord` reads.
+pub fn write_goodreads_csv(books: &[ExportedBook], readings: &[ExportedReading]) -> Result<Vec<u8>, String> {
+    let mut by_book: HashMap<i32, Vec<&ExportedReading>> = HashMap::new();
+    for reading in readings {
+        by_book.entry(reading.book_id).or_default().push(reading);
+    }
+
+    let mut writer = WriterBuilder::new().has_headers(true).from_writer(Vec::new());
+    for book in books {
+        let book_readings = by_book.get(&book.id).map(Vec::as_slice).unwrap_or_default();
+        writer
+            .serialize(to_goodreads_record(book, book_readings))
+            .map_err(|e| format!("Failed to write CSV row: {}", e))?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+/// Describe a book as a Goodreads row
+/// The shelf and rating come from the latest reading: an open one puts the book
+/// on currently-reading, a finished one on read, none on to-read. For readings,
+/// Date Added holds the start date, which is where the importer looks for it.
+/// Goodreads has no room for earlier readings: only their number goes out, as
+/// Read Count, and the importer turns it back into readings without dates.
+fn to_goodreads_record(book: &ExportedBook, readings: &[&ExportedReading]) -> GoodreadsRecord {
+    let latest = readings
+        .iter()
+        .max_by_key(|reading| (reading.end_date.is_none(), reading.start_date, reading.end_date, reading.id));
+    let read_count = readings.iter().filter(|reading| reading.end_date.is_some()).count();
+
+    let (shelf, date_added, date_read) = match latest {
+        Some(reading) if reading.end_date.is_none() => ("currently-reading", reading.start_date, None),
+        Some(reading) => ("read", reading.start_date, reading.end_date),
+        None => ("to-read", book.created_at.date_naive(), None),
+    };
+
+    let (review, private_notes) = split_notes(latest.and_then(|reading| reading.notes.as_deref()));
+    let (isbn, isbn13) = split_isbn(book.isbn.as_deref());
+    let year = |year: Option<i32>| year.map(|year| year.to_string());
+    // The exclusive shelf comes first, as in Goodreads' own exports
+    let bookshelves = std::iter::once(shelf)
+        .chain(book.tags.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    GoodreadsRecord {
+        // Left empty for books that did not come from Goodreads
+        book_id: book.goodreads_id.clone().unwrap_or_default(),
+        title: book.title.clone(),
+        author: book.author.clone(),
+        author_lf: book.author.as_deref().map(last_first),
+        additional_authors: None,
+        isbn,
+        isbn13,
+        my_rating: latest.and_then(|reading| reading.rating).unwrap_or(0).to_string(),
+        average_rating: None,
+        publisher: book.publisher.clone(),
+        binding: book.format.clone(),
+        number_of_pages: book.pages.map(|pages| pages.to_string()),
+        year_published: year(book.publication_year),
+        original_publication_year: year(book.original_publication_year.or(book.publication_year)),
+        date_read: date_read.map(|date| date.format(DATE_FORMAT).to_string()),
+        date_added: Some(date_added.format(DATE_FORMAT).to_string()),
+        bookshelves: Some(bookshelves),
+        bookshelves_with_positions: None,
+        exclusive_shelf: shelf.to_string(),
+        my_review: review,
+        spoiler: None,
+        private_notes,
+        read_count: Some(read_count.to_string()),
+        owned_copies: Some(book.owned_copies.unwrap_or(0).to_string()),
+    }
+}
+
+/// Undo the importer's merge of review and private notes
+/// Notes without the separator were written in Panicless and stay private.
+fn split_notes(notes: Option<&str>) -> (Option<String>, Option<String>) {
+    match notes.map(|notes| notes.split_once(NOTES_SEPARATOR)) {
+        Some(Some((review, private_notes))) => (Some(review.to_string()), Some(private_notes.to_string())),
+        Some(None) => (None, notes.map(str::to_string)),
+        None => (None, None),
+    }
+}
+
+/// Fill the ISBN and ISBN13 columns, quoted the way Goodreads does
+/// 979 ISBNs have no ISBN-10; ISBNs that do not validate go in ISBN13 as stored.
+fn split_isbn(isbn: Option<&str>) -> (Option<String>, Option<String>) {
+    let quoted = |value: &str| format!("=\"{}\"", value);
+    match isbn.map(str::trim).filter(|isbn| !isbn.is_empty()) {
+        Some(isbn) => match Isbn::parse(isbn) {
+            Ok(isbn) => (isbn.to_isbn10().as_deref().map(quoted), Some(quoted(isbn.as_str()))),
+            Err(_) => (None, Some(quoted(isbn))),
+        },
+        None => (None, None),
+    }
+}
+
+/// "Ursula K. Le Guin" -> "Guin, Ursula K. Le" (Goodreads splits on the last word too)
+fn last_first(author: &str) -> String {
+    match author.trim().rsplit_once(' ') {
+        Some((first, last)) => format!("{}, {}", last, first),
+        None => author.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::goodreads_parser::{parse_csv_data, transform_record};
+    use chrono::{NaiveDate, Utc};
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn book(id: i32, title: &str, isbn: Option<&str>) -> ExportedBook {
+        ExportedBook {
+            id,
+            title: title.to_string(),
+            author: Some("Frank Herbert".to_string()),
+            edition: None,
+            isbn: isbn.map(str::to_string),
+            publication_year: Some(1965),
+            publisher: Some("Chilton, Inc.".to_string()),
+            pages: Some(412),
+            language: None,
+            description: None,
+            cover_image_url: None,
+            series: None,
+            series_index: None,
+            tags: Vec::new(),
+            format: None,
+            original_publication_year: None,
+            owned_copies: None,
+            goodreads_id: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn reading(id: i32, book_id: i32, start: &str, end: Option<&str>, rating: Option<i32>, notes: Option<&str>) -> ExportedReading {
+        ExportedReading {
+            id,
+            book_id,
+            start_date: date(start),
+            end_date: end.map(date),
+            rating,
+            notes: notes.map(str::to_string),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_through_importer() {
+        let books = vec![
+            book(1, "Dune", Some("9780441013593")),
+            book(2, "Dune Messiah", Some("0441172695")),
+            book(3, "Children of Dune, \"Part 3\"", None),
+            book(4, "Heretics of Dune", None),
+        ];
+        let readings = vec![
+            reading(1, 1, "2023-01-01", Some("2023-02-01"), Some(3), None),
+            reading(2, 1, "2024-05-01", Some("2024-05-20"), Some(5), Some("Better the second time\n\n---\n\nlent to Sam")),
+            reading(3, 2, "2025-03-01", None, None, Some("slow start")),
+            reading(4, 3, "2024-07-04", Some("2024-07-04"), Some(4), None),
+        ];
+
+        let mut books = books;
+        books[0].format = Some("Paperback".to_string());
+        books[0].original_publication_year = Some(1965);
+        books[0].publication_year = Some(1990);
+        books[0].owned_copies = Some(2);
+        books[0].goodreads_id = Some("234225".to_string());
+        books[0].tags = vec!["sci-fi".to_string(), "favorites".to_string()];
+
+        let csv = write_goodreads_csv(&books, &readings).unwrap();
+        let imported: Vec<_> = parse_csv_data(csv.as_slice())
+            .unwrap()
+            .into_iter()
+            .map(|record| transform_record(&record.unwrap()).unwrap())
+            .collect();
+        assert_eq!(imported.len(), 4);
+
+        let dune = &imported[0];
+        assert_eq!(dune.shelf, "read");
+        assert_eq!(dune.isbn.as_deref(), Some("9780441013593"));
+        assert_eq!(dune.author.as_deref(), Some("Frank Herbert"));
+        assert_eq!(dune.publisher.as_deref(), Some("Chilton, Inc."));
+        assert_eq!((dune.publication_year, dune.original_publication_year), (Some(1990), Some(1965)));
+        assert_eq!(dune.pages, Some(412));
+        assert_eq!((dune.format.as_deref(), dune.owned_copies), (Some("Paperback"), Some(2)));
+        assert_eq!(dune.goodreads_id.as_deref(), Some("234225"));
+        assert_eq!(dune.tags, books[0].tags);
+        // Only the latest reading keeps its dates, rating and notes; the first one
+        // is left with its place in the read count
+        assert_eq!(dune.read_count, Some(2));
+        assert_eq!((dune.start_date, dune.end_date), (Some(date("2024-05-01")), Some(date("2024-05-20"))));
+        assert_eq!(dune.rating, Some(5));
+        assert_eq!(dune.notes.as_deref(), readings[1].notes.as_deref());
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(!csv.contains("2023/01/01") && !csv.contains("2023/02/01"));
+
+        let messiah = &imported[1];
+        assert_eq!(messiah.shelf, "currently-reading");
+        assert_eq!(messiah.isbn.as_deref(), Some("9780441172696"));
+        assert_eq!((messiah.start_date, messiah.end_date), (Some(date("2025-03-01")), None));
+        assert_eq!(messiah.notes.as_deref(), Some("slow start"));
+        assert_eq!((messiah.goodreads_id.as_deref(), messiah.owned_copies), (None, None));
+
+        // Goodreads cannot tell a one-day reading from a book added the day it was
+        // read, so the start date is lost
+        let children = &imported[2];
+        assert_eq!(children.title, books[2].title);
+        assert_eq!((children.start_date, children.end_date), (None, Some(date("2024-07-04"))));
+        assert_eq!((children.rating, children.read_count), (Some(4), Some(1)));
+
+        let heretics = &imported[3];
+        assert_eq!(heretics.shelf, "to-read");
+        assert_eq!((heretics.isbn.as_deref(), heretics.rating), (None, None));
+    }
+
+    #[test]
+    fn test_split_notes() {
+        assert_eq!(
+            split_notes(Some("Review\n\n---\n\nNotes")),
+            (Some("Review".to_string()), Some("Notes".to_string()))
+        );
+        assert_eq!(split_notes(Some("Just mine")), (None, Some("Just mine".to_string())));
+        assert_eq!(split_notes(None), (None, None));
+    }
+
+    #[test]
+    fn test_split_isbn() {
+        assert_eq!(
+            split_isbn(Some("9780441013593")),
+            (Some("=\"0441013597\"".to_string()), Some("=\"9780441013593\"".to_string()))
+        );
+        assert_eq!(split_isbn(Some("9791090636071")), (None, Some("=\"9791090636071\"".to_string())));
+        assert_eq!(split_isbn(Some("12-34")), (None, Some("=\"12-34\"".to_string())));
+        assert_eq!(split_isbn(None), (None, None));
+    }
+
+    #[test]
+    fn test_last_first() {
+        assert_eq!(last_first("Frank Herbert"), "Herbert, Frank");
+        assert_eq!(last_first("Colette"), "Colette");
+    }
+}
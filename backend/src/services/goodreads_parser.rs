@@ -1,8 +1,18 @@
 use chrono::NaiveDate;
 use csv::ReaderBuilder;
+use panicless_mcp_lib::isbn::Isbn;
 use std::io::Read;
 
-use crate::models::import::{GoodreadsRecord, TransformedBook};
+use crate::{
+    models::{
+        identifier::isbn_identifier,
+        import::{GoodreadsRecord, TransformedBook},
+    },
+    services::importer::notes_with_extras,
+};
+
+/// Goodreads' exclusive shelves, which every export lists among the bookshelves too
+const EXCLUSIVE_SHELVES: [&str; 3] = ["read", "currently-reading", "to-read"];
 
 /// Parse CSV data from bytes
 pub fn parse_csv_data<R: Read>(reader: R) -> Result<Vec<Result<GoodreadsRecord, String>>, String> {
@@ -38,20 +48,26 @@ pub fn transform_record(record: &GoodreadsRecord) -> Result<TransformedBook, Str
         .map(|a| a.trim().to_string())
         .filter(|a| !a.is_empty());
 
-    // Use ISBN13 if available, otherwise fall back to ISBN
-    let isbn = record
-        .isbn13
-        .as_ref()
-        .or(record.isbn.as_ref())
-        .map(|i| clean_isbn(i))
-        .filter(|i| !i.is_empty());
-
-    // Parse publication year (prefer original publication year)
+    // Use ISBN13 if it is valid, otherwise fall back to ISBN; stored as ISBN-13 either way
+    let isbn = [&record.isbn13, &record.isbn]
+        .into_iter()
+        .flatten()
+        .find_map(|i| Isbn::parse(&clean_isbn(i)).ok())
+        .map(|isbn| isbn.to_string());
+    // Both ISBNs of the edition, for matching
+    let identifiers = [&record.isbn13, &record.isbn]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| isbn_identifier(&clean_isbn(i)))
+        .collect();
+
+    // The edition's year, and the year the work first came out
+    let original_publication_year = record.original_publication_year.as_ref().and_then(|y| parse_year(y));
     let publication_year = record
-        .original_publication_year
+        .year_published
         .as_ref()
-        .or(record.year_published.as_ref())
-        .and_then(|y| parse_year(y));
+        .and_then(|y| parse_year(y))
+        .or(original_publication_year);
 
     // Parse pages (ignore 0 values as pages must be > 0)
     let pages = record
@@ -77,8 +93,47 @@ pub fn transform_record(record: &GoodreadsRecord) -> Result<TransformedBook, Str
         None => None,
     };
 
-    // Combine review and private notes
-    let notes = combine_notes(&record.my_review, &record.private_notes);
+    // Binding, e.g. "Paperback" or "Kindle Edition"
+    let format = non_empty(&record.binding);
+
+    // 0 is what Goodreads writes for books not marked as owned
+    let owned_copies = record
+        .owned_copies
+        .as_ref()
+        .and_then(|c| parse_integer(c))
+        .filter(|&c| c > 0);
+
+    let read_count = record
+        .read_count
+        .as_ref()
+        .and_then(|c| parse_integer(c))
+        .filter(|&c| c >= 0);
+
+    // Goodreads book IDs are numeric; exports written elsewhere may leave them empty
+    let goodreads_id = Some(record.book_id.trim().to_string())
+        .filter(|id| !id.is_empty() && id.len() <= 20 && id.chars().all(|c| c.is_ascii_digit()));
+
+    // Shelves other than the exclusive one become tags
+    let exclusive_shelf = record.exclusive_shelf.trim();
+    let mut tags: Vec<String> = Vec::new();
+    for shelf in record.bookshelves.as_deref().unwrap_or_default().split(',').map(str::trim) {
+        let exclusive = shelf.eq_ignore_ascii_case(exclusive_shelf)
+            || EXCLUSIVE_SHELVES.iter().any(|s| shelf.eq_ignore_ascii_case(s));
+        if !shelf.is_empty() && !exclusive && !tags.iter().any(|tag| tag == shelf) {
+            tags.push(shelf.to_string());
+        }
+    }
+
+    // Combine review and private notes; a spoiler flag is kept as a line of them
+    let spoiler = record
+        .spoiler
+        .as_deref()
+        .is_some_and(|s| s.trim().eq_ignore_ascii_case("true"))
+        .then(|| "yes".to_string());
+    let notes = notes_with_extras(
+        combine_notes(&record.my_review, &record.private_notes),
+        &[("Review contains spoilers", spoiler)],
+    );
 
     // Parse dates
     let date_read = record.date_read.as_ref().and_then(|d| parse_date(d));
@@ -115,16 +170,24 @@ pub fn transform_record(record: &GoodreadsRecord) -> Result<TransformedBook, Str
         publication_year,
         publisher,
         pages,
-        shelf: record.exclusive_shelf.trim().to_string(),
+        language: None,
+        format,
+        original_publication_year,
+        owned_copies,
+        goodreads_id,
+        identifiers,
+        tags,
+        shelf: exclusive_shelf.to_string(),
         rating,
         notes,
         start_date,
         end_date,
+        read_count,
     })
 }
 
 /// Parse a date string in Goodreads format (YYYY/MM/DD)
-fn parse_date(date_str: &str) -> Option<NaiveDate> {
+pub(crate) fn parse_date(date_str: &str) -> Option<NaiveDate> {
     let date_str = date_str.trim();
     if date_str.is_empty() {
         return None;
@@ -140,7 +203,7 @@ fn parse_date(date_str: &str) -> Option<NaiveDate> {
 }
 
 /// Parse a year string into an integer
-fn parse_year(year_str: &str) -> Option<i32> {
+pub(crate) fn parse_year(year_str: &str) -> Option<i32> {
     let year_str = year_str.trim();
     if year_str.is_empty() {
         return None;
@@ -149,11 +212,11 @@ fn parse_year(year_str: &str) -> Option<i32> {
     // Handle decimal years like "1984.0"
     let year_str = year_str.split('.').next().unwrap_or(year_str);
 
-    year_str.parse::<i32>().ok().filter(|&y| y >= 1000 && y <= 9999)
+    year_str.parse::<i32>().ok().filter(|&y| (1000..=9999).contains(&y))
 }
 
 /// Parse an integer string
-fn parse_integer(int_str: &str) -> Option<i32> {
+pub(crate) fn parse_integer(int_str: &str) -> Option<i32> {
     let int_str = int_str.trim();
     if int_str.is_empty() {
         return None;
@@ -163,15 +226,17 @@ fn parse_integer(int_str: &str) -> Option<i32> {
 }
 
 /// Clean ISBN by removing hyphens and quotes
-fn clean_isbn(isbn: &str) -> String {
+pub(crate) fn clean_isbn(isbn: &str) -> String {
     isbn.trim()
-        .replace('-', "")
-        .replace('\"', "")
-        .replace('=', "")
+        .replace(['-', '\"', '='], "")
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
 }
 
 /// Combine review and private notes into a single notes field
-fn combine_notes(review: &Option<String>, private_notes: &Option<String>) -> Option<String> {
+pub(crate) fn combine_notes(review: &Option<String>, private_notes: &Option<String>) -> Option<String> {
     let review_text = review.as_ref().map(|r| r.trim()).filter(|r| !r.is_empty());
     let notes_text = private_notes
         .as_ref()
@@ -387,6 +452,58 @@ mod tests {
         assert_eq!(result.end_date, Some(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()));
     }
 
+    #[test]
+    fn test_transform_keeps_edition_and_shelf_details() {
+        let record = GoodreadsRecord {
+            book_id: "234225".to_string(),
+            title: "Dune".to_string(),
+            author: Some("Frank Herbert".to_string()),
+            author_lf: None,
+            additional_authors: None,
+            isbn: None,
+            isbn13: None,
+            my_rating: "5".to_string(),
+            average_rating: Some("4.27".to_string()),
+            publisher: Some("Ace".to_string()),
+            binding: Some(" Mass Market Paperback ".to_string()),
+            number_of_pages: Some("896".to_string()),
+            year_published: Some("2005".to_string()),
+            original_publication_year: Some("1965".to_string()),
+            date_read: Some("2024/02/03".to_string()),
+            date_added: Some("2024/01/02".to_string()),
+            bookshelves: Some("sci-fi, read, favorites, sci-fi".to_string()),
+            bookshelves_with_positions: None,
+            exclusive_shelf: "read".to_string(),
+            my_review: Some("Spice!".to_string()),
+            spoiler: Some("true".to_string()),
+            private_notes: None,
+            read_count: Some("3".to_string()),
+            owned_copies: Some("2".to_string()),
+        };
+
+        let result = transform_record(&record).unwrap();
+        assert_eq!((result.publication_year, result.original_publication_year), (Some(2005), Some(1965)));
+        assert_eq!(result.format.as_deref(), Some("Mass Market Paperback"));
+        assert_eq!(result.owned_copies, Some(2));
+        assert_eq!(result.read_count, Some(3));
+        assert_eq!(result.goodreads_id.as_deref(), Some("234225"));
+        assert_eq!(result.tags, vec!["sci-fi".to_string(), "favorites".to_string()]);
+        assert_eq!(result.notes.as_deref(), Some("Spice!\n\nReview contains spoilers: yes"));
+
+        // Unowned books, missing edition years and non-Goodreads IDs
+        let record = GoodreadsRecord {
+            book_id: String::new(),
+            owned_copies: Some("0".to_string()),
+            year_published: None,
+            spoiler: Some("false".to_string()),
+            ..record
+        };
+        let result = transform_record(&record).unwrap();
+        assert_eq!((result.owned_copies, result.goodreads_id), (None, None));
+        assert_eq!(result.publication_year, Some(1965));
+        assert_eq!(result.notes.as_deref(), Some("Spice!"));
+    }
+
     #[test]
     fn test_pages_zero_filtered_out() {
         // Pages with 0 value should be filtered out (None)
@@ -0,0 +1,1083 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::{
+    db::DbPool,
+    errors::{AppError, AppResult},
+    models::{
+        import::{
+            BookImportResult, ImportAction, ImportError, ImportResponse, ImportSuccess,
+            ImportSummary, MergePolicy, RowDecision, TransformedBook,
+        },
+        import_job::{
+            BookSnapshot, ImportJob, RevertConflict, RevertSummary, BOOK_SNAPSHOT_COLUMNS, IMPORT_JOB_COLUMNS,
+        },
+    },
+    services::{
+        book_identifiers::add_identifiers,
+        import_plan::{extra_reading_date, merge_book, row_identifiers, LibraryIndex, RowPlan, EXTRA_READING_NOTES},
+        importer::{GoodreadsImporter, ImportRow, Importer},
+        librarything_parser::LibraryThingImporter,
+        storygraph_parser::StoryGraphImporter,
+    },
+};
+
+/// A row of the file with what importing it will do
+pub type PlannedRow = (usize, TransformedBook, RowPlan);
+
+/// A running job saves its counts, and checks for cancellation, after this many rows
+const PROGRESS_INTERVAL: usize = 20;
+
+/// Unfinished jobs whose row has not changed for this long have lost their server
+/// Saving progress bumps `updated_at`, which running jobs do every few seconds.
+const STALE_JOB_MINUTES: i32 = 10;
+
+/// Error of jobs whose server stopped before they finished
+const INTERRUPTED_ERROR: &str = "The server restarted before the import finished";
+
+/// Importer of a job's `source`
+pub fn importer_for(source: &str) -> Option<&'static dyn Importer> {
+    let importers: [&'static dyn Importer; 3] = [&GoodreadsImporter, &StoryGraphImporter, &LibraryThingImporter];
+    importers.into_iter().find(|importer| importer.key() == source)
+}
+
+/// Parse an export and decide what to do with each row, without writing
+/// Rows that cannot be read, or whose decision cannot be followed, come back as errors.
+pub async fn plan_import(
+    pool: &DbPool,
+    user_id: i32,
+    importer: &dyn Importer,
+    data: &[u8],
+    decisions: &HashMap<usize, RowDecision>,
+    merge: MergePolicy,
+) -> AppResult<(Vec<PlannedRow>, Vec<ImportError>)> {
+    // Pre-fetch user's existing books and readings for duplicate detection
+    let index = LibraryIndex::load(pool, user_id).await?;
+
+    let rows = importer.parse(data).map_err(AppError::Validation)?;
+
+    let mut plans = Vec::new();
+    let mut errors = Vec::new();
+
+    for row in rows {
+        let ImportRow { row_number, title, book } = row;
+
+        let planned = book.and_then(|book| {
+            let plan = index.plan(&book, decisions.get(&row_number), merge)?;
+            Ok((book, plan))
+        });
+
+        match planned {
+            Ok((book, plan)) => plans.push((row_number, book, plan)),
+            Err(error) => errors.push(ImportError {
+                row_number,
+                book_title: title,
+                error,
+            }),
+        }
+    }
+
+    Ok((plans, errors))
+}
+
+/// Carry out planned rows, each in its own transaction so one bad row does not stop the rest
+/// Every created or changed row is recorded for the job, and progress is saved as rows
+/// are done. The import stops early when the user cancels the job; the second value
+/// tells whether that happened.
+async fn apply_import(
+    pool: &DbPool,
+    user_id: i32,
+    job_id: i32,
+    plans: Vec<PlannedRow>,
+    mut errors: Vec<ImportError>,
+) -> AppResult<(ImportResponse, bool)> {
+    let mut successes = Vec::new();
+    let mut summary = ImportSummary {
+        total_rows: plans.len() + errors.len(),
+        successful_imports: 0,
+        failed_imports: 0,
+        books_created: 0,
+        books_updated: 0,
+        books_skipped: 0,
+        readings_created: 0,
+    };
+    let mut cancelled = false;
+
+    for (done, (row_number, book, plan)) in plans.into_iter().enumerate() {
+        if done % PROGRESS_INTERVAL == 0 {
+            summary.failed_imports = errors.len();
+            let processed = done + errors.len();
+            if save_progress(pool, job_id, processed, &summary).await? {
+                cancelled = true;
+                break;
+            }
+        }
+
+        let title = book.title.clone();
+
+        match process_book_import(pool, user_id, job_id, book, &plan).await {
+            Ok(BookImportResult::Success {
+                book_id,
+                book_title,
+                operation,
+                readings_created,
+            }) => {
+                match plan.action {
+                    ImportAction::Create => summary.books_created += 1,
+                    ImportAction::Update => summary.books_updated += 1,
+                    ImportAction::Skip => summary.books_skipped += 1,
+                }
+                summary.readings_created += readings_created;
+                successes.push(ImportSuccess {
+                    row_number,
+                    book_id,
+                    book_title,
+                    operation,
+                });
+            }
+            Ok(BookImportResult::Failure { book_title, error }) => {
+                errors.push(ImportError {
+                    row_number,
+                    book_title,
+                    error,
+                });
+            }
+            Err(e) => {
+                errors.push(ImportError {
+                    row_number,
+                    book_title: Some(title),
+                    error: format!("Database error: {}", e),
+                });
+            }
+        }
+    }
+
+    errors.sort_by_key(|error| error.row_number);
+
+    summary.successful_imports = successes.len();
+    summary.failed_imports = errors.len();
+
+    Ok((
+        ImportResponse {
+            job_id,
+            summary,
+            successes,
+            errors,
+        },
+        cancelled,
+    ))
+}
+
+/// Save a running job's counts; returns whether the user asked to cancel it
+async fn save_progress(
+    pool: &DbPool,
+    job_id: i32,
+    processed_rows: usize,
+    summary: &ImportSummary,
+) -> AppResult<bool> {
+    let cancel_requested = sqlx::query_scalar::<_, bool>(
+        "UPDATE import_jobs
+         SET total_rows = $2, processed_rows = $3, books_created = $4, books_updated = $5,
+             books_skipped = $6, readings_created = $7, failed_rows = $8
+         WHERE id = $1
+         RETURNING cancel_requested",
+    )
+    .bind(job_id)
+    .bind(summary.total_rows as i32)
+    .bind(processed_rows as i32)
+    .bind(summary.books_created as i32)
+    .bind(summary.books_updated as i32)
+    .bind(summary.books_skipped as i32)
+    .bind(summary.readings_created as i32)
+    .bind(summary.failed_imports as i32)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(cancel_requested)
+}
+
+/// Process a single book import in a transaction
+async fn process_book_import(
+    pool: &DbPool,
+    user_id: i32,
+    job_id: i32,
+    book: TransformedBook,
+    plan: &RowPlan,
+) -> AppResult<BookImportResult> {
+    if plan.action == ImportAction::Skip {
+        return Ok(BookImportResult::Success {
+            book_id: plan.book_id,
+            book_title: book.title,
+            operation: "skipped".to_string(),
+            readings_created: 0,
+        });
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let (book_id, operation) = match plan.book_id {
+        Some(existing_id) => {
+            // Merge into the book as it is now, keeping the values the update overwrites
+            let previous = sqlx::query_as::<_, LockedBook>(&format!(
+                "SELECT {}, updated_at FROM books WHERE id = $1 AND user_id = $2 FOR UPDATE",
+                BOOK_SNAPSHOT_COLUMNS
+            ))
+            .bind(existing_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            let merged = merge_book(&previous.snapshot, &book, plan.merge);
+            if merged != previous.snapshot {
+                let applied_at = sqlx::query_scalar::<_, DateTime<Utc>>(
+                    "UPDATE books
+                     SET title = $3, author = $4, isbn = $5, publication_year = $6, original_publication_year = $7,
+                         publisher = $8, pages = $9, language = $10, format = $11, owned_copies = $12,
+                         goodreads_id = $13, tags = $14, updated_at = CURRENT_TIMESTAMP
+                     WHERE id = $1 AND user_id = $2
+                     RETURNING updated_at",
+                )
+                .bind(existing_id)
+                .bind(user_id)
+                .bind(&merged.title)
+                .bind(&merged.author)
+                .bind(&merged.isbn)
+                .bind(merged.publication_year)
+                .bind(merged.original_publication_year)
+                .bind(&merged.publisher)
+                .bind(merged.pages)
+                .bind(&merged.language)
+                .bind(&merged.format)
+                .bind(merged.owned_copies)
+                .bind(&merged.goodreads_id)
+                .bind(&merged.tags)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                let change = Change::Updated {
+                    previous: serde_json::to_value(&previous.snapshot).map_err(|e| AppError::Internal(e.to_string()))?,
+                    previous_updated_at: previous.updated_at,
+                };
+                record_change(&mut tx, job_id, "book", existing_id, change, applied_at).await?;
+            }
+
+            (existing_id, "updated")
+        }
+        None => {
+            // Insert new book
+            let (new_id, applied_at) = sqlx::query_as::<_, (i32, DateTime<Utc>)>(
+                "INSERT INTO books (user_id, title, author, isbn, publication_year, original_publication_year, publisher,
+                                    pages, language, format, owned_copies, goodreads_id, tags)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                 RETURNING id, updated_at",
+            )
+            .bind(user_id)
+            .bind(&book.title)
+            .bind(&book.author)
+            .bind(&book.isbn)
+            .bind(book.publication_year)
+            .bind(book.original_publication_year)
+            .bind(&book.publisher)
+            .bind(book.pages)
+            .bind(&book.language)
+            .bind(&book.format)
+            .bind(book.owned_copies)
+            .bind(&book.goodreads_id)
+            .bind(&book.tags)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            record_change(&mut tx, job_id, "book", new_id, Change::Created, applied_at).await?;
+
+            (new_id, "created")
+        }
+    };
+
+    // The row's identifiers, so later imports find the book by any of them
+    for (identifier_id, applied_at) in add_identifiers(&mut tx, book_id, &row_identifiers(&book), "import").await? {
+        record_change(&mut tx, job_id, "identifier", identifier_id, Change::Created, applied_at).await?;
+    }
+
+    // Create the reading the shelf calls for, unless the book already has it
+    let mut readings_created = 0;
+    if plan.creates_reading {
+        let reading = sqlx::query_as::<_, (i32, DateTime<Utc>)>(
+            "INSERT INTO readings (user_id, book_id, start_date, end_date, rating, notes)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT DO NOTHING
+             RETURNING id, updated_at",
+        )
+        .bind(user_id)
+        .bind(book_id)
+        .bind(book.start_date)
+        .bind(book.end_date.filter(|_| book.shelf.eq_ignore_ascii_case("read")))
+        .bind(book.rating)
+        .bind(&book.notes)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some((reading_id, applied_at)) = reading {
+            record_change(&mut tx, job_id, "reading", reading_id, Change::Created, applied_at).await?;
+            readings_created += 1;
+        }
+    }
+
+    // Earlier finished readings the read count tells of
+    if let Some(date) = extra_reading_date(&book) {
+        for _ in 0..plan.extra_readings {
+            let (reading_id, applied_at) = sqlx::query_as::<_, (i32, DateTime<Utc>)>(
+                "INSERT INTO readings (user_id, book_id, start_date, end_date, notes)
+                 VALUES ($1, $2, $3, $3, $4)
+                 RETURNING id, updated_at",
+            )
+            .bind(user_id)
+            .bind(book_id)
+            .bind(date)
+            .bind(EXTRA_READING_NOTES)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            record_change(&mut tx, job_id, "reading", reading_id, Change::Created, applied_at).await?;
+            readings_created += 1;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(BookImportResult::Success {
+        book_id: Some(book_id),
+        book_title: book.title.clone(),
+        operation: operation.to_string(),
+        readings_created,
+    })
+}
+
+/// A book about to be updated, as it is before the update
+#[derive(sqlx::FromRow)]
+struct LockedBook {
+    #[sqlx(flatten)]
+    snapshot: BookSnapshot,
+    updated_at: DateTime<Utc>,
+}
+
+/// How an import touched a row
+enum Change {
+    Created,
+    /// The overwritten values and when the row was last changed before
+    Updated {
+        previous: serde_json::Value,
+        previous_updated_at: DateTime<Utc>,
+    },
+}
+
+/// Remember a row the job created or changed
+/// A row changed twice keeps the values from before the first change.
+async fn record_change(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    job_id: i32,
+    entity: &str,
+    entity_id: i32,
+    change: Change,
+    applied_at: DateTime<Utc>,
+) -> AppResult<()> {
+    let (operation, previous, previous_updated_at) = match change {
+        Change::Created => ("created", None, None),
+        Change::Updated {
+            previous,
+            previous_updated_at,
+        } => ("updated", Some(previous), Some(previous_updated_at)),
+    };
+
+    sqlx::query(
+        "INSERT INTO import_job_changes (job_id, entity, entity_id, operation, previous, previous_updated_at, applied_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT ON CONSTRAINT import_change_unique DO UPDATE SET applied_at = EXCLUDED.applied_at",
+    )
+    .bind(job_id)
+    .bind(entity)
+    .bind(entity_id)
+    .bind(operation)
+    .bind(previous)
+    .bind(previous_updated_at)
+    .bind(applied_at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a new job for the user, run by the server instance `instance_id`
+/// Fails with a conflict while another import of the user is still queued or running.
+pub async fn create_job(
+    pool: &DbPool,
+    instance_id: &str,
+    user_id: i32,
+    source: &str,
+    file_name: Option<&str>,
+) -> AppResult<ImportJob> {
+    // A job whose server went away would otherwise block the user's imports until it restarts
+    sqlx::query(
+        "UPDATE import_jobs SET status = 'failed', error = $2, finished_at = NOW()
+         WHERE user_id = $1 AND status IN ('queued', 'running') AND updated_at < NOW() - make_interval(mins => $3)",
+    )
+    .bind(user_id)
+    .bind(INTERRUPTED_ERROR)
+    .bind(STALE_JOB_MINUTES)
+    .execute(pool)
+    .await?;
+
+    sqlx::query_as::<_, ImportJob>(&format!(
+        "INSERT INTO import_jobs (user_id, source, file_name, instance_id) VALUES ($1, $2, $3, $4) RETURNING {}",
+        IMPORT_JOB_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(source)
+    .bind(file_name)
+    .bind(instance_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| match &e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("idx_import_jobs_one_unfinished") => {
+            AppError::Conflict("Another import is still running".to_string())
+        }
+        _ => AppError::Database(e),
+    })
+}
+
+/// Import planned rows as part of a new job, waiting for the result
+/// The job is marked failed if the import stops on an error.
+pub async fn run_planned_import(
+    pool: &DbPool,
+    job: &ImportJob,
+    plans: Vec<PlannedRow>,
+    errors: Vec<ImportError>,
+) -> AppResult<ImportResponse> {
+    let result = async {
+        if !mark_running(pool, job.id).await? {
+            return Err(AppError::Conflict("The import was cancelled".to_string()));
+        }
+        import_and_finish(pool, job, plans, errors).await
+    }
+    .await;
+
+    if let Err(e) = &result {
+        fail_job(pool, job, e).await;
+    }
+
+    result
+}
+
+/// Run a job in the background; its row in `import_jobs` tells how it is going
+pub fn spawn_import_job(
+    pool: DbPool,
+    job: ImportJob,
+    importer: &'static dyn Importer,
+    data: Vec<u8>,
+    decisions: HashMap<usize, RowDecision>,
+    merge: MergePolicy,
+) {
+    tokio::spawn(async move {
+        if let Err(e) = run_import_job(&pool, &job, importer, &data, &decisions, merge).await {
+            fail_job(&pool, &job, &e).await;
+        }
+    });
+}
+
+async fn run_import_job(
+    pool: &DbPool,
+    job: &ImportJob,
+    importer: &dyn Importer,
+    data: &[u8],
+    decisions: &HashMap<usize, RowDecision>,
+    merge: MergePolicy,
+) -> AppResult<()> {
+    // A job cancelled before it started ends right away
+    if !mark_running(pool, job.id).await? {
+        sqlx::query(
+            "UPDATE import_jobs SET status = 'cancelled', finished_at = NOW() WHERE id = $1 AND status = 'queued'",
+        )
+        .bind(job.id)
+        .execute(pool)
+        .await?;
+        return Ok(());
+    }
+
+    let (plans, errors) = plan_import(pool, job.user_id, importer, data, decisions, merge).await?;
+    import_and_finish(pool, job, plans, errors).await?;
+
+    Ok(())
+}
+
+/// Move a queued job to running; false when it was cancelled first
+async fn mark_running(pool: &DbPool, job_id: i32) -> AppResult<bool> {
+    let started = sqlx::query_scalar::<_, i32>(
+        "UPDATE import_jobs SET status = 'running', started_at = NOW()
+         WHERE id = $1 AND status = 'queued' AND NOT cancel_requested
+         RETURNING id",
+    )
+    .bind(job_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(started.is_some())
+}
+
+async fn import_and_finish(
+    pool: &DbPool,
+    job: &ImportJob,
+    plans: Vec<PlannedRow>,
+    errors: Vec<ImportError>,
+) -> AppResult<ImportResponse> {
+    let (response, cancelled) = apply_import(pool, job.user_id, job.id, plans, errors).await?;
+    let summary = &response.summary;
+    let processed_rows = summary.successful_imports + summary.failed_imports;
+    let status = if cancelled { "cancelled" } else { "completed" };
+
+    tracing::info!(
+        "Import job {} of user {} ({}) {}: {} created, {} updated, {} skipped, {} failed",
+        job.id,
+        job.user_id,
+        job.source,
+        status,
+        summary.books_created,
+        summary.books_updated,
+        summary.books_skipped,
+        summary.failed_imports
+    );
+
+    let result = serde_json::to_value(&response).map_err(|e| AppError::Internal(e.to_string()))?;
+
+    sqlx::query(
+        "UPDATE import_jobs
+         SET status = $2, total_rows = $3, processed_rows = $4, books_created = $5, books_updated = $6,
+             books_skipped = $7, readings_created = $8, failed_rows = $9, result = $10, finished_at = NOW()
+         WHERE id = $1",
+    )
+    .bind(job.id)
+    .bind(status)
+    .bind(summary.total_rows as i32)
+    .bind(processed_rows as i32)
+    .bind(summary.books_created as i32)
+    .bind(summary.books_updated as i32)
+    .bind(summary.books_skipped as i32)
+    .bind(summary.readings_created as i32)
+    .bind(summary.failed_imports as i32)
+    .bind(result)
+    .execute(pool)
+    .await?;
+
+    Ok(response)
+}
+
+async fn fail_job(pool: &DbPool, job: &ImportJob, error: &AppError) {
+    let message = match error {
+        AppError::Validation(message) | AppError::Conflict(message) => message.clone(),
+        _ => "The import stopped because of a server error".to_string(),
+    };
+    tracing::warn!("Import job {} of user {} failed: {}", job.id, job.user_id, error);
+
+    let _ = sqlx::query(
+        "UPDATE import_jobs SET status = 'failed', error = $2, finished_at = NOW()
+         WHERE id = $1 AND status IN ('queued', 'running')",
+    )
+    .bind(job.id)
+    .bind(message)
+    .execute(pool)
+    .await;
+}
+
+/// Mark jobs that were queued or running when the server stopped as failed
+/// Uploads are only kept in memory, so these jobs cannot be resumed. Only jobs of
+/// this instance are failed, along with stale ones of instances that went away;
+/// other replicas may still be running theirs.
+pub async fn fail_interrupted_jobs(pool: &DbPool, instance_id: &str) -> AppResult<u64> {
+    let result = sqlx::query(
+        "UPDATE import_jobs
+         SET status = 'failed', error = $2, finished_at = NOW()
+         WHERE status IN ('queued', 'running')
+           AND (instance_id = $1 OR updated_at < NOW() - make_interval(mins => $3))",
+    )
+    .bind(instance_id)
+    .bind(INTERRUPTED_ERROR)
+    .bind(STALE_JOB_MINUTES)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// A change recorded by an import
+#[derive(sqlx::FromRow)]
+struct RecordedChange {
+    entity: String,
+    entity_id: i32,
+    operation: String,
+    previous: Option<serde_json::Value>,
+    previous_updated_at: Option<DateTime<Utc>>,
+    applied_at: DateTime<Utc>,
+}
+
+/// Undo a finished import: delete the books, readings and identifiers it created and restore the
+/// values it overwrote
+/// Rows edited since the import, and created books that have gained readings or
+/// highlights, are left alone and reported as conflicts.
+pub async fn revert_import_job(pool: &DbPool, job: &ImportJob) -> AppResult<RevertSummary> {
+    let mut tx = pool.begin().await?;
+
+    // Lock the job so two reverts cannot run at once
+    let status = sqlx::query_scalar::<_, String>("SELECT status FROM import_jobs WHERE id = $1 FOR UPDATE")
+        .bind(job.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    match status.as_str() {
+        "queued" | "running" => {
+            return Err(AppError::Conflict("The import is still running".to_string()));
+        }
+        "reverted" => {
+            return Err(AppError::Conflict("The import has already been reverted".to_string()));
+        }
+        _ => {}
+    }
+
+    let changes = sqlx::query_as::<_, RecordedChange>(
+        "SELECT entity, entity_id, operation, previous, previous_updated_at, applied_at
+         FROM import_job_changes WHERE job_id = $1
+         ORDER BY id DESC",
+    )
+    .bind(job.id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut summary = RevertSummary::default();
+
+    for change in changes.iter().filter(|c| c.entity == "identifier") {
+        sqlx::query("DELETE FROM book_identifiers WHERE id = $1")
+            .bind(change.entity_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Readings first, so created books no longer have the readings of this import
+    for change in changes.iter().filter(|c| c.entity == "reading") {
+        let deleted = sqlx::query("DELETE FROM readings WHERE id = $1 AND updated_at = $2")
+            .bind(change.entity_id)
+            .bind(change.applied_at)
+            .execute(&mut *tx)
+            .await?;
+
+        if deleted.rows_affected() > 0 {
+            summary.readings_deleted += 1;
+        } else if let Some(title) = sqlx::query_scalar::<_, String>(
+            "SELECT b.title FROM readings r JOIN books b ON r.book_id = b.id WHERE r.id = $1",
+        )
+        .bind(change.entity_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            summary.conflicts.push(RevertConflict {
+                entity: "reading",
+                entity_id: change.entity_id,
+                title,
+                reason: "The reading was edited after the import".to_string(),
+            });
+        }
+    }
+
+    for change in changes.iter().filter(|c| c.entity == "book") {
+        let done = match (change.operation.as_str(), &change.previous) {
+            ("updated", Some(previous)) => {
+                let previous: BookSnapshot = serde_json::from_value(previous.clone())
+                    .map_err(|e| AppError::Internal(format!("Invalid import change record: {}", e)))?;
+                let restored = sqlx::query_scalar::<_, DateTime<Utc>>(
+                    "UPDATE books
+                     SET title = $3, author = $4, isbn = $5, publication_year = $6, original_publication_year = $7,
+                         publisher = $8, pages = $9, language = $10, format = $11, owned_copies = $12,
+                         goodreads_id = $13, tags = $14
+                     WHERE id = $1 AND updated_at = $2
+                     RETURNING updated_at",
+                )
+                .bind(change.entity_id)
+                .bind(change.applied_at)
+                .bind(&previous.title)
+                .bind(&previous.author)
+                .bind(&previous.isbn)
+                .bind(previous.publication_year)
+                .bind(previous.original_publication_year)
+                .bind(&previous.publisher)
+                .bind(previous.pages)
+                .bind(&previous.language)
+                .bind(&previous.format)
+                .bind(previous.owned_copies)
+                .bind(&previous.goodreads_id)
+                .bind(&previous.tags)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                if let (Some(restored_at), Some(previous_updated_at)) = (restored, change.previous_updated_at) {
+                    // The book is back as an earlier import left it, so that import can still be reverted
+                    sqlx::query(
+                        "UPDATE import_job_changes SET applied_at = $3
+                         WHERE entity = 'book' AND entity_id = $1 AND applied_at = $2 AND job_id <> $4",
+                    )
+                    .bind(change.entity_id)
+                    .bind(previous_updated_at)
+                    .bind(restored_at)
+                    .bind(job.id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                summary.books_restored += restored.is_some() as usize;
+                restored.is_some()
+            }
+            _ => {
+                let deleted = sqlx::query(
+                    "DELETE FROM books b
+                     WHERE id = $1 AND updated_at = $2
+                       AND NOT EXISTS (SELECT 1 FROM readings WHERE book_id = b.id)
+                       AND NOT EXISTS (SELECT 1 FROM highlights WHERE book_id = b.id)",
+                )
+                .bind(change.entity_id)
+                .bind(change.applied_at)
+                .execute(&mut *tx)
+                .await?;
+                summary.books_deleted += deleted.rows_affected() as usize;
+                deleted.rows_affected() > 0
+            }
+        };
+
+        if done {
+            continue;
+        }
+
+        // Still there, so it changed after the import (a deleted book needs no reverting)
+        let current = sqlx::query_as::<_, (String, DateTime<Utc>)>("SELECT title, updated_at FROM books WHERE id = $1")
+            .bind(change.entity_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if let Some((title, updated_at)) = current {
+            let reason = if updated_at != change.applied_at {
+                "The book was edited after the import"
+            } else {
+                "The book still has readings or highlights"
+            };
+            summary.conflicts.push(RevertConflict {
+                entity: "book",
+                entity_id: change.entity_id,
+                title,
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    sqlx::query("UPDATE import_jobs SET status = 'reverted', reverted_at = NOW() WHERE id = $1")
+        .bind(job.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        "User {} reverted import job {}: {} books deleted, {} restored, {} readings deleted, {} conflicts",
+        job.user_id,
+        job.id,
+        summary.books_deleted,
+        summary.books_restored,
+        summary.readings_deleted,
+        summary.conflicts.len()
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Columns of the Goodreads rows below
+    const HEADER: &str = "Book Id,Title,Author,ISBN13,My Rating,Publisher,Number of Pages,Date Read,Date Added,Exclusive Shelf\n";
+
+    async fn create_user(pool: &DbPool) -> i32 {
+        create_named_user(pool, "reader").await
+    }
+
+    async fn create_named_user(pool: &DbPool, username: &str) -> i32 {
+        sqlx::query_scalar("INSERT INTO users (username, email, password_hash) VALUES ($1, $1 || '@example.com', '') RETURNING id")
+            .bind(username)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    /// `count` to-read books, one per row
+    fn to_read_rows(count: usize) -> String {
+        (1..=count)
+            .map(|n| format!("{},Book {},Frank Herbert,,0,,,,,to-read\n", n, n))
+            .collect()
+    }
+
+    /// Run a queued job the way a background import does
+    async fn run_job(pool: &DbPool, job: &ImportJob, importer: &dyn Importer, data: &[u8]) -> ImportJob {
+        if let Err(e) = run_import_job(pool, job, importer, data, &HashMap::new(), MergePolicy::FillEmpty).await {
+            fail_job(pool, job, &e).await;
+        }
+        load_job(pool, job.id).await
+    }
+
+    async fn load_job(pool: &DbPool, job_id: i32) -> ImportJob {
+        sqlx::query_as(&format!("SELECT {} FROM import_jobs WHERE id = $1", IMPORT_JOB_COLUMNS))
+            .bind(job_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Make unfinished jobs look as if their server stopped an hour ago
+    async fn age_jobs(pool: &DbPool) {
+        sqlx::query("ALTER TABLE import_jobs DISABLE TRIGGER update_import_jobs_updated_at")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE import_jobs SET updated_at = NOW() - INTERVAL '1 hour' WHERE status IN ('queued', 'running')")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("ALTER TABLE import_jobs ENABLE TRIGGER update_import_jobs_updated_at")
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn create_book(pool: &DbPool, user_id: i32, title: &str, isbn: &str) -> i32 {
+        sqlx::query_scalar("INSERT INTO books (user_id, title, author, isbn) VALUES ($1, $2, 'Frank Herbert', $3) RETURNING id")
+            .bind(user_id)
+            .bind(title)
+            .bind(isbn)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Import Goodreads rows as a job, the way the synchronous import endpoint does
+    async fn import(pool: &DbPool, user_id: i32, rows: &str) -> ImportJob {
+        let job = create_job(pool, "test", user_id, "goodreads", None).await.unwrap();
+        let data = format!("{}{}", HEADER, rows);
+        let (plans, errors) =
+            plan_import(pool, user_id, &GoodreadsImporter, data.as_bytes(), &HashMap::new(), MergePolicy::FillEmpty)
+                .await
+                .unwrap();
+        assert!(errors.is_empty());
+        run_planned_import(pool, &job, plans, errors).await.unwrap();
+        job
+    }
+
+    async fn count(pool: &DbPool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn publisher_and_pages(pool: &DbPool, book_id: i32) -> (Option<String>, Option<i32>) {
+        sqlx::query_as("SELECT publisher, pages FROM books WHERE id = $1")
+            .bind(book_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_importer_for() {
+        assert_eq!(importer_for("goodreads").map(|i| i.source()), Some("Goodreads"));
+        assert_eq!(importer_for("storygraph").map(|i| i.source()), Some("StoryGraph"));
+        assert_eq!(importer_for("librarything").map(|i| i.source()), Some("LibraryThing"));
+        assert!(importer_for("calibre").is_none());
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_revert_deletes_created_rows(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = import(&pool, user_id, "1,Dune,Frank Herbert,9780441013593,5,,,2024/05/20,2024/05/01,read\n").await;
+        assert_eq!((count(&pool, "books").await, count(&pool, "readings").await), (1, 1));
+        assert!(count(&pool, "book_identifiers").await > 0);
+
+        let summary = revert_import_job(&pool, &job).await.unwrap();
+        assert_eq!((summary.books_deleted, summary.readings_deleted), (1, 1));
+        assert!(summary.conflicts.is_empty());
+        assert_eq!(count(&pool, "books").await, 0);
+        assert_eq!(count(&pool, "readings").await, 0);
+        assert_eq!(count(&pool, "book_identifiers").await, 0);
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_revert_restores_updated_book(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let book_id = create_book(&pool, user_id, "Dune", "9780441013593").await;
+        let job = import(&pool, user_id, "1,Dune,Frank Herbert,9780441013593,0,\"Chilton, Inc.\",412,,,to-read\n").await;
+        assert_eq!(publisher_and_pages(&pool, book_id).await, (Some("Chilton, Inc.".to_string()), Some(412)));
+
+        let summary = revert_import_job(&pool, &job).await.unwrap();
+        assert_eq!((summary.books_restored, summary.books_deleted), (1, 0));
+        assert!(summary.conflicts.is_empty());
+        assert_eq!(publisher_and_pages(&pool, book_id).await, (None, None));
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_revert_leaves_books_edited_after_import(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = import(&pool, user_id, "1,Dune,Frank Herbert,9780441013593,0,,,,,to-read\n").await;
+        sqlx::query("UPDATE books SET title = 'Dune (annotated)'").execute(&pool).await.unwrap();
+
+        let summary = revert_import_job(&pool, &job).await.unwrap();
+        assert_eq!(summary.books_deleted, 0);
+        assert_eq!(summary.conflicts.len(), 1);
+        assert_eq!(summary.conflicts[0].entity, "book");
+        assert_eq!(summary.conflicts[0].title, "Dune (annotated)");
+        assert_eq!(summary.conflicts[0].reason, "The book was edited after the import");
+        assert_eq!(count(&pool, "books").await, 1);
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_revert_keeps_created_book_with_new_reading(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = import(&pool, user_id, "1,Dune,Frank Herbert,9780441013593,0,,,,,to-read\n").await;
+        sqlx::query("INSERT INTO readings (user_id, book_id, start_date) SELECT user_id, id, '2025-01-01' FROM books")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let summary = revert_import_job(&pool, &job).await.unwrap();
+        assert_eq!(summary.books_deleted, 0);
+        assert_eq!(summary.conflicts.len(), 1);
+        assert_eq!(summary.conflicts[0].reason, "The book still has readings or highlights");
+        assert_eq!((count(&pool, "books").await, count(&pool, "readings").await), (1, 1));
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_revert_twice(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = import(&pool, user_id, "1,Dune,Frank Herbert,9780441013593,0,,,,,to-read\n").await;
+
+        revert_import_job(&pool, &job).await.unwrap();
+        assert!(matches!(revert_import_job(&pool, &job).await, Err(AppError::Conflict(_))));
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_revert_hands_book_back_to_earlier_import(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let book_id = create_book(&pool, user_id, "Dune", "9780441013593").await;
+        let first = import(&pool, user_id, "1,Dune,Frank Herbert,9780441013593,0,\"Chilton, Inc.\",,,,to-read\n").await;
+        let second = import(&pool, user_id, "1,Dune,Frank Herbert,9780441013593,0,,412,,,to-read\n").await;
+        assert_eq!(publisher_and_pages(&pool, book_id).await, (Some("Chilton, Inc.".to_string()), Some(412)));
+
+        // Back to how the first import left it...
+        let summary = revert_import_job(&pool, &second).await.unwrap();
+        assert_eq!((summary.books_restored, summary.conflicts.len()), (1, 0));
+        assert_eq!(publisher_and_pages(&pool, book_id).await, (Some("Chilton, Inc.".to_string()), None));
+
+        // ...which can then be reverted too
+        let summary = revert_import_job(&pool, &first).await.unwrap();
+        assert_eq!((summary.books_restored, summary.conflicts.len()), (1, 0));
+        assert_eq!(publisher_and_pages(&pool, book_id).await, (None, None));
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_job_runs_to_completion(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = create_job(&pool, "test", user_id, "goodreads", Some("export.csv")).await.unwrap();
+        assert_eq!((job.status.as_str(), job.started_at), ("queued", None));
+
+        let data = format!("{}{}", HEADER, to_read_rows(3));
+        let job = run_job(&pool, &job, &GoodreadsImporter, data.as_bytes()).await;
+        assert_eq!(job.status, "completed");
+        assert!(job.started_at.is_some() && job.finished_at.is_some());
+        assert_eq!((job.total_rows, job.processed_rows, job.books_created), (3, 3, 3));
+        assert_eq!(count(&pool, "books").await, 3);
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_job_cancelled_before_start(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = create_job(&pool, "test", user_id, "goodreads", None).await.unwrap();
+        sqlx::query("UPDATE import_jobs SET cancel_requested = TRUE").execute(&pool).await.unwrap();
+
+        let data = format!("{}{}", HEADER, to_read_rows(3));
+        let job = run_job(&pool, &job, &GoodreadsImporter, data.as_bytes()).await;
+        assert_eq!((job.status.as_str(), job.started_at), ("cancelled", None));
+        assert!(job.finished_at.is_some());
+        assert_eq!(count(&pool, "books").await, 0);
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_job_cancelled_while_running(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = create_job(&pool, "test", user_id, "goodreads", None).await.unwrap();
+        // The user cancels as soon as the first book is in
+        sqlx::query(
+            "CREATE FUNCTION cancel_imports() RETURNS TRIGGER AS $$
+             BEGIN UPDATE import_jobs SET cancel_requested = TRUE; RETURN NEW; END
+             $$ LANGUAGE plpgsql",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("CREATE TRIGGER cancel_imports AFTER INSERT ON books FOR EACH ROW EXECUTE FUNCTION cancel_imports()")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // The job only notices when it next saves its progress
+        let data = format!("{}{}", HEADER, to_read_rows(PROGRESS_INTERVAL + 5));
+        let job = run_job(&pool, &job, &GoodreadsImporter, data.as_bytes()).await;
+        assert_eq!(job.status, "cancelled");
+        assert_eq!(job.total_rows as usize, PROGRESS_INTERVAL + 5);
+        assert_eq!((job.processed_rows as usize, job.books_created as usize), (PROGRESS_INTERVAL, PROGRESS_INTERVAL));
+        assert_eq!(count(&pool, "books").await as usize, PROGRESS_INTERVAL);
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_job_failed(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let job = create_job(&pool, "test", user_id, "librarything", None).await.unwrap();
+
+        let job = run_job(&pool, &job, &LibraryThingImporter, b"\xc3\x28").await;
+        assert_eq!(job.status, "failed");
+        assert_eq!(job.error.as_deref(), Some("File is not valid UTF-8"));
+        assert!(job.finished_at.is_some());
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_one_unfinished_job_per_user(pool: DbPool) {
+        let user_id = create_user(&pool).await;
+        let first = create_job(&pool, "test", user_id, "goodreads", None).await.unwrap();
+        assert!(matches!(
+            create_job(&pool, "test", user_id, "goodreads", None).await,
+            Err(AppError::Conflict(_))
+        ));
+
+        // Other users are not held up
+        let other_user = create_named_user(&pool, "other").await;
+        create_job(&pool, "test", other_user, "goodreads", None).await.unwrap();
+
+        // Nor is the user once the first job's server has gone quiet
+        age_jobs(&pool).await;
+        create_job(&pool, "test", user_id, "goodreads", None).await.unwrap();
+        assert_eq!(load_job(&pool, first.id).await.status, "failed");
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_fail_interrupted_jobs_of_this_instance(pool: DbPool) {
+        let ours = create_job(&pool, "a", create_named_user(&pool, "a").await, "goodreads", None).await.unwrap();
+        let theirs = create_job(&pool, "b", create_named_user(&pool, "b").await, "goodreads", None).await.unwrap();
+
+        assert_eq!(fail_interrupted_jobs(&pool, "a").await.unwrap(), 1);
+        let ours = load_job(&pool, ours.id).await;
+        assert_eq!((ours.status.as_str(), ours.error.as_deref()), ("failed", Some(INTERRUPTED_ERROR)));
+        assert_eq!(load_job(&pool, theirs.id).await.status, "queued");
+
+        // Instance b went away without coming back
+        age_jobs(&pool).await;
+        assert_eq!(fail_interrupted_jobs(&pool, "a").await.unwrap(), 1);
+        assert_eq!(load_job(&pool, theirs.id).await.status, "failed");
+    }
+}
@@ -0,0 +1,566 @@
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    db::DbPool,
+    errors::AppResult,
+    models::{
+        identifier::{isbn_identifier, IdentifierType},
+        import::{FieldChange, ImportAction, MatchedBy, MergePolicy, RowDecision, TransformedBook},
+        import_job::{BookSnapshot, BOOK_SNAPSHOT_COLUMNS},
+    },
+    services::book_identifiers::load_user_identifiers,
+};
+
+/// Notes of the readings added to match a read count
+pub const EXTRA_READING_NOTES: &str = "Added from the read count; the dates of this reading are not known";
+
+/// A book of the library as an import compares it
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ExistingBook {
+    pub id: i32,
+    #[sqlx(flatten)]
+    pub fields: BookSnapshot,
+}
+
+/// The user's books and readings, loaded once per import for matching
+#[derive(Debug, Default)]
+pub struct LibraryIndex {
+    books: HashMap<i32, ExistingBook>,
+    by_identifier: HashMap<(IdentifierType, String), i32>,
+    by_title_author: HashMap<(String, String), i32>,
+    /// (book id, start date) of every reading
+    readings: HashSet<(i32, NaiveDate)>,
+    /// Books with a reading in progress; only one is allowed per book
+    open_readings: HashSet<i32>,
+    /// Finished readings per book
+    finished_readings: HashMap<i32, usize>,
+}
+
+/// What importing one row will do
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowPlan {
+    pub action: ImportAction,
+    /// Book to update; None when creating or skipping a new book
+    pub book_id: Option<i32>,
+    pub matched_by: Option<MatchedBy>,
+    /// How an update merges the row into the book
+    pub merge: MergePolicy,
+    pub changes: Vec<FieldChange>,
+    pub creates_reading: bool,
+    /// Finished readings to add so the book has as many as the read count
+    pub extra_readings: usize,
+}
+
+impl LibraryIndex {
+    pub async fn load(pool: &DbPool, user_id: i32) -> AppResult<Self> {
+        let books = sqlx::query_as::<_, ExistingBook>(&format!(
+            "SELECT id, {} FROM books WHERE user_id = $1",
+            BOOK_SNAPSHOT_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let readings = sqlx::query_as::<_, (i32, NaiveDate, Option<NaiveDate>)>(
+            "SELECT book_id, start_date, end_date FROM readings WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut index = Self::default();
+        for book in books {
+            index.add_book(book);
+        }
+        for (book_id, id_type, value) in load_user_identifiers(pool, user_id).await? {
+            index.by_identifier.entry((id_type, value)).or_insert(book_id);
+        }
+        for (book_id, start_date, end_date) in readings {
+            index.readings.insert((book_id, start_date));
+            if end_date.is_none() {
+                index.open_readings.insert(book_id);
+            } else {
+                *index.finished_readings.entry(book_id).or_default() += 1;
+            }
+        }
+
+        Ok(index)
+    }
+
+    fn add_book(&mut self, book: ExistingBook) {
+        let derived = [
+            book.fields.isbn.as_deref().and_then(isbn_identifier),
+            book.fields.goodreads_id.clone().map(|id| (IdentifierType::Goodreads, id)),
+        ];
+        for identifier in derived.into_iter().flatten() {
+            self.by_identifier.entry(identifier).or_insert(book.id);
+        }
+        self.by_title_author
+            .insert(title_author_key(&book.fields.title, book.fields.author.as_deref()), book.id);
+        self.books.insert(book.id, book);
+    }
+
+    /// Book the row is a duplicate of: same Goodreads ID or ISBN first, then any
+    /// other identifier in common, then same title and author
+    pub fn find_duplicate(&self, book: &TransformedBook) -> Option<(i32, MatchedBy)> {
+        let by_identifier = row_identifiers(book).into_iter().find_map(|identifier| {
+            let matched_by = match identifier.0 {
+                IdentifierType::Isbn10 | IdentifierType::Isbn13 => MatchedBy::Isbn,
+                _ => MatchedBy::Identifier,
+            };
+            self.by_identifier.get(&identifier).map(|&book_id| (book_id, matched_by))
+        });
+        if by_identifier.is_some() {
+            return by_identifier;
+        }
+
+        self.by_title_author
+            .get(&title_author_key(&book.title, book.author.as_deref()))
+            .map(|&book_id| (book_id, MatchedBy::TitleAuthor))
+    }
+
+    /// Decide what to do with a row, following the user's decision when there is one
+    /// Without a decision a match is updated, or skipped when the import adds nothing.
+    pub fn plan(
+        &self,
+        book: &TransformedBook,
+        decision: Option<&RowDecision>,
+        merge: MergePolicy,
+    ) -> Result<RowPlan, String> {
+        let target = match decision {
+            Some(RowDecision { action: ImportAction::Create, .. }) => None,
+            Some(RowDecision { action: ImportAction::Update, book_id: Some(book_id), .. }) => {
+                if !self.books.contains_key(book_id) {
+                    return Err(format!("Book {} not found in your library", book_id));
+                }
+                Some((*book_id, MatchedBy::User))
+            }
+            Some(RowDecision { action: ImportAction::Update, book_id: None, .. }) => {
+                return Err("An update decision needs a book_id".to_string());
+            }
+            Some(RowDecision { action: ImportAction::Skip, .. }) | None => self.find_duplicate(book),
+        };
+
+        let Some((book_id, matched_by)) = target else {
+            let action = match decision {
+                Some(RowDecision { action: ImportAction::Skip, .. }) => ImportAction::Skip,
+                _ => ImportAction::Create,
+            };
+            let creates_reading = action == ImportAction::Create && wants_reading(book);
+            return Ok(RowPlan {
+                action,
+                book_id: None,
+                matched_by: None,
+                merge,
+                changes: Vec::new(),
+                creates_reading,
+                extra_readings: if action == ImportAction::Create {
+                    extra_readings(book, 0, creates_reading)
+                } else {
+                    0
+                },
+            });
+        };
+
+        let existing = &self.books[&book_id].fields;
+        let changes = book_changes(existing, &merge_book(existing, book, merge));
+        let creates_reading = wants_reading(book) && !self.has_reading(book_id, book);
+        let finished = self.finished_readings.get(&book_id).copied().unwrap_or_default();
+        let extra = extra_readings(book, finished, creates_reading);
+        let action = match decision.map(|d| d.action) {
+            Some(action) => action,
+            None if changes.is_empty() && !creates_reading && extra == 0 => ImportAction::Skip,
+            None => ImportAction::Update,
+        };
+        let skipped = action == ImportAction::Skip;
+
+        Ok(RowPlan {
+            action,
+            book_id: Some(book_id),
+            matched_by: Some(matched_by),
+            merge,
+            changes,
+            creates_reading: creates_reading && !skipped,
+            extra_readings: if skipped { 0 } else { extra },
+        })
+    }
+
+    fn has_reading(&self, book_id: i32, book: &TransformedBook) -> bool {
+        let same_start = book
+            .start_date
+            .is_some_and(|start| self.readings.contains(&(book_id, start)));
+        let open = book.shelf.eq_ignore_ascii_case("currently-reading") && self.open_readings.contains(&book_id);
+        same_start || open
+    }
+}
+
+/// Every identifier of a row, most specific first
+pub fn row_identifiers(book: &TransformedBook) -> Vec<(IdentifierType, String)> {
+    let mut identifiers = Vec::new();
+    identifiers.extend(
+        book.goodreads_id
+            .as_deref()
+            .and_then(|id| IdentifierType::Goodreads.normalize(id).ok())
+            .map(|id| (IdentifierType::Goodreads, id)),
+    );
+    identifiers.extend(book.isbn.as_deref().and_then(isbn_identifier));
+    for identifier in &book.identifiers {
+        if !identifiers.contains(identifier) {
+            identifiers.push(identifier.clone());
+        }
+    }
+    identifiers
+}
+
+/// Whether the row's shelf and dates call for a reading
+pub fn wants_reading(book: &TransformedBook) -> bool {
+    match book.shelf.to_lowercase().as_str() {
+        "read" => book.start_date.is_some() && book.end_date.is_some(),
+        "currently-reading" => book.start_date.is_some(),
+        _ => false,
+    }
+}
+
+/// Date of the readings added for a read count
+/// Sites only export the dates of the latest reading, so earlier ones get its first
+/// known date; rows without any date get none.
+pub fn extra_reading_date(book: &TransformedBook) -> Option<NaiveDate> {
+    book.start_date.or(book.end_date)
+}
+
+/// Finished readings to add so a book with `finished` of them reaches the row's read count
+fn extra_readings(book: &TransformedBook, finished: usize, creates_reading: bool) -> usize {
+    let (Some(read_count), Some(_)) = (book.read_count, extra_reading_date(book)) else {
+        return 0;
+    };
+    let creates_finished = creates_reading && book.shelf.eq_ignore_ascii_case("read");
+    (read_count.max(0) as usize).saturating_sub(finished + creates_finished as usize)
+}
+
+/// The fields of `existing` after merging in an imported row
+/// Imported empty values never clear a field. The Goodreads ID is only filled in,
+/// and shelves are added to the tags unless local values are kept.
+pub fn merge_book(existing: &BookSnapshot, book: &TransformedBook, policy: MergePolicy) -> BookSnapshot {
+    fn pick<T: Clone>(local: &Option<T>, imported: &Option<T>, policy: MergePolicy) -> Option<T> {
+        match policy {
+            MergePolicy::FillEmpty => local.clone().or_else(|| imported.clone()),
+            MergePolicy::Overwrite => imported.clone().or_else(|| local.clone()),
+            MergePolicy::KeepLocal => local.clone(),
+        }
+    }
+
+    let mut tags = existing.tags.clone();
+    if policy != MergePolicy::KeepLocal {
+        for tag in &book.tags {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+
+    BookSnapshot {
+        title: match policy {
+            MergePolicy::Overwrite => book.title.clone(),
+            _ => existing.title.clone(),
+        },
+        author: pick(&existing.author, &book.author, policy),
+        isbn: pick(&existing.isbn, &book.isbn, policy),
+        publication_year: pick(&existing.publication_year, &book.publication_year, policy),
+        original_publication_year: pick(&existing.original_publication_year, &book.original_publication_year, policy),
+        publisher: pick(&existing.publisher, &book.publisher, policy),
+        pages: pick(&existing.pages, &book.pages, policy),
+        language: pick(&existing.language, &book.language, policy),
+        format: pick(&existing.format, &book.format, policy),
+        owned_copies: pick(&existing.owned_copies, &book.owned_copies, policy),
+        goodreads_id: existing.goodreads_id.clone().or_else(|| book.goodreads_id.clone()),
+        tags,
+    }
+}
+
+/// Fields that differ between a book and its merged version
+pub fn book_changes(existing: &BookSnapshot, merged: &BookSnapshot) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    let mut compare = |field: &'static str, current: Value, proposed: Value| {
+        if current != proposed {
+            changes.push(FieldChange { field, current, proposed });
+        }
+    };
+
+    compare("title", json!(existing.title), json!(merged.title));
+    compare("author", json!(existing.author), json!(merged.author));
+    compare("isbn", json!(existing.isbn), json!(merged.isbn));
+    compare("publication_year", json!(existing.publication_year), json!(merged.publication_year));
+    compare(
+        "original_publication_year",
+        json!(existing.original_publication_year),
+        json!(merged.original_publication_year),
+    );
+    compare("publisher", json!(existing.publisher), json!(merged.publisher));
+    compare("pages", json!(existing.pages), json!(merged.pages));
+    compare("language", json!(existing.language), json!(merged.language));
+    compare("format", json!(existing.format), json!(merged.format));
+    compare("owned_copies", json!(existing.owned_copies), json!(merged.owned_copies));
+    compare("goodreads_id", json!(existing.goodreads_id), json!(merged.goodreads_id));
+    compare("tags", json!(existing.tags), json!(merged.tags));
+
+    changes
+}
+
+fn title_author_key(title: &str, author: Option<&str>) -> (String, String) {
+    (
+        title.trim().to_lowercase(),
+        author.map(|a| a.trim().to_lowercase()).unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing() -> ExistingBook {
+        ExistingBook {
+            id: 42,
+            fields: BookSnapshot {
+                title: "Dune".to_string(),
+                author: Some("Frank Herbert".to_string()),
+                isbn: Some("9780441013593".to_string()),
+                publication_year: Some(1965),
+                original_publication_year: None,
+                publisher: None,
+                pages: Some(412),
+                language: Some("en".to_string()),
+                format: None,
+                owned_copies: None,
+                goodreads_id: None,
+                tags: vec!["classics".to_string()],
+            },
+        }
+    }
+
+    fn row(shelf: &str) -> TransformedBook {
+        TransformedBook {
+            title: "Dune".to_string(),
+            author: Some("Frank Herbert".to_string()),
+            isbn: Some("9780441013593".to_string()),
+            publication_year: Some(1965),
+            publisher: None,
+            pages: Some(412),
+            language: None,
+            format: None,
+            original_publication_year: None,
+            owned_copies: None,
+            goodreads_id: None,
+            identifiers: Vec::new(),
+            tags: Vec::new(),
+            shelf: shelf.to_string(),
+            rating: Some(5),
+            notes: None,
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 2),
+            end_date: NaiveDate::from_ymd_opt(2024, 2, 3),
+            read_count: None,
+        }
+    }
+
+    fn index() -> LibraryIndex {
+        let mut index = LibraryIndex::default();
+        index.add_book(existing());
+        index
+    }
+
+    fn decision(action: ImportAction, book_id: Option<i32>) -> RowDecision {
+        RowDecision { row_number: 2, action, book_id }
+    }
+
+    fn changed_fields(book: &TransformedBook, policy: MergePolicy) -> Vec<&'static str> {
+        let existing = existing().fields;
+        book_changes(&existing, &merge_book(&existing, book, policy))
+            .into_iter()
+            .map(|change| change.field)
+            .collect()
+    }
+
+    #[test]
+    fn test_book_changes() {
+        for policy in [MergePolicy::FillEmpty, MergePolicy::Overwrite, MergePolicy::KeepLocal] {
+            assert!(changed_fields(&row("read"), policy).is_empty());
+        }
+
+        let mut book = row("read");
+        book.publisher = Some("Ace".to_string());
+        book.pages = None;
+        book.language = Some("en".to_string());
+        let existing = existing().fields;
+        let changes = book_changes(&existing, &merge_book(&existing, &book, MergePolicy::Overwrite));
+        assert_eq!(
+            changes,
+            vec![FieldChange { field: "publisher", current: Value::Null, proposed: json!("Ace") }]
+        );
+    }
+
+    #[test]
+    fn test_merge_policies() {
+        let mut book = row("read");
+        book.title = "Dune (40th Anniversary)".to_string();
+        book.publisher = Some("Ace".to_string());
+        book.pages = Some(896);
+        book.isbn = None;
+        book.format = Some("Hardcover".to_string());
+        book.goodreads_id = Some("234225".to_string());
+        book.tags = vec!["sci-fi".to_string(), "Classics".to_string()];
+
+        // Empty fields are filled, set ones kept, and an empty ISBN never clears one
+        assert_eq!(
+            changed_fields(&book, MergePolicy::FillEmpty),
+            vec!["publisher", "format", "goodreads_id", "tags"]
+        );
+        assert_eq!(
+            changed_fields(&book, MergePolicy::Overwrite),
+            vec!["title", "publisher", "pages", "format", "goodreads_id", "tags"]
+        );
+        assert_eq!(changed_fields(&book, MergePolicy::KeepLocal), vec!["goodreads_id"]);
+
+        let existing = existing().fields;
+        let merged = merge_book(&existing, &book, MergePolicy::FillEmpty);
+        assert_eq!(merged.tags, vec!["classics".to_string(), "sci-fi".to_string()]);
+        assert_eq!((merged.pages, merged.isbn), (Some(412), existing.isbn.clone()));
+    }
+
+    #[test]
+    fn test_plan_without_decision() {
+        let mut index = index();
+
+        // Same book and a new reading
+        let plan = index.plan(&row("read"), None, MergePolicy::FillEmpty).unwrap();
+        assert_eq!((plan.action, plan.book_id, plan.matched_by), (ImportAction::Update, Some(42), Some(MatchedBy::Isbn)));
+        assert!(plan.changes.is_empty() && plan.creates_reading);
+
+        // Nothing new once the reading exists
+        index.readings.insert((42, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()));
+        let plan = index.plan(&row("read"), None, MergePolicy::FillEmpty).unwrap();
+        assert_eq!(plan.action, ImportAction::Skip);
+        assert!(!plan.creates_reading);
+
+        // The ISBN-10 of the same edition is the same ISBN
+        let mut book = row("to-read");
+        book.isbn = Some("0-441-01359-7".to_string());
+        assert_eq!(index.find_duplicate(&book), Some((42, MatchedBy::Isbn)));
+
+        // Title and author match when the ISBN differs
+        let mut book = row("to-read");
+        book.isbn = Some("0441172717".to_string());
+        book.title = " DUNE ".to_string();
+        let plan = index.plan(&book, None, MergePolicy::Overwrite).unwrap();
+        assert_eq!((plan.action, plan.matched_by), (ImportAction::Update, Some(MatchedBy::TitleAuthor)));
+        assert_eq!(plan.changes.len(), 2);
+        assert_eq!(index.plan(&book, None, MergePolicy::FillEmpty).unwrap().action, ImportAction::Skip);
+
+        let mut book = row("to-read");
+        book.isbn = None;
+        book.title = "Children of Dune".to_string();
+        let plan = index.plan(&book, None, MergePolicy::FillEmpty).unwrap();
+        assert_eq!((plan.action, plan.book_id), (ImportAction::Create, None));
+    }
+
+    #[test]
+    fn test_identifiers_match_across_editions() {
+        let mut index = index();
+        index.by_identifier.insert((IdentifierType::Goodreads, "234225".to_string()), 42);
+
+        // A different edition with no ISBN, found by its Goodreads ID
+        let mut book = row("to-read");
+        book.title = "Dune: Deluxe Edition".to_string();
+        book.isbn = None;
+        book.goodreads_id = Some("234225".to_string());
+        assert_eq!(index.find_duplicate(&book), Some((42, MatchedBy::Identifier)));
+
+        // Any of the ISBNs a row lists
+        book.goodreads_id = None;
+        book.isbn = Some("0441172717".to_string());
+        book.identifiers = vec![(IdentifierType::Isbn13, "9780441013593".to_string())];
+        assert_eq!(index.find_duplicate(&book), Some((42, MatchedBy::Isbn)));
+        assert_eq!(
+            row_identifiers(&book),
+            vec![
+                (IdentifierType::Isbn13, "9780441172719".to_string()),
+                (IdentifierType::Isbn13, "9780441013593".to_string()),
+            ]
+        );
+
+        book.identifiers.clear();
+        assert_eq!(index.find_duplicate(&book), None);
+    }
+
+    #[test]
+    fn test_plan_with_decision() {
+        let index = index();
+        let policy = MergePolicy::Overwrite;
+
+        let plan = index.plan(&row("read"), Some(&decision(ImportAction::Create, None)), policy).unwrap();
+        assert_eq!((plan.action, plan.book_id), (ImportAction::Create, None));
+        assert!(plan.creates_reading);
+
+        let mut book = row("read");
+        book.title = "Dune (Deluxe Edition)".to_string();
+        book.isbn = None;
+        let plan = index.plan(&book, Some(&decision(ImportAction::Update, Some(42))), policy).unwrap();
+        assert_eq!((plan.action, plan.book_id, plan.matched_by), (ImportAction::Update, Some(42), Some(MatchedBy::User)));
+        assert_eq!(plan.changes.len(), 1);
+
+        let plan = index.plan(&row("read"), Some(&decision(ImportAction::Skip, None)), policy).unwrap();
+        assert_eq!((plan.action, plan.book_id), (ImportAction::Skip, Some(42)));
+        assert!(!plan.creates_reading);
+
+        assert!(index.plan(&row("read"), Some(&decision(ImportAction::Update, Some(7))), policy).is_err());
+        assert!(index.plan(&row("read"), Some(&decision(ImportAction::Update, None)), policy).is_err());
+    }
+
+    #[test]
+    fn test_read_count_adds_readings() {
+        let mut index = index();
+        let mut book = row("read");
+        book.read_count = Some(3);
+
+        // The dated reading and two more
+        let plan = index.plan(&book, None, MergePolicy::FillEmpty).unwrap();
+        assert!(plan.creates_reading);
+        assert_eq!(plan.extra_readings, 2);
+
+        let mut new_book = book.clone();
+        new_book.isbn = None;
+        new_book.title = "Dune Messiah".to_string();
+        assert_eq!(index.plan(&new_book, None, MergePolicy::FillEmpty).unwrap().extra_readings, 2);
+
+        // Importing again adds nothing
+        index.readings.insert((42, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()));
+        index.finished_readings.insert(42, 3);
+        assert_eq!(index.plan(&book, None, MergePolicy::KeepLocal).unwrap().action, ImportAction::Skip);
+
+        // A book being reread counts only finished readings
+        index.finished_readings.insert(42, 1);
+        let mut book = row("currently-reading");
+        book.start_date = NaiveDate::from_ymd_opt(2025, 5, 1);
+        book.end_date = None;
+        book.read_count = Some(2);
+        let plan = index.plan(&book, None, MergePolicy::FillEmpty).unwrap();
+        assert_eq!((plan.creates_reading, plan.extra_readings), (true, 1));
+
+        // Without any date there is nothing to put on a reading
+        let mut book = row("to-read");
+        book.start_date = None;
+        book.end_date = None;
+        book.read_count = Some(2);
+        assert_eq!(index.plan(&book, None, MergePolicy::FillEmpty).unwrap().extra_readings, 0);
+    }
+
+    #[test]
+    fn test_open_reading_blocks_currently_reading() {
+        let mut index = index();
+        index.open_readings.insert(42);
+        let mut book = row("currently-reading");
+        book.start_date = NaiveDate::from_ymd_opt(2025, 5, 1);
+        book.end_date = None;
+        assert_eq!(index.plan(&book, None, MergePolicy::FillEmpty).unwrap().action, ImportAction::Skip);
+    }
+}
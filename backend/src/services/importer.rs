@@ -0,0 +1,160 @@
+use panicless_mcp_lib::isbn::Isbn;
+
+use crate::{
+    models::import::TransformedBook,
+    services::goodreads_parser::{parse_csv_data, transform_record},
+};
+
+/// One row (or entry) of an export file, turned into a book
+#[derive(Debug)]
+pub struct ImportRow {
+    /// Position in the file as the user sees it (1-based, header counted for CSV)
+    pub row_number: usize,
+    /// Title, when the row could be read far enough to find one
+    pub title: Option<String>,
+    pub book: Result<TransformedBook, String>,
+}
+
+/// A reading-site export format
+/// Importers only parse: matching and saving books is shared by all of them.
+pub trait Importer: Send + Sync {
+    /// Name of the source in messages
+    fn source(&self) -> &'static str;
+
+    /// Identifier of the source in requests and import jobs
+    fn key(&self) -> &'static str;
+
+    /// Parse an uploaded file into one result per book
+    /// Fails only when the file as a whole cannot be read.
+    fn parse(&self, data: &[u8]) -> Result<Vec<ImportRow>, String>;
+}
+
+/// Goodreads "Export Library" CSV
+pub struct GoodreadsImporter;
+
+impl Importer for GoodreadsImporter {
+    fn source(&self) -> &'static str {
+        "Goodreads"
+    }
+
+    fn key(&self) -> &'static str {
+        "goodreads"
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Vec<ImportRow>, String> {
+        let records = parse_csv_data(data).map_err(|e| format!("CSV parsing failed: {}", e))?;
+
+        Ok(records
+            .into_iter()
+            .enumerate()
+            .map(|(idx, result)| {
+                let row_number = idx + 2; // header row, 1-based
+                match result {
+                    Ok(record) => ImportRow {
+                        row_number,
+                        title: Some(record.title.clone()),
+                        book: transform_record(&record),
+                    },
+                    Err(e) => ImportRow { row_number, title: None, book: Err(e) },
+                }
+            })
+            .collect())
+    }
+}
+
+/// Text of an upload, whatever Unicode encoding the site used
+/// LibraryThing has exported UTF-16 with a byte order mark; most others write UTF-8,
+/// sometimes with a BOM.
+pub(crate) fn decode_text(data: &[u8]) -> Result<String, String> {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16(&units).map_err(|_| "File is not valid UTF-16".to_string())
+    };
+
+    match data {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8(rest.to_vec()).map_err(|_| "File is not valid UTF-8".to_string()),
+        _ => String::from_utf8(data.to_vec()).map_err(|_| "File is not valid UTF-8".to_string()),
+    }
+}
+
+/// Extra details the schema has no field for, kept as lines of the reading notes
+/// Empty values are left out; returns None when nothing is left.
+pub(crate) fn notes_with_extras(notes: Option<String>, extras: &[(&str, Option<String>)]) -> Option<String> {
+    let mut parts: Vec<String> = notes.into_iter().collect();
+    for (label, value) in extras {
+        if let Some(value) = value.as_deref().map(str::trim).filter(|value| !value.is_empty()) {
+            parts.push(format!("{}: {}", label, value));
+        }
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("\n\n"))
+    }
+}
+
+/// Integer rating 1-5 from a star rating that may have fractions ("4.25")
+/// 0 and empty mean unrated.
+pub(crate) fn round_rating(value: &str) -> Result<Option<i32>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let stars: f64 = value.parse().map_err(|_| format!("Invalid rating: {}", value))?;
+    match stars {
+        0.0 => Ok(None),
+        s if (0.0..=5.0).contains(&s) => Ok(Some((s.round() as i32).max(1))),
+        _ => Err(format!("Invalid rating: {} (must be 0-5)", value)),
+    }
+}
+
+/// The ISBN-13 of a valid ISBN-10 or ISBN-13, or None for anything else
+/// (site-specific IDs, wrong check digits)
+pub(crate) fn isbn_or_none(value: &str) -> Option<String> {
+    let cleaned: String = value.chars().filter(|c| !matches!(c, '"' | '=' | '[' | ']')).collect();
+    Isbn::parse(&cleaned).ok().map(|isbn| isbn.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text(b"\xEF\xBB\xBFTitle").unwrap(), "Title");
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter().chain("Dün".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        assert_eq!(decode_text(&utf16).unwrap(), "Dün");
+        assert!(decode_text(b"\xFFoops").is_err());
+    }
+
+    #[test]
+    fn test_round_rating() {
+        assert_eq!(round_rating("4.25"), Ok(Some(4)));
+        assert_eq!(round_rating("4.5"), Ok(Some(5)));
+        assert_eq!(round_rating("0.25"), Ok(Some(1)));
+        assert_eq!(round_rating("0"), Ok(None));
+        assert_eq!(round_rating(""), Ok(None));
+        assert!(round_rating("6").is_err());
+    }
+
+    #[test]
+    fn test_isbn_or_none() {
+        assert_eq!(isbn_or_none("[080442957x]"), Some("9780804429573".to_string()));
+        assert_eq!(isbn_or_none("978-0-441-01359-3"), Some("9780441013593".to_string()));
+        assert_eq!(isbn_or_none("9780441013594"), None);
+        assert_eq!(isbn_or_none("b1a2c3d4-storygraph-uid"), None);
+    }
+
+    #[test]
+    fn test_notes_with_extras() {
+        assert_eq!(
+            notes_with_extras(Some("Great".to_string()), &[("Moods", Some("dark, tense".to_string())), ("Tags", Some(" ".to_string()))]),
+            Some("Great\n\nMoods: dark, tense".to_string())
+        );
+        assert_eq!(notes_with_extras(None, &[("Tags", None)]), None);
+    }
+}
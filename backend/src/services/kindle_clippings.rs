@@ -0,0 +1,709 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::{
+    db::DbPool,
+    errors::AppResult,
+    models::highlight::{KindleImportSummary, UnmatchedBook},
+    services::{importer::decode_text, librarything_parser::first_last},
+};
+
+/// Line between two clippings of `My Clippings.txt`
+const SEPARATOR: &str = "==========";
+
+/// Words naming the kind of a clipping, in the languages Kindle writes them in
+const HIGHLIGHT_WORDS: &[&str] = &["highlight", "markierung", "surlignement", "subrayado", "evidenziazione", "destaque", "markering", "ハイライト"];
+const BOOKMARK_WORDS: &[&str] = &["bookmark", "lesezeichen", "signet", "marcador", "segnalibro", "bladwijzer", "ブックマーク"];
+const NOTE_WORDS: &[&str] = &["note", "notiz", "nota", "notitie", "メモ"];
+const LOCATION_WORDS: &[&str] = &["location", "loc.", "position", "emplacement", "posición", "posizione", "posição", "locatie", "位置"];
+const PAGE_WORDS: &[&str] = &["page", "seite", "página", "pagina"];
+
+/// Month names by number, English, German, French, Spanish, Italian, Portuguese and Dutch
+const MONTHS: [&[&str]; 12] = [
+    &["january", "jan", "januar", "janvier", "enero", "gennaio", "janeiro", "januari"],
+    &["february", "feb", "februar", "février", "febrero", "febbraio", "fevereiro", "februari"],
+    &["march", "mar", "märz", "mars", "marzo", "março", "maart"],
+    &["april", "apr", "avril", "abril", "aprile"],
+    &["may", "mai", "mayo", "maggio", "maio", "mei"],
+    &["june", "jun", "juni", "juin", "junio", "giugno", "junho"],
+    &["july", "jul", "juli", "juillet", "julio", "luglio", "julho"],
+    &["august", "aug", "août", "agosto", "augustus"],
+    &["september", "sep", "sept", "septembre", "septiembre", "settembre", "setembro"],
+    &["october", "oct", "oktober", "octobre", "octubre", "ottobre", "outubro"],
+    &["november", "nov", "novembre", "noviembre", "novembro"],
+    &["december", "dec", "dezember", "décembre", "diciembre", "dicembre", "dezembro"],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClippingKind {
+    Highlight,
+    Note,
+    Bookmark,
+}
+
+/// One entry of `My Clippings.txt`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clipping {
+    pub title: String,
+    pub author: Option<String>,
+    pub kind: ClippingKind,
+    pub page: Option<i32>,
+    /// First and last location; a note has a single location
+    pub location: Option<(i32, i32)>,
+    pub added_at: Option<NaiveDateTime>,
+    pub content: String,
+}
+
+/// A highlight with the note made on it, or a note on its own
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KindleHighlight {
+    pub text: Option<String>,
+    pub note: Option<String>,
+    pub location: Option<(i32, i32)>,
+    pub page: Option<i32>,
+    pub highlighted_at: Option<NaiveDateTime>,
+}
+
+/// The highlights of one book of the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct KindleBook {
+    pub title: String,
+    pub author: Option<String>,
+    pub highlights: Vec<KindleHighlight>,
+}
+
+impl KindleHighlight {
+    /// Whether `other` is the same passage, possibly as edited later
+    /// Kindle keeps the old clipping when a highlight is extended or shortened,
+    /// so overlapping highlights sharing an end or one containing the other are one passage.
+    fn is_same_passage(&self, other: &KindleHighlight) -> bool {
+        match (self.text.as_deref(), other.text.as_deref()) {
+            (Some(a), Some(b)) => {
+                if a == b {
+                    return true;
+                }
+                let contained = a.contains(b) || b.contains(a);
+                match (self.location, other.location) {
+                    (Some((s1, e1)), Some((s2, e2))) => s1 <= e2 && s2 <= e1 && (contained || s1 == s2 || e1 == e2),
+                    (None, None) => contained && self.page == other.page,
+                    _ => false,
+                }
+            }
+            (None, None) => match (self.location, other.location) {
+                (Some(a), Some(b)) => a.1 == b.1,
+                (None, None) => self.page.is_some() && self.page == other.page,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether a note at `location` / `page` was made on this highlight
+    fn covers(&self, location: Option<(i32, i32)>, page: Option<i32>) -> bool {
+        self.text.is_some()
+            && match (self.location, location) {
+                (Some((start, end)), Some((at, _))) => start <= at && at <= end,
+                (None, None) => page.is_some() && self.page == page,
+                _ => false,
+            }
+    }
+}
+
+/// Parse a `My Clippings.txt` file (UTF-8, with or without BOM, or UTF-16)
+/// Entries that do not look like clippings are skipped.
+pub fn parse_clippings(data: &[u8]) -> Result<Vec<Clipping>, String> {
+    let text = decode_text(data)?;
+    let text = text.replace("\r\n", "\n");
+
+    let clippings: Vec<Clipping> = text.split(SEPARATOR).filter_map(parse_clipping).collect();
+
+    if clippings.is_empty() && !text.trim().is_empty() {
+        return Err("No Kindle clippings found; expected a 'My Clippings.txt' file".to_string());
+    }
+    Ok(clippings)
+}
+
+fn parse_clipping(entry: &str) -> Option<Clipping> {
+    let mut lines = entry.trim_matches(['\n', '\u{feff}', ' ']).lines();
+    let (title, author) = parse_title_line(lines.next()?)?;
+    let meta = lines.next()?.trim().trim_start_matches('-').trim();
+
+    let mut segments: Vec<&str> = meta.split('|').map(str::trim).collect();
+    let lowered = segments[0].to_lowercase();
+    let kind = if HIGHLIGHT_WORDS.iter().any(|word| lowered.contains(word)) {
+        ClippingKind::Highlight
+    } else if BOOKMARK_WORDS.iter().any(|word| lowered.contains(word)) {
+        ClippingKind::Bookmark
+    } else if NOTE_WORDS.iter().any(|word| lowered.contains(word)) {
+        ClippingKind::Note
+    } else {
+        return None;
+    };
+
+    // The last segment is the date ("Added on ..."), unless the device left it out
+    let added_at = match segments.last().and_then(|last| parse_added_at(last)) {
+        Some(date) if segments.len() > 1 => {
+            segments.pop();
+            Some(date)
+        }
+        _ => None,
+    };
+
+    let mut page = None;
+    let mut location = None;
+    for segment in &segments {
+        let segment = segment.to_lowercase();
+        location = location.or_else(|| after_word(&segment, LOCATION_WORDS).and_then(parse_range));
+        page = page.or_else(|| after_word(&segment, PAGE_WORDS).and_then(parse_range).map(|(page, _)| page));
+    }
+
+    let content = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+
+    Some(Clipping {
+        title,
+        author,
+        kind,
+        page: page.filter(|page| *page > 0),
+        location,
+        added_at,
+        content,
+    })
+}
+
+/// "Dune (Dune Chronicles, Book 1) (Herbert, Frank)" -> title and "Frank Herbert"
+/// The author is the last parenthesised group; several authors are separated by ';'.
+fn parse_title_line(line: &str) -> Option<(String, Option<String>)> {
+    let line = line.trim().trim_start_matches('\u{feff}').trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    if let Some(inner) = line.strip_suffix(')') {
+        let mut depth = 0;
+        for (index, c) in inner.char_indices().rev() {
+            match c {
+                ')' => depth += 1,
+                '(' if depth > 0 => depth -= 1,
+                '(' => {
+                    let title = inner[..index].trim();
+                    let authors: Vec<String> = inner[index + 1..]
+                        .split(';')
+                        .map(|author| first_last(author.trim()))
+                        .filter(|author| !author.is_empty())
+                        .collect();
+                    if title.is_empty() {
+                        break;
+                    }
+                    return Some((title.to_string(), (!authors.is_empty()).then(|| authors.join(", "))));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Some((line.to_string(), None))
+}
+
+/// Text following the first of `words` in `segment`
+fn after_word<'a>(segment: &'a str, words: &[&str]) -> Option<&'a str> {
+    words
+        .iter()
+        .filter_map(|word| segment.find(word).map(|index| &segment[index + word.len()..]))
+        .next()
+}
+
+/// "150-152", "150" or "1502-04" (older Kindles shorten the end) in the text
+fn parse_range(text: &str) -> Option<(i32, i32)> {
+    let start_index = text.find(|c: char| c.is_ascii_digit())?;
+    let rest = &text[start_index..];
+    let start_digits: &str = &rest[..rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())];
+    let start: i32 = start_digits.parse().ok()?;
+
+    let after = rest[start_digits.len()..].trim_start();
+    let end = after
+        .strip_prefix(['-', '–'])
+        .map(str::trim_start)
+        .map(|after| &after[..after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len())])
+        .filter(|digits| !digits.is_empty())
+        .and_then(|digits| {
+            let end: i32 = digits.parse().ok()?;
+            if end >= start || digits.len() >= start_digits.len() {
+                return Some(end);
+            }
+            // "1502-04" -> 1504
+            format!("{}{}", &start_digits[..start_digits.len() - digits.len()], digits).parse().ok()
+        })
+        .filter(|end| *end >= start)
+        .unwrap_or(start);
+
+    Some((start, end))
+}
+
+/// The date of "Added on Sunday, 5 March 2023 10:00:00" in any of the known
+/// languages, or of "作成日: 2023年3月5日日曜日 10:00:00"
+fn parse_added_at(segment: &str) -> Option<NaiveDateTime> {
+    let lowered = segment.to_lowercase();
+
+    let mut time = None;
+    let mut pm = None;
+    let mut numbers = Vec::new();
+    let mut month = None;
+
+    for token in lowered.split(|c: char| c.is_whitespace() || c == ',') {
+        let token = token.trim_matches('.');
+        if token.contains(':') {
+            // Labels such as "作成日:" are no time
+            time = NaiveTime::parse_from_str(token, "%H:%M:%S")
+                .or_else(|_| NaiveTime::parse_from_str(token, "%H:%M"))
+                .ok()
+                .or(time);
+        } else if time.is_some() && (token == "am" || token == "pm") {
+            // Only after the time: "am" is also German for "on"
+            pm = Some(token == "pm");
+        } else if let Some((year, rest)) = token.split_once('年') {
+            // Japanese and Chinese: 2023年3月5日
+            let year = year.trim_start_matches(|c: char| !c.is_ascii_digit());
+            let (month_number, rest) = rest.split_once('月')?;
+            let day = rest.split('日').next()?;
+            numbers = vec![year.parse().ok()?, day.parse().ok()?];
+            month = month_number.parse().ok();
+        } else if let Ok(number) = token.parse::<u32>() {
+            numbers.push(number);
+        } else if let Some(index) = MONTHS.iter().position(|names| names.contains(&token)) {
+            month = month.or(Some(index as u32 + 1));
+        }
+    }
+
+    let year = *numbers.iter().find(|n| (1000..=9999).contains(*n))?;
+    let day = *numbers.iter().find(|n| (1..=31).contains(*n))?;
+    let date = NaiveDate::from_ymd_opt(year as i32, month?, day)?;
+
+    let mut time = time.unwrap_or(NaiveTime::MIN);
+    if let Some(pm) = pm {
+        time = time.with_hour(time.hour() % 12 + if pm { 12 } else { 0 })?;
+    }
+    Some(date.and_time(time))
+}
+
+/// Group clippings by book, merging edited highlights and attaching notes to
+/// the highlight they were made on
+/// Bookmarks are dropped. Books keep the order of their first clipping.
+pub fn group_by_book(clippings: Vec<Clipping>) -> Vec<KindleBook> {
+    let mut books: Vec<KindleBook> = Vec::new();
+    let mut notes: Vec<Vec<Clipping>> = Vec::new();
+
+    for clipping in clippings {
+        if clipping.kind == ClippingKind::Bookmark || clipping.content.is_empty() {
+            continue;
+        }
+
+        let index = match books.iter().position(|b| b.title == clipping.title && b.author == clipping.author) {
+            Some(index) => index,
+            None => {
+                books.push(KindleBook { title: clipping.title.clone(), author: clipping.author.clone(), highlights: Vec::new() });
+                notes.push(Vec::new());
+                books.len() - 1
+            }
+        };
+
+        if clipping.kind == ClippingKind::Note {
+            notes[index].push(clipping);
+            continue;
+        }
+
+        let highlight = KindleHighlight {
+            text: Some(clipping.content),
+            note: None,
+            location: clipping.location,
+            page: clipping.page,
+            highlighted_at: clipping.added_at,
+        };
+        // A later clipping of the same passage is the edited version
+        let highlights = &mut books[index].highlights;
+        match highlights.iter_mut().find(|h| h.is_same_passage(&highlight)) {
+            Some(existing) => *existing = highlight,
+            None => highlights.push(highlight),
+        }
+    }
+
+    for (book, notes) in books.iter_mut().zip(notes) {
+        for note in notes {
+            match book.highlights.iter_mut().rev().find(|h| h.covers(note.location, note.page)) {
+                Some(highlight) => highlight.note = Some(note.content),
+                None => {
+                    let standalone = KindleHighlight {
+                        text: None,
+                        note: Some(note.content),
+                        location: note.location,
+                        page: note.page,
+                        highlighted_at: note.added_at,
+                    };
+                    match book.highlights.iter_mut().find(|h| h.is_same_passage(&standalone)) {
+                        Some(existing) => *existing = standalone,
+                        None => book.highlights.push(standalone),
+                    }
+                }
+            }
+        }
+    }
+
+    books.retain(|book| !book.highlights.is_empty());
+    books
+}
+
+/// Title for matching: lowercase words without subtitle, series or punctuation
+/// "Dune: Deluxe Edition (Dune Chronicles, Book 1)" -> "dune"
+fn title_key(title: &str) -> String {
+    let mut depth = 0;
+    let without_groups: String = title
+        .chars()
+        .filter(|c| {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth = (depth - 1).max(0),
+                _ => return depth == 0,
+            }
+            false
+        })
+        .collect();
+    let main = without_groups.split([':', '—']).next().unwrap_or_default();
+    words(main).join(" ")
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether two author fields name a common author, by surname
+fn same_author(kindle: &str, book: &str) -> bool {
+    let book_words = words(book);
+    kindle
+        .split([',', '&', ';'])
+        .filter_map(|name| words(name).pop())
+        .any(|surname| book_words.contains(&surname))
+}
+
+/// The library book a Kindle book is, by title and author
+/// Subtitles and series are ignored; when both sides have an author, it must match.
+pub fn match_book(books: &[(i32, String, Option<String>)], title: &str, author: Option<&str>) -> Option<i32> {
+    let key = title_key(title);
+    if key.is_empty() {
+        return None;
+    }
+
+    let candidates: Vec<&(i32, String, Option<String>)> = books.iter().filter(|(_, t, _)| title_key(t) == key).collect();
+    let author_matches = |book_author: &Option<String>| match (author, book_author.as_deref()) {
+        (Some(kindle), Some(book)) => same_author(kindle, book),
+        _ => false,
+    };
+
+    candidates
+        .iter()
+        .find(|(_, _, book_author)| author_matches(book_author))
+        .or_else(|| candidates.iter().find(|(_, _, book_author)| author.is_none() || book_author.is_none()))
+        .map(|(id, _, _)| *id)
+}
+
+/// Add the highlights of a clippings file to the library
+/// Books are matched by title and author; unmatched books are created when
+/// `create_books` is set and reported otherwise. Highlights are attached to the
+/// reading that was in progress when they were made. Highlights the library
+/// already has are skipped, and edited versions replace the earlier one, so the
+/// same (growing) file can be imported again.
+pub async fn import_kindle_highlights(
+    pool: &DbPool,
+    user_id: i32,
+    books: &[KindleBook],
+    create_books: bool,
+) -> AppResult<KindleImportSummary> {
+    let mut tx = pool.begin().await?;
+    let mut summary = KindleImportSummary {
+        clippings_found: books.iter().map(|book| book.highlights.len()).sum(),
+        ..Default::default()
+    };
+
+    let library = sqlx::query_as::<_, (i32, String, Option<String>)>(
+        "SELECT id, title, author FROM books WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut readings: HashMap<i32, Vec<(i32, NaiveDate, Option<NaiveDate>)>> = HashMap::new();
+    for (id, book_id, start_date, end_date) in sqlx::query_as::<_, (i32, i32, NaiveDate, Option<NaiveDate>)>(
+        "SELECT id, book_id, start_date, end_date FROM readings WHERE user_id = $1 ORDER BY start_date DESC"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?
+    {
+        readings.entry(book_id).or_default().push((id, start_date, end_date));
+    }
+
+    let mut existing: HashMap<i32, Vec<(i32, KindleHighlight)>> = HashMap::new();
+    for (id, book_id, text, note, location_start, location_end, page) in sqlx::query_as::<
+        _,
+        (i32, i32, Option<String>, Option<String>, Option<i32>, Option<i32>, Option<i32>),
+    >(
+        "SELECT id, book_id, text, note, location_start, location_end, page FROM highlights WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?
+    {
+        let location = location_start.map(|start| (start, location_end.unwrap_or(start)));
+        existing
+            .entry(book_id)
+            .or_default()
+            .push((id, KindleHighlight { text, note, location, page, highlighted_at: None }));
+    }
+
+    for book in books {
+        let book_id = match match_book(&library, &book.title, book.author.as_deref()) {
+            Some(id) => id,
+            None if create_books => {
+                let id = sqlx::query_scalar::<_, i32>(
+                    "INSERT INTO books (user_id, title, author) VALUES ($1, $2, $3) RETURNING id"
+                )
+                .bind(user_id)
+                .bind(book.title.chars().take(500).collect::<String>())
+                .bind(book.author.as_deref().map(|author| author.chars().take(255).collect::<String>()))
+                .fetch_one(&mut *tx)
+                .await?;
+                summary.books_created += 1;
+                id
+            }
+            None => {
+                summary.unmatched_books.push(UnmatchedBook {
+                    title: book.title.clone(),
+                    author: book.author.clone(),
+                    highlights: book.highlights.len(),
+                });
+                continue;
+            }
+        };
+
+        let known = existing.entry(book_id).or_default();
+        for highlight in &book.highlights {
+            let reading_id = highlight.highlighted_at.and_then(|at| {
+                let date = at.date();
+                readings.get(&book_id)?.iter().find_map(|(id, start, end)| {
+                    (*start <= date && end.is_none_or(|end| date <= end)).then_some(*id)
+                })
+            });
+
+            match known.iter_mut().find(|(_, k)| k.is_same_passage(highlight)) {
+                Some((_, k)) if k.text == highlight.text && (highlight.note.is_none() || k.note == highlight.note) => {
+                    summary.highlights_skipped += 1;
+                }
+                Some((id, k)) => {
+                    sqlx::query(
+                        "UPDATE highlights
+                         SET text = $3, note = COALESCE($4, note), location_start = $5, location_end = $6,
+                             page = COALESCE($7, page), highlighted_at = COALESCE($8, highlighted_at),
+                             reading_id = COALESCE(reading_id, $9), updated_at = NOW()
+                         WHERE id = $1 AND user_id = $2"
+                    )
+                    .bind(*id)
+                    .bind(user_id)
+                    .bind(&highlight.text)
+                    .bind(&highlight.note)
+                    .bind(highlight.location.map(|l| l.0))
+                    .bind(highlight.location.map(|l| l.1))
+                    .bind(highlight.page)
+                    .bind(highlight.highlighted_at.map(|at| at.and_utc()))
+                    .bind(reading_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    k.text = highlight.text.clone();
+                    k.note = highlight.note.clone().or(k.note.take());
+                    k.location = highlight.location;
+                    summary.highlights_updated += 1;
+                }
+                None => {
+                    let id = sqlx::query_scalar::<_, i32>(
+                        "INSERT INTO highlights (user_id, book_id, reading_id, text, note, location_start, location_end, page, highlighted_at, source)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'kindle')
+                         RETURNING id"
+                    )
+                    .bind(user_id)
+                    .bind(book_id)
+                    .bind(reading_id)
+                    .bind(&highlight.text)
+                    .bind(&highlight.note)
+                    .bind(highlight.location.map(|l| l.0))
+                    .bind(highlight.location.map(|l| l.1))
+                    .bind(highlight.page)
+                    .bind(highlight.highlighted_at.map(|at| at.and_utc()))
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+                    known.push((id, highlight.clone()));
+                    summary.highlights_imported += 1;
+                }
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").ok()
+    }
+
+    const CLIPPINGS: &str = "\u{feff}Dune (Dune Chronicles, Book 1) (Herbert, Frank)
+- Your Highlight on page 8 | Location 100-101 | Added on Sunday, 5 March 2023 10:00:00
+
+I must not fear.
+==========
+Dune (Dune Chronicles, Book 1) (Herbert, Frank)
+- Your Highlight on page 8 | Location 100-102 | Added on Sunday, 5 March 2023 10:01:00
+
+I must not fear. Fear is the mind-killer.
+==========
+Dune (Dune Chronicles, Book 1) (Herbert, Frank)
+- Your Note on page 8 | Location 102 | Added on Sunday, 5 March 2023 10:02:00
+
+Litany against fear
+==========
+Dune (Dune Chronicles, Book 1) (Herbert, Frank)
+- Your Bookmark on page 9 | Location 120 | Added on Sunday, 5 March 2023 10:05:00
+
+
+==========
+Der Steppenwolf (Hesse, Hermann)
+- Ihre Markierung bei Position 55-57 | Hinzugefügt am Montag, 6. März 2023 21:15:04
+
+Nur für Verrückte.
+==========
+L'Étranger (Camus, Albert)
+- Votre surlignement sur la page 3 | emplacement 40-41 | Ajouté le mardi 7 mars 2023 08:30:00
+
+Aujourd'hui, maman est morte.
+==========
+Cien años de soledad (García Márquez, Gabriel)
+- Tu subrayado en la página 1 | posición 10-12 | Añadido el miércoles, 8 de marzo de 2023 0:05:09
+
+Muchos años después.
+==========
+Meditations (Marcus Aurelius)
+- Your Note Loc. 1502 | Added on Thursday, March 9, 2023 9:15:00 PM
+
+Stoic note
+==========
+吾輩は猫である (夏目 漱石)
+- 位置No. 150-152のハイライト |作成日: 2023年3月10日金曜日 7:00:00
+
+名前はまだ無い。
+==========
+";
+
+    #[test]
+    fn test_parse_clippings() {
+        let clippings = parse_clippings(CLIPPINGS.as_bytes()).unwrap();
+        assert_eq!(clippings.len(), 9);
+
+        let first = &clippings[0];
+        assert_eq!(first.title, "Dune (Dune Chronicles, Book 1)");
+        assert_eq!(first.author.as_deref(), Some("Frank Herbert"));
+        assert_eq!(first.kind, ClippingKind::Highlight);
+        assert_eq!((first.page, first.location), (Some(8), Some((100, 101))));
+        assert_eq!(first.added_at, at("2023-03-05 10:00:00"));
+        assert_eq!(first.content, "I must not fear.");
+
+        assert_eq!(clippings[2].kind, ClippingKind::Note);
+        assert_eq!(clippings[2].location, Some((102, 102)));
+        assert_eq!(clippings[3].kind, ClippingKind::Bookmark);
+
+        let german = &clippings[4];
+        assert_eq!((german.kind, german.location, german.page), (ClippingKind::Highlight, Some((55, 57)), None));
+        assert_eq!(german.added_at, at("2023-03-06 21:15:04"));
+
+        let french = &clippings[5];
+        assert_eq!((french.page, french.location), (Some(3), Some((40, 41))));
+        assert_eq!(french.added_at, at("2023-03-07 08:30:00"));
+
+        let spanish = &clippings[6];
+        assert_eq!(spanish.author.as_deref(), Some("Gabriel García Márquez"));
+        assert_eq!((spanish.page, spanish.location), (Some(1), Some((10, 12))));
+        assert_eq!(spanish.added_at, at("2023-03-08 00:05:09"));
+
+        let english_us = &clippings[7];
+        assert_eq!((english_us.kind, english_us.location), (ClippingKind::Note, Some((1502, 1502))));
+        assert_eq!(english_us.added_at, at("2023-03-09 21:15:00"));
+
+        let japanese = &clippings[8];
+        assert_eq!((japanese.kind, japanese.location), (ClippingKind::Highlight, Some((150, 152))));
+        assert_eq!(japanese.added_at, at("2023-03-10 07:00:00"));
+
+        assert!(parse_clippings(b"just some text").is_err());
+        assert!(parse_clippings(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_group_by_book() {
+        let books = group_by_book(parse_clippings(CLIPPINGS.as_bytes()).unwrap());
+        assert_eq!(books.len(), 6);
+
+        // The extended highlight replaces the first one and gets the note
+        let dune = &books[0];
+        assert_eq!(dune.highlights.len(), 1);
+        assert_eq!(dune.highlights[0].text.as_deref(), Some("I must not fear. Fear is the mind-killer."));
+        assert_eq!(dune.highlights[0].location, Some((100, 102)));
+        assert_eq!(dune.highlights[0].note.as_deref(), Some("Litany against fear"));
+
+        // A note without a highlight stays on its own
+        let meditations = &books[4];
+        assert_eq!(meditations.highlights[0].text, None);
+        assert_eq!(meditations.highlights[0].note.as_deref(), Some("Stoic note"));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(" 150-152 "), Some((150, 152)));
+        assert_eq!(parse_range("1502-04"), Some((1502, 1504)));
+        assert_eq!(parse_range(" 7"), Some((7, 7)));
+        assert_eq!(parse_range("150 – 160"), Some((150, 160)));
+        assert_eq!(parse_range("xii"), None);
+    }
+
+    #[test]
+    fn test_parse_added_at() {
+        assert_eq!(parse_added_at("Added on Sunday, 5 March 2023 10:00:00"), at("2023-03-05 10:00:00"));
+        assert_eq!(parse_added_at("Aggiunto in data domenica 5 marzo 2023 10:00:00"), at("2023-03-05 10:00:00"));
+        assert_eq!(parse_added_at("Adicionado: domingo, 5 de março de 2023 10:00:00"), at("2023-03-05 10:00:00"));
+        assert_eq!(parse_added_at("Added on Sunday, March 5, 2023 12:30:00 AM"), at("2023-03-05 00:30:00"));
+        assert_eq!(parse_added_at("追加日：2023年3月5日日曜日 10:00:00"), at("2023-03-05 10:00:00"));
+        assert_eq!(parse_added_at("Location 150-152"), None);
+    }
+
+    #[test]
+    fn test_match_book() {
+        let library = vec![
+            (1, "Dune".to_string(), Some("Frank Herbert".to_string())),
+            (2, "Meditations".to_string(), Some("Marcus Aurelius".to_string())),
+            (3, "Meditations".to_string(), Some("Someone Else".to_string())),
+            (4, "Notes".to_string(), None),
+        ];
+
+        assert_eq!(match_book(&library, "Dune (Dune Chronicles, Book 1)", Some("Frank Herbert")), Some(1));
+        assert_eq!(match_book(&library, "DUNE: Deluxe Edition", Some("F. Herbert")), Some(1));
+        assert_eq!(match_book(&library, "Meditations", Some("Marcus Aurelius")), Some(2));
+        assert_eq!(match_book(&library, "Dune", Some("Kevin Anderson")), None);
+        assert_eq!(match_book(&library, "Notes", Some("Anyone")), Some(4));
+        assert_eq!(match_book(&library, "Unknown", None), None);
+    }
+}
@@ -0,0 +1,875 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use panicless_mcp_lib::isbn::Isbn;
+
+use crate::{
+    chat::egress::check_endpoint,
+    db::DbPool,
+    errors::{AppError, AppResult},
+    models::{
+        connector::normalize_base_url,
+        cover::is_uploaded_cover_url,
+        export::{
+            ConflictPolicy, ExportedBook, ExportedConnector, ExportedHighlight, ExportedIdentifier, ExportedReading,
+            ExportedUser, LibraryExport, LibraryImportSummary, EXPORT_FORMAT_VERSION,
+        },
+        identifier::{isbn_identifier, IdentifierType},
+    },
+    services::book_identifiers::{add_identifiers, load_user_identifiers},
+};
+
+/// Export the profile, books, identifiers, readings, highlights and connectors of a user
+/// Connector keys are left out.
+pub async fn export_library(pool: &DbPool, user_id: i32) -> AppResult<LibraryExport> {
+    let user = sqlx::query_as::<_, (String, String, Option<String>)>(
+        "SELECT username, email, full_name FROM users WHERE id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let mut books = sqlx::query_as::<_, ExportedBook>(
+        "SELECT id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, series, series_index, tags,
+                format, original_publication_year, owned_copies, goodreads_id, created_at, updated_at
+         FROM books WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    // Uploaded covers are not part of the archive, and their URLs only work here
+    for book in &mut books {
+        book.cover_image_url = book.cover_image_url.take().filter(|url| !is_uploaded_cover_url(url));
+    }
+
+    // Identifiers derived from the books' own columns come back with the books
+    let identifiers = sqlx::query_as::<_, ExportedIdentifier>(
+        "SELECT i.book_id, i.id_type, i.value, i.source
+         FROM book_identifiers i JOIN books b ON i.book_id = b.id
+         WHERE b.user_id = $1 AND i.source <> 'book' ORDER BY i.id"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let readings = sqlx::query_as::<_, ExportedReading>(
+        "SELECT id, book_id, start_date, end_date, rating, notes, created_at, updated_at
+         FROM readings WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let highlights = sqlx::query_as::<_, ExportedHighlight>(
+        "SELECT id, book_id, reading_id, text, note, location_start, location_end, page, highlighted_at, source, created_at, updated_at
+         FROM highlights WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let connectors = sqlx::query_as::<_, ExportedConnector>(
+        "SELECT provider, base_url, model, is_active, created_at
+         FROM connectors WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(LibraryExport {
+        format_version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        user: ExportedUser {
+            username: user.0,
+            email: user.1,
+            full_name: user.2,
+        },
+        books,
+        readings,
+        connectors,
+        highlights,
+        identifiers,
+    })
+}
+
+/// Existing books of a user, keyed the way imports detect duplicates
+#[derive(Default)]
+pub(crate) struct BookIndex {
+    by_identifier: HashMap<(IdentifierType, String), i32>,
+    by_isbn: HashMap<String, i32>,
+    by_title_author: HashMap<(String, String), i32>,
+}
+
+impl BookIndex {
+    /// Book carrying any of the identifiers, trying them in order
+    pub(crate) fn find_by_identifier<'a>(
+        &self,
+        identifiers: impl IntoIterator<Item = &'a (IdentifierType, String)>,
+    ) -> Option<i32> {
+        identifiers.into_iter().find_map(|identifier| self.by_identifier.get(identifier).copied())
+    }
+
+    pub(crate) fn insert_identifier(&mut self, id: i32, identifier: (IdentifierType, String)) {
+        self.by_identifier.entry(identifier).or_insert(id);
+    }
+
+    pub(crate) fn find(&self, isbn: Option<&str>, title: &str, author: Option<&str>) -> Option<i32> {
+        isbn_key(isbn)
+            .and_then(|isbn| self.by_isbn.get(&isbn))
+            .or_else(|| self.by_title_author.get(&title_author_key(title, author)))
+            .copied()
+    }
+
+    pub(crate) fn insert(&mut self, id: i32, isbn: Option<&str>, title: &str, author: Option<&str>) {
+        if let Some(isbn) = isbn_key(isbn) {
+            self.by_isbn.entry(isbn).or_insert(id);
+        }
+        self.by_title_author.entry(title_author_key(title, author)).or_insert(id);
+    }
+}
+
+/// Valid ISBNs in their ISBN-13 form; anything else as typed
+fn isbn_key(isbn: Option<&str>) -> Option<String> {
+    let isbn = isbn.map(str::trim).filter(|isbn| !isbn.is_empty())?;
+    Some(Isbn::parse(isbn).map_or_else(|_| isbn.to_lowercase(), |isbn| isbn.to_string()))
+}
+
+fn title_author_key(title: &str, author: Option<&str>) -> (String, String) {
+    (title.trim().to_lowercase(), author.unwrap_or_default().trim().to_lowercase())
+}
+
+/// A reading already in the library
+struct KnownReading {
+    id: i32,
+    start_date: NaiveDate,
+    open: bool,
+}
+
+/// The existing reading an exported one corresponds to: same start date, or
+/// both still open (a book can only have one open reading)
+fn matching_reading<'a>(known: &'a [KnownReading], reading: &ExportedReading) -> Option<&'a KnownReading> {
+    known
+        .iter()
+        .find(|k| k.start_date == reading.start_date || (k.open && reading.end_date.is_none()))
+}
+
+/// Add the books, identifiers, readings, highlights and connectors of an export to a user's library
+/// Everything is applied in one transaction. Books already in the library
+/// (an identifier in common, same ISBN, or same title and author) are handled according to `policy`;
+/// readings and highlights are attached to the books they pointed at in the
+/// document, and highlights the book already has are skipped.
+/// Custom connectors are restored without a key and must pass `allowed_hosts`;
+/// hosted providers are only reported, since their key is never exported.
+pub async fn import_library(
+    pool: &DbPool,
+    user_id: i32,
+    export: &LibraryExport,
+    policy: ConflictPolicy,
+    allowed_hosts: &[String],
+) -> AppResult<LibraryImportSummary> {
+    check_format_version(export.format_version)?;
+
+    let mut tx = pool.begin().await?;
+    let mut summary = LibraryImportSummary::default();
+
+    let mut index = BookIndex::default();
+    let existing_books = sqlx::query_as::<_, (i32, String, Option<String>, Option<String>)>(
+        "SELECT id, title, author, isbn FROM books WHERE user_id = $1 ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    for (id, title, author, isbn) in &existing_books {
+        index.insert(*id, isbn.as_deref(), title, author.as_deref());
+    }
+    for (book_id, id_type, value) in load_user_identifiers(&mut *tx, user_id).await? {
+        index.insert_identifier(book_id, (id_type, value));
+    }
+
+    let mut exported_identifiers: HashMap<i32, Vec<(IdentifierType, String, &str)>> = HashMap::new();
+    for identifier in &export.identifiers {
+        let id_type = IdentifierType::parse(&identifier.id_type).ok_or_else(|| {
+            AppError::Validation(format!("Unknown identifier type '{}'", identifier.id_type))
+        })?;
+        let (id_type, value) = id_type.canonicalize(&identifier.value).map_err(AppError::Validation)?;
+        let source = match identifier.source.as_str() {
+            source @ ("import" | "calibre") => source,
+            _ => "manual",
+        };
+        exported_identifiers.entry(identifier.book_id).or_default().push((id_type, value, source));
+    }
+
+    let mut known_readings: HashMap<i32, Vec<KnownReading>> = HashMap::new();
+    let existing_readings = sqlx::query_as::<_, (i32, i32, NaiveDate, Option<NaiveDate>)>(
+        "SELECT id, book_id, start_date, end_date FROM readings WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    for (id, book_id, start_date, end_date) in existing_readings {
+        known_readings.entry(book_id).or_default().push(KnownReading {
+            id,
+            start_date,
+            open: end_date.is_none(),
+        });
+    }
+
+    let mut book_ids = HashMap::with_capacity(export.books.len());
+
+    for book in &export.books {
+        // Valid ISBNs are stored as ISBN-13; older archives may hold others, kept as they were
+        let isbn = book
+            .isbn
+            .as_deref()
+            .map(|isbn| Isbn::parse(isbn).map_or_else(|_| isbn.to_string(), |isbn| isbn.to_string()));
+        // Archives from before uploaded covers were left out may point at another account's book
+        let cover_image_url = book.cover_image_url.as_deref().filter(|url| !is_uploaded_cover_url(url));
+        let extra_identifiers = exported_identifiers.remove(&book.id).unwrap_or_default();
+        let identifiers: Vec<(IdentifierType, String)> = book
+            .goodreads_id
+            .as_deref()
+            .and_then(|id| IdentifierType::Goodreads.normalize(id).ok())
+            .map(|id| (IdentifierType::Goodreads, id))
+            .into_iter()
+            .chain(book.isbn.as_deref().and_then(isbn_identifier))
+            .chain(extra_identifiers.iter().map(|(id_type, value, _)| (*id_type, value.clone())))
+            .collect();
+
+        let existing = match policy {
+            ConflictPolicy::Duplicate => None,
+            _ => index
+                .find_by_identifier(&identifiers)
+                .or_else(|| index.find(book.isbn.as_deref(), &book.title, book.author.as_deref())),
+        };
+
+        let book_id = match existing {
+            Some(id) if policy == ConflictPolicy::Skip => {
+                summary.books_skipped += 1;
+                id
+            }
+            Some(id) => {
+                sqlx::query(
+                    "UPDATE books
+                     SET title = $3, author = $4, edition = $5, isbn = $6, publication_year = $7, publisher = $8,
+                         pages = $9, language = $10, description = $11,
+                         cover_image_url = CASE WHEN cover_image_url LIKE '/api/books/' || id || '/cover%'
+                                                THEN cover_image_url ELSE $12 END,
+                         series = $13, series_index = $14, tags = $15, format = $16, original_publication_year = $17,
+                         owned_copies = $18, goodreads_id = $19, updated_at = NOW()
+                     WHERE id = $1 AND user_id = $2"
+                )
+                .bind(id)
+                .bind(user_id)
+                .bind(&book.title)
+                .bind(&book.author)
+                .bind(&book.edition)
+                .bind(&isbn)
+                .bind(book.publication_year)
+                .bind(&book.publisher)
+                .bind(book.pages)
+                .bind(&book.language)
+                .bind(&book.description)
+                .bind(cover_image_url)
+                .bind(&book.series)
+                .bind(book.series_index)
+                .bind(&book.tags)
+                .bind(&book.format)
+                .bind(book.original_publication_year)
+                .bind(book.owned_copies)
+                .bind(&book.goodreads_id)
+                .execute(&mut *tx)
+                .await?;
+
+                summary.books_updated += 1;
+                id
+            }
+            None => {
+                let new_id = sqlx::query_scalar::<_, i32>(
+                    "INSERT INTO books (user_id, title, author, edition, isbn, publication_year, publisher, pages, language, description, cover_image_url, series, series_index, tags,
+                                        format, original_publication_year, owned_copies, goodreads_id, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+                     RETURNING id"
+                )
+                .bind(user_id)
+                .bind(&book.title)
+                .bind(&book.author)
+                .bind(&book.edition)
+                .bind(&isbn)
+                .bind(book.publication_year)
+                .bind(&book.publisher)
+                .bind(book.pages)
+                .bind(&book.language)
+                .bind(&book.description)
+                .bind(cover_image_url)
+                .bind(&book.series)
+                .bind(book.series_index)
+                .bind(&book.tags)
+                .bind(&book.format)
+                .bind(book.original_publication_year)
+                .bind(book.owned_copies)
+                .bind(&book.goodreads_id)
+                .bind(book.created_at)
+                .bind(book.updated_at)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                index.insert(new_id, book.isbn.as_deref(), &book.title, book.author.as_deref());
+                for identifier in identifiers {
+                    index.insert_identifier(new_id, identifier);
+                }
+                summary.books_imported += 1;
+                new_id
+            }
+        };
+
+        for (id_type, value, source) in extra_identifiers {
+            summary.identifiers_imported += add_identifiers(&mut tx, book_id, &[(id_type, value)], source).await?.len();
+        }
+
+        book_ids.insert(book.id, book_id);
+    }
+
+    if let Some(book_id) = exported_identifiers.keys().next() {
+        return Err(AppError::Validation(format!(
+            "An identifier refers to book {} which is not in the export",
+            book_id
+        )));
+    }
+
+    let mut reading_ids = HashMap::with_capacity(export.readings.len());
+
+    for reading in &export.readings {
+        let book_id = *book_ids.get(&reading.book_id).ok_or_else(|| {
+            AppError::Validation(format!(
+                "Reading {} refers to book {} which is not in the export",
+                reading.id, reading.book_id
+            ))
+        })?;
+
+        let known = known_readings.entry(book_id).or_default();
+        match matching_reading(known, reading).map(|k| k.id) {
+            Some(existing_id) if policy != ConflictPolicy::Overwrite => {
+                reading_ids.insert(reading.id, existing_id);
+                summary.readings_skipped += 1;
+            }
+            Some(existing_id) => {
+                sqlx::query(
+                    "UPDATE readings
+                     SET start_date = $3, end_date = $4, rating = $5, notes = $6, updated_at = NOW()
+                     WHERE id = $1 AND user_id = $2"
+                )
+                .bind(existing_id)
+                .bind(user_id)
+                .bind(reading.start_date)
+                .bind(reading.end_date)
+                .bind(reading.rating)
+                .bind(&reading.notes)
+                .execute(&mut *tx)
+                .await?;
+
+                if let Some(k) = known.iter_mut().find(|k| k.id == existing_id) {
+                    k.start_date = reading.start_date;
+                    k.open = reading.end_date.is_none();
+                }
+                reading_ids.insert(reading.id, existing_id);
+                summary.readings_updated += 1;
+            }
+            None => {
+                let new_id = sqlx::query_scalar::<_, i32>(
+                    "INSERT INTO readings (user_id, book_id, start_date, end_date, rating, notes, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                     RETURNING id"
+                )
+                .bind(user_id)
+                .bind(book_id)
+                .bind(reading.start_date)
+                .bind(reading.end_date)
+                .bind(reading.rating)
+                .bind(&reading.notes)
+                .bind(reading.created_at)
+                .bind(reading.updated_at)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                known.push(KnownReading {
+                    id: new_id,
+                    start_date: reading.start_date,
+                    open: reading.end_date.is_none(),
+                });
+                reading_ids.insert(reading.id, new_id);
+                summary.readings_imported += 1;
+            }
+        }
+    }
+
+    for highlight in &export.highlights {
+        let book_id = *book_ids.get(&highlight.book_id).ok_or_else(|| {
+            AppError::Validation(format!(
+                "Highlight {} refers to book {} which is not in the export",
+                highlight.id, highlight.book_id
+            ))
+        })?;
+
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM highlights
+                            WHERE user_id = $1 AND book_id = $2 AND text IS NOT DISTINCT FROM $3 AND note IS NOT DISTINCT FROM $4)"
+        )
+        .bind(user_id)
+        .bind(book_id)
+        .bind(&highlight.text)
+        .bind(&highlight.note)
+        .fetch_one(&mut *tx)
+        .await?;
+        if exists && policy != ConflictPolicy::Duplicate {
+            summary.highlights_skipped += 1;
+            continue;
+        }
+
+        sqlx::query(
+            "INSERT INTO highlights (user_id, book_id, reading_id, text, note, location_start, location_end, page,
+                                     highlighted_at, source, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"
+        )
+        .bind(user_id)
+        .bind(book_id)
+        .bind(highlight.reading_id.and_then(|id| reading_ids.get(&id)))
+        .bind(&highlight.text)
+        .bind(&highlight.note)
+        .bind(highlight.location_start)
+        .bind(highlight.location_end)
+        .bind(highlight.page)
+        .bind(highlight.highlighted_at)
+        .bind(&highlight.source)
+        .bind(highlight.created_at)
+        .bind(highlight.updated_at)
+        .execute(&mut *tx)
+        .await?;
+        summary.highlights_imported += 1;
+    }
+
+    // The profile keeps its own name unless it has none or the export wins
+    if let Some(full_name) = &export.user.full_name {
+        let updated = sqlx::query(
+            "UPDATE users SET full_name = $2, updated_at = NOW()
+             WHERE id = $1 AND (full_name IS NULL OR $3) AND full_name IS DISTINCT FROM $2"
+        )
+        .bind(user_id)
+        .bind(full_name)
+        .bind(policy == ConflictPolicy::Overwrite)
+        .execute(&mut *tx)
+        .await?;
+        summary.profile_updated = updated.rows_affected() > 0;
+    }
+
+    for connector in &export.connectors {
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM connectors WHERE user_id = $1 AND provider = $2)"
+        )
+        .bind(user_id)
+        .bind(&connector.provider)
+        .fetch_one(&mut *tx)
+        .await?;
+        if exists {
+            continue;
+        }
+
+        let custom_endpoint = match (connector.provider.as_str(), &connector.base_url, &connector.model) {
+            ("custom", Some(base_url), Some(model)) => Some((
+                normalize_base_url(base_url, allowed_hosts).map_err(AppError::Validation)?,
+                model,
+            )),
+            _ => None,
+        };
+
+        match custom_endpoint {
+            Some((base_url, model)) => {
+                check_endpoint(&base_url, allowed_hosts).await.map_err(AppError::Validation)?;
+                sqlx::query(
+                    "INSERT INTO connectors (user_id, provider, encrypted_token, base_url, model, is_active, created_at, updated_at)
+                     VALUES ($1, 'custom', NULL, $2, $3, $4, $5, NOW())"
+                )
+                .bind(user_id)
+                .bind(&base_url)
+                .bind(model)
+                .bind(connector.is_active)
+                .bind(connector.created_at)
+                .execute(&mut *tx)
+                .await?;
+                summary.connectors_imported += 1;
+            }
+            None => summary.connectors_without_key.push(connector.provider.clone()),
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+fn check_format_version(version: u32) -> AppResult<()> {
+    if version == 0 || version > EXPORT_FORMAT_VERSION {
+        return Err(AppError::Validation(format!(
+            "Unsupported export format version {} (this server reads versions up to {})",
+            version, EXPORT_FORMAT_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_format_version() {
+        assert!(check_format_version(1).is_ok());
+        assert!(check_format_version(0).is_err());
+        assert!(check_format_version(EXPORT_FORMAT_VERSION + 1).is_err());
+    }
+
+    #[test]
+    fn test_export_round_trips_through_json() {
+        let json = r#"{
+            "format_version": 1,
+            "exported_at": "2025-01-06T10:00:00Z",
+            "user": {"username": "johndoe", "email": "john@example.com", "full_name": null},
+            "books": [{
+                "id": 7, "title": "Dune", "author": "Frank Herbert", "edition": null, "isbn": null,
+                "publication_year": 1965, "publisher": null, "pages": 412, "language": null,
+                "description": null, "cover_image_url": null,
+                "created_at": "2025-01-01T00:00:00Z", "updated_at": "2025-01-01T00:00:00Z"
+            }],
+            "readings": [{
+                "id": 3, "book_id": 7, "start_date": "2025-01-02", "end_date": null, "rating": null,
+                "notes": "Re-read", "created_at": "2025-01-02T00:00:00Z", "updated_at": "2025-01-02T00:00:00Z"
+            }]
+        }"#;
+
+        let export: LibraryExport = serde_json::from_str(json).unwrap();
+        assert_eq!(export.books[0].id, export.readings[0].book_id);
+
+        let reparsed: LibraryExport = serde_json::from_str(&serde_json::to_string(&export).unwrap()).unwrap();
+        assert_eq!(reparsed.books[0].title, "Dune");
+        assert_eq!(reparsed.readings[0].notes.as_deref(), Some("Re-read"));
+        assert!(reparsed.connectors.is_empty());
+    }
+
+    #[test]
+    fn test_book_index_matches_isbn_then_title_and_author() {
+        let mut index = BookIndex::default();
+        index.insert(1, Some(" 978-0441013593 "), "Dune", Some("Frank Herbert"));
+        index.insert(2, None, "The Dispossessed", Some("Ursula K. Le Guin"));
+
+        assert_eq!(index.find(Some("978-0441013593"), "Dune Messiah", None), Some(1));
+        assert_eq!(index.find(None, "the dispossessed ", Some("URSULA K. LE GUIN")), Some(2));
+        assert_eq!(index.find(Some(""), "The Dispossessed", Some("Ursula K. Le Guin")), Some(2));
+        assert_eq!(index.find(None, "The Dispossessed", None), None);
+    }
+
+    #[test]
+    fn test_book_index_matches_identifiers_first() {
+        let mut index = BookIndex::default();
+        index.insert(1, None, "Dune", Some("Frank Herbert"));
+        index.insert(2, None, "Dune (Deluxe)", Some("Frank Herbert"));
+        index.insert_identifier(2, (IdentifierType::Goodreads, "234225".to_string()));
+        index.insert_identifier(1, (IdentifierType::Goodreads, "234225".to_string()));
+
+        let identifiers = [
+            (IdentifierType::Asin, "B00B7NPRY8".to_string()),
+            (IdentifierType::Goodreads, "234225".to_string()),
+        ];
+        // The first book inserted with an identifier keeps it
+        assert_eq!(index.find_by_identifier(&identifiers), Some(2));
+        assert_eq!(index.find_by_identifier(&identifiers[..1]), None);
+    }
+
+    #[test]
+    fn test_matching_reading() {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let known = vec![
+            KnownReading { id: 1, start_date: date("2024-01-01"), open: false },
+            KnownReading { id: 2, start_date: date("2025-03-01"), open: true },
+        ];
+        let reading = |start: &str, end: Option<&str>| ExportedReading {
+            id: 9,
+            book_id: 7,
+            start_date: date(start),
+            end_date: end.map(date),
+            rating: None,
+            notes: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        assert_eq!(matching_reading(&known, &reading("2024-01-01", Some("2024-02-01"))).map(|k| k.id), Some(1));
+        // Only one reading of a book can be open
+        assert_eq!(matching_reading(&known, &reading("2025-04-01", None)).map(|k| k.id), Some(2));
+        assert!(matching_reading(&known, &reading("2023-06-01", Some("2023-07-01"))).is_none());
+    }
+
+    async fn create_user(pool: &DbPool, username: &str) -> i32 {
+        sqlx::query_scalar("INSERT INTO users (username, email, password_hash) VALUES ($1, $1 || '@example.com', '') RETURNING id")
+            .bind(username)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn cover_urls(pool: &DbPool, user_id: i32) -> Vec<Option<String>> {
+        sqlx::query_scalar("SELECT cover_image_url FROM books WHERE user_id = $1 ORDER BY id")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_uploaded_covers_stay_out_of_archives(pool: DbPool) {
+        let owner = create_user(&pool, "owner").await;
+        let book_id: i32 = sqlx::query_scalar("INSERT INTO books (user_id, title) VALUES ($1, 'Dune') RETURNING id")
+            .bind(owner)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let uploaded = format!("/api/books/{}/cover?v=9f86d081884c7d65", book_id);
+        sqlx::query("UPDATE books SET cover_image_url = $1 WHERE id = $2")
+            .bind(&uploaded)
+            .bind(book_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO books (user_id, title, cover_image_url) VALUES ($1, 'Emma', 'https://example.com/emma.jpg')")
+            .bind(owner)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut export = export_library(&pool, owner).await.unwrap();
+        let exported: Vec<_> = export.books.iter().map(|book| book.cover_image_url.as_deref()).collect();
+        assert_eq!(exported, [None, Some("https://example.com/emma.jpg")]);
+
+        // Archives written before uploaded covers were left out still carry their URLs
+        export.books[0].cover_image_url = Some(uploaded.clone());
+        let other = create_user(&pool, "other").await;
+        import_library(&pool, other, &export, ConflictPolicy::Duplicate, &[]).await.unwrap();
+        assert_eq!(cover_urls(&pool, other).await, [None, Some("https://example.com/emma.jpg".to_string())]);
+
+        // Restoring over the same books keeps the covers they have
+        export.books[0].cover_image_url = None;
+        import_library(&pool, owner, &export, ConflictPolicy::Overwrite, &[]).await.unwrap();
+        assert_eq!(cover_urls(&pool, owner).await, [Some(uploaded), Some("https://example.com/emma.jpg".to_string())]);
+    }
+
+    type Readings = Vec<(String, NaiveDate, Option<NaiveDate>, Option<i32>)>;
+    type Highlights = Vec<(String, Option<NaiveDate>, Option<String>)>;
+    type Identifiers = Vec<(String, String, String)>;
+
+    /// Readings, highlights and identifiers of a user by book title, following only
+    /// links that stay within the user's library
+    async fn library_by_title(pool: &DbPool, user_id: i32) -> (Readings, Highlights, Identifiers) {
+        let readings = sqlx::query_as(
+            "SELECT b.title, r.start_date, r.end_date, r.rating
+             FROM readings r JOIN books b ON b.id = r.book_id AND b.user_id = r.user_id
+             WHERE r.user_id = $1 ORDER BY b.title, r.start_date"
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        let highlights = sqlx::query_as(
+            "SELECT b.title, r.start_date, h.text
+             FROM highlights h
+             JOIN books b ON b.id = h.book_id AND b.user_id = h.user_id
+             LEFT JOIN readings r ON r.id = h.reading_id AND r.book_id = h.book_id
+             WHERE h.user_id = $1 ORDER BY b.title, h.text"
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        let identifiers = sqlx::query_as(
+            "SELECT b.title, i.id_type, i.value
+             FROM book_identifiers i JOIN books b ON b.id = i.book_id
+             WHERE b.user_id = $1 AND i.source <> 'book' ORDER BY b.title, i.id_type"
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        (readings, highlights, identifiers)
+    }
+
+    async fn add_book(pool: &DbPool, user_id: i32, title: &str, author: &str, isbn: Option<&str>) -> i32 {
+        sqlx::query_scalar("INSERT INTO books (user_id, title, author, isbn) VALUES ($1, $2, $3, $4) RETURNING id")
+            .bind(user_id)
+            .bind(title)
+            .bind(author)
+            .bind(isbn)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn add_reading(pool: &DbPool, user_id: i32, book_id: i32, start: &str, end: Option<&str>, rating: Option<i32>) -> i32 {
+        sqlx::query_scalar(
+            "INSERT INTO readings (user_id, book_id, start_date, end_date, rating)
+             VALUES ($1, $2, $3::DATE, $4::DATE, $5) RETURNING id"
+        )
+        .bind(user_id)
+        .bind(book_id)
+        .bind(start)
+        .bind(end)
+        .bind(rating)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn add_highlight(pool: &DbPool, user_id: i32, book_id: i32, reading_id: Option<i32>, text: &str) {
+        sqlx::query("INSERT INTO highlights (user_id, book_id, reading_id, text) VALUES ($1, $2, $3, $4)")
+            .bind(user_id)
+            .bind(book_id)
+            .bind(reading_id)
+            .bind(text)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    /// Two books with readings, highlights and an identifier each
+    async fn create_library(pool: &DbPool) -> i32 {
+        let owner = create_user(pool, "owner").await;
+        let dune = add_book(pool, owner, "Dune", "Frank Herbert", Some("9780441013593")).await;
+        let emma = add_book(pool, owner, "Emma", "Jane Austen", None).await;
+        sqlx::query(
+            "INSERT INTO book_identifiers (book_id, id_type, value, source)
+             VALUES ($1, 'goodreads', '234225', 'manual'), ($2, 'openlibrary_work', 'OL66562W', 'import')"
+        )
+        .bind(dune)
+        .bind(emma)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let first_read = add_reading(pool, owner, dune, "2024-01-01", Some("2024-02-01"), Some(5)).await;
+        add_reading(pool, owner, dune, "2025-03-01", None, None).await;
+        let emma_read = add_reading(pool, owner, emma, "2024-05-01", Some("2024-05-10"), Some(4)).await;
+        add_highlight(pool, owner, dune, Some(first_read), "Fear is the mind-killer.").await;
+        add_highlight(pool, owner, emma, Some(emma_read), "Handsome, clever, and rich").await;
+        owner
+    }
+
+    /// The restore target's own copy of Dune, under another title, and a book of its own
+    async fn create_overlapping_library(pool: &DbPool, username: &str) -> (i32, i32) {
+        let user_id = create_user(pool, username).await;
+        let dune = add_book(pool, user_id, "Dune (old copy)", "Frank Herbert", Some("0441013597")).await;
+        add_reading(pool, user_id, dune, "2024-01-01", Some("2024-01-20"), Some(3)).await;
+        add_highlight(pool, user_id, dune, None, "Fear is the mind-killer.").await;
+        add_book(pool, user_id, "Solaris", "Stanislaw Lem", None).await;
+        (user_id, dune)
+    }
+
+    fn counts(summary: &LibraryImportSummary) -> [usize; 9] {
+        [
+            summary.books_imported,
+            summary.books_updated,
+            summary.books_skipped,
+            summary.readings_imported,
+            summary.readings_updated,
+            summary.readings_skipped,
+            summary.highlights_imported,
+            summary.highlights_skipped,
+            summary.identifiers_imported,
+        ]
+    }
+
+    async fn book_titles(pool: &DbPool, user_id: i32) -> Vec<String> {
+        sqlx::query_scalar("SELECT title FROM books WHERE user_id = $1 ORDER BY title")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_restore_into_empty_account(pool: DbPool) {
+        let owner = create_library(&pool).await;
+        let export = export_library(&pool, owner).await.unwrap();
+
+        let target = create_user(&pool, "target").await;
+        let summary = import_library(&pool, target, &export, ConflictPolicy::Skip, &[]).await.unwrap();
+        assert_eq!(counts(&summary), [2, 0, 0, 3, 0, 0, 2, 0, 2]);
+
+        // Every link points at the restored rows, which carry what the originals did
+        assert_eq!(book_titles(&pool, target).await, ["Dune", "Emma"]);
+        assert_eq!(library_by_title(&pool, target).await, library_by_title(&pool, owner).await);
+        let (readings, highlights, identifiers) = library_by_title(&pool, target).await;
+        assert_eq!((readings.len(), highlights.len(), identifiers.len()), (3, 2, 2));
+        assert!(highlights.iter().all(|(_, reading_start, _)| reading_start.is_some()));
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_restore_over_overlapping_books(pool: DbPool) {
+        let owner = create_library(&pool).await;
+        let export = export_library(&pool, owner).await.unwrap();
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+
+        // Skip keeps the book and its reading, and adds what it lacks
+        let (target, _) = create_overlapping_library(&pool, "skip").await;
+        let summary = import_library(&pool, target, &export, ConflictPolicy::Skip, &[]).await.unwrap();
+        assert_eq!(counts(&summary), [1, 0, 1, 2, 0, 1, 1, 1, 2]);
+        assert_eq!(book_titles(&pool, target).await, ["Dune (old copy)", "Emma", "Solaris"]);
+        let (readings, highlights, identifiers) = library_by_title(&pool, target).await;
+        assert_eq!(
+            readings,
+            [
+                ("Dune (old copy)".to_string(), date("2024-01-01"), Some(date("2024-01-20")), Some(3)),
+                ("Dune (old copy)".to_string(), date("2025-03-01"), None, None),
+                ("Emma".to_string(), date("2024-05-01"), Some(date("2024-05-10")), Some(4)),
+            ]
+        );
+        assert_eq!(highlights.len(), 2);
+        assert_eq!(identifiers[0], ("Dune (old copy)".to_string(), "goodreads".to_string(), "234225".to_string()));
+
+        // Overwrite takes the archive's details and reading
+        let (target, dune) = create_overlapping_library(&pool, "overwrite").await;
+        let summary = import_library(&pool, target, &export, ConflictPolicy::Overwrite, &[]).await.unwrap();
+        assert_eq!(counts(&summary), [1, 1, 0, 2, 1, 0, 1, 1, 2]);
+        assert_eq!(book_titles(&pool, target).await, ["Dune", "Emma", "Solaris"]);
+        let (readings, _, _) = library_by_title(&pool, target).await;
+        assert_eq!(readings[0], ("Dune".to_string(), date("2024-01-01"), Some(date("2024-02-01")), Some(5)));
+        let isbn: Option<String> = sqlx::query_scalar("SELECT isbn FROM books WHERE id = $1")
+            .bind(dune)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(isbn.as_deref(), Some("9780441013593"));
+
+        // Duplicate leaves the existing books alone and restores everything beside them
+        let (target, _) = create_overlapping_library(&pool, "duplicate").await;
+        let summary = import_library(&pool, target, &export, ConflictPolicy::Duplicate, &[]).await.unwrap();
+        assert_eq!(counts(&summary), [2, 0, 0, 3, 0, 0, 2, 0, 2]);
+        assert_eq!(book_titles(&pool, target).await, ["Dune", "Dune (old copy)", "Emma", "Solaris"]);
+        let (readings, highlights, _) = library_by_title(&pool, target).await;
+        assert_eq!((readings.len(), highlights.len()), (4, 3));
+        assert_eq!(readings[0], ("Dune".to_string(), date("2024-01-01"), Some(date("2024-02-01")), Some(5)));
+    }
+
+    #[sqlx::test(migrator = "crate::db::MIGRATOR")]
+    async fn test_restore_rejects_dangling_reading(pool: DbPool) {
+        let owner = create_library(&pool).await;
+        let mut export = export_library(&pool, owner).await.unwrap();
+        export.readings[2].book_id = 999_999;
+
+        let target = create_user(&pool, "target").await;
+        let result = import_library(&pool, target, &export, ConflictPolicy::Skip, &[]).await;
+        assert!(matches!(result, Err(AppError::Validation(message)) if message.contains("book 999999")));
+
+        // The books before the bad reading were rolled back with it
+        assert!(book_titles(&pool, target).await.is_empty());
+        assert_eq!(library_by_title(&pool, target).await, (vec![], vec![], vec![]));
+    }
+}
@@ -0,0 +1,393 @@
+use csv::ReaderBuilder;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    models::{identifier::isbn_identifier, import::TransformedBook},
+    services::{
+        goodreads_parser::{combine_notes, parse_date, parse_integer, parse_year},
+        importer::{decode_text, isbn_or_none, notes_with_extras, round_rating, ImportRow, Importer},
+    },
+};
+
+/// Collection every LibraryThing book is in unless moved
+const DEFAULT_COLLECTION: &str = "your library";
+
+/// A row of the LibraryThing tab-separated export
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct LibraryThingTsvRecord {
+    #[serde(rename = "Title")]
+    title: String,
+    /// "Last, First"
+    #[serde(rename = "Primary Author")]
+    primary_author: Option<String>,
+    /// "Publisher (year), binding, pages"
+    #[serde(rename = "Publication")]
+    publication: Option<String>,
+    #[serde(rename = "Date")]
+    date: Option<String>,
+    #[serde(rename = "Page Count")]
+    page_count: Option<String>,
+    /// "[0441013597]"
+    #[serde(rename = "ISBN")]
+    isbn: Option<String>,
+    #[serde(rename = "ISBNs")]
+    isbns: Option<String>,
+    #[serde(rename = "Rating")]
+    rating: Option<String>,
+    #[serde(rename = "Review")]
+    review: Option<String>,
+    #[serde(rename = "Private Comment")]
+    private_comment: Option<String>,
+    #[serde(rename = "Languages")]
+    languages: Option<String>,
+    #[serde(rename = "Tags")]
+    tags: Option<String>,
+    #[serde(rename = "Collections")]
+    collections: Option<String>,
+    #[serde(rename = "Date Started")]
+    date_started: Option<String>,
+    #[serde(rename = "Date Read")]
+    date_read: Option<String>,
+    #[serde(rename = "Entry Date")]
+    entry_date: Option<String>,
+}
+
+/// A LibraryThing book, from either export format
+#[derive(Debug, Default)]
+pub struct LibraryThingEntry {
+    pub title: String,
+    /// "First Last", when the export has it
+    pub author: Option<String>,
+    /// "Last, First"
+    pub author_lf: Option<String>,
+    pub publication: Option<String>,
+    pub date: Option<String>,
+    pub pages: Option<String>,
+    pub isbns: Vec<String>,
+    pub rating: Option<String>,
+    pub review: Option<String>,
+    pub private_comment: Option<String>,
+    pub languages: Vec<String>,
+    pub tags: Vec<String>,
+    pub collections: Vec<String>,
+    pub date_started: Option<String>,
+    pub date_read: Option<String>,
+    pub entry_date: Option<String>,
+}
+
+impl From<LibraryThingTsvRecord> for LibraryThingEntry {
+    fn from(record: LibraryThingTsvRecord) -> Self {
+        LibraryThingEntry {
+            title: record.title,
+            author: None,
+            author_lf: record.primary_author,
+            publication: record.publication,
+            date: record.date,
+            pages: record.page_count,
+            isbns: record.isbn.into_iter().chain(record.isbns).flat_map(|isbns| split_list(&isbns)).collect(),
+            rating: record.rating,
+            review: record.review,
+            private_comment: record.private_comment,
+            languages: record.languages.as_deref().map(split_list).unwrap_or_default(),
+            tags: record.tags.as_deref().map(split_list).unwrap_or_default(),
+            collections: record.collections.as_deref().map(split_list).unwrap_or_default(),
+            date_started: record.date_started,
+            date_read: record.date_read,
+            entry_date: record.entry_date,
+        }
+    }
+}
+
+impl LibraryThingEntry {
+    /// Read an entry of the JSON export
+    fn from_json(value: &Value) -> Self {
+        let first_author = &value["authors"][0];
+        LibraryThingEntry {
+            title: text(&value["title"]).unwrap_or_default(),
+            author: text(&first_author["fl"]),
+            author_lf: text(&value["primaryauthor"]).or_else(|| text(&first_author["lf"])),
+            publication: text(&value["publication"]),
+            date: text(&value["date"]),
+            pages: text(&value["pages"]),
+            isbns: list(&value["isbn"]),
+            rating: text(&value["rating"]),
+            review: text(&value["review"]),
+            private_comment: text(&value["privatecomment"]),
+            languages: list(&value["language"]),
+            tags: list(&value["tags"]),
+            collections: list(&value["collections"]),
+            date_started: text(&value["datestarted"]),
+            date_read: text(&value["datefinished"]),
+            entry_date: text(&value["entrydate"]),
+        }
+    }
+}
+
+/// LibraryThing "Export" as tab-separated text or JSON
+pub struct LibraryThingImporter;
+
+impl Importer for LibraryThingImporter {
+    fn source(&self) -> &'static str {
+        "LibraryThing"
+    }
+
+    fn key(&self) -> &'static str {
+        "librarything"
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Vec<ImportRow>, String> {
+        let text = decode_text(data)?;
+        if text.trim_start().starts_with(['{', '[']) {
+            parse_json(&text)
+        } else {
+            Ok(parse_tsv(&text))
+        }
+    }
+}
+
+fn parse_tsv(text: &str) -> Vec<ImportRow> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    reader
+        .deserialize::<LibraryThingTsvRecord>()
+        .enumerate()
+        .map(|(idx, result)| {
+            let row_number = idx + 2;
+            match result {
+                Ok(record) => entry_row(row_number, record.into()),
+                Err(e) => ImportRow {
+                    row_number,
+                    title: None,
+                    book: Err(format!("Row {}: TSV parse error - {}", row_number, e)),
+                },
+            }
+        })
+        .collect()
+}
+
+/// The JSON export is an object keyed by LibraryThing book ID
+fn parse_json(text: &str) -> Result<Vec<ImportRow>, String> {
+    let document: Value = serde_json::from_str(text).map_err(|e| format!("JSON parsing failed: {}", e))?;
+    let entries: Vec<&Value> = match &document {
+        Value::Object(books) => books.values().collect(),
+        Value::Array(books) => books.iter().collect(),
+        _ => return Err("JSON parsing failed: expected an object of books".to_string()),
+    };
+
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| entry_row(idx + 1, LibraryThingEntry::from_json(value)))
+        .collect())
+}
+
+fn entry_row(row_number: usize, entry: LibraryThingEntry) -> ImportRow {
+    ImportRow {
+        row_number,
+        title: Some(entry.title.clone()),
+        book: transform_librarything_entry(&entry),
+    }
+}
+
+/// Transform a LibraryThing book into internal book/reading data
+/// The "Currently reading" collection and the started/read dates decide the shelf.
+/// Tags and collections other than the default one are kept in the notes.
+pub fn transform_librarything_entry(entry: &LibraryThingEntry) -> Result<TransformedBook, String> {
+    let title = entry.title.trim();
+    if title.is_empty() {
+        return Err("Title is required".to_string());
+    }
+
+    let author = entry
+        .author
+        .clone()
+        .or_else(|| entry.author_lf.as_deref().map(first_last))
+        .map(|author| author.trim().to_string())
+        .filter(|author| !author.is_empty());
+
+    // The ISBN-10 and ISBN-13 of an edition come out the same
+    let isbns: Vec<String> = entry.isbns.iter().filter_map(|isbn| isbn_or_none(isbn)).collect();
+    let isbn = isbns.first().cloned();
+    let identifiers = isbns.iter().filter_map(|isbn| isbn_identifier(isbn)).collect();
+
+    let publisher = entry
+        .publication
+        .as_deref()
+        .and_then(|publication| publication.split(['(', ',']).next())
+        .map(str::trim)
+        .filter(|publisher| !publisher.is_empty())
+        .map(str::to_string);
+    let publication_year = entry.date.as_deref().and_then(|date| date.get(..4)).and_then(parse_year);
+    let pages = entry.pages.as_deref().and_then(parse_integer).filter(|&pages| pages > 0);
+    let language = entry.languages.first().cloned();
+    let rating = round_rating(entry.rating.as_deref().unwrap_or_default())?;
+
+    let started = entry.date_started.as_deref().and_then(parse_date);
+    let read = entry.date_read.as_deref().and_then(parse_date);
+    let entered = entry.entry_date.as_deref().and_then(parse_date);
+    let in_collection = |name: &str| entry.collections.iter().any(|collection| collection.eq_ignore_ascii_case(name));
+
+    let (shelf, start_date, end_date) = if in_collection("currently reading") || (started.is_some() && read.is_none()) {
+        ("currently-reading", started.or(entered), None)
+    } else if read.is_some() {
+        let start = started.or(match (entered, read) {
+            (Some(entered), Some(read)) if entered <= read => Some(entered),
+            _ => None,
+        });
+        ("read", start, read)
+    } else {
+        ("to-read", None, None)
+    };
+
+    let collections: Vec<&str> = entry
+        .collections
+        .iter()
+        .map(String::as_str)
+        .filter(|collection| !collection.eq_ignore_ascii_case(DEFAULT_COLLECTION))
+        .collect();
+    let notes = notes_with_extras(
+        combine_notes(&entry.review, &entry.private_comment),
+        &[
+            ("Tags", Some(entry.tags.join(", "))),
+            ("Collections", Some(collections.join(", "))),
+        ],
+    );
+
+    Ok(TransformedBook {
+        title: title.to_string(),
+        author,
+        isbn,
+        publication_year,
+        publisher,
+        pages,
+        language,
+        format: None,
+        original_publication_year: None,
+        owned_copies: None,
+        goodreads_id: None,
+        identifiers,
+        tags: Vec::new(),
+        shelf: shelf.to_string(),
+        rating,
+        notes,
+        start_date,
+        end_date,
+        read_count: None,
+    })
+}
+
+/// "Herbert, Frank" -> "Frank Herbert"
+pub(crate) fn first_last(author: &str) -> String {
+    match author.split_once(',') {
+        Some((last, first)) => format!("{} {}", first.trim(), last.trim()),
+        None => author.to_string(),
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().trim_matches(['[', ']']).trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A JSON string or number as text
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// A JSON array, object of values or comma-separated string as a list
+fn list(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(text).collect(),
+        Value::Object(items) => items.values().filter_map(text).collect(),
+        Value::String(s) => split_list(s),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_tsv() {
+        let tsv = "Book Id\tTitle\tPrimary Author\tPublication\tDate\tReview\tRating\tPrivate Comment\tPage Count\tDate Started\tDate Read\tTags\tCollections\tLanguages\tISBN\tISBNs\tEntry Date\n\
+                   101\tDune\tHerbert, Frank\tAce (1990), Paperback, 535 pages\t1965\tClassic\t4.5\tsigned copy\t535\t2024-01-02\t2024-02-01\tsci-fi, desert\tYour library, Favorites\tEnglish\t[0441013597]\t0441013597, 9780441013593\t2023-12-01\n\
+                   102\tCirce\tMiller, Madeline\t\t2018\t\t\t\t\t\t\t\tCurrently reading\t\t\t\t2024-03-10\n\
+                   103\tUbik\tDick, Philip K.\t\t\t\t\t\t\t\t\t\tWishlist\t\t\t\t2024-01-01\n";
+
+        let rows = LibraryThingImporter.parse(tsv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 3);
+        let books: Vec<_> = rows.into_iter().map(|row| row.book.unwrap()).collect();
+
+        let dune = &books[0];
+        assert_eq!(dune.author.as_deref(), Some("Frank Herbert"));
+        assert_eq!(dune.isbn.as_deref(), Some("9780441013593"));
+        assert_eq!(dune.publisher.as_deref(), Some("Ace"));
+        assert_eq!((dune.publication_year, dune.pages, dune.rating), (Some(1965), Some(535), Some(5)));
+        assert_eq!(dune.language.as_deref(), Some("English"));
+        assert_eq!(dune.shelf, "read");
+        assert_eq!((dune.start_date, dune.end_date), (Some(date("2024-01-02")), Some(date("2024-02-01"))));
+        assert_eq!(
+            dune.notes.as_deref(),
+            Some("Classic\n\n---\n\nsigned copy\n\nTags: sci-fi, desert\n\nCollections: Favorites")
+        );
+
+        assert_eq!(books[1].shelf, "currently-reading");
+        assert_eq!(books[1].start_date, Some(date("2024-03-10")));
+        assert_eq!(books[2].shelf, "to-read");
+        assert_eq!(books[2].notes.as_deref(), Some("Collections: Wishlist"));
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = r#"{
+            "101": {
+                "books_id": "101", "title": "Dune", "primaryauthor": "Herbert, Frank",
+                "authors": [{"lf": "Herbert, Frank", "fl": "Frank Herbert"}],
+                "date": "1965", "publication": "Ace (1990)", "pages": "535 ",
+                "isbn": {"0": "0441013597", "2": "9780441013593"}, "rating": 4,
+                "language": ["English"], "tags": ["sci-fi"], "collections": ["Your library"],
+                "datestarted": "2024-01-02", "datefinished": "2024-02-01", "entrydate": "2023-12-01"
+            },
+            "102": {"title": "Untitled draft", "collections": ["To read"]}
+        }"#;
+
+        let rows = LibraryThingImporter.parse(json.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+        let dune = rows[0].book.as_ref().unwrap();
+        assert_eq!(dune.author.as_deref(), Some("Frank Herbert"));
+        assert_eq!(dune.isbn.as_deref(), Some("9780441013593"));
+        assert_eq!((dune.pages, dune.rating), (Some(535), Some(4)));
+        assert_eq!(dune.end_date, Some(date("2024-02-01")));
+        assert_eq!(dune.notes.as_deref(), Some("Tags: sci-fi"));
+
+        assert_eq!(rows[1].book.as_ref().unwrap().shelf, "to-read");
+        assert!(LibraryThingImporter.parse(b"[1, ").is_err());
+    }
+
+    #[test]
+    fn test_first_last() {
+        assert_eq!(first_last("Dick, Philip K."), "Philip K. Dick");
+        assert_eq!(first_last("Homer"), "Homer");
+    }
+}
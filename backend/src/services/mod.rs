@@ -1 +1,15 @@
+pub mod barcode;
+pub mod book_identifiers;
+pub mod calibre;
+pub mod connector_verification;
+pub mod goodreads_export;
 pub mod goodreads_parser;
+pub mod import_jobs;
+pub mod import_plan;
+pub mod importer;
+pub mod kindle_clippings;
+pub mod librarything_parser;
+pub mod library_export;
+pub mod openlibrary_dump;
+pub mod reencryption;
+pub mod storygraph_parser;
@@ -0,0 +1,412 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use flate2::bufread::MultiGzDecoder;
+use panicless_mcp_lib::{enrichment::title_key, isbn::Isbn};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    db::DbPool,
+    errors::{AppError, AppResult},
+};
+
+/// First bytes of a gzip stream, which is how the dumps are published
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Records written per statement
+const BATCH_SIZE: usize = 1000;
+
+/// The Open Library dumps that can be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpKind {
+    Authors,
+    Works,
+    Editions,
+}
+
+impl DumpKind {
+    /// The record type of the dump lines, as in "/type/edition"
+    fn record_type(&self) -> &'static str {
+        match self {
+            DumpKind::Authors => "/type/author",
+            DumpKind::Works => "/type/work",
+            DumpKind::Editions => "/type/edition",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DumpLoadSummary {
+    /// Records written to the tables
+    pub records: usize,
+    /// Lines of another type, unreadable or without a title or name
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuthorRecord {
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkRecord {
+    pub key: String,
+    pub title: String,
+    pub title_key: String,
+    pub author_keys: Vec<String>,
+    pub description: Option<String>,
+    pub covers: Vec<i32>,
+    pub first_publish_year: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EditionRecord {
+    pub key: String,
+    pub work_key: Option<String>,
+    pub title: String,
+    pub title_key: String,
+    pub author_keys: Vec<String>,
+    pub description: Option<String>,
+    pub covers: Vec<i32>,
+    pub number_of_pages: Option<i32>,
+    pub languages: Vec<String>,
+    pub publishers: Vec<String>,
+    pub publish_year: Option<i32>,
+    /// Canonical ISBN-13s of the edition's ISBN-10s and ISBN-13s
+    pub isbns: Vec<String>,
+}
+
+/// Open a dump file, decompressing it when it is gzipped
+pub fn open_dump(path: &Path) -> std::io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Load a dump into its Open Library table, replacing records already loaded
+/// Lines are "type, key, revision, last modified, JSON record", tab separated;
+/// `progress` is called with the number of lines read after every batch.
+pub async fn load_dump(
+    pool: &DbPool,
+    kind: DumpKind,
+    reader: impl BufRead,
+    mut progress: impl FnMut(usize),
+) -> AppResult<DumpLoadSummary> {
+    let mut summary = DumpLoadSummary::default();
+    let mut batch: Vec<Value> = Vec::with_capacity(BATCH_SIZE);
+    let mut lines_read = 0;
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| AppError::Validation(format!("Failed to read the dump: {}", e)))?;
+        lines_read += 1;
+
+        let record = parse_line(kind, &line).and_then(|record| match kind {
+            DumpKind::Authors => parse_author(&record).map(|r| serde_json::to_value(r).ok()),
+            DumpKind::Works => parse_work(&record).map(|r| serde_json::to_value(r).ok()),
+            DumpKind::Editions => parse_edition(&record).map(|r| serde_json::to_value(r).ok()),
+        });
+        match record.flatten() {
+            Some(record) => batch.push(record),
+            None => summary.skipped += 1,
+        }
+
+        if batch.len() == BATCH_SIZE {
+            summary.records += write_batch(pool, kind, std::mem::take(&mut batch)).await?;
+            progress(lines_read);
+        }
+    }
+
+    if !batch.is_empty() {
+        summary.records += write_batch(pool, kind, batch).await?;
+    }
+    progress(lines_read);
+
+    Ok(summary)
+}
+
+async fn write_batch(pool: &DbPool, kind: DumpKind, batch: Vec<Value>) -> AppResult<usize> {
+    let count = batch.len();
+    let records = Value::Array(batch);
+    let mut tx = pool.begin().await?;
+
+    // DISTINCT ON: a key repeated within a batch would otherwise make the upsert fail
+    match kind {
+        DumpKind::Authors => {
+            sqlx::query(
+                r#"
+                INSERT INTO openlibrary_authors (key, name)
+                SELECT DISTINCT ON (key) key, name
+                FROM jsonb_to_recordset($1::jsonb) AS r(key TEXT, name TEXT)
+                ON CONFLICT (key) DO UPDATE SET name = EXCLUDED.name
+                "#,
+            )
+            .bind(&records)
+            .execute(&mut *tx)
+            .await?;
+        }
+        DumpKind::Works => {
+            sqlx::query(
+                r#"
+                INSERT INTO openlibrary_works (key, title, title_key, author_keys, description, covers, first_publish_year)
+                SELECT DISTINCT ON (key) key, title, title_key, author_keys, description, covers, first_publish_year
+                FROM jsonb_to_recordset($1::jsonb) AS r(
+                    key TEXT, title TEXT, title_key TEXT, author_keys TEXT[], description TEXT,
+                    covers INTEGER[], first_publish_year INTEGER
+                )
+                ON CONFLICT (key) DO UPDATE SET
+                    title = EXCLUDED.title,
+                    title_key = EXCLUDED.title_key,
+                    author_keys = EXCLUDED.author_keys,
+                    description = EXCLUDED.description,
+                    covers = EXCLUDED.covers,
+                    first_publish_year = EXCLUDED.first_publish_year
+                "#,
+            )
+            .bind(&records)
+            .execute(&mut *tx)
+            .await?;
+        }
+        DumpKind::Editions => {
+            sqlx::query(
+                r#"
+                INSERT INTO openlibrary_editions (
+                    key, work_key, title, title_key, author_keys, description, covers,
+                    number_of_pages, languages, publishers, publish_year
+                )
+                SELECT DISTINCT ON (key) key, work_key, title, title_key, author_keys, description, covers,
+                    number_of_pages, languages, publishers, publish_year
+                FROM jsonb_to_recordset($1::jsonb) AS r(
+                    key TEXT, work_key TEXT, title TEXT, title_key TEXT, author_keys TEXT[], description TEXT,
+                    covers INTEGER[], number_of_pages INTEGER, languages TEXT[], publishers TEXT[], publish_year INTEGER
+                )
+                ON CONFLICT (key) DO UPDATE SET
+                    work_key = EXCLUDED.work_key,
+                    title = EXCLUDED.title,
+                    title_key = EXCLUDED.title_key,
+                    author_keys = EXCLUDED.author_keys,
+                    description = EXCLUDED.description,
+                    covers = EXCLUDED.covers,
+                    number_of_pages = EXCLUDED.number_of_pages,
+                    languages = EXCLUDED.languages,
+                    publishers = EXCLUDED.publishers,
+                    publish_year = EXCLUDED.publish_year
+                "#,
+            )
+            .bind(&records)
+            .execute(&mut *tx)
+            .await?;
+
+            // A reloaded edition keeps only the ISBNs of its new revision
+            sqlx::query(
+                r#"
+                DELETE FROM openlibrary_isbns
+                WHERE edition_key IN (SELECT key FROM jsonb_to_recordset($1::jsonb) AS r(key TEXT))
+                "#,
+            )
+            .bind(&records)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO openlibrary_isbns (isbn13, edition_key)
+                SELECT DISTINCT isbn, r.key
+                FROM jsonb_to_recordset($1::jsonb) AS r(key TEXT, isbns TEXT[])
+                CROSS JOIN LATERAL UNNEST(r.isbns) AS isbn
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(&records)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(count)
+}
+
+/// The JSON record of a dump line of the given kind
+pub fn parse_line(kind: DumpKind, line: &str) -> Option<Value> {
+    let mut columns = line.splitn(5, '\t');
+    let record_type = columns.next()?;
+    if record_type != kind.record_type() {
+        return None;
+    }
+    serde_json::from_str(columns.nth(3)?).ok()
+}
+
+pub fn parse_author(record: &Value) -> Option<AuthorRecord> {
+    Some(AuthorRecord {
+        key: record_key(record.get("key")?)?,
+        name: non_blank(record.get("name"))?,
+    })
+}
+
+pub fn parse_work(record: &Value) -> Option<WorkRecord> {
+    let title = non_blank(record.get("title"))?;
+    // Work authors are wrapped in a role object: {"author": {"key": "/authors/..."}}
+    let author_keys = array(record.get("authors"))
+        .filter_map(|author| author.get("author").or(Some(author)))
+        .filter_map(|author| author.get("key"))
+        .filter_map(record_key)
+        .collect();
+
+    Some(WorkRecord {
+        key: record_key(record.get("key")?)?,
+        title_key: title_key(&title),
+        title,
+        author_keys,
+        description: description(record.get("description")),
+        covers: covers(record.get("covers")),
+        first_publish_year: record.get("first_publish_date").and_then(Value::as_str).and_then(publish_year),
+    })
+}
+
+pub fn parse_edition(record: &Value) -> Option<EditionRecord> {
+    let title = non_blank(record.get("title"))?;
+    let keys = |value: Option<&Value>| -> Vec<String> {
+        array(value).filter_map(|v| v.get("key")).filter_map(record_key).collect()
+    };
+
+    let mut seen = HashSet::new();
+    let isbns = array(record.get("isbn_13"))
+        .chain(array(record.get("isbn_10")))
+        .filter_map(Value::as_str)
+        .filter_map(|isbn| Isbn::parse(isbn).ok())
+        .map(|isbn| isbn.as_str().to_string())
+        .filter(|isbn| seen.insert(isbn.clone()))
+        .collect();
+
+    Some(EditionRecord {
+        key: record_key(record.get("key")?)?,
+        work_key: keys(record.get("works")).into_iter().next(),
+        title_key: title_key(&title),
+        title,
+        author_keys: keys(record.get("authors")),
+        description: description(record.get("description")),
+        covers: covers(record.get("covers")),
+        number_of_pages: record
+            .get("number_of_pages")
+            .and_then(Value::as_i64)
+            .filter(|pages| (1..=100_000).contains(pages))
+            .map(|pages| pages as i32),
+        languages: keys(record.get("languages")),
+        publishers: array(record.get("publishers"))
+            .filter_map(|publisher| non_blank(Some(publisher)))
+            .collect(),
+        publish_year: record.get("publish_date").and_then(Value::as_str).and_then(publish_year),
+        isbns,
+    })
+}
+
+/// The ID at the end of a key such as "/books/OL7353617M"
+fn record_key(value: &Value) -> Option<String> {
+    let key = value.as_str()?.rsplit('/').next()?.trim();
+    (!key.is_empty() && key.len() <= 32).then(|| key.to_string())
+}
+
+/// Descriptions are either plain strings or {"type": "/type/text", "value": "..."}
+fn description(value: Option<&Value>) -> Option<String> {
+    let value = value?;
+    non_blank(Some(value.get("value").unwrap_or(value)))
+}
+
+/// Cover IDs; Open Library uses -1 for a removed cover
+fn covers(value: Option<&Value>) -> Vec<i32> {
+    array(value)
+        .filter_map(Value::as_i64)
+        .filter(|id| *id > 0 && *id <= i32::MAX as i64)
+        .map(|id| id as i32)
+        .collect()
+}
+
+/// The first four-digit year of a free-form date such as "March 1999" or "c1985"
+pub fn publish_year(date: &str) -> Option<i32> {
+    let bytes = date.as_bytes();
+    (0..bytes.len().saturating_sub(3))
+        .filter(|&i| i == 0 || !bytes[i - 1].is_ascii_digit())
+        .filter(|&i| bytes[i..i + 4].iter().all(u8::is_ascii_digit))
+        .filter(|&i| bytes.get(i + 4).is_none_or(|b| !b.is_ascii_digit()))
+        .filter_map(|i| date[i..i + 4].parse().ok())
+        .find(|year| (1000..=2100).contains(year))
+}
+
+fn array(value: Option<&Value>) -> impl Iterator<Item = &Value> {
+    value.and_then(Value::as_array).into_iter().flatten()
+}
+
+fn non_blank(value: Option<&Value>) -> Option<String> {
+    value?.as_str().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_edition_line() {
+        let line = concat!(
+            "/type/edition\t/books/OL7353617M\t4\t2010-04-24T17:54:01.503315\t",
+            r#"{"key": "/books/OL7353617M", "title": "The Left Hand of Darkness", "#,
+            r#""authors": [{"key": "/authors/OL31938A"}], "works": [{"key": "/works/OL59800W"}], "#,
+            r#""isbn_10": ["0441478123"], "isbn_13": ["9780441478125"], "covers": [-1, 8231856], "#,
+            r#""languages": [{"key": "/languages/eng"}], "publishers": ["Ace Books"], "#,
+            r#""publish_date": "March 1987", "number_of_pages": 304, "#,
+            r#""description": {"type": "/type/text", "value": "A lone envoy."}}"#
+        );
+        let record = parse_line(DumpKind::Editions, line).unwrap();
+        let edition = parse_edition(&record).unwrap();
+
+        assert_eq!(edition.key, "OL7353617M");
+        assert_eq!(edition.work_key.as_deref(), Some("OL59800W"));
+        assert_eq!(edition.title_key, "left hand of darkness");
+        assert_eq!(edition.author_keys, vec!["OL31938A"]);
+        assert_eq!(edition.isbns, vec!["9780441478125"]);
+        assert_eq!(edition.covers, vec![8231856]);
+        assert_eq!(edition.languages, vec!["eng"]);
+        assert_eq!(edition.publishers, vec!["Ace Books"]);
+        assert_eq!(edition.publish_year, Some(1987));
+        assert_eq!(edition.number_of_pages, Some(304));
+        assert_eq!(edition.description.as_deref(), Some("A lone envoy."));
+
+        assert!(parse_line(DumpKind::Works, line).is_none());
+    }
+
+    #[test]
+    fn test_parse_work_and_author() {
+        let work = serde_json::json!({
+            "key": "/works/OL59800W",
+            "title": "The Left Hand of Darkness",
+            "authors": [{"type": {"key": "/type/author_role"}, "author": {"key": "/authors/OL31938A"}}],
+            "description": "Genly Ai is sent to Gethen.",
+            "first_publish_date": "1969",
+        });
+        let work = parse_work(&work).unwrap();
+        assert_eq!(work.author_keys, vec!["OL31938A"]);
+        assert_eq!(work.description.as_deref(), Some("Genly Ai is sent to Gethen."));
+        assert_eq!(work.first_publish_year, Some(1969));
+
+        let author = parse_author(&serde_json::json!({"key": "/authors/OL31938A", "name": "Ursula K. Le Guin"}));
+        assert_eq!(author.unwrap().name, "Ursula K. Le Guin");
+        assert!(parse_author(&serde_json::json!({"key": "/authors/OL1A", "name": " "})).is_none());
+    }
+
+    #[test]
+    fn test_publish_year() {
+        assert_eq!(publish_year("1999"), Some(1999));
+        assert_eq!(publish_year("c1985."), Some(1985));
+        assert_eq!(publish_year("2001-05-03"), Some(2001));
+        assert_eq!(publish_year("May 12, 12345"), None);
+        assert_eq!(publish_year("n.d."), None);
+    }
+}
@@ -0,0 +1,106 @@
+use serde::Serialize;
+
+use crate::{crypto::TokenCrypto, db::DbPool, errors::AppResult};
+
+/// A column holding values encrypted with ENCRYPTION_KEY
+struct EncryptedColumn {
+    table: &'static str,
+    key_column: &'static str,
+    value_column: &'static str,
+}
+
+const ENCRYPTED_COLUMNS: &[EncryptedColumn] = &[
+    EncryptedColumn { table: "connectors", key_column: "id", value_column: "encrypted_token" },
+    EncryptedColumn { table: "user_totp", key_column: "user_id", value_column: "encrypted_secret" },
+    EncryptedColumn { table: "oidc_signing_keys", key_column: "id", value_column: "encrypted_private_key" },
+];
+
+/// Outcome of re-encrypting one column
+#[derive(Debug, Serialize)]
+pub struct ReencryptionReport {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub total: usize,
+    pub outdated: usize,
+    pub reencrypted: usize,
+    /// Rows that no key of the keyring can decrypt, by primary key
+    pub failed: Vec<i32>,
+}
+
+/// Rewrite every stored ciphertext that is not encrypted with the active key
+/// Each row is updated only if it still holds the value that was read, so
+/// rows changed concurrently are left alone (and picked up by the next run).
+/// With `dry_run` nothing is written; `reencrypted` then counts what would be.
+pub async fn reencrypt_all(
+    pool: &DbPool,
+    crypto: &TokenCrypto,
+    dry_run: bool,
+) -> AppResult<Vec<ReencryptionReport>> {
+    let mut reports = Vec::with_capacity(ENCRYPTED_COLUMNS.len());
+
+    for column in ENCRYPTED_COLUMNS {
+        reports.push(reencrypt_column(pool, crypto, column, dry_run).await?);
+    }
+
+    Ok(reports)
+}
+
+async fn reencrypt_column(
+    pool: &DbPool,
+    crypto: &TokenCrypto,
+    column: &EncryptedColumn,
+    dry_run: bool,
+) -> AppResult<ReencryptionReport> {
+    let rows = sqlx::query_as::<_, (i32, String)>(&format!(
+        "SELECT {}, {} FROM {} WHERE {} IS NOT NULL ORDER BY {}",
+        column.key_column, column.value_column, column.table, column.value_column, column.key_column
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let mut report = ReencryptionReport {
+        table: column.table,
+        column: column.value_column,
+        total: rows.len(),
+        outdated: 0,
+        reencrypted: 0,
+        failed: Vec::new(),
+    };
+
+    let update = format!(
+        "UPDATE {} SET {} = $1 WHERE {} = $2 AND {} = $3",
+        column.table, column.value_column, column.key_column, column.value_column
+    );
+
+    for (id, ciphertext) in rows {
+        if !crypto.needs_reencryption(&ciphertext) {
+            continue;
+        }
+        report.outdated += 1;
+
+        let reencrypted = match crypto.reencrypt(&ciphertext) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!("Cannot re-encrypt {}.{} of row {}: {}", column.table, column.value_column, id, e);
+                report.failed.push(id);
+                continue;
+            }
+        };
+
+        if dry_run {
+            report.reencrypted += 1;
+            continue;
+        }
+
+        let result = sqlx::query(&update)
+            .bind(&reencrypted)
+            .bind(id)
+            .bind(&ciphertext)
+            .execute(pool)
+            .await?;
+
+        report.reencrypted += result.rows_affected() as usize;
+    }
+
+    Ok(report)
+}
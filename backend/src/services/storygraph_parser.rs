@@ -0,0 +1,246 @@
+use chrono::NaiveDate;
+use csv::ReaderBuilder;
+use serde::Deserialize;
+
+use crate::{
+    models::import::TransformedBook,
+    services::{
+        goodreads_parser::parse_date,
+        importer::{decode_text, isbn_or_none, notes_with_extras, round_rating, ImportRow, Importer},
+    },
+};
+
+/// A row of the StoryGraph CSV export
+/// Columns that are missing from older exports read as empty.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StoryGraphRecord {
+    #[serde(rename = "Title")]
+    pub title: String,
+
+    #[serde(rename = "Authors")]
+    pub authors: Option<String>,
+
+    #[serde(rename = "ISBN/UID")]
+    pub isbn_uid: Option<String>,
+
+    #[serde(rename = "Read Status")]
+    pub read_status: String,
+
+    #[serde(rename = "Date Added")]
+    pub date_added: Option<String>,
+
+    #[serde(rename = "Last Date Read")]
+    pub last_date_read: Option<String>,
+
+    /// Comma-separated ranges like "2024/01/05-2024/01/20"; the end is missing while reading
+    #[serde(rename = "Dates Read")]
+    pub dates_read: Option<String>,
+
+    #[serde(rename = "Moods")]
+    pub moods: Option<String>,
+
+    #[serde(rename = "Pace")]
+    pub pace: Option<String>,
+
+    /// Quarter stars, e.g. "4.25"
+    #[serde(rename = "Star Rating")]
+    pub star_rating: Option<String>,
+
+    #[serde(rename = "Review")]
+    pub review: Option<String>,
+
+    #[serde(rename = "Tags")]
+    pub tags: Option<String>,
+}
+
+/// StoryGraph "Export StoryGraph Library" CSV
+pub struct StoryGraphImporter;
+
+impl Importer for StoryGraphImporter {
+    fn source(&self) -> &'static str {
+        "StoryGraph"
+    }
+
+    fn key(&self) -> &'static str {
+        "storygraph"
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Vec<ImportRow>, String> {
+        let text = decode_text(data)?;
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+
+        Ok(reader
+            .deserialize::<StoryGraphRecord>()
+            .enumerate()
+            .map(|(idx, result)| {
+                let row_number = idx + 2;
+                match result {
+                    Ok(record) => ImportRow {
+                        row_number,
+                        title: Some(record.title.clone()),
+                        book: transform_storygraph_record(&record),
+                    },
+                    Err(e) => ImportRow {
+                        row_number,
+                        title: None,
+                        book: Err(format!("Row {}: CSV parse error - {}", row_number, e)),
+                    },
+                }
+            })
+            .collect())
+    }
+}
+
+/// Transform a StoryGraph row into internal book/reading data
+/// Only the latest read-through is kept. Did-not-finish books get a finished
+/// reading noted as such; moods, pace and tags are kept in the notes.
+pub fn transform_storygraph_record(record: &StoryGraphRecord) -> Result<TransformedBook, String> {
+    let title = record.title.trim();
+    if title.is_empty() {
+        return Err("Title is required".to_string());
+    }
+
+    let author = record
+        .authors
+        .as_deref()
+        .map(str::trim)
+        .filter(|authors| !authors.is_empty())
+        .map(str::to_string);
+    let isbn = record.isbn_uid.as_deref().and_then(isbn_or_none);
+    let rating = round_rating(record.star_rating.as_deref().unwrap_or_default())?;
+
+    let (range_start, range_end) = record
+        .dates_read
+        .as_deref()
+        .and_then(|dates| dates.split(',').map(str::trim).rfind(|range| !range.is_empty()))
+        .map(parse_date_range)
+        .unwrap_or_default();
+    let date_added = record.date_added.as_deref().and_then(parse_date);
+    let last_read = record.last_date_read.as_deref().and_then(parse_date);
+
+    let status = record.read_status.trim().to_lowercase();
+    let did_not_finish = status == "did-not-finish";
+    let (shelf, start_date, end_date) = match status.as_str() {
+        "read" | "did-not-finish" => {
+            let end = range_end.or(last_read).or(if did_not_finish { range_start } else { None });
+            // Without a recorded start, fall back to when the book was added
+            let start = range_start.or(match (date_added, end) {
+                (Some(added), Some(end)) if added <= end => Some(added),
+                _ => None,
+            });
+            ("read", start, end)
+        }
+        "currently-reading" => ("currently-reading", range_start.or(date_added), None),
+        _ => ("to-read", None, None),
+    };
+
+    let review = record
+        .review
+        .as_deref()
+        .map(str::trim)
+        .filter(|review| !review.is_empty())
+        .map(str::to_string);
+    let notes = match (did_not_finish, review) {
+        (true, Some(review)) => Some(format!("Did not finish\n\n{}", review)),
+        (true, None) => Some("Did not finish".to_string()),
+        (false, review) => review,
+    };
+    let notes = notes_with_extras(
+        notes,
+        &[
+            ("Moods", record.moods.clone()),
+            ("Pace", record.pace.clone()),
+            ("Tags", record.tags.clone()),
+        ],
+    );
+
+    Ok(TransformedBook {
+        title: title.to_string(),
+        author,
+        isbn,
+        publication_year: None,
+        publisher: None,
+        pages: None,
+        language: None,
+        format: None,
+        original_publication_year: None,
+        owned_copies: None,
+        goodreads_id: None,
+        identifiers: Vec::new(),
+        tags: Vec::new(),
+        shelf: shelf.to_string(),
+        rating,
+        notes,
+        start_date,
+        end_date,
+        read_count: None,
+    })
+}
+
+/// "2024/01/05-2024/01/20" or "2024/01/05-" (still reading)
+fn parse_date_range(range: &str) -> (Option<NaiveDate>, Option<NaiveDate>) {
+    // Both halves are ten characters long, whichever separator the dates use
+    let start = range.get(..10).and_then(parse_date);
+    let end = range
+        .get(10..)
+        .map(|rest| rest.trim_start_matches([' ', '-']))
+        .and_then(parse_date);
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "Title,Authors,Contributors,ISBN/UID,Format,Read Status,Date Added,Last Date Read,Dates Read,Read Count,Moods,Pace,Character- or Plot-Driven?,Strong Character Development?,Loveable Characters?,Diverse Characters?,Flawed Characters?,Star Rating,Review,Content Warnings,Content Warning Description,Tags,Owned?
+Piranesi,Susanna Clarke,,9781635575637,hardcover,read,2023/12/28,2024/01/20,\"2023/01/02-2023/01/09, 2024/01/05-2024/01/20\",2,\"mysterious, reflective\",medium,,,,,,4.75,Haunting.,,,\"fantasy, reread\",Yes
+The Silmarillion,J.R.R. Tolkien,,sg-8f2a0c,paperback,did-not-finish,2024/02/01,,2024/02/03-2024/03/01,0,,,,,,,,,,,,,No
+Circe,Madeline Miller,,9780316556347,ebook,currently-reading,2024/04/01,,2024/04/02-,0,,,,,,,,,,,,,No
+Dune,Frank Herbert,,,,to-read,2024/05/01,,,0,,,,,,,,,,,,,No
+";
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let rows = StoryGraphImporter.parse(EXPORT.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 4);
+        let books: Vec<_> = rows.into_iter().map(|row| row.book.unwrap()).collect();
+
+        let piranesi = &books[0];
+        assert_eq!(piranesi.shelf, "read");
+        assert_eq!(piranesi.isbn.as_deref(), Some("9781635575637"));
+        assert_eq!((piranesi.start_date, piranesi.end_date), (Some(date("2024-01-05")), Some(date("2024-01-20"))));
+        assert_eq!(piranesi.rating, Some(5));
+        assert_eq!(
+            piranesi.notes.as_deref(),
+            Some("Haunting.\n\nMoods: mysterious, reflective\n\nPace: medium\n\nTags: fantasy, reread")
+        );
+
+        let silmarillion = &books[1];
+        assert_eq!(silmarillion.shelf, "read");
+        assert_eq!(silmarillion.isbn, None);
+        assert_eq!(silmarillion.end_date, Some(date("2024-03-01")));
+        assert_eq!(silmarillion.notes.as_deref(), Some("Did not finish"));
+
+        let circe = &books[2];
+        assert_eq!(circe.shelf, "currently-reading");
+        assert_eq!((circe.start_date, circe.end_date), (Some(date("2024-04-02")), None));
+
+        assert_eq!(books[3].shelf, "to-read");
+        assert_eq!(books[3].start_date, None);
+    }
+
+    #[test]
+    fn test_parse_date_range() {
+        assert_eq!(parse_date_range("2024/01/05-2024/01/20"), (Some(date("2024-01-05")), Some(date("2024-01-20"))));
+        assert_eq!(parse_date_range("2024-01-05 - 2024-01-20"), (Some(date("2024-01-05")), Some(date("2024-01-20"))));
+        assert_eq!(parse_date_range("2024/04/02-"), (Some(date("2024-04-02")), None));
+    }
+}
@@ -0,0 +1,33 @@
+-- OpenID Connect signing keys and id_token support
+-- Migration: 00000000000006_create_oidc_signing_keys_table
+
+CREATE TABLE IF NOT EXISTS oidc_signing_keys (
+    id SERIAL PRIMARY KEY,
+    kid VARCHAR(100) NOT NULL UNIQUE,
+    algorithm VARCHAR(20) NOT NULL,
+    encrypted_private_key TEXT NOT NULL,
+    public_jwk JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    retired_at TIMESTAMP WITH TIME ZONE NULL,
+
+    CONSTRAINT check_signing_algorithm CHECK (algorithm IN ('RS256', 'EdDSA'))
+);
+
+-- Only one key may be used for signing at any time
+CREATE UNIQUE INDEX IF NOT EXISTS idx_oidc_signing_keys_active
+    ON oidc_signing_keys ((retired_at IS NULL))
+    WHERE retired_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_oidc_signing_keys_retired_at ON oidc_signing_keys(retired_at);
+
+-- Nonce sent by the relying party in the authorization request, echoed in the id_token
+ALTER TABLE oauth_codes ADD COLUMN IF NOT EXISTS nonce VARCHAR(255);
+
+-- Add comments for documentation
+COMMENT ON TABLE oidc_signing_keys IS 'Asymmetric keys used to sign OpenID Connect id_tokens';
+COMMENT ON COLUMN oidc_signing_keys.kid IS 'Key identifier (RFC 7638 JWK thumbprint), published in the JWKS';
+COMMENT ON COLUMN oidc_signing_keys.algorithm IS 'JOSE signing algorithm: RS256 or EdDSA';
+COMMENT ON COLUMN oidc_signing_keys.encrypted_private_key IS 'PKCS#8 PEM private key, encrypted with ENCRYPTION_KEY';
+COMMENT ON COLUMN oidc_signing_keys.public_jwk IS 'Public key in JWK format';
+COMMENT ON COLUMN oidc_signing_keys.retired_at IS 'When the key stopped signing (NULL for the active key); retired keys stay in the JWKS for a grace period';
+COMMENT ON COLUMN oauth_codes.nonce IS 'OpenID Connect nonce from the authorization request';
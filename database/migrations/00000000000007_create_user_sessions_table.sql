@@ -0,0 +1,27 @@
+-- Server-side sessions backing refresh tokens
+-- Migration: 00000000000007_create_user_sessions_table
+
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    user_agent VARCHAR(500),
+    ip_address VARCHAR(45),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NULL,
+    revoked_reason VARCHAR(50) NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_sessions_user_id ON user_sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_user_sessions_expires_at ON user_sessions(expires_at);
+
+-- Add comments for documentation
+COMMENT ON TABLE user_sessions IS 'Login sessions; each holds the hash of its only valid refresh token';
+COMMENT ON COLUMN user_sessions.refresh_token_hash IS 'SHA-256 of the current refresh token; replaced on every rotation';
+COMMENT ON COLUMN user_sessions.user_agent IS 'User-Agent of the client that last used the session';
+COMMENT ON COLUMN user_sessions.ip_address IS 'IP address of the client that last used the session';
+COMMENT ON COLUMN user_sessions.expires_at IS 'Session expiry, extended on every refresh';
+COMMENT ON COLUMN user_sessions.revoked_at IS 'When the session was revoked (logout, user action, reuse detection)';
+COMMENT ON COLUMN user_sessions.revoked_reason IS 'logout, revoked, reuse_detected';
@@ -0,0 +1,25 @@
+-- Single-use tokens for password reset and email verification
+-- Migration: 00000000000008_create_user_tokens_table
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMP WITH TIME ZONE NULL;
+
+CREATE TABLE IF NOT EXISTS user_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose VARCHAR(30) NOT NULL CHECK (purpose IN ('password_reset', 'email_verification')),
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    email VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_tokens_user_purpose ON user_tokens(user_id, purpose);
+
+-- Add comments for documentation
+COMMENT ON COLUMN users.email_verified_at IS 'When the current email address was verified; cleared when the email changes';
+COMMENT ON TABLE user_tokens IS 'Single-use tokens sent by email (password reset, email verification)';
+COMMENT ON COLUMN user_tokens.purpose IS 'password_reset or email_verification';
+COMMENT ON COLUMN user_tokens.token_hash IS 'SHA-256 of the token; the token itself is only sent by email';
+COMMENT ON COLUMN user_tokens.email IS 'Address the token was sent to; verification only applies while it is still the user email';
+COMMENT ON COLUMN user_tokens.used_at IS 'When the token was consumed; tokens are single-use';
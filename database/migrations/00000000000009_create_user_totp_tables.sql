@@ -0,0 +1,29 @@
+-- TOTP two-factor authentication
+-- Migration: 00000000000009_create_user_totp_tables
+
+CREATE TABLE IF NOT EXISTS user_totp (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    encrypted_secret TEXT NOT NULL,
+    enabled_at TIMESTAMP WITH TIME ZONE NULL,
+    last_used_step BIGINT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_recovery_codes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE(user_id, code_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_recovery_codes_user_id ON user_recovery_codes(user_id);
+
+-- Add comments for documentation
+COMMENT ON TABLE user_totp IS 'TOTP (RFC 6238) shared secrets; one per user';
+COMMENT ON COLUMN user_totp.encrypted_secret IS 'Shared secret encrypted with ChaCha20-Poly1305 (ENCRYPTION_KEY)';
+COMMENT ON COLUMN user_totp.enabled_at IS 'Set once enrollment is confirmed with a valid code; NULL while pending';
+COMMENT ON COLUMN user_totp.last_used_step IS 'Time step of the last accepted code; codes cannot be replayed';
+COMMENT ON TABLE user_recovery_codes IS 'Single-use recovery codes for when the authenticator is unavailable';
+COMMENT ON COLUMN user_recovery_codes.code_hash IS 'SHA-256 of the normalized recovery code';
@@ -0,0 +1,20 @@
+-- Shared rate limit state (RATE_LIMIT_BACKEND=postgres)
+-- Migration: 00000000000010_create_rate_limit_tables
+
+CREATE TABLE IF NOT EXISTS rate_limit_buckets (
+    key VARCHAR(255) PRIMARY KEY,
+    tat_ms BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS login_failures (
+    key VARCHAR(255) PRIMARY KEY,
+    failure_count INTEGER NOT NULL,
+    last_failure_ms BIGINT NOT NULL,
+    locked_until_ms BIGINT NULL
+);
+
+-- Add comments for documentation
+COMMENT ON TABLE rate_limit_buckets IS 'GCRA rate limit buckets, keyed by route group and client (IP or user)';
+COMMENT ON COLUMN rate_limit_buckets.tat_ms IS 'Theoretical arrival time in Unix milliseconds; the bucket is full once it is in the past';
+COMMENT ON TABLE login_failures IS 'Consecutive failed login / second-factor attempts for progressive lockout';
+COMMENT ON COLUMN login_failures.locked_until_ms IS 'Unix milliseconds until which attempts are refused';
@@ -0,0 +1,60 @@
+-- Admin role, account disabling, registration policy, invites and audit trail
+-- Migration: 00000000000011_create_admin_tables
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role VARCHAR(20) NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'admin'));
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled_at TIMESTAMP WITH TIME ZONE NULL;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled_reason VARCHAR(500) NULL;
+
+-- Existing installations: the first account becomes the administrator
+UPDATE users SET role = 'admin'
+WHERE id = (SELECT MIN(id) FROM users)
+  AND NOT EXISTS (SELECT 1 FROM users WHERE role = 'admin');
+
+-- Single-row table of instance-wide settings
+CREATE TABLE IF NOT EXISTS instance_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    registration_policy VARCHAR(20) NOT NULL DEFAULT 'open' CHECK (registration_policy IN ('open', 'invite_only', 'closed')),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+INSERT INTO instance_settings (id) VALUES (TRUE) ON CONFLICT (id) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS invite_codes (
+    id SERIAL PRIMARY KEY,
+    code_hash VARCHAR(64) NOT NULL UNIQUE,
+    code_hint VARCHAR(8) NOT NULL,
+    created_by INTEGER NULL REFERENCES users(id) ON DELETE SET NULL,
+    email VARCHAR(255) NULL,
+    max_uses INTEGER NOT NULL DEFAULT 1 CHECK (max_uses > 0),
+    use_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP WITH TIME ZONE NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS admin_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    admin_user_id INTEGER NULL REFERENCES users(id) ON DELETE SET NULL,
+    admin_username VARCHAR(50) NOT NULL,
+    action VARCHAR(50) NOT NULL,
+    target_user_id INTEGER NULL,
+    details JSONB NOT NULL DEFAULT '{}'::jsonb,
+    ip_address VARCHAR(45) NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_created_at ON admin_audit_log(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_target_user_id ON admin_audit_log(target_user_id);
+
+-- Add comments for documentation
+COMMENT ON COLUMN users.role IS 'user or admin';
+COMMENT ON COLUMN users.disabled_at IS 'When an administrator disabled the account; disabled users cannot log in';
+COMMENT ON TABLE instance_settings IS 'Instance-wide settings managed by administrators (single row)';
+COMMENT ON COLUMN instance_settings.registration_policy IS 'open, invite_only (requires an invite code) or closed';
+COMMENT ON TABLE invite_codes IS 'Invite codes for invite-only registration';
+COMMENT ON COLUMN invite_codes.code_hash IS 'SHA-256 of the code; the code is only shown when created';
+COMMENT ON COLUMN invite_codes.code_hint IS 'First characters of the code, to tell codes apart in listings';
+COMMENT ON COLUMN invite_codes.email IS 'If set, the code can only be used to register this email';
+COMMENT ON TABLE admin_audit_log IS 'Append-only trail of administrator actions';
+COMMENT ON COLUMN admin_audit_log.admin_username IS 'Username at the time of the action, kept if the admin is deleted';
+COMMENT ON COLUMN admin_audit_log.target_user_id IS 'Affected user, if any (no foreign key so entries survive deletion)';
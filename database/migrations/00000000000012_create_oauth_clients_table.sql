@@ -0,0 +1,18 @@
+-- OAuth2 clients registered by operators (in addition to the one from OAUTH_CLIENT_ID)
+-- Migration: 00000000000012_create_oauth_clients_table
+
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    id SERIAL PRIMARY KEY,
+    client_id VARCHAR(255) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    client_secret_hash VARCHAR(64) NOT NULL,
+    redirect_uris TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NULL
+);
+
+-- Add comments for documentation
+COMMENT ON TABLE oauth_clients IS 'OAuth2 clients managed with panicless-admin';
+COMMENT ON COLUMN oauth_clients.client_secret_hash IS 'SHA-256 of the client secret; the secret is only shown when created or rotated';
+COMMENT ON COLUMN oauth_clients.redirect_uris IS 'Allowed redirect URIs; empty allows any';
+COMMENT ON COLUMN oauth_clients.revoked_at IS 'Revoked clients can no longer authorize or exchange codes';
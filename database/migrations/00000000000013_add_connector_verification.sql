@@ -0,0 +1,19 @@
+-- Outcome of checking each connector's key against its provider
+-- Migration: 00000000000013_add_connector_verification
+
+ALTER TABLE connectors
+    ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'unverified',
+    ADD COLUMN IF NOT EXISTS status_detail TEXT NULL,
+    ADD COLUMN IF NOT EXISTS verified_at TIMESTAMP WITH TIME ZONE NULL;
+
+ALTER TABLE connectors
+    ADD CONSTRAINT check_connector_status
+    CHECK (status IN ('unverified', 'valid', 'invalid', 'rate_limited', 'unreachable'));
+
+-- Periodic re-verification scans active connectors by age of the last check
+CREATE INDEX IF NOT EXISTS idx_connectors_verified_at ON connectors(verified_at) WHERE is_active;
+
+-- Add comments for documentation
+COMMENT ON COLUMN connectors.status IS 'Result of the last key check: unverified, valid, invalid, rate_limited or unreachable';
+COMMENT ON COLUMN connectors.status_detail IS 'Provider message explaining a failed check';
+COMMENT ON COLUMN connectors.verified_at IS 'When the key was last checked against the provider; NULL if never';
@@ -0,0 +1,28 @@
+-- Self-hosted / OpenAI-compatible connectors (Ollama, llama.cpp, vLLM, ...)
+-- Migration: 00000000000014_add_custom_connectors
+
+-- Local model servers often need no key
+ALTER TABLE connectors ALTER COLUMN encrypted_token DROP NOT NULL;
+
+ALTER TABLE connectors
+    ADD COLUMN IF NOT EXISTS base_url TEXT NULL,
+    ADD COLUMN IF NOT EXISTS model VARCHAR(255) NULL;
+
+ALTER TABLE connectors DROP CONSTRAINT IF EXISTS check_provider_type;
+ALTER TABLE connectors
+    ADD CONSTRAINT check_provider_type
+    CHECK (provider IN ('anthropic', 'gemini', 'chatgpt', 'custom'));
+
+-- Custom connectors need an endpoint; hosted providers need a key and use the configured endpoint
+ALTER TABLE connectors
+    ADD CONSTRAINT check_connector_endpoint
+    CHECK (
+        (provider = 'custom' AND base_url IS NOT NULL AND model IS NOT NULL)
+        OR (provider <> 'custom' AND base_url IS NULL AND encrypted_token IS NOT NULL)
+    );
+
+-- Add comments for documentation
+COMMENT ON COLUMN connectors.provider IS 'AI provider name: anthropic, gemini, chatgpt, or custom (OpenAI-compatible server)';
+COMMENT ON COLUMN connectors.encrypted_token IS 'Encrypted API token/key for the provider; NULL for custom connectors without a key';
+COMMENT ON COLUMN connectors.base_url IS 'Base URL of an OpenAI-compatible server (custom connectors only)';
+COMMENT ON COLUMN connectors.model IS 'Model name; required for custom connectors, overrides the server default otherwise';
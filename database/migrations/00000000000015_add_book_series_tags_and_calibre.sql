@@ -0,0 +1,24 @@
+-- Series, tags and Calibre identity for books
+-- Migration: 00000000000015_add_book_series_tags_and_calibre
+
+ALTER TABLE books
+    ADD COLUMN IF NOT EXISTS series VARCHAR(255) NULL,
+    ADD COLUMN IF NOT EXISTS series_index DOUBLE PRECISION NULL,
+    ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS calibre_uuid VARCHAR(36) NULL,
+    ADD COLUMN IF NOT EXISTS calibre_last_modified VARCHAR(40) NULL;
+
+-- A Calibre book is synced into at most one book per user
+CREATE UNIQUE INDEX IF NOT EXISTS idx_books_user_calibre_uuid
+    ON books(user_id, calibre_uuid)
+    WHERE calibre_uuid IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_books_series ON books(user_id, series) WHERE series IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_books_tags ON books USING GIN (tags);
+
+-- Add comments for documentation
+COMMENT ON COLUMN books.series IS 'Series the book belongs to';
+COMMENT ON COLUMN books.series_index IS 'Position in the series (Calibre allows fractions such as 1.5)';
+COMMENT ON COLUMN books.tags IS 'Free-form tags';
+COMMENT ON COLUMN books.calibre_uuid IS 'UUID of the Calibre book this book is synced from';
+COMMENT ON COLUMN books.calibre_last_modified IS 'Calibre last_modified value at the last sync, compared as text to skip unchanged books';
@@ -0,0 +1,72 @@
+-- Migration: Create highlights table
+-- Description: Quotes, highlights and notes taken while reading a book
+
+CREATE TABLE IF NOT EXISTS highlights (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    reading_id INTEGER REFERENCES readings(id) ON DELETE SET NULL,
+    text TEXT,
+    note TEXT,
+    location_start INTEGER,
+    location_end INTEGER,
+    page INTEGER,
+    highlighted_at TIMESTAMP WITH TIME ZONE,
+    source VARCHAR(20) NOT NULL DEFAULT 'manual',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+
+    -- A highlight is a quote, a note, or both
+    CONSTRAINT highlight_content_check CHECK (text IS NOT NULL OR note IS NOT NULL),
+    CONSTRAINT highlight_location_check CHECK (location_end IS NULL OR location_end >= location_start),
+    CONSTRAINT highlight_page_check CHECK (page IS NULL OR page > 0)
+);
+
+-- Create indexes for faster lookups and queries
+CREATE INDEX IF NOT EXISTS idx_highlights_user_id ON highlights(user_id);
+CREATE INDEX IF NOT EXISTS idx_highlights_book_id ON highlights(book_id);
+CREATE INDEX IF NOT EXISTS idx_highlights_reading_id ON highlights(reading_id);
+
+-- Create trigger to automatically update updated_at timestamp
+CREATE TRIGGER update_highlights_updated_at
+    BEFORE UPDATE ON highlights
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Highlights may only point at the user's own book, and at a reading of that book
+CREATE OR REPLACE FUNCTION validate_highlight_ownership()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM books
+        WHERE id = NEW.book_id AND user_id = NEW.user_id
+    ) THEN
+        RAISE EXCEPTION 'Cannot create highlight: book % does not belong to user %',
+            NEW.book_id, NEW.user_id;
+    END IF;
+    IF NEW.reading_id IS NOT NULL AND NOT EXISTS (
+        SELECT 1 FROM readings
+        WHERE id = NEW.reading_id AND book_id = NEW.book_id AND user_id = NEW.user_id
+    ) THEN
+        RAISE EXCEPTION 'Cannot create highlight: reading % is not a reading of book %',
+            NEW.reading_id, NEW.book_id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER validate_highlight_ownership_trigger
+    BEFORE INSERT OR UPDATE ON highlights
+    FOR EACH ROW
+    EXECUTE FUNCTION validate_highlight_ownership();
+
+-- Add comments for documentation
+COMMENT ON TABLE highlights IS 'Quotes, highlights and notes attached to a book';
+COMMENT ON COLUMN highlights.reading_id IS 'Reading during which the highlight was made (optional)';
+COMMENT ON COLUMN highlights.text IS 'Highlighted passage (NULL for a note without a quote)';
+COMMENT ON COLUMN highlights.note IS 'Reader note on the passage';
+COMMENT ON COLUMN highlights.location_start IS 'E-reader location where the passage starts';
+COMMENT ON COLUMN highlights.location_end IS 'E-reader location where the passage ends';
+COMMENT ON COLUMN highlights.page IS 'Printed page number';
+COMMENT ON COLUMN highlights.highlighted_at IS 'When the passage was highlighted';
+COMMENT ON COLUMN highlights.source IS 'Where the highlight came from: manual or kindle';
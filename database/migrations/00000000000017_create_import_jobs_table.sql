@@ -0,0 +1,50 @@
+-- Migration: Create import jobs table
+-- Description: Reading-site imports run in the background; one row per import with its progress and results
+
+CREATE TABLE IF NOT EXISTS import_jobs (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    source VARCHAR(20) NOT NULL,
+    file_name VARCHAR(255),
+    status VARCHAR(20) NOT NULL DEFAULT 'queued',
+    total_rows INTEGER NOT NULL DEFAULT 0,
+    processed_rows INTEGER NOT NULL DEFAULT 0,
+    books_created INTEGER NOT NULL DEFAULT 0,
+    books_updated INTEGER NOT NULL DEFAULT 0,
+    books_skipped INTEGER NOT NULL DEFAULT 0,
+    readings_created INTEGER NOT NULL DEFAULT 0,
+    failed_rows INTEGER NOT NULL DEFAULT 0,
+    cancel_requested BOOLEAN NOT NULL DEFAULT FALSE,
+    result JSONB,
+    error TEXT,
+    started_at TIMESTAMP WITH TIME ZONE,
+    finished_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+
+    CONSTRAINT import_job_status_check CHECK (status IN ('queued', 'running', 'completed', 'failed', 'cancelled')),
+    CONSTRAINT import_job_progress_check CHECK (processed_rows >= 0 AND processed_rows <= total_rows)
+);
+
+-- Create indexes for faster lookups and queries
+CREATE INDEX IF NOT EXISTS idx_import_jobs_user_created ON import_jobs(user_id, created_at DESC);
+
+-- One import at a time per user, so concurrent jobs cannot both create the same book
+CREATE UNIQUE INDEX IF NOT EXISTS idx_import_jobs_one_unfinished
+    ON import_jobs(user_id)
+    WHERE status IN ('queued', 'running');
+
+-- Create trigger to automatically update updated_at timestamp
+CREATE TRIGGER update_import_jobs_updated_at
+    BEFORE UPDATE ON import_jobs
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Add comments for documentation
+COMMENT ON TABLE import_jobs IS 'Background imports of reading-site exports and their history';
+COMMENT ON COLUMN import_jobs.source IS 'Export format: goodreads, storygraph or librarything';
+COMMENT ON COLUMN import_jobs.status IS 'queued, running, completed, failed or cancelled';
+COMMENT ON COLUMN import_jobs.processed_rows IS 'Rows handled so far, for progress reporting';
+COMMENT ON COLUMN import_jobs.cancel_requested IS 'Set by the user; the job stops before its next row';
+COMMENT ON COLUMN import_jobs.result IS 'Per-row import results (ImportResponse), kept for later review';
+COMMENT ON COLUMN import_jobs.error IS 'Why the job failed as a whole, e.g. an unreadable file';
@@ -0,0 +1,39 @@
+-- Migration: Create import job changes table
+-- Description: What each import created or changed, with the previous values, so it can be reverted
+
+CREATE TABLE IF NOT EXISTS import_job_changes (
+    id BIGSERIAL PRIMARY KEY,
+    job_id INTEGER NOT NULL REFERENCES import_jobs(id) ON DELETE CASCADE,
+    entity VARCHAR(10) NOT NULL,
+    entity_id INTEGER NOT NULL,
+    operation VARCHAR(10) NOT NULL,
+    previous JSONB,
+    previous_updated_at TIMESTAMP WITH TIME ZONE,
+    applied_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    CONSTRAINT import_change_entity_check CHECK (entity IN ('book', 'reading')),
+    CONSTRAINT import_change_operation_check CHECK (operation IN ('created', 'updated')),
+    -- Updates keep the values from before the import
+    CONSTRAINT import_change_previous_check CHECK ((operation = 'updated') = (previous IS NOT NULL AND previous_updated_at IS NOT NULL)),
+    -- A row touched twice by one import is recorded once, with its state before the first change
+    CONSTRAINT import_change_unique UNIQUE (job_id, entity, entity_id)
+);
+
+-- Create indexes for faster lookups and queries
+CREATE INDEX IF NOT EXISTS idx_import_job_changes_job_id ON import_job_changes(job_id);
+
+-- Reverted imports keep their history
+ALTER TABLE import_jobs ADD COLUMN IF NOT EXISTS reverted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE import_jobs DROP CONSTRAINT IF EXISTS import_job_status_check;
+ALTER TABLE import_jobs ADD CONSTRAINT import_job_status_check
+    CHECK (status IN ('queued', 'running', 'completed', 'failed', 'cancelled', 'reverted'));
+
+-- Add comments for documentation
+COMMENT ON TABLE import_job_changes IS 'Books and readings created or changed by an import, for reverting it';
+COMMENT ON COLUMN import_job_changes.entity IS 'book or reading';
+COMMENT ON COLUMN import_job_changes.operation IS 'created or updated';
+COMMENT ON COLUMN import_job_changes.previous IS 'Field values before an update (NULL for created rows)';
+COMMENT ON COLUMN import_job_changes.previous_updated_at IS 'updated_at of the row before an update, to hand a restored row back to the import before';
+COMMENT ON COLUMN import_job_changes.applied_at IS 'updated_at of the row after the import; a later value means it was edited since';
+COMMENT ON COLUMN import_jobs.reverted_at IS 'When the import was reverted';
+COMMENT ON COLUMN import_jobs.status IS 'queued, running, completed, failed, cancelled or reverted';
@@ -0,0 +1,18 @@
+-- Format, ownership and original publication year for books, and their Goodreads ID
+-- Migration: 00000000000019_add_book_format_and_goodreads_fields
+
+ALTER TABLE books
+    ADD COLUMN IF NOT EXISTS format VARCHAR(50) NULL,
+    ADD COLUMN IF NOT EXISTS original_publication_year INTEGER NULL
+        CHECK (original_publication_year >= 1000 AND original_publication_year <= 9999),
+    ADD COLUMN IF NOT EXISTS owned_copies INTEGER NULL CHECK (owned_copies >= 0),
+    ADD COLUMN IF NOT EXISTS goodreads_id VARCHAR(20) NULL;
+
+CREATE INDEX IF NOT EXISTS idx_books_user_goodreads_id ON books(user_id, goodreads_id) WHERE goodreads_id IS NOT NULL;
+
+-- Add comments for documentation
+COMMENT ON COLUMN books.format IS 'Binding or format of the edition, e.g. Paperback or Kindle Edition';
+COMMENT ON COLUMN books.publication_year IS 'Year this edition was published';
+COMMENT ON COLUMN books.original_publication_year IS 'Year the work was first published';
+COMMENT ON COLUMN books.owned_copies IS 'Copies the user owns (NULL when unknown)';
+COMMENT ON COLUMN books.goodreads_id IS 'Goodreads book ID the book was imported from';
@@ -0,0 +1,93 @@
+-- Migration: Create book_identifiers table
+-- Description: Typed external identifiers of books (ISBNs, ASIN, Goodreads, Open Library, ...)
+-- used to match books across imports and to look them up
+
+CREATE TABLE IF NOT EXISTS book_identifiers (
+    id SERIAL PRIMARY KEY,
+    book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    id_type VARCHAR(30) NOT NULL CHECK (id_type IN (
+        'isbn10', 'isbn13', 'asin', 'goodreads', 'openlibrary_work', 'openlibrary_edition',
+        'calibre_uuid', 'oclc', 'lccn'
+    )),
+    value VARCHAR(64) NOT NULL,
+    source VARCHAR(20) NOT NULL DEFAULT 'manual' CHECK (source IN ('book', 'manual', 'import', 'calibre')),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+
+    CONSTRAINT book_identifiers_unique UNIQUE (book_id, id_type, value)
+);
+
+-- Lookups go from an identifier to the books carrying it
+CREATE INDEX IF NOT EXISTS idx_book_identifiers_type_value ON book_identifiers(id_type, value);
+
+-- Keep the identifiers derived from the isbn, goodreads_id and calibre_uuid columns in sync.
+-- Values are normalized the way the API normalizes them: ISBNs keep only digits and X,
+-- Calibre UUIDs are lowercased.
+CREATE OR REPLACE FUNCTION book_isbn_identifier(isbn TEXT)
+RETURNS TABLE (id_type TEXT, value TEXT) AS $$
+DECLARE
+    cleaned TEXT := upper(regexp_replace(COALESCE(isbn, ''), '[^0-9Xx]', '', 'g'));
+BEGIN
+    IF cleaned ~ '^[0-9]{13}$' THEN
+        RETURN QUERY SELECT 'isbn13'::TEXT, cleaned;
+    ELSIF cleaned ~ '^[0-9]{9}[0-9X]$' THEN
+        RETURN QUERY SELECT 'isbn10'::TEXT, cleaned;
+    END IF;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+CREATE OR REPLACE FUNCTION sync_book_identifiers()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' THEN
+        DELETE FROM book_identifiers i
+        WHERE i.book_id = NEW.id AND i.source = 'book'
+          AND (
+              (NEW.isbn IS DISTINCT FROM OLD.isbn
+                  AND (i.id_type, i.value) IN (SELECT * FROM book_isbn_identifier(OLD.isbn)))
+              OR (NEW.goodreads_id IS DISTINCT FROM OLD.goodreads_id
+                  AND i.id_type = 'goodreads' AND i.value = OLD.goodreads_id)
+              OR (NEW.calibre_uuid IS DISTINCT FROM OLD.calibre_uuid
+                  AND i.id_type = 'calibre_uuid' AND i.value = lower(OLD.calibre_uuid))
+          );
+    END IF;
+
+    INSERT INTO book_identifiers (book_id, id_type, value, source)
+    SELECT NEW.id, derived.id_type, derived.value, 'book'
+    FROM (
+        SELECT * FROM book_isbn_identifier(NEW.isbn)
+        UNION ALL SELECT 'goodreads', NEW.goodreads_id WHERE NEW.goodreads_id IS NOT NULL
+        UNION ALL SELECT 'calibre_uuid', lower(NEW.calibre_uuid) WHERE NEW.calibre_uuid IS NOT NULL
+    ) AS derived
+    ON CONFLICT (book_id, id_type, value) DO NOTHING;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sync_book_identifiers_trigger
+    AFTER INSERT OR UPDATE OF isbn, goodreads_id, calibre_uuid ON books
+    FOR EACH ROW
+    EXECUTE FUNCTION sync_book_identifiers();
+
+-- Backfill the identifiers of existing books
+INSERT INTO book_identifiers (book_id, id_type, value, source)
+SELECT b.id, derived.id_type, derived.value, 'book'
+FROM books b
+CROSS JOIN LATERAL (
+    SELECT * FROM book_isbn_identifier(b.isbn)
+    UNION ALL SELECT 'goodreads', b.goodreads_id WHERE b.goodreads_id IS NOT NULL
+    UNION ALL SELECT 'calibre_uuid', lower(b.calibre_uuid) WHERE b.calibre_uuid IS NOT NULL
+) AS derived
+ON CONFLICT (book_id, id_type, value) DO NOTHING;
+
+-- Imports record the identifiers they add, so reverting removes them
+ALTER TABLE import_job_changes DROP CONSTRAINT IF EXISTS import_change_entity_check;
+ALTER TABLE import_job_changes ADD CONSTRAINT import_change_entity_check
+    CHECK (entity IN ('book', 'reading', 'identifier'));
+
+-- Add comments for documentation
+COMMENT ON TABLE book_identifiers IS 'Typed external identifiers of books, used for matching and lookups';
+COMMENT ON COLUMN book_identifiers.book_id IS 'Foreign key to books table - book the identifier belongs to';
+COMMENT ON COLUMN book_identifiers.id_type IS 'Kind of identifier: isbn10, isbn13, asin, goodreads, openlibrary_work, openlibrary_edition, calibre_uuid, oclc or lccn';
+COMMENT ON COLUMN book_identifiers.value IS 'Normalized identifier value';
+COMMENT ON COLUMN book_identifiers.source IS 'Where the identifier came from: book (derived from the book''s own columns), manual, import or calibre';
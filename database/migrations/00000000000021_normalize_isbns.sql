@@ -0,0 +1,75 @@
+-- Migration: Normalize ISBNs
+-- Description: Store book ISBNs as checksum-validated ISBN-13 digits, and keep ISBN
+-- identifiers in the same form so ISBN-10s and ISBN-13s of an edition match
+
+-- Canonical ISBN-13 of an ISBN-10 or ISBN-13, or NULL when it is not a valid one.
+-- Mirrors panicless_mcp_lib::isbn::Isbn::parse.
+CREATE OR REPLACE FUNCTION isbn13(isbn TEXT)
+RETURNS TEXT AS $$
+DECLARE
+    cleaned TEXT := upper(regexp_replace(COALESCE(isbn, ''), '[^0-9Xx]', '', 'g'));
+    body TEXT;
+    total INTEGER := 0;
+BEGIN
+    IF cleaned ~ '^[0-9]{9}[0-9X]$' THEN
+        FOR i IN 1..9 LOOP
+            total := total + substr(cleaned, i, 1)::INTEGER * (11 - i);
+        END LOOP;
+        IF (CASE WHEN right(cleaned, 1) = 'X' THEN 10 ELSE right(cleaned, 1)::INTEGER END) <> (11 - total % 11) % 11 THEN
+            RETURN NULL;
+        END IF;
+        body := '978' || left(cleaned, 9);
+    ELSIF cleaned ~ '^97[89][0-9]{10}$' THEN
+        body := left(cleaned, 12);
+    ELSE
+        RETURN NULL;
+    END IF;
+
+    total := 0;
+    FOR i IN 1..12 LOOP
+        total := total + substr(body, i, 1)::INTEGER * (CASE WHEN i % 2 = 1 THEN 1 ELSE 3 END);
+    END LOOP;
+    IF length(cleaned) = 13 AND right(cleaned, 1)::INTEGER <> (10 - total % 10) % 10 THEN
+        RETURN NULL;
+    END IF;
+
+    RETURN body || ((10 - total % 10) % 10)::TEXT;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- Books now derive a single isbn13 identifier from their ISBN
+CREATE OR REPLACE FUNCTION book_isbn_identifier(isbn TEXT)
+RETURNS TABLE (id_type TEXT, value TEXT) AS $$
+    SELECT 'isbn13'::TEXT, isbn13(isbn) WHERE isbn13(isbn) IS NOT NULL;
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Drop the identifiers derived the old way, then normalize the ISBNs themselves.
+-- Values that do not validate are left as they were; edits will have to fix them.
+DELETE FROM book_identifiers WHERE source = 'book' AND id_type IN ('isbn10', 'isbn13');
+
+ALTER TABLE books DISABLE TRIGGER update_books_updated_at;
+UPDATE books SET isbn = isbn13(isbn) WHERE isbn13(isbn) IS NOT NULL AND isbn <> isbn13(isbn);
+ALTER TABLE books ENABLE TRIGGER update_books_updated_at;
+
+INSERT INTO book_identifiers (book_id, id_type, value, source)
+SELECT b.id, derived.id_type, derived.value, 'book'
+FROM books b
+CROSS JOIN LATERAL book_isbn_identifier(b.isbn) AS derived
+ON CONFLICT (book_id, id_type, value) DO NOTHING;
+
+-- Manual, imported and Calibre ISBN-10s become ISBN-13s, in place so import
+-- history still points at them; those the book already has as ISBN-13 go
+UPDATE book_identifiers i
+SET id_type = 'isbn13', value = isbn13(i.value)
+WHERE i.id_type = 'isbn10'
+  AND isbn13(i.value) IS NOT NULL
+  AND NOT EXISTS (
+      SELECT 1 FROM book_identifiers j
+      WHERE j.book_id = i.book_id AND j.id_type = 'isbn13' AND j.value = isbn13(i.value)
+  );
+
+DELETE FROM book_identifiers WHERE id_type = 'isbn10' AND isbn13(value) IS NOT NULL;
+
+-- Add comments for documentation
+COMMENT ON FUNCTION isbn13(TEXT) IS 'Canonical ISBN-13 of an ISBN-10 or ISBN-13, NULL when the check digit is wrong';
+COMMENT ON COLUMN books.isbn IS 'ISBN-13 digits without hyphens; ISBN-10s are converted when saved';
@@ -0,0 +1,68 @@
+-- Migration: Create Open Library tables
+-- Description: A locally loaded copy of the Open Library editions, works and authors dumps,
+-- used to fill missing book fields, and per-field provenance of book data
+
+CREATE TABLE IF NOT EXISTS openlibrary_authors (
+    key VARCHAR(32) PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS openlibrary_works (
+    key VARCHAR(32) PRIMARY KEY,
+    title TEXT NOT NULL,
+    title_key TEXT NOT NULL,
+    author_keys TEXT[] NOT NULL DEFAULT '{}',
+    description TEXT,
+    covers INTEGER[] NOT NULL DEFAULT '{}',
+    first_publish_year INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS openlibrary_editions (
+    key VARCHAR(32) PRIMARY KEY,
+    work_key VARCHAR(32),
+    title TEXT NOT NULL,
+    title_key TEXT NOT NULL,
+    author_keys TEXT[] NOT NULL DEFAULT '{}',
+    description TEXT,
+    covers INTEGER[] NOT NULL DEFAULT '{}',
+    number_of_pages INTEGER,
+    languages TEXT[] NOT NULL DEFAULT '{}',
+    publishers TEXT[] NOT NULL DEFAULT '{}',
+    publish_year INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS openlibrary_isbns (
+    isbn13 CHAR(13) NOT NULL,
+    edition_key VARCHAR(32) NOT NULL,
+
+    PRIMARY KEY (isbn13, edition_key)
+);
+
+-- Books without an ISBN are matched on their normalized title
+CREATE INDEX IF NOT EXISTS idx_openlibrary_editions_title_key ON openlibrary_editions(title_key);
+CREATE INDEX IF NOT EXISTS idx_openlibrary_works_title_key ON openlibrary_works(title_key);
+
+CREATE TABLE IF NOT EXISTS book_field_sources (
+    book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    field VARCHAR(40) NOT NULL,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('user', 'openlibrary')),
+    reference VARCHAR(64),
+    recorded_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+
+    PRIMARY KEY (book_id, field)
+);
+
+-- Enrichment records the Open Library edition and work it matched
+ALTER TABLE book_identifiers DROP CONSTRAINT IF EXISTS book_identifiers_source_check;
+ALTER TABLE book_identifiers ADD CONSTRAINT book_identifiers_source_check
+    CHECK (source IN ('book', 'manual', 'import', 'calibre', 'openlibrary'));
+
+-- Add comments for documentation
+COMMENT ON TABLE openlibrary_editions IS 'Editions from the Open Library dump, keyed by edition ID (OL...M)';
+COMMENT ON TABLE openlibrary_works IS 'Works from the Open Library dump, keyed by work ID (OL...W)';
+COMMENT ON TABLE openlibrary_authors IS 'Authors from the Open Library dump, keyed by author ID (OL...A)';
+COMMENT ON TABLE openlibrary_isbns IS 'ISBN-13s of the Open Library editions';
+COMMENT ON COLUMN openlibrary_editions.title_key IS 'Title normalized for matching: lowercase, no accents, punctuation, subtitle or leading article';
+COMMENT ON TABLE book_field_sources IS 'Where the current value of a book field came from, when known';
+COMMENT ON COLUMN book_field_sources.source IS 'user (edited by the user, never overwritten by enrichment) or openlibrary';
+COMMENT ON COLUMN book_field_sources.reference IS 'Open Library edition or work the value was taken from';
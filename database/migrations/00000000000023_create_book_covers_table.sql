@@ -0,0 +1,20 @@
+-- Migration: Create book covers table
+-- Description: Cover images uploaded for books; the files themselves live in the
+-- configured cover storage (local directory or S3-compatible bucket)
+
+CREATE TABLE IF NOT EXISTS book_covers (
+    book_id INTEGER PRIMARY KEY REFERENCES books(id) ON DELETE CASCADE,
+    storage_prefix VARCHAR(128) NOT NULL,
+    content_type VARCHAR(40) NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    byte_size INTEGER NOT NULL,
+    sha256 CHAR(64) NOT NULL,
+    uploaded_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+-- Add comments for documentation
+COMMENT ON TABLE book_covers IS 'Uploaded cover image of a book, at most one per book';
+COMMENT ON COLUMN book_covers.storage_prefix IS 'Storage key prefix: <user id>/<book id>/<first 16 hex digits of sha256>; the original, medium and thumbnail files are under it';
+COMMENT ON COLUMN book_covers.content_type IS 'Media type of the original, sniffed from its content (image/jpeg, image/png, image/webp or image/gif)';
+COMMENT ON COLUMN book_covers.sha256 IS 'SHA-256 of the original file, hex encoded';
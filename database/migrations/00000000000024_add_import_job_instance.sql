@@ -0,0 +1,13 @@
+-- Server instance running each import job, so a restarting instance only fails its own jobs
+-- Migration: 00000000000024_add_import_job_instance
+
+ALTER TABLE import_jobs
+    ADD COLUMN IF NOT EXISTS instance_id VARCHAR(255) NULL;
+
+CREATE INDEX IF NOT EXISTS idx_import_jobs_unfinished_instance
+    ON import_jobs(instance_id)
+    WHERE status IN ('queued', 'running');
+
+-- Add comments for documentation
+COMMENT ON COLUMN import_jobs.instance_id IS 'INSTANCE_ID of the server running the job (NULL for jobs from before it was recorded)';
+COMMENT ON COLUMN import_jobs.updated_at IS 'Also the heartbeat of a running job, which saves its progress every few rows';
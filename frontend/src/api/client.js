@@ -30,6 +30,9 @@ apiClient.interceptors.request.use(
   (error) => Promise.reject(error)
 )
 
+// Public auth endpoints answer 401 for bad credentials, not for expired tokens
+const NO_REFRESH_URLS = ['/api/auth/login', '/api/auth/refresh', '/api/auth/logout', '/api/auth/2fa/verify']
+
 // Response interceptor to handle token refresh
 apiClient.interceptors.response.use(
   (response) => response,
@@ -37,7 +40,11 @@ apiClient.interceptors.response.use(
     const originalRequest = error.config
 
     // If error is 401 and we haven't tried to refresh yet
-    if (error.response?.status === 401 && !originalRequest._retry) {
+    if (
+      error.response?.status === 401 &&
+      !originalRequest._retry &&
+      !NO_REFRESH_URLS.includes(originalRequest.url)
+    ) {
       originalRequest._retry = true
 
       const authStore = useAuthStore()
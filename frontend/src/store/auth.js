@@ -21,6 +21,18 @@ export const useAuthStore = defineStore('auth', () => {
 
   async function login(credentials) {
     const response = await apiClient.post('/api/auth/login', credentials)
+    // Two-factor accounts get a challenge token instead of a session
+    if (!response.data.mfa_required) {
+      setAuthData(response.data)
+    }
+    return response.data
+  }
+
+  async function verifyTwoFactor(challengeToken, code) {
+    const response = await apiClient.post('/api/auth/2fa/verify', {
+      challenge_token: challengeToken,
+      code
+    })
     setAuthData(response.data)
     return response.data
   }
@@ -61,6 +73,13 @@ export const useAuthStore = defineStore('auth', () => {
   }
 
   function logout() {
+    // Revoke the server-side session; local state is cleared regardless
+    if (refreshTokenValue.value) {
+      apiClient
+        .post('/api/auth/logout', { refresh_token: refreshTokenValue.value })
+        .catch(() => {})
+    }
+
     user.value = null
     accessToken.value = null
     refreshTokenValue.value = null
@@ -81,6 +100,7 @@ export const useAuthStore = defineStore('auth', () => {
     currentUser,
     register,
     login,
+    verifyTwoFactor,
     refreshToken,
     fetchCurrentUser,
     logout
@@ -135,14 +135,231 @@ export const useBooksStore = defineStore('books', () => {
     }
   }
 
-  async function importGoodreadsCSV(file) {
+  async function fetchBookHighlights(id) {
+    error.value = null
+    try {
+      const response = await apiClient.get(`/api/books/${id}/highlights`)
+      return response.data
+    } catch (err) {
+      error.value = err.response?.data?.message || 'Failed to fetch highlights'
+      throw err
+    }
+  }
+
+  async function fetchBookIdentifiers(id) {
+    const response = await apiClient.get(`/api/books/${id}/identifiers`)
+    return response.data
+  }
+
+  async function addBookIdentifier(id, idType, value) {
+    const response = await apiClient.post(`/api/books/${id}/identifiers`, { id_type: idType, value })
+    return response.data
+  }
+
+  async function deleteBookIdentifier(id, identifierId) {
+    await apiClient.delete(`/api/books/${id}/identifiers/${identifierId}`)
+  }
+
+  async function lookupBooks(value, idType = null) {
+    const params = idType ? { type: idType, value } : { value }
+    const response = await apiClient.get('/api/books/lookup', { params })
+    return response.data
+  }
+
+  async function fetchIsbnInfo(isbn) {
+    const response = await apiClient.get(`/api/isbn/${encodeURIComponent(isbn)}`)
+    return response.data
+  }
+
+  async function enrichBook(id) {
+    const response = await apiClient.post(`/api/books/${id}/enrich`)
+    return response.data
+  }
+
+  async function enrichLibrary() {
+    const response = await apiClient.post('/api/books/enrich')
+    return response.data
+  }
+
+  async function fetchBookProvenance(id) {
+    const response = await apiClient.get(`/api/books/${id}/provenance`)
+    return response.data
+  }
+
+  async function uploadCover(id, file) {
+    const formData = new FormData()
+    formData.append('file', file)
+    const response = await apiClient.post(`/api/books/${id}/cover`, formData, {
+      headers: { 'Content-Type': 'multipart/form-data' }
+    })
+    const book = books.value.find(b => b.id === id)
+    if (book) book.cover_image_url = response.data.url
+    return response.data
+  }
+
+  async function deleteCover(id) {
+    await apiClient.delete(`/api/books/${id}/cover`)
+  }
+
+  // Uploaded covers need the auth header, so they are fetched as blobs;
+  // the caller revokes the object URL when done
+  async function fetchCoverObjectUrl(id, size = 'medium') {
+    const response = await apiClient.get(`/api/books/${id}/cover`, { params: { size }, responseType: 'blob' })
+    return URL.createObjectURL(response.data)
+  }
+
+  // One result per photo; with create, ISBNs not in the library become draft books
+  async function scanBarcodes(files, create = false) {
+    const formData = new FormData()
+    for (const file of files) formData.append('file', file)
+    const response = await apiClient.post('/api/books/scan', formData, {
+      params: { create },
+      headers: { 'Content-Type': 'multipart/form-data' }
+    })
+    for (const result of response.data) {
+      for (const isbn of result.isbns) {
+        if (isbn.draft) books.value.unshift(isbn.draft)
+      }
+    }
+    return response.data
+  }
+
+  const importPaths = {
+    goodreads: '/api/import/goodreads/csv',
+    storygraph: '/api/import/storygraph/csv',
+    librarything: '/api/import/librarything',
+    calibre: '/api/import/calibre',
+    kindle: '/api/import/kindle'
+  }
+
+  async function importFromSite(source, file, { dryRun = false, decisions = [], merge = 'fill_empty' } = {}) {
+    loading.value = true
+    error.value = null
+    try {
+      const formData = new FormData()
+      formData.append('file', file)
+      if (decisions.length > 0) {
+        formData.append('decisions', JSON.stringify(decisions))
+      }
+
+      const response = await apiClient.post(importPaths[source], formData, {
+        params: dryRun ? { dry_run: true, merge } : { merge },
+        headers: {
+          'Content-Type': 'multipart/form-data'
+        }
+      })
+
+      return response.data
+    } catch (err) {
+      error.value = err.response?.data?.message || 'Failed to import file'
+      throw err
+    } finally {
+      loading.value = false
+    }
+  }
+
+  async function startImportJob(source, file, decisions = [], merge = 'fill_empty') {
+    error.value = null
+    try {
+      const formData = new FormData()
+      formData.append('source', source)
+      formData.append('merge', merge)
+      formData.append('file', file)
+      if (decisions.length > 0) {
+        formData.append('decisions', JSON.stringify(decisions))
+      }
+
+      const response = await apiClient.post('/api/import/jobs', formData, {
+        headers: {
+          'Content-Type': 'multipart/form-data'
+        }
+      })
+      return response.data
+    } catch (err) {
+      error.value = err.response?.data?.message || 'Failed to start import'
+      throw err
+    }
+  }
+
+  async function fetchImportJobs(params = {}) {
+    const response = await apiClient.get('/api/import/jobs', { params })
+    return response.data
+  }
+
+  async function fetchImportJob(id) {
+    const response = await apiClient.get(`/api/import/jobs/${id}`)
+    return response.data
+  }
+
+  async function cancelImportJob(id) {
+    const response = await apiClient.post(`/api/import/jobs/${id}/cancel`)
+    return response.data
+  }
+
+  async function revertImportJob(id) {
+    const response = await apiClient.post(`/api/import/jobs/${id}/revert`)
+    return response.data
+  }
+
+  // Calls onEvent(name, job) for each progress event until the job has finished.
+  // EventSource cannot send the Authorization header, so the stream is read with fetch.
+  async function followImportJob(id, onEvent) {
+    const response = await fetch(`${apiClient.defaults.baseURL}/api/import/jobs/${id}/events`, {
+      headers: { Authorization: `Bearer ${localStorage.getItem('access_token')}` }
+    })
+    if (!response.ok) {
+      throw new Error(`Failed to follow import: ${response.statusText}`)
+    }
+
+    const reader = response.body.pipeThrough(new TextDecoderStream()).getReader()
+    let buffer = ''
+    for (;;) {
+      const { value, done } = await reader.read()
+      if (done) break
+      buffer += value
+
+      let end
+      while ((end = buffer.indexOf('\n\n')) !== -1) {
+        const block = buffer.slice(0, end)
+        buffer = buffer.slice(end + 2)
+        const name = block.match(/^event: ?(.*)$/m)?.[1]
+        const data = block.match(/^data: ?(.*)$/m)?.[1]
+        if (name && data) {
+          onEvent(name, JSON.parse(data))
+        }
+      }
+    }
+  }
+
+  async function exportAccount(format = 'json') {
+    error.value = null
+    try {
+      const path = format === 'goodreads' ? '/api/export/goodreads.csv' : '/api/export'
+      const response = await apiClient.get(path, { responseType: 'blob' })
+      const disposition = response.headers['content-disposition'] || ''
+      const filename = disposition.match(/filename="([^"]+)"/)?.[1] || 'panicless-export.json'
+
+      const url = URL.createObjectURL(response.data)
+      const link = document.createElement('a')
+      link.href = url
+      link.download = filename
+      link.click()
+      URL.revokeObjectURL(url)
+    } catch (err) {
+      error.value = err.response?.data?.message || 'Failed to export account'
+      throw err
+    }
+  }
+
+  async function importPaniclessArchive(file, onConflict = 'skip') {
     loading.value = true
     error.value = null
     try {
       const formData = new FormData()
       formData.append('file', file)
 
-      const response = await apiClient.post('/api/import/goodreads/csv', formData, {
+      const response = await apiClient.post('/api/import/panicless', formData, {
+        params: { on_conflict: onConflict },
         headers: {
           'Content-Type': 'multipart/form-data'
         }
@@ -150,7 +367,7 @@ export const useBooksStore = defineStore('books', () => {
 
       return response.data
     } catch (err) {
-      error.value = err.response?.data?.message || 'Failed to import CSV'
+      error.value = err.response?.data?.message || 'Failed to restore archive'
       throw err
     } finally {
       loading.value = false
@@ -170,7 +387,28 @@ export const useBooksStore = defineStore('books', () => {
     createBook,
     updateBook,
     deleteBook,
-    importGoodreadsCSV,
+    fetchBookHighlights,
+    fetchBookIdentifiers,
+    addBookIdentifier,
+    deleteBookIdentifier,
+    lookupBooks,
+    fetchIsbnInfo,
+    enrichBook,
+    enrichLibrary,
+    fetchBookProvenance,
+    uploadCover,
+    deleteCover,
+    fetchCoverObjectUrl,
+    scanBarcodes,
+    importFromSite,
+    startImportJob,
+    fetchImportJobs,
+    fetchImportJob,
+    cancelImportJob,
+    revertImportJob,
+    followImportJob,
+    exportAccount,
+    importPaniclessArchive,
     setCurrentPage,
     setPageSize,
     advancedSearch
@@ -37,14 +37,16 @@ export const useConnectorsStore = defineStore('connectors', () => {
     }
   }
 
-  async function createOrUpdateConnector(provider, apiToken) {
+  async function createOrUpdateConnector(provider, apiToken, { baseUrl, model } = {}) {
     loading.value = true
     error.value = null
     try {
-      const response = await apiClient.post('/api/connectors', {
-        provider,
-        api_token: apiToken
-      })
+      const payload = { provider }
+      if (apiToken) payload.api_token = apiToken
+      if (baseUrl) payload.base_url = baseUrl
+      if (model) payload.model = model
+
+      const response = await apiClient.post('/api/connectors', payload)
 
       // Update local state
       const existingIndex = connectors.value.findIndex(c => c.provider === provider)
@@ -102,6 +104,24 @@ export const useConnectorsStore = defineStore('connectors', () => {
     }
   }
 
+  async function verifyConnector(provider) {
+    error.value = null
+    try {
+      const response = await apiClient.post(`/api/connectors/${provider}/verify`)
+
+      // Update local state
+      const index = connectors.value.findIndex(c => c.provider === provider)
+      if (index >= 0) {
+        connectors.value[index] = response.data
+      }
+
+      return response.data
+    } catch (err) {
+      error.value = err.response?.data?.message || 'Failed to verify connector'
+      throw err
+    }
+  }
+
   return {
     // State
     connectors,
@@ -117,6 +137,7 @@ export const useConnectorsStore = defineStore('connectors', () => {
     fetchConnectors,
     createOrUpdateConnector,
     deleteConnector,
-    toggleConnector
+    toggleConnector,
+    verifyConnector
   }
 })
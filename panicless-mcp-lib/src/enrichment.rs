@@ -0,0 +1,570 @@
+//! Filling missing book fields from the locally loaded Open Library dump
+//!
+//! A book is matched to an Open Library edition through an Open Library identifier it
+//! already carries, then through its ISBNs, and finally to a work with the same title
+//! and a similar author. Only empty fields are filled, and never fields the user edited;
+//! edition details (pages, publisher, language, year) are only taken from an edition
+//! matched by identifier or ISBN, not from a work found by title.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection, PgExecutor, PgPool};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Book fields enrichment may fill
+pub const ENRICHED_FIELDS: [&str; 7] = [
+    "description",
+    "cover_image_url",
+    "language",
+    "pages",
+    "publisher",
+    "publication_year",
+    "original_publication_year",
+];
+
+/// Lowest author similarity (Jaro-Winkler over sorted name words) accepted with a title match
+const AUTHOR_SIMILARITY: f64 = 0.9;
+
+/// Works sharing a title that are compared by author
+const TITLE_CANDIDATES: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrichmentMatch {
+    /// The book already carried the Open Library edition ID
+    Identifier,
+    Isbn,
+    /// A work with the same title and a similar author
+    TitleAuthor,
+}
+
+/// Outcome of enriching one book
+#[derive(Debug, Serialize)]
+pub struct BookEnrichment {
+    pub book_id: i32,
+    /// None when nothing in the dump matched
+    pub matched_by: Option<EnrichmentMatch>,
+    pub edition_key: Option<String>,
+    pub work_key: Option<String>,
+    /// Fields that were filled
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct EnrichmentSummary {
+    /// Books with at least one empty field
+    pub books_checked: usize,
+    pub books_matched: usize,
+    pub books_enriched: usize,
+    pub fields_filled: usize,
+}
+
+/// Where the current value of a book field came from
+#[derive(Debug, Serialize, FromRow)]
+pub struct FieldSource {
+    pub field: String,
+    /// "user" or "openlibrary"
+    pub source: String,
+    /// Open Library edition or work the value was taken from
+    pub reference: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Title and authors of the Open Library edition with an ISBN
+#[derive(Debug, Serialize, FromRow)]
+pub struct EditionTitle {
+    pub key: String,
+    pub title: String,
+    /// In the order the edition lists them
+    pub authors: Vec<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct EnrichableBook {
+    title: String,
+    author: Option<String>,
+    description: Option<String>,
+    cover_image_url: Option<String>,
+    language: Option<String>,
+    pages: Option<i32>,
+    publisher: Option<String>,
+    publication_year: Option<i32>,
+    original_publication_year: Option<i32>,
+}
+
+#[derive(Debug, FromRow)]
+struct Edition {
+    key: String,
+    work_key: Option<String>,
+    description: Option<String>,
+    covers: Vec<i32>,
+    number_of_pages: Option<i32>,
+    languages: Vec<String>,
+    publishers: Vec<String>,
+    publish_year: Option<i32>,
+}
+
+#[derive(Debug, FromRow)]
+struct Work {
+    key: String,
+    description: Option<String>,
+    covers: Vec<i32>,
+    first_publish_year: Option<i32>,
+}
+
+const EDITION_COLUMNS: &str =
+    "e.key, e.work_key, e.description, e.covers, e.number_of_pages, e.languages, e.publishers, e.publish_year";
+const WORK_COLUMNS: &str = "w.key, w.description, w.covers, w.first_publish_year";
+
+/// Values found for a book, before checking which fields are empty
+#[derive(Debug, Default)]
+struct Found {
+    description: Option<(String, String)>,
+    cover_image_url: Option<(String, String)>,
+    language: Option<(String, String)>,
+    pages: Option<(i32, String)>,
+    publisher: Option<(String, String)>,
+    publication_year: Option<(i32, String)>,
+    original_publication_year: Option<(i32, String)>,
+}
+
+/// Fill the empty fields of one book
+/// Returns None when the user has no such book.
+pub async fn enrich_book(pool: &PgPool, user_id: i32, book_id: i32) -> Result<Option<BookEnrichment>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let book = sqlx::query_as::<_, EnrichableBook>(
+        "SELECT title, author, description, cover_image_url, language, pages, publisher,
+                publication_year, original_publication_year
+         FROM books WHERE id = $1 AND user_id = $2
+         FOR UPDATE"
+    )
+    .bind(book_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(book) = book else {
+        return Ok(None);
+    };
+
+    let mut enrichment = BookEnrichment {
+        book_id,
+        matched_by: None,
+        edition_key: None,
+        work_key: None,
+        fields: Vec::new(),
+    };
+
+    let (edition, matched_by) = match find_edition(&mut tx, book_id).await? {
+        Some((edition, matched_by)) => (Some(edition), Some(matched_by)),
+        None => (None, None),
+    };
+    let (work, matched_by) = match &edition {
+        Some(edition) => (find_work(&mut tx, edition.work_key.as_deref()).await?, matched_by),
+        None => match find_work_by_title(&mut tx, &book).await? {
+            Some(work) => (Some(work), Some(EnrichmentMatch::TitleAuthor)),
+            None => (None, None),
+        },
+    };
+    if matched_by.is_none() {
+        return Ok(Some(enrichment));
+    }
+    enrichment.matched_by = matched_by;
+    enrichment.edition_key = edition.as_ref().map(|edition| edition.key.clone());
+    enrichment.work_key = work.as_ref().map(|work| work.key.clone());
+
+    let found = found_values(edition.as_ref(), work.as_ref());
+
+    let user_fields = sqlx::query_scalar::<_, String>(
+        "SELECT field FROM book_field_sources WHERE book_id = $1 AND source = 'user'"
+    )
+    .bind(book_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    let fillable = |field: &str, empty: bool| empty && !user_fields.iter().any(|f| f == field);
+
+    let mut references: Vec<String> = Vec::new();
+    // Whether to fill a field, noting it if so
+    let mut take = |field: &str, empty: bool, reference: &String| -> bool {
+        if !fillable(field, empty) {
+            return false;
+        }
+        enrichment.fields.push(field.to_string());
+        references.push(reference.clone());
+        true
+    };
+
+    let description = found
+        .description
+        .filter(|(_, reference)| take("description", is_blank(&book.description), reference))
+        .map(|(value, _)| value);
+    let cover_image_url = found
+        .cover_image_url
+        .filter(|(_, reference)| take("cover_image_url", is_blank(&book.cover_image_url), reference))
+        .map(|(value, _)| value);
+    let language = found
+        .language
+        .filter(|(_, reference)| take("language", is_blank(&book.language), reference))
+        .map(|(value, _)| value);
+    let pages = found
+        .pages
+        .filter(|(_, reference)| take("pages", book.pages.is_none(), reference))
+        .map(|(value, _)| value);
+    let publisher = found
+        .publisher
+        .filter(|(_, reference)| take("publisher", is_blank(&book.publisher), reference))
+        .map(|(value, _)| value);
+    let publication_year = found
+        .publication_year
+        .filter(|(_, reference)| take("publication_year", book.publication_year.is_none(), reference))
+        .map(|(value, _)| value);
+    let original_publication_year = found
+        .original_publication_year
+        .filter(|(_, reference)| {
+            take("original_publication_year", book.original_publication_year.is_none(), reference)
+        })
+        .map(|(value, _)| value);
+
+    if !enrichment.fields.is_empty() {
+        sqlx::query(
+            "UPDATE books
+             SET description = COALESCE($2, description), cover_image_url = COALESCE($3, cover_image_url),
+                 language = COALESCE($4, language), pages = COALESCE($5, pages), publisher = COALESCE($6, publisher),
+                 publication_year = COALESCE($7, publication_year),
+                 original_publication_year = COALESCE($8, original_publication_year)
+             WHERE id = $1"
+        )
+        .bind(book_id)
+        .bind(description)
+        .bind(cover_image_url)
+        .bind(language)
+        .bind(pages)
+        .bind(publisher)
+        .bind(publication_year)
+        .bind(original_publication_year)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO book_field_sources (book_id, field, source, reference)
+             SELECT $1, field, 'openlibrary', reference FROM UNNEST($2::text[], $3::text[]) AS f(field, reference)
+             ON CONFLICT (book_id, field)
+             DO UPDATE SET source = 'openlibrary', reference = EXCLUDED.reference, recorded_at = NOW()"
+        )
+        .bind(book_id)
+        .bind(&enrichment.fields)
+        .bind(&references)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // Remember what matched, so the next run (and imports) find it directly
+    let identifiers: Vec<(&str, &String)> = [
+        ("openlibrary_edition", enrichment.edition_key.as_ref()),
+        ("openlibrary_work", enrichment.work_key.as_ref()),
+    ]
+    .into_iter()
+    .filter_map(|(id_type, key)| key.map(|key| (id_type, key)))
+    .collect();
+    let (types, values): (Vec<&str>, Vec<&String>) = identifiers.into_iter().unzip();
+    sqlx::query(
+        "INSERT INTO book_identifiers (book_id, id_type, value, source)
+         SELECT $1, id_type, value, 'openlibrary' FROM UNNEST($2::text[], $3::text[]) AS i(id_type, value)
+         ON CONFLICT (book_id, id_type, value) DO NOTHING"
+    )
+    .bind(book_id)
+    .bind(&types)
+    .bind(&values)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(enrichment))
+}
+
+/// Enrich every book of a user that has an empty field
+pub async fn enrich_library(pool: &PgPool, user_id: i32) -> Result<EnrichmentSummary, sqlx::Error> {
+    let book_ids = sqlx::query_scalar::<_, i32>(
+        "SELECT id FROM books
+         WHERE user_id = $1
+           AND (COALESCE(description, '') = '' OR COALESCE(cover_image_url, '') = '' OR COALESCE(language, '') = ''
+                OR pages IS NULL OR COALESCE(publisher, '') = '' OR publication_year IS NULL
+                OR original_publication_year IS NULL)
+         ORDER BY id"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut summary = EnrichmentSummary::default();
+    for book_id in book_ids {
+        let Some(enrichment) = enrich_book(pool, user_id, book_id).await? else {
+            continue;
+        };
+        summary.books_checked += 1;
+        if enrichment.matched_by.is_some() {
+            summary.books_matched += 1;
+        }
+        if !enrichment.fields.is_empty() {
+            summary.books_enriched += 1;
+            summary.fields_filled += enrichment.fields.len();
+        }
+    }
+    Ok(summary)
+}
+
+/// Record that the user set these fields, so enrichment leaves them alone
+pub async fn mark_user_fields(executor: impl PgExecutor<'_>, book_id: i32, fields: &[&str]) -> Result<(), sqlx::Error> {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    sqlx::query(
+        "INSERT INTO book_field_sources (book_id, field, source)
+         SELECT $1, field, 'user' FROM UNNEST($2::text[]) AS f(field)
+         ON CONFLICT (book_id, field) DO UPDATE SET source = 'user', reference = NULL, recorded_at = NOW()"
+    )
+    .bind(book_id)
+    .bind(fields)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Recorded sources of a book's fields
+pub async fn field_sources(executor: impl PgExecutor<'_>, book_id: i32) -> Result<Vec<FieldSource>, sqlx::Error> {
+    sqlx::query_as::<_, FieldSource>(
+        "SELECT field, source, reference, recorded_at FROM book_field_sources WHERE book_id = $1 ORDER BY field"
+    )
+    .bind(book_id)
+    .fetch_all(executor)
+    .await
+}
+
+/// The edition an ISBN-13 belongs to, to name a book known only by its ISBN
+pub async fn edition_title(executor: impl PgExecutor<'_>, isbn13: &str) -> Result<Option<EditionTitle>, sqlx::Error> {
+    sqlx::query_as::<_, EditionTitle>(
+        "SELECT e.key, e.title,
+                ARRAY(SELECT a.name FROM UNNEST(e.author_keys) WITH ORDINALITY AS k(key, n)
+                      JOIN openlibrary_authors a ON a.key = k.key
+                      ORDER BY k.n) AS authors
+         FROM openlibrary_editions e
+         JOIN openlibrary_isbns o ON o.edition_key = e.key
+         WHERE o.isbn13 = $1
+         ORDER BY e.key LIMIT 1"
+    )
+    .bind(isbn13)
+    .fetch_optional(executor)
+    .await
+}
+
+async fn find_edition(conn: &mut PgConnection, book_id: i32) -> Result<Option<(Edition, EnrichmentMatch)>, sqlx::Error> {
+    let by_identifier = sqlx::query_as::<_, Edition>(&format!(
+        "SELECT {} FROM openlibrary_editions e
+         JOIN book_identifiers i ON i.value = e.key AND i.id_type = 'openlibrary_edition'
+         WHERE i.book_id = $1
+         ORDER BY i.id LIMIT 1",
+        EDITION_COLUMNS
+    ))
+    .bind(book_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(edition) = by_identifier {
+        return Ok(Some((edition, EnrichmentMatch::Identifier)));
+    }
+
+    // The book's ISBN and any other ISBN it carries
+    let by_isbn = sqlx::query_as::<_, Edition>(&format!(
+        "SELECT {} FROM openlibrary_editions e
+         JOIN openlibrary_isbns o ON o.edition_key = e.key
+         JOIN book_identifiers i ON i.value = o.isbn13 AND i.id_type = 'isbn13'
+         WHERE i.book_id = $1
+         ORDER BY i.source = 'book' DESC, e.key LIMIT 1",
+        EDITION_COLUMNS
+    ))
+    .bind(book_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(by_isbn.map(|edition| (edition, EnrichmentMatch::Isbn)))
+}
+
+async fn find_work(conn: &mut PgConnection, key: Option<&str>) -> Result<Option<Work>, sqlx::Error> {
+    let Some(key) = key else {
+        return Ok(None);
+    };
+    sqlx::query_as::<_, Work>(&format!("SELECT {} FROM openlibrary_works w WHERE w.key = $1", WORK_COLUMNS))
+        .bind(key)
+        .fetch_optional(&mut *conn)
+        .await
+}
+
+/// The work with the book's title whose author is most like the book's
+async fn find_work_by_title(conn: &mut PgConnection, book: &EnrichableBook) -> Result<Option<Work>, sqlx::Error> {
+    let Some(author) = book.author.as_deref().filter(|author| !author.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let title = title_key(&book.title);
+    if title.is_empty() {
+        return Ok(None);
+    }
+
+    let candidates = sqlx::query_as::<_, (String, Vec<String>, bool)>(
+        "SELECT w.key, COALESCE(array_agg(a.name) FILTER (WHERE a.name IS NOT NULL), '{}'), w.description IS NOT NULL
+         FROM openlibrary_works w
+         LEFT JOIN openlibrary_authors a ON a.key = ANY(w.author_keys)
+         WHERE w.title_key = $1
+         GROUP BY w.key
+         ORDER BY w.key
+         LIMIT $2"
+    )
+    .bind(&title)
+    .bind(TITLE_CANDIDATES)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let best = candidates
+        .into_iter()
+        .filter_map(|(key, names, described)| {
+            let score = names.iter().map(|name| author_similarity(author, name)).fold(0.0, f64::max);
+            (score >= AUTHOR_SIMILARITY).then_some((key, score, described))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(a.2.cmp(&b.2)));
+
+    match best {
+        Some((key, _, _)) => find_work(conn, Some(&key)).await,
+        None => Ok(None),
+    }
+}
+
+/// Values an edition and its work offer, each with the key it comes from
+fn found_values(edition: Option<&Edition>, work: Option<&Work>) -> Found {
+    let from_edition = |value: Option<String>| edition.and_then(|e| value.map(|v| (v, e.key.clone())));
+    let from_work = |value: Option<String>| work.and_then(|w| value.map(|v| (v, w.key.clone())));
+    let cover = |covers: &[i32]| {
+        covers
+            .iter()
+            .find(|id| **id > 0)
+            .map(|id| format!("https://covers.openlibrary.org/b/id/{}-L.jpg", id))
+    };
+
+    Found {
+        description: from_edition(edition.and_then(|e| non_blank(e.description.as_deref())))
+            .or_else(|| from_work(work.and_then(|w| non_blank(w.description.as_deref())))),
+        cover_image_url: from_edition(edition.and_then(|e| cover(&e.covers)))
+            .or_else(|| from_work(work.and_then(|w| cover(&w.covers)))),
+        language: from_edition(edition.and_then(|e| e.languages.first().cloned())),
+        pages: edition.and_then(|e| e.number_of_pages.filter(|pages| *pages > 0).map(|pages| (pages, e.key.clone()))),
+        publisher: from_edition(edition.and_then(|e| e.publishers.first().and_then(|p| non_blank(Some(p))))),
+        publication_year: edition.and_then(|e| e.publish_year.map(|year| (year, e.key.clone()))),
+        original_publication_year: work.and_then(|w| w.first_publish_year.map(|year| (year, w.key.clone()))),
+    }
+}
+
+fn non_blank(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+fn is_blank(value: &Option<String>) -> bool {
+    value.as_deref().is_none_or(|value| value.trim().is_empty())
+}
+
+/// Lowercase words without accents or punctuation
+fn fold(value: &str) -> String {
+    value
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A title reduced for matching: no subtitle, series suffix, accents, punctuation
+/// or leading article ("The Hobbit: or There and Back Again" -> "hobbit")
+pub fn title_key(title: &str) -> String {
+    let main = title.split([':', '(']).next().unwrap_or(title);
+    let folded = fold(main);
+    let words: Vec<&str> = folded.split(' ').filter(|word| !word.is_empty()).collect();
+    let articles = ["the", "a", "an", "le", "la", "les", "l", "il", "lo", "gli", "der", "die", "das", "el", "los", "las"];
+    let skip = usize::from(words.len() > 1 && articles.contains(&words[0]));
+    words[skip..].join(" ")
+}
+
+/// How alike two author names are, from 0 to 1
+/// Word order is ignored, so "Herbert, Frank" matches "Frank Herbert"; a book
+/// listing several authors matches when any of them does.
+fn author_similarity(book_author: &str, name: &str) -> f64 {
+    let key = |name: &str| {
+        let mut words: Vec<String> = fold(name).split(' ').filter(|w| !w.is_empty()).map(str::to_string).collect();
+        words.sort();
+        words.join(" ")
+    };
+    let name = key(name);
+
+    std::iter::once(book_author)
+        .chain(book_author.split([',', ';', '&']))
+        .chain(book_author.split(" and "))
+        .map(key)
+        .filter(|candidate| !candidate.is_empty())
+        .map(|candidate| strsim::jaro_winkler(&candidate, &name))
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_key() {
+        assert_eq!(title_key("The Hobbit: or There and Back Again"), "hobbit");
+        assert_eq!(title_key("Dune (Dune Chronicles, #1)"), "dune");
+        assert_eq!(title_key("L'Étranger"), "etranger");
+        assert_eq!(title_key("The"), "the");
+        assert_eq!(title_key("A Wizard of Earthsea"), "wizard of earthsea");
+    }
+
+    #[test]
+    fn test_author_similarity() {
+        assert!(author_similarity("Frank Herbert", "Herbert, Frank") > 0.99);
+        assert!(author_similarity("Ursula K. Le Guin", "Ursula K Le Guin") > 0.99);
+        assert!(author_similarity("Steve Klabnik, Carol Nichols", "Carol Nichols") > 0.99);
+        assert!(author_similarity("J. R. R. Tolkien", "J.R.R. Tolkien") >= AUTHOR_SIMILARITY);
+        assert!(author_similarity("Frank Herbert", "Brian Herbert") < AUTHOR_SIMILARITY);
+    }
+
+    #[test]
+    fn test_found_values_from_edition_and_work() {
+        let edition = Edition {
+            key: "OL7353617M".to_string(),
+            work_key: Some("OL893415W".to_string()),
+            description: None,
+            covers: vec![-1, 8231856],
+            number_of_pages: Some(0),
+            languages: vec!["eng".to_string()],
+            publishers: vec!["Ace Books".to_string()],
+            publish_year: Some(1990),
+        };
+        let work = Work {
+            key: "OL893415W".to_string(),
+            description: Some("Desert planet".to_string()),
+            covers: vec![1],
+            first_publish_year: Some(1965),
+        };
+
+        let found = found_values(Some(&edition), Some(&work));
+        assert_eq!(found.description, Some(("Desert planet".to_string(), "OL893415W".to_string())));
+        assert_eq!(
+            found.cover_image_url.map(|(url, _)| url),
+            Some("https://covers.openlibrary.org/b/id/8231856-L.jpg".to_string())
+        );
+        assert_eq!(found.pages, None);
+        assert_eq!(found.language.map(|(language, _)| language), Some("eng".to_string()));
+        assert_eq!(found.original_publication_year.map(|(year, _)| year), Some(1965));
+
+        // A work found by title gives no edition details
+        let found = found_values(None, Some(&work));
+        assert!(found.publisher.is_none() && found.publication_year.is_none() && found.language.is_none());
+        assert_eq!(found.cover_image_url.map(|(_, key)| key), Some("OL893415W".to_string()));
+    }
+}
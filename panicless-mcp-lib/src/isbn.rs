@@ -0,0 +1,287 @@
+//! ISBN validation, normalization and hyphenation
+//!
+//! Books store ISBNs as bare ISBN-13 digits. ISBN-10s are converted on the way in,
+//! and hyphenation is only applied for display.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A checksum-validated ISBN, held in its canonical ISBN-13 form
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Isbn(String);
+
+impl Isbn {
+    /// Parse an ISBN-10 or ISBN-13
+    /// Hyphens, spaces and an "ISBN", "ISBN-10:" or "ISBN-13:" prefix are ignored;
+    /// the check digit must be right.
+    pub fn parse(value: &str) -> Result<Isbn, String> {
+        let not_isbn = || format!("'{}' is not an ISBN", value.trim());
+
+        let cleaned: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '\u{2010}' | '\u{2011}' | '\u{2013}'))
+            .collect::<String>()
+            .to_uppercase();
+        let cleaned = match cleaned.strip_prefix("ISBN") {
+            Some(rest) => rest
+                .strip_prefix("13:")
+                .or_else(|| rest.strip_prefix("10:"))
+                .or_else(|| rest.strip_prefix(':'))
+                .unwrap_or(rest),
+            None => &cleaned,
+        };
+
+        match cleaned.len() {
+            10 => {
+                let valid_chars = cleaned
+                    .char_indices()
+                    .all(|(i, c)| c.is_ascii_digit() || (i == 9 && c == 'X'));
+                if !valid_chars {
+                    return Err(not_isbn());
+                }
+                if isbn10_check_digit(&cleaned[..9]) != cleaned.chars().last().unwrap_or_default() {
+                    return Err(format!("'{}' has a wrong ISBN-10 check digit", value.trim()));
+                }
+                let body = format!("978{}", &cleaned[..9]);
+                let check = isbn13_check_digit(&body);
+                Ok(Isbn(format!("{}{}", body, check)))
+            }
+            13 => {
+                if !cleaned.chars().all(|c| c.is_ascii_digit()) || !(cleaned.starts_with("978") || cleaned.starts_with("979")) {
+                    return Err(not_isbn());
+                }
+                if isbn13_check_digit(&cleaned[..12]) != cleaned.chars().last().unwrap_or_default() {
+                    return Err(format!("'{}' has a wrong ISBN-13 check digit", value.trim()));
+                }
+                Ok(Isbn(cleaned.to_string()))
+            }
+            _ => Err(not_isbn()),
+        }
+    }
+
+    /// The 13 digits, e.g. "9780441013593"
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The ISBN-10 form, which only 978 ISBNs have
+    pub fn to_isbn10(&self) -> Option<String> {
+        let body = self.0.strip_prefix("978")?;
+        let body = &body[..9];
+        Some(format!("{}{}", body, isbn10_check_digit(body)))
+    }
+
+    /// The ISBN-13 split into prefix, registration group, registrant, publication
+    /// and check digit, e.g. "978-0-441-01359-3"
+    /// Registrant ranges are only known for the larger groups; elsewhere the
+    /// registrant and publication are left together ("978-88-0700000-3").
+    pub fn hyphenated(&self) -> String {
+        let (prefix, rest) = self.0.split_at(3);
+        let (body, check) = rest.split_at(9);
+
+        let group_len = range_length(GROUP_RANGES, prefix, body);
+        if group_len == 0 {
+            return format!("{}-{}-{}", prefix, body, check);
+        }
+        let (group, rest) = body.split_at(group_len);
+
+        let registrant_len = range_length(REGISTRANT_RANGES, &format!("{}-{}", prefix, group), rest);
+        if registrant_len == 0 || registrant_len >= rest.len() {
+            return format!("{}-{}-{}-{}", prefix, group, rest, check);
+        }
+        let (registrant, publication) = rest.split_at(registrant_len);
+        format!("{}-{}-{}-{}-{}", prefix, group, registrant, publication, check)
+    }
+}
+
+impl fmt::Display for Isbn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Isbn {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Isbn::parse(value)
+    }
+}
+
+/// The canonical ISBN-13 of an optional ISBN field, None when it is blank
+pub fn normalize_isbn(value: Option<&str>) -> Result<Option<String>, String> {
+    match value.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(value) => Isbn::parse(value).map(|isbn| Some(isbn.0)),
+        None => Ok(None),
+    }
+}
+
+fn isbn10_check_digit(body: &str) -> char {
+    let sum: u32 = body
+        .chars()
+        .zip((2..=10).rev())
+        .map(|(c, weight)| c.to_digit(10).unwrap_or_default() * weight)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        digit => char::from_digit(digit, 10).unwrap_or_default(),
+    }
+}
+
+fn isbn13_check_digit(body: &str) -> char {
+    let sum: u32 = body
+        .chars()
+        .enumerate()
+        .map(|(i, c)| c.to_digit(10).unwrap_or_default() * if i % 2 == 0 { 1 } else { 3 })
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap_or_default()
+}
+
+/// Segment lengths over ranges of seven digits, by EAN prefix or prefix and group
+type RangeTable = &'static [(&'static str, &'static [(u32, u32, usize)])];
+
+/// Length of the segment starting `digits`, from ranges over its first seven
+/// digits as published by the International ISBN Agency; 0 when unassigned
+fn range_length(table: RangeTable, key: &str, digits: &str) -> usize {
+    let Some((_, ranges)) = table.iter().find(|(k, _)| *k == key) else {
+        return 0;
+    };
+    let padded = format!("{:0<7}", &digits[..digits.len().min(7)]);
+    let value: u32 = padded.parse().unwrap_or_default();
+    ranges
+        .iter()
+        .find(|(start, end, _)| (*start..=*end).contains(&value))
+        .map_or(0, |(_, _, length)| *length)
+}
+
+/// Registration group lengths by EAN prefix
+const GROUP_RANGES: RangeTable = &[
+    (
+        "978",
+        &[
+            (0, 5_999_999, 1),
+            (6_000_000, 6_499_999, 3),
+            (6_500_000, 6_599_999, 2),
+            (6_600_000, 6_999_999, 3),
+            (7_000_000, 7_999_999, 1),
+            (8_000_000, 9_499_999, 2),
+            (9_500_000, 9_899_999, 3),
+            (9_900_000, 9_989_999, 4),
+            (9_990_000, 9_999_999, 5),
+        ],
+    ),
+    (
+        "979",
+        &[
+            (1_000_000, 1_299_999, 2),
+            (8_000_000, 8_999_999, 1),
+        ],
+    ),
+];
+
+/// Registrant lengths of the English, French and German language groups
+const REGISTRANT_RANGES: RangeTable = &[
+    (
+        "978-0",
+        &[
+            (0, 1_999_999, 2),
+            (2_000_000, 6_999_999, 3),
+            (7_000_000, 8_499_999, 4),
+            (8_500_000, 8_999_999, 5),
+            (9_000_000, 9_499_999, 6),
+            (9_500_000, 9_999_999, 7),
+        ],
+    ),
+    (
+        "978-1",
+        &[
+            (0, 999_999, 2),
+            (1_000_000, 3_999_999, 3),
+            (4_000_000, 5_499_999, 4),
+            (5_500_000, 8_697_999, 5),
+            (8_698_000, 9_989_999, 6),
+            (9_990_000, 9_999_999, 7),
+        ],
+    ),
+    (
+        "978-2",
+        &[
+            (0, 1_999_999, 2),
+            (2_000_000, 3_499_999, 3),
+            (3_500_000, 3_999_999, 5),
+            (4_000_000, 6_999_999, 3),
+            (7_000_000, 8_399_999, 4),
+            (8_400_000, 8_999_999, 5),
+            (9_000_000, 9_499_999, 6),
+            (9_500_000, 9_999_999, 7),
+        ],
+    ),
+    (
+        "978-3",
+        &[
+            (0, 299_999, 2),
+            (300_000, 339_999, 3),
+            (340_000, 369_999, 4),
+            (370_000, 399_999, 5),
+            (400_000, 1_999_999, 2),
+            (2_000_000, 6_999_999, 3),
+            (7_000_000, 8_499_999, 4),
+            (8_500_000, 8_999_999, 5),
+            (9_000_000, 9_499_999, 6),
+            (9_500_000, 9_539_999, 7),
+            (9_540_000, 9_699_999, 5),
+            (9_700_000, 9_849_999, 7),
+            (9_850_000, 9_999_999, 5),
+        ],
+    ),
+    (
+        "979-10",
+        &[
+            (0, 1_999_999, 2),
+            (2_000_000, 6_999_999, 3),
+            (7_000_000, 8_999_999, 4),
+            (9_000_000, 9_759_999, 5),
+            (9_760_000, 9_999_999, 6),
+        ],
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_isbn13_and_isbn10() {
+        assert_eq!(Isbn::parse("978-0-441-01359-3").unwrap().as_str(), "9780441013593");
+        assert_eq!(Isbn::parse("ISBN-13: 978 0 441 01359 3").unwrap().as_str(), "9780441013593");
+        assert_eq!(Isbn::parse("0-441-01359-7").unwrap().as_str(), "9780441013593");
+        assert_eq!(Isbn::parse("isbn 080442957x").unwrap().as_str(), "9780804429573");
+        assert!(Isbn::parse("978-0-441-01359-4").unwrap_err().contains("check digit"));
+        assert!(Isbn::parse("0441013598").unwrap_err().contains("check digit"));
+        assert!(Isbn::parse("1234567890123").is_err());
+        assert!(Isbn::parse("B00B7NPTR0").is_err());
+        assert_eq!(normalize_isbn(Some("  ")), Ok(None));
+        assert_eq!(normalize_isbn(Some("0441013597")), Ok(Some("9780441013593".to_string())));
+    }
+
+    #[test]
+    fn test_to_isbn10() {
+        assert_eq!(Isbn::parse("9780441013593").unwrap().to_isbn10(), Some("0441013597".to_string()));
+        assert_eq!(Isbn::parse("9780804429573").unwrap().to_isbn10(), Some("080442957X".to_string()));
+        assert_eq!(Isbn::parse("9791090636071").unwrap().to_isbn10(), None);
+    }
+
+    #[test]
+    fn test_hyphenated() {
+        let hyphenated = |isbn: &str| Isbn::parse(isbn).unwrap().hyphenated();
+        assert_eq!(hyphenated("9780441013593"), "978-0-441-01359-3");
+        assert_eq!(hyphenated("9781250237347"), "978-1-250-23734-7");
+        assert_eq!(hyphenated("9783161484100"), "978-3-16-148410-0");
+        assert_eq!(hyphenated("9782070368228"), "978-2-07-036822-8");
+        assert_eq!(hyphenated("9791090636071"), "979-10-90636-07-1");
+        // Group known, registrants not
+        assert_eq!(hyphenated("9788807000003"), "978-88-0700000-3");
+        // Unassigned group
+        assert_eq!(hyphenated("9795000000006"), "979-500000000-6");
+    }
+}
@@ -1,3 +1,5 @@
+pub mod enrichment;
+pub mod isbn;
 pub mod protocol;
 pub mod queries;
 pub mod tools;
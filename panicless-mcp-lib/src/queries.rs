@@ -48,6 +48,20 @@ pub struct Reading {
     pub notes: Option<String>,
 }
 
+#[derive(Debug, FromRow)]
+pub struct Highlight {
+    pub id: i32,
+    pub book_id: i32,
+    pub book_title: String,
+    pub book_author: Option<String>,
+    pub text: Option<String>,
+    pub note: Option<String>,
+    pub location_start: Option<i32>,
+    pub location_end: Option<i32>,
+    pub page: Option<i32>,
+    pub highlighted_at: Option<DateTime<Utc>>,
+}
+
 pub struct ReadingStats {
     pub total_readings: i64,
     pub completed_readings: i64,
@@ -148,6 +162,55 @@ pub async fn get_book_with_readings(
     .await
 }
 
+pub async fn search_highlights(
+    pool: &PgPool,
+    user_id: i32,
+    query: Option<&str>,
+    book_id: Option<i32>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<Highlight>, sqlx::Error> {
+    let mut sql = String::from(
+        "SELECT h.id, h.book_id, b.title as book_title, b.author as book_author, h.text, h.note,
+         h.location_start, h.location_end, h.page, h.highlighted_at
+         FROM highlights h
+         JOIN books b ON h.book_id = b.id
+         WHERE h.user_id = $1"
+    );
+
+    let mut param_count = 2;
+
+    if query.is_some() {
+        sql.push_str(&format!(" AND (h.text ILIKE ${} OR h.note ILIKE ${} OR b.title ILIKE ${})", param_count, param_count, param_count));
+        param_count += 1;
+    }
+
+    if book_id.is_some() {
+        sql.push_str(&format!(" AND h.book_id = ${}", param_count));
+        param_count += 1;
+    }
+
+    sql.push_str(" ORDER BY b.title, COALESCE(h.location_start, h.page), h.id");
+
+    let limit_val = limit.unwrap_or(50);
+    let offset_val = offset.unwrap_or(0);
+    sql.push_str(&format!(" LIMIT ${} OFFSET ${}", param_count, param_count + 1));
+
+    let mut query_builder = sqlx::query_as::<_, Highlight>(&sql).bind(user_id);
+
+    if let Some(q) = query {
+        query_builder = query_builder.bind(format!("%{}%", q));
+    }
+
+    if let Some(id) = book_id {
+        query_builder = query_builder.bind(id);
+    }
+
+    query_builder = query_builder.bind(limit_val).bind(offset_val);
+
+    query_builder.fetch_all(pool).await
+}
+
 pub async fn list_readings(
     pool: &PgPool,
     user_id: i32,
@@ -278,6 +341,7 @@ pub async fn find_similar_books(
     .await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn advanced_search_books(
     pool: &PgPool,
     user_id: i32,
@@ -305,8 +369,13 @@ pub async fn advanced_search_books(
         param_count += 1;
     }
 
+    // Any ISBN the book carries, normalized the way book_identifiers stores them
     if isbn.is_some() {
-        sql.push_str(&format!(" AND isbn = ${}", param_count));
+        sql.push_str(&format!(
+            " AND (isbn = ${0} OR id IN (SELECT book_id FROM book_identifiers
+                                          WHERE (id_type, value) IN (SELECT * FROM book_isbn_identifier(${0}))))",
+            param_count
+        ));
         param_count += 1;
     }
 
@@ -386,6 +455,7 @@ pub async fn advanced_search_books(
     query_builder.fetch_all(pool).await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn insert_book(
     pool: &PgPool,
     user_id: i32,
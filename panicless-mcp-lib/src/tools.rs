@@ -2,6 +2,8 @@ use serde_json::{json, Value};
 use sqlx::PgPool;
 
 use super::protocol::{ContentItem, ToolCallResult, ToolDefinition};
+use crate::enrichment::{self, EnrichmentMatch};
+use crate::isbn::{normalize_isbn, Isbn};
 use crate::queries;
 
 pub fn get_tool_definitions() -> Vec<ToolDefinition> {
@@ -55,7 +57,7 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     },
                     "isbn": {
                         "type": "string",
-                        "description": "Filter by exact ISBN number (optional)"
+                        "description": "Filter by ISBN-10 or ISBN-13, with or without hyphens (optional)"
                     },
                     "edition": {
                         "type": "string",
@@ -193,7 +195,7 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     },
                     "isbn": {
                         "type": "string",
-                        "description": "ISBN (optional)"
+                        "description": "ISBN-10 or ISBN-13 (optional); must have a valid check digit"
                     },
                     "publication_year": {
                         "type": "integer",
@@ -263,6 +265,49 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 "required": ["reading_id"]
             }),
         },
+        ToolDefinition {
+            name: "search_highlights".to_string(),
+            description: "Search the user's highlights, quotes and reading notes (e.g. imported from Kindle) by text, note or book title".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Text to find in the highlight, its note or the book title (case-insensitive, optional)"
+                    },
+                    "book_id": {
+                        "type": "integer",
+                        "description": "Only highlights of this book (optional)"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of results to return (default: 50, max: 500)",
+                        "minimum": 1,
+                        "maximum": 500
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Number of results to skip for pagination (default: 0)",
+                        "minimum": 0
+                    }
+                },
+                "required": []
+            }),
+        },
+        ToolDefinition {
+            name: "enrich_books".to_string(),
+            description: "Fill in missing descriptions, covers, languages, page counts, publishers and years from Open Library, for one book or the whole library. Details the user entered are never changed".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "book_id": {
+                        "type": "integer",
+                        "description": "Only enrich this book (optional, default: every book in the library)"
+                    }
+                },
+                "required": []
+            }),
+        },
     ]
 }
 
@@ -284,6 +329,8 @@ pub async fn execute_tool(
         "create_book" => create_book(pool, args, user_id).await,
         "create_reading" => create_reading(pool, args, user_id).await,
         "update_reading_review" => update_reading_review(pool, args, user_id).await,
+        "search_highlights" => search_highlights(pool, args, user_id).await,
+        "enrich_books" => enrich_books(pool, args, user_id).await,
         _ => Err(format!("Unknown tool: {}", name)),
     }
 }
@@ -311,7 +358,7 @@ async fn search_books(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCa
                 book.author.as_deref().unwrap_or("Unknown"),
                 book.publication_year.map(|y| y.to_string()).unwrap_or_else(|| "N/A".to_string()),
                 book.pages.map(|p| p.to_string()).unwrap_or_else(|| "N/A".to_string()),
-                book.isbn.as_deref().unwrap_or("N/A")
+                display_isbn(book.isbn.as_deref())
             ));
         }
         result
@@ -364,7 +411,7 @@ async fn advanced_search_books(pool: &PgPool, args: Value, user_id: i32) -> Resu
                 book.author.as_deref().unwrap_or("Unknown"),
                 book.publication_year.map(|y| y.to_string()).unwrap_or_else(|| "N/A".to_string()),
                 book.pages.map(|p| p.to_string()).unwrap_or_else(|| "N/A".to_string()),
-                book.isbn.as_deref().unwrap_or("N/A")
+                display_isbn(book.isbn.as_deref())
             );
 
             if let Some(edition) = &book.edition {
@@ -412,7 +459,7 @@ async fn get_book_details(pool: &PgPool, args: Value, user_id: i32) -> Result<To
         book.title,
         book.author.as_deref().unwrap_or("Unknown"),
         book.edition.as_deref().unwrap_or("N/A"),
-        book.isbn.as_deref().unwrap_or("N/A"),
+        display_isbn(book.isbn.as_deref()),
         book.publication_year.map(|y| y.to_string()).unwrap_or_else(|| "N/A".to_string()),
         book.publisher.as_deref().unwrap_or("Unknown"),
         book.pages.map(|p| p.to_string()).unwrap_or_else(|| "N/A".to_string()),
@@ -526,7 +573,7 @@ async fn find_similar_books(pool: &PgPool, args: Value, user_id: i32) -> Result<
 async fn create_book(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCallResult, String> {
     let title = args["title"].as_str().ok_or("title is required")?;
     let author = args["author"].as_str();
-    let isbn = args["isbn"].as_str();
+    let isbn = normalize_isbn(args["isbn"].as_str())?;
     let publication_year = args["publication_year"].as_i64().map(|y| y as i32);
     let publisher = args["publisher"].as_str();
     let pages = args["pages"].as_i64().map(|p| p as i32);
@@ -538,7 +585,7 @@ async fn create_book(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCal
         user_id,
         title,
         author,
-        isbn,
+        isbn.as_deref(),
         publication_year,
         publisher,
         pages,
@@ -548,12 +595,26 @@ async fn create_book(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCal
     .await
     .map_err(|e| e.to_string())?;
 
+    let user_fields: Vec<&str> = [
+        ("publication_year", publication_year.is_some()),
+        ("publisher", publisher.is_some()),
+        ("pages", pages.is_some()),
+        ("language", language.is_some()),
+        ("description", description.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(field, set)| set.then_some(field))
+    .collect();
+    enrichment::mark_user_fields(pool, book_id, &user_fields)
+        .await
+        .map_err(|e| e.to_string())?;
+
     let text = format!(
         "Book created successfully!\n\nBook ID: {}\nTitle: {}\nAuthor: {}\nISBN: {}\nPages: {}\n\nYou can now create reading records for this book.",
         book_id,
         title,
         author.unwrap_or("N/A"),
-        isbn.unwrap_or("N/A"),
+        display_isbn(isbn.as_deref()),
         pages.map(|p| p.to_string()).unwrap_or_else(|| "N/A".to_string())
     );
 
@@ -563,6 +624,50 @@ async fn create_book(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCal
     })
 }
 
+async fn enrich_books(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCallResult, String> {
+    let text = match args["book_id"].as_i64() {
+        Some(book_id) => {
+            let enrichment = enrichment::enrich_book(pool, user_id, book_id as i32)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Book {} not found", book_id))?;
+
+            let matched_by = match enrichment.matched_by {
+                Some(EnrichmentMatch::Identifier) => "its Open Library identifier",
+                Some(EnrichmentMatch::Isbn) => "ISBN",
+                Some(EnrichmentMatch::TitleAuthor) => "title and author",
+                None => "",
+            };
+            let reference = enrichment.edition_key.or(enrichment.work_key).unwrap_or_default();
+            if enrichment.matched_by.is_none() {
+                format!("No Open Library match found for book {}.", book_id)
+            } else if enrichment.fields.is_empty() {
+                format!("Book {} matched Open Library {} by {}; no missing fields could be filled.", book_id, reference, matched_by)
+            } else {
+                format!(
+                    "Book {} matched Open Library {} by {}.\n\nFilled: {}",
+                    book_id,
+                    reference,
+                    matched_by,
+                    enrichment.fields.join(", ")
+                )
+            }
+        }
+        None => {
+            let summary = enrichment::enrich_library(pool, user_id).await.map_err(|e| e.to_string())?;
+            format!(
+                "Checked {} book(s): {} matched Open Library, {} enriched with {} field(s) in total.",
+                summary.books_checked, summary.books_matched, summary.books_enriched, summary.fields_filled
+            )
+        }
+    };
+
+    Ok(ToolCallResult {
+        content: vec![ContentItem::Text { text }],
+        is_error: None,
+    })
+}
+
 async fn create_reading(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCallResult, String> {
     use chrono::NaiveDate;
     let book_id = args["book_id"].as_i64().ok_or("book_id is required")? as i32;
@@ -631,3 +736,60 @@ async fn update_reading_review(pool: &PgPool, args: Value, user_id: i32) -> Resu
         is_error: if updated { None } else { Some(true) },
     })
 }
+
+async fn search_highlights(pool: &PgPool, args: Value, user_id: i32) -> Result<ToolCallResult, String> {
+    let query = args["query"].as_str();
+    let book_id = args["book_id"].as_i64().map(|id| id as i32);
+    let limit = args["limit"].as_i64().map(|l| l.clamp(1, 500));
+    let offset = args["offset"].as_i64();
+
+    let highlights = queries::search_highlights(pool, user_id, query, book_id, limit, offset)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let text = if highlights.is_empty() {
+        "No highlights found.".to_string()
+    } else {
+        let mut result = format!("Found {} highlight(s):\n\n", highlights.len());
+        for (i, highlight) in highlights.iter().enumerate() {
+            let position = match (highlight.page, highlight.location_start, highlight.location_end) {
+                (Some(page), _, _) => format!("page {}", page),
+                (None, Some(start), Some(end)) if end != start => format!("location {}-{}", start, end),
+                (None, Some(start), _) => format!("location {}", start),
+                _ => "no position".to_string(),
+            };
+            result.push_str(&format!(
+                "{}. {} by {} ({}, book ID {})\n",
+                i + 1,
+                highlight.book_title,
+                highlight.book_author.as_deref().unwrap_or("Unknown"),
+                position,
+                highlight.book_id
+            ));
+            if let Some(text) = &highlight.text {
+                result.push_str(&format!("   \"{}\"\n", text));
+            }
+            if let Some(note) = &highlight.note {
+                result.push_str(&format!("   Note: {}\n", note));
+            }
+            if let Some(at) = highlight.highlighted_at {
+                result.push_str(&format!("   Highlighted: {}\n", at.format("%Y-%m-%d")));
+            }
+            result.push('\n');
+        }
+        result
+    };
+
+    Ok(ToolCallResult {
+        content: vec![ContentItem::Text { text }],
+        is_error: None,
+    })
+}
+
+/// Hyphenated ISBN for display; stored values that do not validate are shown as they are
+fn display_isbn(isbn: Option<&str>) -> String {
+    match isbn {
+        Some(isbn) => Isbn::parse(isbn).map_or_else(|_| isbn.to_string(), |isbn| isbn.hyphenated()),
+        None => "N/A".to_string(),
+    }
+}